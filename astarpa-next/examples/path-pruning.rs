@@ -40,7 +40,7 @@ fn main() {
         }),
         block_width: 1,
         v: config.with_filename("path-pruning"),
-        front: AffineFront,
+        front: AffineFront::default(),
         trace: true,
         sparse_h: true,
         prune: true,
@@ -40,7 +40,7 @@ fn make_path_heuristic_aligner(vis: impl VisualizerT + 'static) -> Box<dyn Affin
                 domain: Domain::Astar(PathHeuristic { h }),
                 block_width: 1,
                 v: self.v,
-                front: AffineFront,
+                front: AffineFront::default(),
                 trace: true,
                 sparse_h: true,
                 prune: true,
@@ -1,5 +1,7 @@
 //! Types related to the pairwise alignment graph.
+use pa_heuristic::{util, TraceEdge};
 use pa_types::*;
+use std::cmp::min;
 use std::fmt::{Debug, Display};
 
 #[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
@@ -62,6 +64,16 @@ impl Edge {
     }
 }
 
+impl From<TraceEdge> for Edge {
+    fn from(edge: TraceEdge) -> Self {
+        match edge {
+            TraceEdge::Sub => Edge::Substitution,
+            TraceEdge::Del => Edge::Right,
+            TraceEdge::Ins => Edge::Down,
+        }
+    }
+}
+
 #[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
 pub struct DtPos {
     pub diagonal: i32,
@@ -134,16 +146,36 @@ impl<'a> EditGraph<'a> {
         }
     }
 
-    /// Counts the number of matching characters starting at the given position.
-    #[allow(unused)]
+    /// Counts the number of matching characters starting at the given
+    /// position, up to at most `max`.
+    ///
+    /// Compares 8 characters at a time. See `util::count_matches`.
     #[inline]
-    pub fn count_match(&self, Pos(i, j): Pos) -> usize {
-        let max = std::cmp::min(self.target.0 - i, self.target.1 - j) as usize;
-        let mut cnt = 0;
-        while cnt < max && self.a[i as usize + cnt] == self.b[j as usize + cnt] {
-            cnt += 1;
-        }
-        cnt
+    pub fn count_match(&self, Pos(i, j): Pos, max: usize) -> usize {
+        let a = &self.a[i as usize..];
+        util::count_matches(&a[..min(max, a.len())], &self.b[j as usize..])
+    }
+
+    /// The positions along the run of matches starting at `pos`, each followed by a match.
+    ///
+    /// The run is counted lazily in chunks of `MATCH_CHUNK` characters, so that
+    /// stopping early, e.g. at a seed boundary, does not scan the rest of a long run.
+    #[inline]
+    pub fn match_run(&self, mut pos: Pos) -> impl Iterator<Item = Pos> + '_ {
+        const MATCH_CHUNK: usize = 32;
+        let mut left = 0;
+        std::iter::from_fn(move || {
+            if left == 0 {
+                left = self.count_match(pos, MATCH_CHUNK);
+                if left == 0 {
+                    return None;
+                }
+            }
+            left -= 1;
+            let p = pos;
+            pos += Pos(1, 1);
+            Some(p)
+        })
     }
 
     /// Map a function `f` over all the outgoing edges of the given position.
@@ -180,3 +212,22 @@ impl<'a> EditGraph<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_run() {
+        // Runs longer than a chunk are continued, and stop at the first mismatch.
+        let a = [b"C".repeat(70), b"A".to_vec()].concat();
+        let b = [b"C".repeat(70), b"G".to_vec()].concat();
+        let graph = EditGraph::new(&a, &b, true);
+        for i in [0, 1, 30, 32, 69, 70] {
+            let run: Vec<Pos> = graph.match_run(Pos(i, i)).collect();
+            assert_eq!(run, (i..70).map(|i| Pos(i, i)).collect::<Vec<_>>());
+        }
+        assert_eq!(graph.count_match(Pos(0, 0), 32), 32);
+        assert_eq!(graph.count_match(Pos(60, 60), 32), 10);
+    }
+}
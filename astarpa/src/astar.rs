@@ -2,10 +2,16 @@ use crate::{
     alignment_graph::*,
     bucket_queue::{QueueElement, ShiftOrderT, ShiftQueue},
     prelude::*,
+    reseed::Reseeder,
+    search_tree::SearchTree,
     stats::AstarStats,
 };
+use astarpa2::{AstarPa2Params, Domain, DoublingStart, DoublingType};
 use pa_heuristic::{util::Timer, *};
-use pa_vis::{VisualizerInstance, VisualizerT};
+use pa_vis::{NoVis, VisualizerInstance, VisualizerT};
+use std::cmp::{max, min};
+use std::mem::size_of;
+use tracing::trace;
 
 const D: bool = false;
 
@@ -49,6 +55,175 @@ pub fn astar_with_vis<'a, H: Heuristic>(
     h: &H,
     v: &mut impl VisualizerInstance,
 ) -> ((Cost, Cigar), AstarStats) {
+    astar_inner(
+        a,
+        b,
+        h,
+        v,
+        None,
+        SearchLimits::default(),
+        None,
+        SearchOptions::default(),
+    )
+    .unwrap()
+}
+
+/// Same as `astar`, but breaks ties between parents of equal cost during
+/// traceback using `trace_order`.
+///
+/// With `None`, substitutions are preferred, then deletions, then insertions,
+/// and matches are only used when no other parent is found. Otherwise,
+/// matches are preferred over all other edges.
+pub fn astar_with_trace_order<'a, H: Heuristic>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    h: &H,
+    v: &impl VisualizerT,
+    trace_order: Option<TraceOrder>,
+) -> ((Cost, Cigar), AstarStats) {
+    let mut v = v.build(a, b);
+    astar_inner(
+        a,
+        b,
+        h,
+        &mut v,
+        None,
+        SearchLimits::default(),
+        trace_order,
+        SearchOptions::default(),
+    )
+    .unwrap()
+}
+
+/// Options that change how A* searches, but not the distance it finds.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchOptions {
+    /// When true, expanding a state only pushes the successors with the same `f`.
+    /// The state itself is pushed again with the smallest `f` of the other
+    /// successors, which are pushed when it is popped again.
+    /// This reduces the size of the priority queue when `h` is flat.
+    /// Ignored by `astar_dt`.
+    pub partial_expansion: bool,
+    /// When true, the heuristic is re-seeded with more permissive matches around
+    /// rows of `a` with many expanded states, where `h` has collapsed.
+    /// Only supported by CSH with exact matches.
+    pub adaptive_reseeding: bool,
+    /// When true, the priority queue keeps an index of its elements, so that a
+    /// state that is pushed again with smaller `g` replaces its old element
+    /// instead of leaving a duplicate that is skipped when popped.
+    pub decrease_key: bool,
+}
+
+/// Same as `astar`, but with the given search `options`.
+pub fn astar_with_options<'a, H: Heuristic>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    h: &H,
+    v: &impl VisualizerT,
+    options: SearchOptions,
+) -> ((Cost, Cigar), AstarStats) {
+    let mut v = v.build(a, b);
+    astar_inner(
+        a,
+        b,
+        h,
+        &mut v,
+        None,
+        SearchLimits::default(),
+        None,
+        options,
+    )
+    .unwrap()
+}
+
+/// Limits on the work and memory of A*, after which `astar_with_fallback` gives up.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchLimits {
+    /// The maximal number of expanded states per column of `a`, on average.
+    pub max_expanded_per_column: Option<usize>,
+    /// The maximal approximate size in bytes of the map of states and the priority queue.
+    pub max_memory: Option<usize>,
+}
+
+/// Same as `astar`, but gives up on A* once one of the `limits` is exceeded.
+///
+/// This caps the blowup of A* in time and memory on divergent inputs, where
+/// `h` is not informative. The alignment is then computed using band doubling
+/// on the `GapGap` domain of A*PA2, starting at the lower bound on the distance
+/// found by A*. Band doubling only stores the fronts of a band around the
+/// diagonal, which is much smaller than the states of A*.
+pub fn astar_with_fallback<'a, H: Heuristic>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    h: &H,
+    v: &impl VisualizerT,
+    limits: SearchLimits,
+) -> ((Cost, Cigar), AstarStats) {
+    let mut v = v.build(a, b);
+    let (lower_bound, mut stats) = match astar_inner(
+        a,
+        b,
+        h,
+        &mut v,
+        None,
+        limits,
+        None,
+        SearchOptions::default(),
+    ) {
+        Ok(r) => return r,
+        Err(r) => r,
+    };
+
+    let start = instant::Instant::now();
+    let params = AstarPa2Params {
+        name: "fallback-gapgap".into(),
+        domain: Domain::GapGap,
+        doubling: DoublingType::BandDoubling {
+            start: DoublingStart::Fixed(lower_bound),
+            factor: 2.0,
+        },
+        ..AstarPa2Params::simple()
+    };
+    let (cost, cigar, _) = params.make_aligner(true).align_with_stats(a, b);
+    stats.fallbacks += 1;
+    stats.distance = cost;
+    stats.timing.total += start.elapsed().as_secs_f64();
+    ((cost, cigar.unwrap()), stats)
+}
+
+/// Same as `astar`, but also returns the DAG of visited states.
+/// Only use this for small inputs.
+pub fn astar_with_tree<'a, H: Heuristic>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    h: &H,
+) -> ((Cost, Cigar), AstarStats, SearchTree) {
+    let mut tree = SearchTree::default();
+    let (r, stats) = astar_inner(
+        a,
+        b,
+        h,
+        &mut NoVis.build(a, b),
+        Some(&mut tree),
+        SearchLimits::default(),
+        None,
+        SearchOptions::default(),
+    )
+    .unwrap();
+    (r, stats, tree)
+}
+
+fn astar_inner<'a, H: Heuristic>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    h: &H,
+    v: &mut impl VisualizerInstance,
+    tree: Option<&mut SearchTree>,
+    limits: SearchLimits,
+    trace_order: Option<TraceOrder>,
+    options: SearchOptions,
+) -> Result<((Cost, Cigar), AstarStats), (Cost, AstarStats)> {
+    util::assert_fits_index(a, b);
     let mut stats = AstarStats::init(a, b);
 
     let start = instant::Instant::now();
@@ -63,12 +238,17 @@ pub fn astar_with_vis<'a, H: Heuristic>(
         } else {
             0
         },
+        options.decrease_key,
     );
 
     let mut states =
         HashMap::<Pos, State<<H::Instance<'a> as HeuristicInstance<'a>>::Hint>>::default();
 
     let mut max_f = 0;
+    let mut reseeder = Reseeder::new(a);
+    // With partial expansion, the states that were pushed again to push their
+    // deferred successors.
+    let mut partially_expanded = HashSet::<Pos>::default();
     v.new_layer(Some(h));
 
     // Initialization with the root state.
@@ -102,13 +282,18 @@ pub fn astar_with_vis<'a, H: Heuristic>(
 
         assert!(queue_g == state.g);
 
+        // With partial expansion, popping a state again only pushes its
+        // deferred successors with `f` up to `queue_f`. It was already
+        // checked, expanded, and pruned the first time.
+        let repop = options.partial_expansion && partially_expanded.remove(&pos);
+
         // Whenever A* pops a position, if the value of h and f is outdated, the point is pushed and not expanded.
         // Must be true for correctness.
-        {
+        if !repop {
             let ((current_h, new_hint), hint_t) = h.h_with_hint_timed(pos, state.hint);
 
             state.hint = new_hint;
-            let current_f = state.g + current_h;
+            let current_f = util::add_cost(state.g, current_h);
             assert!(
                     current_f >= queue_f && current_h >= queue_f - queue_g,
                     "Retry {pos} Current_f {current_f} smaller than queue_f {queue_f}! state.g={} queue_g={} queue_h={} current_h={}", state.g, queue_g, queue_f-queue_g, current_h
@@ -126,7 +311,7 @@ pub fn astar_with_vis<'a, H: Heuristic>(
             }
             assert!(current_f == queue_f);
             if D {
-                println!(
+                trace!(
                     "Expand {pos} at \tg={queue_g} \tf={queue_f} \th={current_h}\tqueue_h={}",
                     queue_f - queue_g
                 );
@@ -135,11 +320,33 @@ pub fn astar_with_vis<'a, H: Heuristic>(
 
         // Expand u
         if D {
-            println!("Expand {pos} {}", state.g);
+            trace!("Expand {pos} {}", state.g);
+        }
+
+        if repop {
+            stats.partial_expansions += 1;
+        } else {
+            stats.expanded += 1;
+            v.expand(pos, queue_g, queue_f, Some(h));
+        }
+
+        if !repop && options.adaptive_reseeding && reseeder.expand(pos, a, b, h) {
+            stats.reseeds += 1;
         }
 
-        stats.expanded += 1;
-        v.expand(pos, queue_g, queue_f, Some(h));
+        // All states in the queue have `f >= queue_f`, and one of them is on
+        // an optimal path, so `queue_f` is a lower bound on the distance.
+        let memory = memory(&states, queue.len());
+        stats.max_memory = max(stats.max_memory, memory);
+        if limits
+            .max_expanded_per_column
+            .is_some_and(|m| stats.expanded > m * (a.len() + 1))
+            || limits.max_memory.is_some_and(|m| memory > m)
+        {
+            stats.h = h.stats();
+            stats.timing.total = start.elapsed().as_secs_f64();
+            return Err((queue_f, stats));
+        }
 
         if queue_f > max_f {
             max_f = queue_f;
@@ -152,26 +359,29 @@ pub fn astar_with_vis<'a, H: Heuristic>(
         // Retrace path to root and return.
         if pos == graph.target() {
             if D {
-                println!("Reached target {pos} with state {state:?}");
+                trace!("Reached target {pos} with state {state:?}");
             }
             break state.g;
         }
 
         // Prune is needed
-        if h.is_seed_start_or_end(pos) {
+        if !repop && h.is_seed_start_or_end(pos) {
             let (shift, pos) = h.prune(pos, state.hint);
             if REDUCE_REORDERING {
                 stats.pq_shifts += queue.shift(shift, pos) as usize;
             }
         }
 
+        // With partial expansion, the smallest `f` of a successor that was not pushed.
+        let mut deferred_f = Cost::MAX;
         graph.iterate_outgoing_edges(pos, |mut next, edge| {
             // Explore next
-            let next_g = state.g + edge.cost() as Cost;
+            let next_g = util::add_cost(state.g, edge.cost() as Cost);
 
             // Do greedy matching within the current seed.
+            // The run of matches is found lazily, many characters at a time.
             if graph.greedy_matching {
-                while let Some(n) = graph.is_match(next) {
+                for _ in graph.match_run(next) {
                     // Never greedy expand the start of a seed.
                     // Doing so may cause problems when h is not consistent and is
                     // larger at the start of seed than at the position where the
@@ -187,11 +397,11 @@ pub fn astar_with_vis<'a, H: Heuristic>(
                     v.explore(next, queue_g, queue_f, Some(h));
                     v.expand(next, queue_g, queue_f, Some(h));
                     if D {
-                        println!("Greedy expand {next} {}", state.g);
+                        trace!("Greedy expand {next} {}", state.g);
                     }
 
                     // Move to the next state.
-                    next = n;
+                    next += Pos(1, 1);
                 }
             }
 
@@ -202,16 +412,25 @@ pub fn astar_with_vis<'a, H: Heuristic>(
                 return;
             };
 
+            let (next_h, next_hint) = h.h_with_hint_timed(next, state.hint).0;
+            let next_f = util::add_cost(next_g, next_h);
+
+            // With partial expansion, successors with larger `f` are only pushed
+            // once `pos` is popped again with that `f`.
+            if options.partial_expansion && next_f > queue_f {
+                deferred_f = min(deferred_f, next_f);
+                return;
+            }
+
             // Open next
             if D {
-                println!("Open {next} from {pos} g {next_g}");
+                trace!("Open {next} from {pos} g {next_g}");
             }
 
             cur_next.g = next_g;
-
-            let (next_h, next_hint) = h.h_with_hint_timed(next, state.hint).0;
             cur_next.hint = next_hint;
-            let next_f = next_g + next_h;
+            // A deferred entry of `next` is now outdated and skipped when popped.
+            partially_expanded.remove(&next);
 
             queue.push(QueueElement {
                 f: next_f,
@@ -222,11 +441,22 @@ pub fn astar_with_vis<'a, H: Heuristic>(
             stats.explored += 1;
             v.explore(next, next_g, next_f, Some(h));
         });
+
+        if deferred_f != Cost::MAX {
+            queue.push(QueueElement {
+                f: deferred_f,
+                data: (pos, queue_g),
+            });
+            partially_expanded.insert(pos);
+        }
     };
 
     stats.hashmap_capacity = states.capacity();
+    stats.pq_pushes = queue.pushes;
+    stats.pq_pops = queue.pops;
+    stats.pq_decreased_keys = queue.decreased_keys;
     let traceback_start = instant::Instant::now();
-    let (d, path) = traceback(&states, graph.target());
+    let (d, path) = traceback(&states, graph.a, graph.b, trace_order);
     let cigar = Cigar::from_path(graph.a, graph.b, &path);
     let end = instant::Instant::now();
 
@@ -249,11 +479,36 @@ pub fn astar_with_vis<'a, H: Heuristic>(
         stats.h.h0
     );
     stats.distance = d;
-    ((d, cigar), stats)
+    if let Some(tree) = tree {
+        *tree = SearchTree::new(
+            a,
+            b,
+            |p| states.get(&p).map(|s| s.g).filter(|&g| g != Cost::MAX),
+            states.keys().copied().collect(),
+        );
+    }
+    Ok(((d, cigar), stats))
 }
 
-fn parent<'a, Hint: Default>(states: &HashMap<Pos, State<Hint>>, pos: Pos, g: Cost) -> Edge {
-    for edge in [Edge::Substitution, Edge::Right, Edge::Down] {
+/// The approximate size in bytes of the map of states and a queue of the given length.
+fn memory<Hint>(states: &HashMap<Pos, State<Hint>>, queue_len: usize) -> usize {
+    states.capacity() * size_of::<(Pos, State<Hint>)>() + queue_len * size_of::<(Pos, Cost)>()
+}
+
+fn parent<'a, Hint: Default>(
+    states: &HashMap<Pos, State<Hint>>,
+    a: Seq,
+    b: Seq,
+    pos: Pos,
+    g: Cost,
+    trace_order: Option<TraceOrder>,
+) -> Edge {
+    // States reached via a match may not be stored, so by default matches are
+    // only used when no other parent is found.
+    let is_match = pos.0 > 0 && pos.1 > 0 && a[pos.0 as usize - 1] == b[pos.1 as usize - 1];
+    let first = (trace_order.is_some() && is_match).then_some(Edge::Match);
+    let edges = trace_order.unwrap_or_default().edges().map(Edge::from);
+    for edge in first.into_iter().chain(edges) {
         if let Some(p) = edge.back(&pos) {
             if let Some(state) = states.get(&p) {
                 if state.g + edge.cost() == g {
@@ -268,8 +523,11 @@ fn parent<'a, Hint: Default>(states: &HashMap<Pos, State<Hint>>, pos: Pos, g: Co
 // TODO: Make this return Cigar instead.
 fn traceback<'a, Hint: Default>(
     states: &HashMap<Pos, State<Hint>>,
-    target: Pos,
+    a: Seq,
+    b: Seq,
+    trace_order: Option<TraceOrder>,
 ) -> (Cost, Vec<Pos>) {
+    let target = Pos::target(a, b);
     let Some(state) = states.get(&target) else {
         panic!();
     };
@@ -279,7 +537,7 @@ fn traceback<'a, Hint: Default>(
     let mut current = target;
     // If the state is not in the map, it was found via a match.
     while current != Pos(0, 0) {
-        let e = parent(states, current, g - cost);
+        let e = parent(states, a, b, current, g - cost, trace_order);
         cost += e.cost();
         current = e.back(&current).expect("No parent found for position!");
         path.push(current);
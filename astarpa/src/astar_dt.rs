@@ -1,11 +1,14 @@
 use crate::{
     alignment_graph::*,
+    astar::SearchOptions,
     bucket_queue::{QueueElement, ShiftOrderT, ShiftQueue},
     prelude::*,
+    reseed::Reseeder,
     stats::AstarStats,
 };
 use pa_heuristic::{util::Timer, *};
 use pa_vis::{VisualizerInstance, VisualizerT};
+use tracing::trace;
 
 const D: bool = false;
 
@@ -37,6 +40,43 @@ pub fn astar_dt<'a, H: Heuristic>(
     h: &H,
     v: &impl VisualizerT,
 ) -> ((Cost, Cigar), AstarStats) {
+    astar_dt_with_trace_order(a, b, h, v, None)
+}
+
+/// Same as `astar_dt`, but breaks ties between parents reaching equally far
+/// during traceback using `trace_order`.
+///
+/// With `None`, substitutions are preferred, then insertions, then deletions.
+pub fn astar_dt_with_trace_order<'a, H: Heuristic>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    h: &H,
+    v: &impl VisualizerT,
+    trace_order: Option<TraceOrder>,
+) -> ((Cost, Cigar), AstarStats) {
+    astar_dt_inner(a, b, h, v, trace_order, SearchOptions::default())
+}
+
+/// Same as `astar_dt`, but with the given search `options`.
+pub fn astar_dt_with_options<'a, H: Heuristic>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    h: &H,
+    v: &impl VisualizerT,
+    options: SearchOptions,
+) -> ((Cost, Cigar), AstarStats) {
+    astar_dt_inner(a, b, h, v, None, options)
+}
+
+fn astar_dt_inner<'a, H: Heuristic>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    h: &H,
+    v: &impl VisualizerT,
+    trace_order: Option<TraceOrder>,
+    options: SearchOptions,
+) -> ((Cost, Cigar), AstarStats) {
+    util::assert_fits_index(a, b);
     let mut stats = AstarStats::init(a, b);
 
     let start = instant::Instant::now();
@@ -53,12 +93,14 @@ pub fn astar_dt<'a, H: Heuristic>(
         } else {
             0
         },
+        options.decrease_key,
     );
 
     let mut states =
         HashMap::<DtPos, State<<H::Instance<'a> as HeuristicInstance>::Hint>>::default();
 
     let mut max_f = 0;
+    let mut reseeder = Reseeder::new(a);
     v.new_layer(Some(h));
 
     // Initialization with the root state.
@@ -130,7 +172,7 @@ pub fn astar_dt<'a, H: Heuristic>(
             }
             assert!(current_f == queue_f);
             if D {
-                println!(
+                trace!(
                     "Expand {pos} at \tg={queue_g} \tf={queue_f} \th={current_h}\tqueue_h={}",
                     queue_f - queue_g
                 );
@@ -139,12 +181,16 @@ pub fn astar_dt<'a, H: Heuristic>(
 
         // Expand u
         if D {
-            println!("Expand {queue_f}: {pos} g={queue_g}");
+            trace!("Expand {queue_f}: {pos} g={queue_g}");
         }
 
         stats.expanded += 1;
         v.expand(pos, queue_g, queue_f, Some(h));
 
+        if options.adaptive_reseeding && reseeder.expand(pos, a, b, h) {
+            stats.reseeds += 1;
+        }
+
         if queue_f > max_f {
             max_f = queue_f;
             v.new_layer(Some(h));
@@ -156,7 +202,7 @@ pub fn astar_dt<'a, H: Heuristic>(
         // Retrace path to root and return.
         if pos == graph.target() {
             if D {
-                println!("Reached target {pos} with state {state:?}");
+                trace!("Reached target {pos} with state {state:?}");
             }
             break queue_g;
         }
@@ -182,8 +228,9 @@ pub fn astar_dt<'a, H: Heuristic>(
             };
 
             // Do greedy matching within the current seed.
+            // The run of matches is found lazily, many characters at a time.
             if graph.greedy_matching {
-                while let Some(n) = graph.is_match(next) {
+                for _ in graph.match_run(next) {
                     // Never greedy expand the start of a seed.
                     // Doing so may cause problems when h is not consistent and is
                     // larger at the start of seed than at the position where the
@@ -197,11 +244,11 @@ pub fn astar_dt<'a, H: Heuristic>(
                     v.explore(next, queue_g, queue_f, Some(h));
                     v.expand(next, queue_g, queue_f, Some(h));
                     if D {
-                        println!("Greedy {queue_f}: {next} g={queue_g} from {pos}");
+                        trace!("Greedy {queue_f}: {next} g={queue_g} from {pos}");
                     }
 
                     // Move to the next state.
-                    next = n;
+                    next += Pos(1, 1);
                 }
             }
             // Update the value after greedy extension.
@@ -215,7 +262,7 @@ pub fn astar_dt<'a, H: Heuristic>(
 
             // Open next
             if D {
-                println!(
+                trace!(
                     "Open   {next_f}: {next} g={next_g} fr={next_fr} cur_fr={} from {pos}",
                     cur_next.fr
                 );
@@ -233,8 +280,13 @@ pub fn astar_dt<'a, H: Heuristic>(
     };
 
     stats.hashmap_capacity = states.capacity();
+    stats.pq_pushes = queue.pushes;
+    stats.pq_pops = queue.pops;
+    stats.pq_decreased_keys = queue.decreased_keys;
     let traceback_start = instant::Instant::now();
-    let (d, path) = traceback(&states, graph.target(), dist);
+    let trace_order =
+        trace_order.unwrap_or(TraceOrder([TraceEdge::Sub, TraceEdge::Ins, TraceEdge::Del]));
+    let (d, path) = traceback(&states, graph.target(), dist, trace_order);
     let cigar = Cigar::from_path(graph.a, graph.b, &path);
     let end = instant::Instant::now();
 
@@ -259,9 +311,14 @@ pub fn astar_dt<'a, H: Heuristic>(
     ((d, cigar), stats)
 }
 
-fn dt_parent<'a, Hint: Default>(states: &HashMap<DtPos, State<Hint>>, dt_pos: DtPos) -> (I, Edge) {
+fn dt_parent<'a, Hint: Default>(
+    states: &HashMap<DtPos, State<Hint>>,
+    dt_pos: DtPos,
+    trace_order: TraceOrder,
+) -> (I, Edge) {
     let mut max_fr = (0, Edge::None);
-    for edge in [Edge::Right, Edge::Down, Edge::Substitution] {
+    // Later edges win ties, so iterate from least to most preferred.
+    for edge in trace_order.edges().rev().map(Edge::from) {
         if let Some(p) = edge.dt_back(&dt_pos) {
             if let Some(state) = states.get(&p) {
                 if state.fr + edge.to_f() >= max_fr.0 + max_fr.1.to_f() {
@@ -277,6 +334,7 @@ fn traceback<'a, Hint: Default>(
     states: &HashMap<DtPos, State<Hint>>,
     target: Pos,
     g: Cost,
+    trace_order: TraceOrder,
 ) -> (Cost, Vec<Pos>) {
     let target_dt = DtPos::from_pos(target, g);
     // Traceback algorithm from Ukkonen'85.
@@ -287,22 +345,22 @@ fn traceback<'a, Hint: Default>(
     let mut cur_dt = target_dt;
     // If the state is not in the map, it was found via a match.
     while cur_dt != (DtPos { diagonal: 0, g: 0 }) {
-        let (parent_fr, edge) = dt_parent(states, cur_dt);
+        let (parent_fr, edge) = dt_parent(states, cur_dt, trace_order);
         cost += edge.cost();
         let next_dt = edge
             .dt_back(&cur_dt)
             .expect("No parent found for position!");
         let next_pos = next_dt.to_pos(parent_fr);
         if D {
-            eprintln!("Current pos {cost_from_start}\t / {cur_pos}\t at cost {cost} edge {edge:?}");
-            eprintln!("Target  pos {cost_from_start}\t / {next_pos} at fr {parent_fr}");
+            trace!("Current pos {cost_from_start}\t / {cur_pos}\t at cost {cost} edge {edge:?}");
+            trace!("Target  pos {cost_from_start}\t / {next_pos} at fr {parent_fr}");
         }
         // Add as many matches as needed to end exactly in next_pos.
         // NOTE: We need the > here (!= won't do), since next_pos may actually be larger
         // than cur_pos, resulting in a possible infinite loop.
         while edge.back(&cur_pos).unwrap() > next_pos {
             if D {
-                eprintln!(
+                trace!(
                     "Push {} @ {cost_from_start}, since {} > {next_pos}",
                     Edge::Match.back(&cur_pos).unwrap(),
                     edge.back(&cur_pos).unwrap()
@@ -314,7 +372,7 @@ fn traceback<'a, Hint: Default>(
         cur_pos = edge.back(&cur_pos).unwrap();
         cost_from_start -= edge.cost();
         if D {
-            eprintln!("Push {cur_pos} @ {cost_from_start}");
+            trace!("Push {cur_pos} @ {cost_from_start}");
         }
         path.push(cur_pos);
         cur_dt = next_dt;
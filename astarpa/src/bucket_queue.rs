@@ -1,6 +1,7 @@
 use crate::config::USE_TIP_BUFFER;
 use pa_heuristic::PosOrderT;
-use pa_types::Cost;
+use pa_types::{Cost, Pos};
+use rustc_hash::FxHashMap as HashMap;
 use std::cmp::{max, min};
 
 #[derive(Copy, Clone, Debug)]
@@ -100,6 +101,18 @@ pub trait ShiftOrderT<T>: PosOrderT + Default + Copy {
     fn from_t(t: &T) -> Self;
 }
 
+/// The position of a queue element. With `decrease_key`, the queue contains at
+/// most one element per position.
+pub trait QueueKey {
+    fn key(&self) -> Pos;
+}
+
+impl QueueKey for (Pos, Cost) {
+    fn key(&self) -> Pos {
+        self.0
+    }
+}
+
 // NOTE: 0 for paper
 const TIP_SIZE: usize = 0;
 
@@ -123,28 +136,65 @@ pub struct ShiftQueue<T, O> {
     down_shift: Cost,
 
     missed: usize,
+
+    /// Keep at most one element per position. See `SearchOptions::decrease_key`.
+    decrease_key: bool,
+    /// With `decrease_key`, the layer and index in the main queue of the element for each position.
+    index: HashMap<Pos, (usize, usize)>,
+
+    pub pushes: usize,
+    pub pops: usize,
+    /// Number of pushes that replaced the element already in the queue for the same position.
+    pub decreased_keys: usize,
 }
 
-impl<T, O: ShiftOrderT<T>> ShiftQueue<T, O>
+impl<T: QueueKey, O: ShiftOrderT<T>> ShiftQueue<T, O>
 where
     T: std::fmt::Debug,
     O: std::fmt::Debug,
 {
-    pub fn new(max_shift: Cost) -> Self {
+    pub fn new(max_shift: Cost, decrease_key: bool) -> Self {
+        assert!(
+            !(decrease_key && USE_TIP_BUFFER),
+            "decrease_key does not support USE_TIP_BUFFER."
+        );
         ShiftQueue {
             queue: BucketQueue::default(),
             tip_queue: BucketQueue::default(),
             tip_start: O::default(),
             down_shift: max_shift,
             missed: 0,
+            decrease_key,
+            index: HashMap::default(),
+            pushes: 0,
+            pops: 0,
+            decreased_keys: 0,
         }
     }
     pub fn push(&mut self, mut element: QueueElement<T>)
     where
         T: Clone + std::fmt::Debug,
     {
+        self.pushes += 1;
         element.f += self.down_shift;
-        if !USE_TIP_BUFFER {
+        if self.decrease_key {
+            // A new push for a position is always for a smaller `g`, or after
+            // the previous element was popped, so the old element is outdated.
+            let key = element.data.key();
+            if let Some((f, idx)) = self.index.remove(&key) {
+                self.decreased_keys += 1;
+                let layer = &mut self.queue.layers[f];
+                layer.swap_remove(idx);
+                if let Some(moved) = layer.get(idx) {
+                    self.index.insert(moved.key(), (f, idx));
+                }
+                self.queue.size -= 1;
+            }
+            let f = element.f as usize;
+            self.tip_start = O::max(self.tip_start, O::from_t(&element.data));
+            self.queue.push(element);
+            self.index.insert(key, (f, self.queue.layers[f].len() - 1));
+        } else if !USE_TIP_BUFFER {
             self.tip_start = O::max(self.tip_start, O::from_t(&element.data));
             self.queue.push(element);
         } else {
@@ -155,10 +205,23 @@ where
             }
         }
     }
+    /// The number of elements in the queue.
+    pub fn len(&self) -> usize {
+        self.queue.size + self.tip_queue.size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
     pub fn pop(&mut self) -> Option<QueueElement<T>> {
+        self.pops += 1;
         if !USE_TIP_BUFFER {
             let mut e = self.queue.pop();
             if let Some(e) = e.as_mut() {
+                if self.decrease_key {
+                    self.index.remove(&e.data.key());
+                }
                 e.f -= self.down_shift;
             }
             e
@@ -223,3 +286,33 @@ where
         shift
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decrease_key() {
+        let mut q = ShiftQueue::<(Pos, Cost), Pos>::new(0, true);
+        for (f, i, g) in [(5, 0, 3), (5, 1, 3), (4, 0, 2), (5, 2, 3), (3, 1, 1)] {
+            q.push(QueueElement {
+                f,
+                data: (Pos(i, i), g),
+            });
+        }
+        assert_eq!(q.decreased_keys, 2);
+        let mut popped = vec![];
+        while let Some(QueueElement { f, data }) = q.pop() {
+            popped.push((f, data));
+        }
+        assert_eq!(
+            popped,
+            [
+                (3, (Pos(1, 1), 1)),
+                (4, (Pos(0, 0), 2)),
+                (5, (Pos(2, 2), 3))
+            ]
+        );
+        assert!(q.index.is_empty());
+    }
+}
@@ -12,3 +12,9 @@ pub const REDUCE_REORDERING: bool = true;
 /// separately for shifting purposes.
 /// This seems helpful for CSH with high error rate, but causes significant slowdown for SH.
 pub const USE_TIP_BUFFER: bool = false;
+
+/// With `SearchOptions::adaptive_reseeding`, the number of expanded states in a row after which the rows around it are re-seeded.
+pub const RESEED_ROW_EXPANSIONS: u32 = 256;
+
+/// The rows within this distance of a row with many expanded states are re-seeded.
+pub const RESEED_RADIUS: pa_types::I = 128;
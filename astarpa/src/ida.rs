@@ -0,0 +1,126 @@
+//! IDA*: iterative deepening A*.
+//!
+//! Instead of storing all visited states, IDA* does a depth-first search that
+//! only follows states with `f = g + h <= bound`, and restarts with the
+//! smallest `f` that exceeded the bound until the target is reached.
+//! Memory is linear in the length of the path, but states can be visited many
+//! times, so this is only an option when even the map of states does not fit.
+//!
+//! Runs of matches are followed greedily up to the next seed boundary, so that
+//! each exact diagonal stretch costs a single stack frame. Still, states are
+//! not deduplicated, and the number of paths with `f <= bound` grows
+//! exponentially with `bound - h(start)`. IDA* is only practical when the
+//! heuristic is close to the distance, e.g. GCSH with inexact matches on
+//! sequences with low divergence.
+use crate::{alignment_graph::*, prelude::*, stats::AstarStats};
+use pa_heuristic::*;
+use smallvec::SmallVec;
+use std::cmp::min;
+
+/// A state on the DFS stack.
+struct Frame {
+    /// The state reached via an edge from the previous frame, before extending
+    /// along matches to `pos`.
+    entry: Pos,
+    pos: Pos,
+    g: Cost,
+    /// The successors that remain to be visited. The last one, which is the
+    /// diagonal edge, is visited first.
+    next: SmallVec<[(Pos, Cost); 3]>,
+}
+
+impl Frame {
+    fn new(graph: &EditGraph, entry: Pos, pos: Pos, g: Cost) -> Self {
+        let mut next = SmallVec::new();
+        graph.iterate_outgoing_edges(pos, |next_pos, edge| next.push((next_pos, edge.cost())));
+        Self {
+            entry,
+            pos,
+            g,
+            next,
+        }
+    }
+}
+
+/// Align two sequences using IDA* with the given heuristic.
+///
+/// The heuristic must be admissible. It is never pruned, since pruning relies
+/// on the order in which A* expands states.
+pub fn ida_star<'a, H: Heuristic>(a: Seq<'a>, b: Seq<'a>, h: &H) -> ((Cost, Cigar), AstarStats) {
+    util::assert_fits_index(a, b);
+    let mut stats = AstarStats::init(a, b);
+
+    let start = instant::Instant::now();
+    let ref graph = EditGraph::new(a, b, true);
+    let ref mut h = h.build(a, b);
+    stats.timing.precomp = start.elapsed().as_secs_f64();
+
+    let mut bound = h.h(graph.start());
+    let (d, path) = loop {
+        match search(graph, &*h, bound, &mut stats) {
+            Ok(r) => break r,
+            Err(next_bound) => bound = next_bound,
+        }
+    };
+    let cigar = Cigar::from_path(a, b, &path);
+
+    stats.h = h.stats();
+    stats.distance = d;
+    stats.timing.total = start.elapsed().as_secs_f64();
+    stats.timing.astar = stats.timing.total - stats.timing.precomp;
+    ((d, cigar), stats)
+}
+
+/// A single depth-first search with the given bound on `f`.
+/// Returns the distance and path to the target, or the next bound to try.
+fn search<'a>(
+    graph: &EditGraph,
+    h: &impl HeuristicInstance<'a>,
+    bound: Cost,
+    stats: &mut AstarStats,
+) -> Result<(Cost, Vec<Pos>), Cost> {
+    let target = graph.target();
+    if graph.start() == target {
+        return Ok((0, vec![target]));
+    }
+    let mut next_bound = Cost::MAX;
+    let mut stack = vec![Frame::new(graph, graph.start(), graph.start(), 0)];
+    while let Some(frame) = stack.last_mut() {
+        let Some((entry, cost)) = frame.next.pop() else {
+            stack.pop();
+            continue;
+        };
+        let g = frame.g + cost;
+
+        // Greedily extend along matches, but never past the start or end of a
+        // seed, like A* does.
+        let mut next = entry;
+        for _ in graph.match_run(entry) {
+            if h.is_seed_start_or_end(next) {
+                break;
+            }
+            stats.extended += 1;
+            next += Pos(1, 1);
+        }
+
+        let f = g + h.h(next);
+        stats.explored += 1;
+        if f > bound {
+            next_bound = min(next_bound, f);
+            continue;
+        }
+        // Since `bound` is at most the distance, the first path found is optimal.
+        if next == target {
+            let path = stack
+                .iter()
+                .map(|f| (f.entry, f.pos))
+                .chain([(entry, next)])
+                .flat_map(|(entry, pos)| (0..=pos.0 - entry.0).map(move |d| entry + Pos(d, d)))
+                .collect();
+            return Ok((g, path));
+        }
+        stats.expanded += 1;
+        stack.push(Frame::new(graph, entry, next, g));
+    }
+    Err(next_bound)
+}
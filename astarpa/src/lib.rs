@@ -30,6 +30,9 @@ mod astar;
 mod astar_dt;
 mod bucket_queue;
 mod config;
+mod ida;
+mod reseed;
+mod search_tree;
 #[cfg(test)]
 mod tests;
 
@@ -38,12 +41,16 @@ pub mod stats;
 mod prelude {
     pub use pa_types::*;
     pub use rustc_hash::FxHashMap as HashMap;
+    pub use rustc_hash::FxHashSet as HashSet;
 
     pub use crate::config::*;
 }
 
+use pa_affine_types::{AffineAligner, AffineCigar};
 use pa_heuristic::seeds::MatchCost;
-use pa_heuristic::{Heuristic, HeuristicMapper, Prune};
+use pa_heuristic::util::{check_cost_overflow, CostOverflow};
+use pa_heuristic::wrappers::WeightedHeuristic;
+use pa_heuristic::{AlignResult, Heuristic, HeuristicMapper, Prune, ResultAligner, TraceOrder};
 use pa_heuristic::{MatchConfig, Pruning, GCSH};
 use pa_types::{Aligner, Cigar, Cost, Seq, I};
 use pa_vis::{NoVis, VisualizerT};
@@ -51,9 +58,14 @@ use stats::AstarStats;
 
 // ------------ Root alignment interface follows from here ------------
 
-pub use astar::{astar, astar_with_vis};
-pub use astar_dt::astar_dt;
+pub use astar::{
+    astar, astar_with_fallback, astar_with_options, astar_with_trace_order, astar_with_tree,
+    astar_with_vis, SearchLimits, SearchOptions,
+};
+pub use astar_dt::{astar_dt, astar_dt_with_options, astar_dt_with_trace_order};
+pub use ida::ida_star;
 pub use pa_heuristic::HeuristicParams;
+pub use search_tree::SearchTree;
 
 /// Align using default settings:
 /// - Gap-cost chaining seed heuristic (GCSH)
@@ -78,6 +90,20 @@ pub fn astarpa_gcsh(a: Seq, b: Seq, r: MatchCost, k: I, pruning: Prune) -> (Cost
     .0
 }
 
+/// Align using weighted A*, with `f = g + w*h` for a weight `w >= 1`.
+///
+/// This trades accuracy for speed: the returned cost is at most `w` times the
+/// optimal cost. The second return value is a lower bound on the optimal cost,
+/// so the actual suboptimality is at most `cost / lower_bound`.
+///
+/// Since the weighted heuristic is not consistent, this does not use DT.
+pub fn astarpa_weighted<H: Heuristic>(a: Seq, b: Seq, h: H, w: f32) -> ((Cost, Cigar), Cost) {
+    let h = WeightedHeuristic::new(h, w);
+    let ((cost, cigar), stats) = astar(a, b, &h, &NoVis);
+    let lower_bound = std::cmp::max(stats.h.h0, h.lower_bound(cost));
+    ((cost, cigar), lower_bound)
+}
+
 /// Build an `AstarStatsAligner` instance from
 pub fn make_aligner(dt: bool, h: &HeuristicParams) -> Box<dyn AstarStatsAligner> {
     make_aligner_with_visualizer(dt, h, NoVis)
@@ -100,6 +126,7 @@ pub fn make_aligner_with_visualizer<V: VisualizerT + 'static>(
                 dt: self.dt,
                 h,
                 v: self.v,
+                trace_order: None,
             })
         }
     }
@@ -113,25 +140,39 @@ pub struct AstarPa<V: VisualizerT, H: Heuristic> {
     pub dt: bool,
     pub h: H,
     pub v: V,
+    /// The preferred order of equal-cost parents during traceback.
+    /// `None` keeps the order of `astar` and `astar_dt`, which differ.
+    pub trace_order: Option<TraceOrder>,
 }
 
 impl<H: Heuristic> AstarPa<NoVis, H> {
     pub fn new(dt: bool, h: H) -> Self {
-        AstarPa { dt, h, v: NoVis }
+        AstarPa {
+            dt,
+            h,
+            v: NoVis,
+            trace_order: None,
+        }
     }
 }
 impl<V: VisualizerT, H: Heuristic> AstarPa<V, H> {
     pub fn align(&self, a: Seq, b: Seq) -> ((Cost, Cigar), AstarStats) {
         if self.dt {
-            astar_dt(a, b, &self.h, &self.v)
+            astar_dt_with_trace_order(a, b, &self.h, &self.v, self.trace_order)
         } else {
-            astar(a, b, &self.h, &self.v)
+            astar_with_trace_order(a, b, &self.h, &self.v, self.trace_order)
         }
     }
+
+    /// Same as `align`, but returns an error when a cost addition overflowed.
+    /// Overflows are only detected with the `checked_cost` feature.
+    pub fn try_align(&self, a: Seq, b: Seq) -> Result<((Cost, Cigar), AstarStats), CostOverflow> {
+        check_cost_overflow(|| self.align(a, b))
+    }
 }
 
 /// Helper trait to erase the type of the heuristic that additionally returns alignment statistics.
-pub trait AstarStatsAligner: Aligner {
+pub trait AstarStatsAligner: ResultAligner {
     fn align(&self, a: Seq, b: Seq) -> ((Cost, Cigar), AstarStats);
 }
 
@@ -142,6 +183,13 @@ impl<V: VisualizerT, H: Heuristic> AstarStatsAligner for AstarPa<V, H> {
     }
 }
 
+impl<V: VisualizerT, H: Heuristic> ResultAligner for AstarPa<V, H> {
+    fn align_result(&mut self, a: Seq, b: Seq) -> AlignResult {
+        let ((cost, cigar), stats) = AstarPa::align(self, a, b);
+        AlignResult::new(cost, Some(cigar), stats.to_align_stats())
+    }
+}
+
 /// A simple aligner interface.
 impl<V: VisualizerT, H: Heuristic> Aligner for AstarPa<V, H> {
     fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>) {
@@ -149,3 +197,11 @@ impl<V: VisualizerT, H: Heuristic> Aligner for AstarPa<V, H> {
         (cost, Some(cigar))
     }
 }
+
+/// Unit costs only, so that A*PA can be used wherever the affine aligners are.
+impl<V: VisualizerT, H: Heuristic> AffineAligner for AstarPa<V, H> {
+    fn align_affine(&mut self, a: Seq, b: Seq) -> (Cost, Option<AffineCigar>) {
+        let ((cost, cigar), _stats) = AstarPa::align(self, a, b);
+        (cost, Some(AffineCigar::from(&cigar)))
+    }
+}
@@ -0,0 +1,38 @@
+use crate::prelude::*;
+use pa_heuristic::HeuristicInstance;
+use std::cmp::min;
+
+/// Counts the expanded states in each row of `a`, and asks the heuristic to
+/// re-seed the rows around a row where many states are expanded.
+///
+/// A row with many expanded states means that the band has grown, i.e. that
+/// `h` has collapsed there, so more permissive matches are only paid for in
+/// this region instead of for the full sequence.
+pub struct Reseeder {
+    expanded: Vec<u32>,
+}
+
+impl Reseeder {
+    pub fn new(a: Seq) -> Self {
+        Self {
+            expanded: vec![0; a.len() + 1],
+        }
+    }
+
+    /// Count an expanded state. Returns true when the heuristic changed.
+    pub fn expand<'a>(
+        &mut self,
+        pos: Pos,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        h: &mut impl HeuristicInstance<'a>,
+    ) -> bool {
+        let cnt = &mut self.expanded[pos.0 as usize];
+        *cnt += 1;
+        if *cnt != RESEED_ROW_EXPANSIONS {
+            return false;
+        }
+        let rows = pos.0.saturating_sub(RESEED_RADIUS)..min(pos.0 + RESEED_RADIUS, a.len() as I);
+        h.reseed(a, b, rows)
+    }
+}
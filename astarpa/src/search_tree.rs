@@ -0,0 +1,103 @@
+//! The states visited by A*, with the edges along which they are reached with
+//! minimal cost, exported as DOT or GraphML for inspection in graphviz or Gephi.
+//!
+//! This is intended for small inputs only.
+use crate::{alignment_graph::Edge, prelude::*};
+use std::fmt::Write;
+
+/// The shortest-path DAG of the states stored by A*.
+///
+/// NOTE: States skipped through by greedy matching are not stored, so that
+/// runs of matches are contracted into a single edge.
+#[derive(Default, Clone, Debug)]
+pub struct SearchTree {
+    /// Each state with its distance `g` from the start, sorted by position.
+    pub states: Vec<(Pos, Cost)>,
+    /// Edges `(from, to)` such that `g(from) + cost(from, to) = g(to)`.
+    pub edges: Vec<(Pos, Pos)>,
+}
+
+impl SearchTree {
+    /// Build the DAG from the final `g` of each stored state.
+    pub(crate) fn new(a: Seq, b: Seq, g: impl Fn(Pos) -> Option<Cost>, states: Vec<Pos>) -> Self {
+        let mut tree = SearchTree::default();
+        for pos in states {
+            let Some(gp) = g(pos) else { continue };
+            tree.states.push((pos, gp));
+            // Walk back over the run of matches that was skipped by greedy
+            // matching, and find parents of each position in it.
+            let mut cur = pos;
+            loop {
+                for edge in [Edge::Substitution, Edge::Right, Edge::Down] {
+                    if let Some(p) = edge.back(&cur)
+                        && (edge != Edge::Substitution || a[p.0 as usize] != b[p.1 as usize])
+                        && g(p) == Some(gp - 1)
+                    {
+                        tree.edges.push((p, pos));
+                    }
+                }
+                let Some(p) = Edge::Match.back(&cur) else {
+                    break;
+                };
+                if a[p.0 as usize] != b[p.1 as usize] {
+                    break;
+                }
+                if g(p) == Some(gp) {
+                    tree.edges.push((p, pos));
+                }
+                if g(p).is_some() {
+                    break;
+                }
+                cur = p;
+            }
+        }
+        tree.states.sort_unstable_by_key(|&(p, _)| (p.0, p.1));
+        tree.edges
+            .sort_unstable_by_key(|&(s, t)| (t.0, t.1, s.0, s.1));
+        tree
+    }
+
+    /// A graphviz DOT digraph. Nodes are placed at their position in the DP matrix.
+    pub fn to_dot(&self) -> String {
+        let mut s = String::from("digraph astar {\n  node [shape=point];\n");
+        for &(Pos(i, j), g) in &self.states {
+            writeln!(
+                s,
+                r#"  "{i},{j}" [pos="{i},{}!", label="{g}"];"#,
+                -(j as i64)
+            )
+            .unwrap();
+        }
+        for &(Pos(i, j), Pos(i2, j2)) in &self.edges {
+            writeln!(s, r#"  "{i},{j}" -> "{i2},{j2}";"#).unwrap();
+        }
+        s.push_str("}\n");
+        s
+    }
+
+    /// A GraphML graph, with `i`, `j`, and `g` as node attributes.
+    pub fn to_graphml(&self) -> String {
+        let mut s = String::new();
+        s.push_str(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="i" for="node" attr.name="i" attr.type="int"/>
+  <key id="j" for="node" attr.name="j" attr.type="int"/>
+  <key id="g" for="node" attr.name="g" attr.type="int"/>
+  <graph id="astar" edgedefault="directed">
+"#,
+        );
+        for &(Pos(i, j), g) in &self.states {
+            writeln!(
+                s,
+                r#"    <node id="{i},{j}"><data key="i">{i}</data><data key="j">{j}</data><data key="g">{g}</data></node>"#
+            )
+            .unwrap();
+        }
+        for &(Pos(i, j), Pos(i2, j2)) in &self.edges {
+            writeln!(s, r#"    <edge source="{i},{j}" target="{i2},{j2}"/>"#).unwrap();
+        }
+        s.push_str("  </graph>\n</graphml>\n");
+        s
+    }
+}
@@ -1,12 +1,13 @@
 use std::{
     fmt::Display,
     io::{stdout, Write},
+    time::Duration,
 };
 
 use derive_more::AddAssign;
 use pa_types::{Cost, Seq};
 
-use pa_heuristic::HeuristicStats;
+use pa_heuristic::{AlignStats, HeuristicStats};
 
 #[derive(Default, Clone, Copy, AddAssign, Debug)]
 pub struct Timing {
@@ -37,8 +38,22 @@ pub struct AstarStats {
     pub reordered: usize,
     /// Total priority queue shift after pruning.
     pub pq_shifts: usize,
+    /// Number of elements pushed to the priority queue.
+    pub pq_pushes: usize,
+    /// Number of elements popped from the priority queue.
+    pub pq_pops: usize,
+    /// Number of pushes that replaced an outdated element. See `SearchOptions::decrease_key`.
+    pub pq_decreased_keys: usize,
+    /// Number of times the heuristic was re-seeded around a row with many expanded states.
+    pub reseeds: usize,
+    /// Number of times A* gave up and fell back to band doubling. See `astar_with_fallback`.
+    pub fallbacks: usize,
+    /// Number of times a state was popped again to push its deferred successors. See `SearchOptions::partial_expansion`.
+    pub partial_expansions: usize,
     /// Number of states allocated in the DiagonalMap
     pub hashmap_capacity: usize,
+    /// The maximal approximate size in bytes of the map of states and the priority queue.
+    pub max_memory: usize,
 
     pub h: HeuristicStats,
 
@@ -68,6 +83,17 @@ impl AstarStats {
             ..Default::default()
         }
     }
+    /// Convert to the statistics shared by all aligners.
+    pub fn to_align_stats(&self) -> AlignStats {
+        AlignStats {
+            h: self.h,
+            expanded: self.expanded,
+            computed_rows: 0,
+            t_total: Duration::from_secs_f64(self.timing.total),
+            t_precomp: Duration::from_secs_f64(self.timing.precomp),
+        }
+    }
+
     pub fn print(&self) {
         self.print_internal(true);
     }
@@ -1,5 +1,5 @@
 //! Tests that test A*PA with various configurations.
-use crate::AstarPa;
+use crate::{AstarPa, SearchLimits};
 use pa_heuristic::*;
 use pa_test::*;
 use pa_types::*;
@@ -13,7 +13,12 @@ macro_rules! make_test {
 
             fn test_heuristic<H: Heuristic + 'static>(h: H, dt: bool) {
                 test_aligner_up_to(
-                    AstarPa { dt, h, v: NoVis },
+                    AstarPa {
+                        dt,
+                        h,
+                        v: NoVis,
+                        trace_order: None,
+                    },
                     if $slow { 100 } else { usize::MAX },
                 );
             }
@@ -90,6 +95,7 @@ mod dijkstra {
             dt: false,
             h: NoCost,
             v: NoVis,
+            trace_order: None,
         });
     }
     #[test]
@@ -98,6 +104,7 @@ mod dijkstra {
             dt: true,
             h: NoCost,
             v: NoVis,
+            trace_order: None,
         });
     }
 }
@@ -106,6 +113,9 @@ type CSH = DefaultCSH;
 make_test!(sh, SH, false, |h| h);
 make_test!(csh, CSH, false, |h| h);
 make_test!(gch, GCSH, false, |h| h);
+make_test!(max_gap_sh, SH, false, |h| MaxHeuristic::new(GapCost, h));
+make_test!(max_gap_csh, CSH, false, |h| MaxHeuristic::new(GapCost, h));
+make_test!(gch_overlap, GCSH, false, |h| OverlappingSeeds::new(h, 2));
 
 // These tests are very slow
 make_test!(csh_bruteforce_contours, CSH, true, |h: CSH| h
@@ -117,6 +127,255 @@ make_test!(gch_bruteforce_contours, GCSH, true, |h: CSH| h
 make_test!(gch_bruteforce_gcsh, GCSH, true, |h: CSH| h
     .equal_to_bruteforce_gcsh());
 
+mod landmark {
+    use super::*;
+
+    fn test_heuristic(match_config: MatchConfig, dt: bool) {
+        for landmark_distance in [1, 16] {
+            test_aligner(AstarPa {
+                dt,
+                h: LandmarkSH::new(match_config, landmark_distance),
+                v: NoVis,
+                trace_order: None,
+            });
+        }
+    }
+
+    #[test]
+    fn exact() {
+        for k in [3, 5, 6] {
+            test_heuristic(MatchConfig::exact(k), false);
+        }
+    }
+    #[test]
+    fn inexact() {
+        for k in [4, 7, 9] {
+            test_heuristic(MatchConfig::inexact(k), false);
+        }
+    }
+    #[test]
+    fn exact_dt() {
+        for k in [3, 5, 6] {
+            test_heuristic(MatchConfig::exact(k), true);
+        }
+    }
+}
+
+mod weighted {
+    use super::*;
+
+    #[test]
+    fn bounded_suboptimality() {
+        for (n, e) in [(1000, 0.05), (3000, 0.15), (3000, 0.3)] {
+            let (a, b) = pa_generate::uniform_seeded(n, e, 31415);
+            let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+            for w in [1., 1.5, 2.] {
+                let h = GCSH::new(MatchConfig::inexact(9), Pruning::start());
+                let ((cost, cigar), lower_bound) = crate::astarpa_weighted(&a, &b, h, w);
+                assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+                assert!(
+                    d <= cost && cost as f32 <= w * d as f32,
+                    "d={d} cost={cost} w={w}"
+                );
+                assert!(lower_bound <= d, "lower_bound={lower_bound} d={d} w={w}");
+                if w == 1. {
+                    assert_eq!(cost, d);
+                }
+            }
+        }
+    }
+}
+
+mod fallback {
+    use super::*;
+
+    #[test]
+    fn divergent() {
+        for (n, e) in [(1000, 0.01), (2000, 0.3)] {
+            let (a, b) = pa_generate::uniform_seeded(n, e, 31415);
+            let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+            let h = GCSH::new(MatchConfig::exact(10), Pruning::start());
+            let limits = SearchLimits {
+                max_expanded_per_column: Some(10),
+                max_memory: None,
+            };
+            let ((cost, cigar), stats) = crate::astar_with_fallback(&a, &b, &h, &NoVis, limits);
+            assert_eq!(cost, d);
+            assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+            // With exact matches, A* only finishes on the low-divergence input.
+            assert_eq!(stats.fallbacks, (e > 0.1) as usize);
+        }
+    }
+
+    #[test]
+    fn memory() {
+        let (a, b) = pa_generate::uniform_seeded(1000, 0.01, 31415);
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        let h = GCSH::new(MatchConfig::exact(10), Pruning::start());
+        for max_memory in [1 << 10, 1 << 30] {
+            let limits = SearchLimits {
+                max_expanded_per_column: None,
+                max_memory: Some(max_memory),
+            };
+            let ((cost, _), stats) = crate::astar_with_fallback(&a, &b, &h, &NoVis, limits);
+            assert_eq!(cost, d);
+            assert_eq!(stats.fallbacks, (max_memory < 1 << 20) as usize);
+        }
+    }
+}
+
+mod partial_expansion {
+    use super::*;
+    use crate::SearchOptions;
+
+    #[test]
+    fn same_distance() {
+        let options = SearchOptions {
+            partial_expansion: true,
+            ..Default::default()
+        };
+        for (n, e) in [(500, 0.05), (1000, 0.2)] {
+            let (a, b) = pa_generate::uniform_seeded(n, e, 31415);
+            let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+            for prune in [Prune::None, Prune::Both] {
+                let h = GCSH::new(MatchConfig::inexact(9), Pruning::new(prune));
+                let ((cost, cigar), stats) = crate::astar_with_options(&a, &b, &h, &NoVis, options);
+                assert_eq!(cost, d);
+                assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+                assert!(stats.partial_expansions > 0);
+            }
+        }
+    }
+}
+
+mod adaptive_reseeding {
+    use super::*;
+    use crate::SearchOptions;
+
+    #[test]
+    fn same_distance() {
+        let options = SearchOptions {
+            adaptive_reseeding: true,
+            ..Default::default()
+        };
+        let (a, b) = pa_generate::uniform_seeded(2000, 0.2, 31415);
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        let h = CSH::new(MatchConfig::exact(10), Pruning::start());
+        for dt in [false, true] {
+            let ((cost, cigar), stats) = if dt {
+                crate::astar_dt_with_options(&a, &b, &h, &NoVis, options)
+            } else {
+                crate::astar_with_options(&a, &b, &h, &NoVis, options)
+            };
+            assert_eq!(cost, d);
+            assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+            // `h` collapses at this divergence, so some rows are re-seeded.
+            // DT expands too few states per row to be sure of this.
+            if !dt {
+                assert!(stats.reseeds > 0);
+            }
+        }
+    }
+}
+
+mod decrease_key {
+    use super::*;
+    use crate::SearchOptions;
+
+    #[test]
+    fn same_distance() {
+        let options = SearchOptions {
+            decrease_key: true,
+            ..Default::default()
+        };
+        let (a, b) = pa_generate::uniform_seeded(2000, 0.2, 31415);
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        let h = GCSH::new(MatchConfig::exact(10), Pruning::start());
+        let ((cost, cigar), stats) = crate::astar_with_options(&a, &b, &h, &NoVis, options);
+        assert_eq!(cost, d);
+        assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+        assert!(stats.pq_decreased_keys > 0);
+        let ((cost, _), stats) = crate::astar(&a, &b, &h, &NoVis);
+        assert_eq!(cost, d);
+        assert_eq!(stats.pq_decreased_keys, 0);
+    }
+}
+
+mod ida {
+    use super::*;
+
+    #[test]
+    fn small() {
+        for (n, e) in [(50, 0.0), (100, 0.05), (200, 0.02)] {
+            let (a, b) = pa_generate::uniform_seeded(n, e, 31415);
+            let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+            let h = GCSH::new(MatchConfig::inexact(5), Pruning::disabled());
+            let ((cost, cigar), _stats) = crate::ida_star(&a, &b, &h);
+            assert_eq!(cost, d);
+            assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+        }
+    }
+
+    /// With an accurate heuristic, long low-divergence inputs are fine.
+    #[test]
+    fn long() {
+        for (n, e) in [(10_000, 0.0), (10_000, 0.005), (5_000, 0.01)] {
+            let (a, b) = pa_generate::uniform_seeded(n, e, 31415);
+            let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+            let h = GCSH::new(MatchConfig::inexact(10), Pruning::disabled());
+            let ((cost, cigar), _stats) = crate::ida_star(&a, &b, &h);
+            assert_eq!(cost, d);
+            assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+        }
+    }
+
+    /// Greedy extension takes whole seeds at a time, so identical sequences
+    /// only expand the seed boundaries and the states just after them.
+    #[test]
+    fn greedy_extension() {
+        let n = 10_000;
+        let k = 10;
+        let (a, _) = pa_generate::uniform_seeded(n, 0.0, 31415);
+        let h = GCSH::new(MatchConfig::inexact(k), Pruning::disabled());
+        let ((cost, _), stats) = crate::ida_star(&a, &a, &h);
+        assert_eq!(cost, 0);
+        assert!(
+            stats.expanded <= 2 * n / k as usize + 2,
+            "{}",
+            stats.expanded
+        );
+        assert!(stats.extended >= n - 2 * n / k as usize - 2);
+    }
+}
+
+mod search_tree {
+    use super::*;
+    use itertools::Itertools;
+
+    #[test]
+    fn dag() {
+        let (a, b) = pa_generate::uniform_seeded(300, 0.1, 31415);
+        let h = GCSH::new(MatchConfig::exact(5), Pruning::start());
+        let ((cost, cigar), stats, tree) = crate::astar_with_tree(&a, &b, &h);
+        assert_eq!(cost, triple_accel::levenshtein_exp(&a, &b) as Cost);
+        assert!(tree.states.len() <= stats.explored);
+        let g = |p: Pos| tree.states.iter().find(|&&(q, _)| q == p).map(|&(_, g)| g);
+        assert_eq!(g(Pos(0, 0)), Some(0));
+        assert_eq!(g(Pos::target(&a, &b)), Some(cost));
+        // The stored states on the path are connected by edges.
+        let path = cigar.to_path().into_iter().filter(|&p| g(p).is_some());
+        for (p, q) in path.tuple_windows() {
+            assert!(tree.edges.contains(&(p, q)), "No edge {p} -> {q}");
+        }
+
+        assert_eq!(tree.to_dot().matches("->").count(), tree.edges.len());
+        assert_eq!(
+            tree.to_graphml().matches("<node ").count(),
+            tree.states.len()
+        );
+    }
+}
+
 mod edge_cases {
     use super::*;
 
@@ -129,6 +388,7 @@ mod edge_cases {
             dt: false,
             h: GCSH::new(MatchConfig::new(3, 2), Pruning::both()),
             v: NoVis,
+            trace_order: None,
         };
 
         let a = "CCCGTCGTCCCTCAAACTTGGAACCCCATCGCAAATCACCCCACAGGTAACGTCATAACTACCGCATGGTACGGTACCCCTTCTGCGATAGAGATGGTAGTAGCCGATAGGCCACCCTGGGAACACTATGTCACCCTGGTGGTAACCGTCGGGTCAGAAATAGGAGAACATACGGTGGACCGCTAA".as_bytes();
@@ -158,6 +418,7 @@ mod edge_cases {
             dt: true,
             h: GCSH::new(MatchConfig::new(3, 2), Pruning::both()),
             v: NoVis,
+            trace_order: None,
         };
 
         let a = "GCCGCGCGCGCAGCCGCGCGCGCGCGCGCGCCGG".as_bytes();
@@ -169,3 +430,40 @@ mod edge_cases {
         test_aligner_on_input(a, b, aligner, "");
     }
 }
+
+mod trace_order {
+    use super::*;
+
+    fn aligner(dt: bool, order: [TraceEdge; 3]) -> AstarPa<NoVis, GCSH> {
+        AstarPa {
+            dt,
+            h: GCSH::new(MatchConfig::exact(4), Pruning::both()),
+            v: NoVis,
+            trace_order: Some(TraceOrder(order)),
+        }
+    }
+
+    #[test]
+    fn all_orders() {
+        use TraceEdge::*;
+        for order in [[Sub, Ins, Del], [Ins, Del, Sub], [Del, Sub, Ins]] {
+            for dt in [false, true] {
+                test_aligner_up_to(aligner(dt, order), 100);
+            }
+        }
+    }
+
+    #[test]
+    fn order_breaks_ties() {
+        use TraceEdge::*;
+        // Two substitutions and a deletion + insertion both cost 2.
+        let (a, b) = (b"AC", b"CA");
+        for dt in [false, true] {
+            let (sub_cost, sub) = aligner(dt, [Sub, Ins, Del]).align(a, b).0;
+            let (del_cost, del) = aligner(dt, [Del, Ins, Sub]).align(a, b).0;
+            assert_eq!(sub_cost, 2);
+            assert_eq!(del_cost, 2);
+            assert_ne!(sub, del, "dt {dt}");
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! All-vs-all pairwise alignment of a set of sequences.
+use super::*;
+use rayon::prelude::*;
+
+/// Condensed upper-triangular matrix of pairwise costs.
+///
+/// Only pairs `i < j` are stored, row by row.
+/// A value of `None` means that the cost exceeds the threshold.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairwiseCosts {
+    n: usize,
+    costs: Vec<Option<Cost>>,
+}
+
+impl PairwiseCosts {
+    /// The number of sequences.
+    pub fn len(&self) -> usize {
+        self.n
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n == 0
+    }
+
+    /// The index of pair `(i, j)` with `i < j` in the condensed vector.
+    fn index(&self, i: usize, j: usize) -> usize {
+        assert!(i < j && j < self.n);
+        i * (2 * self.n - i - 1) / 2 + (j - i - 1)
+    }
+
+    /// The cost of aligning sequences `i` and `j`, or `None` if it exceeds the threshold.
+    pub fn get(&self, i: usize, j: usize) -> Option<Cost> {
+        match i.cmp(&j) {
+            std::cmp::Ordering::Less => self.costs[self.index(i, j)],
+            std::cmp::Ordering::Equal => {
+                assert!(
+                    i < self.n,
+                    "Index {i} out of bounds for {} sequences.",
+                    self.n
+                );
+                Some(0)
+            }
+            std::cmp::Ordering::Greater => self.costs[self.index(j, i)],
+        }
+    }
+
+    /// The condensed vector of costs for pairs `(0,1), (0,2), ..., (1,2), ...`.
+    pub fn condensed(&self) -> &[Option<Cost>] {
+        &self.costs
+    }
+}
+
+/// Compute the cost of all pairs `i < j` of `seqs` in parallel.
+///
+/// Rows of the upper triangle are distributed over a work-stealing thread pool.
+/// When `max_cost` is set, each alignment uses a single band of that size and
+/// pairs with larger cost are reported as `None`. Otherwise, pairs exceeding
+/// the `max_divergence` of `params` are reported as `None`, and any other
+/// `AlignError`, e.g. `TooLong`, is returned.
+pub fn align_all_pairs(
+    seqs: &[Seq],
+    params: &AstarPa2Params,
+    max_cost: Option<Cost>,
+) -> Result<PairwiseCosts, AlignError> {
+    let n = seqs.len();
+    let costs = (0..n)
+        .into_par_iter()
+        .flat_map_iter(|i| (i + 1..n).map(move |j| (i, j)))
+        .map_init(
+            || params.make_aligner(false),
+            |aligner, (i, j)| match max_cost {
+                Some(max_cost) => Ok(aligner
+                    .cost_for_bounded_dist(seqs[i], seqs[j], max_cost)
+                    .filter(|&c| c <= max_cost)),
+                None => match aligner.try_cost(seqs[i], seqs[j]) {
+                    Ok(cost) => Ok(Some(cost)),
+                    Err(AlignError::TooDivergent { .. }) => Ok(None),
+                    Err(e) => Err(e),
+                },
+            },
+        )
+        .collect::<Result<_, _>>()?;
+    Ok(PairwiseCosts { n, costs })
+}
@@ -0,0 +1,104 @@
+//! Automatic selection of the domain and heuristic, based on the input.
+use super::*;
+use pa_heuristic::{HeuristicParams, HeuristicType, LengthConfig, MatchConfig, Prune};
+
+/// Inputs with at most this many cells are aligned with a plain full DP.
+const MAX_FULL_CELLS: usize = 1 << 16;
+
+/// Below this estimated divergence, the plain seed heuristic is accurate enough.
+const MAX_SH_DIVERGENCE: f32 = 0.02;
+
+/// Below this estimated divergence, chaining is needed, but gap costs are not.
+const MAX_CSH_DIVERGENCE: f32 = 0.06;
+
+impl AstarPa2Params {
+    /// Choose parameters for aligning `a` and `b`:
+    /// - Small inputs use the full DP matrix, without doubling.
+    /// - Otherwise, `k` and `r` are chosen by `MatchConfig::auto` for the
+    ///   estimated divergence `e`. When this gives `k < log_4(n)`, each seed
+    ///   is expected to have spurious matches, which use up all the potential
+    ///   of `h(0,0)`. Then GapGap with band doubling and no heuristic is used
+    ///   instead.
+    /// - Otherwise, A*PA2-full is used, with SH for `e < 2%`, CSH for
+    ///   `e < 6%`, and GCSH beyond that.
+    pub fn auto(a: Seq, b: Seq) -> Self {
+        if (a.len() + 1) * (b.len() + 1) <= MAX_FULL_CELLS {
+            return Self {
+                name: "auto-full".into(),
+                domain: Domain::Full,
+                doubling: DoublingType::None,
+                ..Self::simple()
+            };
+        }
+
+        let e = estimate_divergence(a, b);
+        let MatchConfig { length, r, .. } = MatchConfig::auto(a, b, e);
+        let LengthConfig::Fixed(k) = length else {
+            unreachable!()
+        };
+        let n = max(a.len(), b.len()) as f32;
+        if (k as f32) < n.log(4.) {
+            let simple = Self::simple();
+            return Self {
+                name: "auto-gapgap".into(),
+                domain: Domain::GapGap,
+                heuristic: HeuristicParams {
+                    heuristic: HeuristicType::None,
+                    ..simple.heuristic
+                },
+                doubling: DoublingType::band_doubling(),
+                ..simple
+            };
+        }
+
+        let (name, heuristic) = if e < MAX_SH_DIVERGENCE {
+            ("auto-sh", HeuristicType::SH)
+        } else if e < MAX_CSH_DIVERGENCE {
+            ("auto-csh", HeuristicType::CSH)
+        } else {
+            ("auto-gcsh", HeuristicType::GCSH)
+        };
+        let full = Self::full();
+        Self {
+            name: name.into(),
+            heuristic: HeuristicParams {
+                heuristic,
+                prune: Prune::Start,
+                k,
+                r,
+                ..full.heuristic
+            },
+            ..full
+        }
+    }
+}
+
+/// An aligner that chooses its parameters for each pair of sequences using `AstarPa2Params::auto`.
+#[derive(Debug, Clone, Copy)]
+pub struct AstarPa2Auto {
+    /// Whether to return a trace.
+    pub trace: bool,
+}
+
+impl Aligner for AstarPa2Auto {
+    fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>) {
+        AstarPa2Params::auto(a, b)
+            .make_aligner(self.trace)
+            .align(a, b)
+    }
+}
+
+impl AffineAligner for AstarPa2Auto {
+    fn align_affine(&mut self, a: Seq, b: Seq) -> (Cost, Option<AffineCigar>) {
+        let (cost, cigar) = self.align(a, b);
+        (cost, cigar.as_ref().map(AffineCigar::from))
+    }
+}
+
+impl ResultAligner for AstarPa2Auto {
+    fn align_result(&mut self, a: Seq, b: Seq) -> AlignResult {
+        AstarPa2Params::auto(a, b)
+            .make_aligner(self.trace)
+            .align_result(a, b)
+    }
+}
@@ -1,23 +1,37 @@
 use super::*;
 use std::cmp::*;
+use std::ops::ControlFlow;
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize)]
 pub enum DoublingStart {
     Zero,
     Gap,
     H0,
+    /// The maximum of the gap cost and `h(0,0)`.
+    Max,
+    /// A user-provided initial bound, e.g. from a previous alignment.
+    Fixed(Cost),
+    /// The cost implied by `estimate_divergence`, but at least `h(0,0)`.
+    Estimate,
 }
 
 impl DoublingStart {
     /// Return the start value and initial increment based on DoublingStart.
     pub fn initial_values(&self, a: &[u8], b: &[u8], h0: Cost) -> (i32, i32) {
-        let (start_f, start_increment) = match self {
+        let gap = || pa_affine_types::AffineCost::unit().gap_cost(Pos(0, 0), Pos::target(a, b));
+        let (start_f, start_increment) = match *self {
             DoublingStart::Zero => (0, 1),
             DoublingStart::Gap => {
-                let x = pa_affine_types::AffineCost::unit().gap_cost(Pos(0, 0), Pos::target(a, b));
+                let x = gap();
                 (x, x)
             }
             DoublingStart::H0 => (h0, 1),
+            DoublingStart::Max => (max(gap(), h0), 1),
+            DoublingStart::Fixed(f) => (f, 1),
+            DoublingStart::Estimate => {
+                let e = pa_heuristic::estimate_divergence(a, b);
+                (max((e * max(a.len(), b.len()) as f32) as Cost, h0), 1)
+            }
         };
         (start_f, start_increment)
     }
@@ -35,6 +49,9 @@ pub enum DoublingType {
         delta: f32,
     },
     LocalDoubling,
+    /// First compute an upper bound `U` using a cheap greedy alignment, and
+    /// then do a single bounded run with `f_max = U`.
+    TwoPhase,
     /// For visualization purposes only.
     BandDoublingStartIncrement {
         start: DoublingStart,
@@ -51,6 +68,25 @@ impl DoublingType {
             factor: 2.0,
         }
     }
+
+    /// The growth factor of band doubling, if applicable.
+    pub fn factor(&self) -> Option<f32> {
+        match *self {
+            DoublingType::BandDoubling { factor, .. }
+            | DoublingType::BandDoublingStartIncrement { factor, .. } => Some(factor),
+            _ => None,
+        }
+    }
+
+    /// Set the growth factor of band doubling. Does nothing for other types.
+    pub fn with_factor(mut self, new_factor: f32) -> Self {
+        match &mut self {
+            DoublingType::BandDoubling { factor, .. }
+            | DoublingType::BandDoublingStartIncrement { factor, .. } => *factor = new_factor,
+            _ => {}
+        }
+        self
+    }
 }
 
 impl Default for DoublingType {
@@ -62,6 +98,44 @@ impl Default for DoublingType {
     }
 }
 
+/// The cost of a greedy alignment of `a` and `b`, as an upper bound on the edit distance.
+///
+/// Matches are extended greedily. On a mismatch, the substitution, insertion or
+/// deletion that is followed by the longest run of matches is taken.
+pub fn greedy_upper_bound(a: Seq, b: Seq) -> Cost {
+    const LOOKAHEAD: usize = 16;
+    let run = |i: usize, j: usize| {
+        a[i..]
+            .iter()
+            .zip(&b[j..])
+            .take(LOOKAHEAD)
+            .take_while(|(ca, cb)| ca == cb)
+            .count()
+    };
+    let (mut i, mut j) = (0, 0);
+    let mut cost = 0;
+    while i < a.len() && j < b.len() {
+        if a[i] == b[j] {
+            i += 1;
+            j += 1;
+            continue;
+        }
+        cost += 1;
+        let sub = run(i + 1, j + 1);
+        let ins = run(i, j + 1);
+        let del = run(i + 1, j);
+        if sub >= ins && sub >= del {
+            i += 1;
+            j += 1;
+        } else if ins >= del {
+            j += 1;
+        } else {
+            i += 1;
+        }
+    }
+    cost + (a.len() - i + b.len() - j) as Cost
+}
+
 /// Find the cost using exponential search based on `f`.
 ///
 /// Tries values `offset + s0 * f^i`.
@@ -101,29 +175,122 @@ pub fn exponential_search<T>(
     offset: Cost,
     s0: Cost,
     factor: f32,
-    mut f: impl FnMut(Cost) -> Option<(Cost, T)>,
+    f: impl FnMut(Cost) -> Option<(Cost, T)>,
 ) -> (Cost, T) {
-    let mut last_s = -1;
-    let mut s = offset + s0;
-    let mut maxs = Cost::MAX;
-    // TODO: Fix the potential infinite loop here.
-    //
-    // Sanity checks:
-    // - Once the answer is found, this should be larger than all previous thresholds.
-    // - Once a value for maxs has been found, all subsequent larger values of s
-    //   should return a value that is smaller.
+    bounded_exponential_search(offset, s0, factor, Cost::MAX, f)
+        .unwrap_or_else(|_| unreachable!("Unbounded search can not fail."))
+}
+
+/// Same as `exponential_search`, but gives up once `s_max` has been tried.
+/// In that case, returns the lower bound `s_max + 1` on the cost.
+pub fn bounded_exponential_search<T>(
+    offset: Cost,
+    s0: Cost,
+    factor: f32,
+    s_max: Cost,
+    f: impl FnMut(Cost) -> Option<(Cost, T)>,
+) -> Result<(Cost, T), Cost> {
+    abortable_exponential_search(offset, s0, factor, s_max, || false, f).map_err(|(lb, _)| lb)
+}
+
+/// Same as `bounded_exponential_search`, but additionally stops before trying
+/// the next value of `s` when `abort` returns true.
+///
+/// On failure, returns a lower bound on the cost, and the best upper bound
+/// found so far, if any.
+pub fn abortable_exponential_search<T>(
+    offset: Cost,
+    s0: Cost,
+    factor: f32,
+    s_max: Cost,
+    mut abort: impl FnMut() -> bool,
+    mut f: impl FnMut(Cost) -> Option<(Cost, T)>,
+) -> Result<(Cost, T), (Cost, Option<Cost>)> {
+    let mut search = ExponentialSearch::new(offset, s0, factor, s_max);
     loop {
-        if let Some((cost, t)) = f(s) {
+        if abort() {
+            return Err(search.bounds());
+        }
+        let r = f(search.s());
+        match search.step(r.as_ref().map(|&(cost, _)| cost)) {
+            ControlFlow::Continue(()) => {}
+            ControlFlow::Break(Ok(_)) => return Ok(r.unwrap()),
+            ControlFlow::Break(Err(bounds)) => return Err(bounds),
+        }
+    }
+}
+
+/// The state of an exponential search between two tries, so that it can be
+/// interrupted and resumed. See `abortable_exponential_search`.
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ExponentialSearch {
+    offset: Cost,
+    factor: f32,
+    s_max: Cost,
+    /// The last value of `s` that was tried, or `-1`.
+    last_s: Cost,
+    /// The next value of `s` to try.
+    s: Cost,
+    /// The lowest cost found so far, or `Cost::MAX`.
+    maxs: Cost,
+}
+
+impl ExponentialSearch {
+    /// The first `s` to try is `offset + s0`, but at most `s_max`.
+    pub fn new(offset: Cost, s0: Cost, factor: f32, s_max: Cost) -> Self {
+        Self {
+            offset,
+            factor,
+            s_max,
+            last_s: -1,
+            s: min(offset.saturating_add(s0), s_max),
+            maxs: Cost::MAX,
+        }
+    }
+
+    /// The next value of `s` to try.
+    pub fn s(&self) -> Cost {
+        self.s
+    }
+
+    /// A lower bound on the cost, and the best upper bound found so far, if any.
+    pub fn bounds(&self) -> (Cost, Option<Cost>) {
+        (
+            self.last_s + 1,
+            (self.maxs != Cost::MAX).then_some(self.maxs),
+        )
+    }
+
+    /// Process the cost found when trying `s`, if any.
+    ///
+    /// Breaks with the cost when it is at most `s`, or with the bounds when
+    /// `s_max` has been tried. Otherwise, continues with the next `s`.
+    pub fn step(&mut self, cost: Option<Cost>) -> ControlFlow<Result<Cost, (Cost, Option<Cost>)>> {
+        let Self {
+            offset,
+            factor,
+            s_max,
+            last_s,
+            s,
+            maxs,
+        } = *self;
+        // TODO: Fix the potential infinite loop here.
+        //
+        // Sanity checks:
+        // - Once the answer is found, this should be larger than all previous thresholds.
+        // - Once a value for maxs has been found, all subsequent larger values of s
+        //   should return a value that is smaller.
+        if let Some(cost) = cost {
             assert!(
                 cost <= maxs,
                 "A solution {maxs} was found for a previous s<={last_s}, but s={s} gives {cost}"
             );
             if cost <= s {
                 assert!(cost > last_s, "Cost {cost} was found at s {s} but should already have been found at last_s {last_s}");
-                return (cost, t);
+                return ControlFlow::Break(Ok(cost));
             } else {
                 // If some value was returned this is an upper bound on the answer.
-                maxs = min(maxs, cost);
+                self.maxs = min(maxs, cost);
             }
         } else {
             assert!(
@@ -131,9 +298,15 @@ pub fn exponential_search<T>(
                 "A solution {maxs} was found for a previous s<={last_s}, but not for current s={s}"
             );
         }
-        last_s = s;
-        s = max((factor * (s - offset) as f32).ceil() as Cost, 1) + offset;
-        s = min(s, maxs);
+        if s >= s_max {
+            return ControlFlow::Break(Err((s.saturating_add(1), self.bounds().1)));
+        }
+        self.last_s = s;
+        let mut s = max((factor * (s - offset) as f32).ceil() as Cost, 1) + offset;
+        s = min(s, self.maxs);
+        s = min(s, s_max);
+        self.s = s;
+        ControlFlow::Continue(())
     }
 }
 
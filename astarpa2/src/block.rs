@@ -28,6 +28,12 @@ pub struct Block {
 
     /// Horizontal differences for row `j_h`, which will be the end of the fixed j_range if set.
     pub j_h: Option<I>,
+
+    /// For blocks recomputed during traceback, the value at the start of each
+    /// word of `v`, and the value at its end. This makes `index` constant time
+    /// for the horizontal and diagonal deltas in `parent`.
+    /// Empty for blocks computed in the forward direction.
+    pub word_vals: Vec<Cost>,
 }
 
 impl Default for Block {
@@ -42,6 +48,7 @@ impl Default for Block {
             top_val: Cost::MAX,
             bot_val: Cost::MAX,
             j_h: None,
+            word_vals: vec![],
         }
     }
 }
@@ -61,6 +68,7 @@ impl Block {
             top_val: 0,
             bot_val: j_range.exclusive_len(),
             j_h: None,
+            word_vals: vec![],
         }
     }
 
@@ -97,6 +105,15 @@ impl Block {
         if j > j_range.1 {
             return self.bot_val + (j - j_range.1) as Cost;
         }
+        if !self.word_vals.is_empty() {
+            let idx = (j - self.offset) as usize;
+            let val = self.word_vals[idx / W];
+            return if idx % W == 0 {
+                val
+            } else {
+                val + self.v[idx / W].value_of_prefix((idx % W) as I) as Cost
+            };
+        }
         if j - j_range.0 < j_range.1 - j {
             // go from top
             let mut val = self.top_val;
@@ -144,6 +161,18 @@ impl Block {
         Some(((self.v[idx].p() >> bit) & 1) as Cost - ((self.v[idx].m() >> bit) & 1) as Cost)
     }
 
+    /// Store the value at the start of each word of `v`, for fast `index` calls.
+    pub fn store_word_vals(&mut self) {
+        assert_eq!(self.offset, self.j_range.0);
+        let mut val = self.top_val;
+        self.word_vals.clear();
+        self.word_vals.push(val);
+        for v in &self.v[..self.j_range.exclusive_len() as usize / W] {
+            val += v.value();
+            self.word_vals.push(val);
+        }
+    }
+
     /// Assert that the vertical difference between the top and bottom values is correct.
     pub fn check_top_bot_val(&self) {
         if !DEBUG {
@@ -0,0 +1,104 @@
+//! Adapting the block width to the measured cost of computing blocks, or to
+//! the height of the band.
+//!
+//! Each block has a roughly fixed overhead: computing its `j_range` and range
+//! of fixed states, and pruning matches. The time to fill a block grows with
+//! its width and band, and wider blocks compute more states, since their band
+//! is the union of the bands of their columns.
+//! Small bands (as for A*) are cheap to fill, so that the overhead dominates
+//! and wider blocks are better, while wide bands (for divergent sequences)
+//! benefit from narrower blocks.
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// The width is never decreased below this.
+const MIN_WIDTH: I = 32;
+/// The width is never increased above this.
+const MAX_WIDTH: I = 4096;
+
+/// Double the width when the overhead exceeds this fraction of the fill time.
+const GROW_RATIO: u32 = 4;
+/// Halve the width when the overhead is below this fraction of the fill time.
+const SHRINK_RATIO: u32 = 32;
+
+/// How the width of each block is chosen.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BlockWidthMode {
+    /// All blocks have width `block_width`.
+    #[default]
+    Fixed,
+    /// `block_width` is only the width of the first block, and is adjusted
+    /// during the alignment based on the measured time to fill blocks versus
+    /// the overhead between blocks.
+    Adaptive,
+    /// The width of each block is chosen from the height of the band before
+    /// it: narrow bands get wide blocks and wide bands narrow ones, with
+    /// `block_width` the width for a band of height `block_width`.
+    Band,
+}
+
+/// The blocks chosen when `AstarPa2::block_width_mode` is not `Fixed`.
+#[derive(Debug)]
+pub(crate) struct BlockWidths {
+    /// The end column of each block chosen so far. Later band doubling
+    /// iterations use the same blocks, so that they can be reused.
+    ends: Vec<I>,
+    /// The width of the next new block.
+    width: I,
+    /// For band-based widths, the number of states each block should cover.
+    target_states: Option<I>,
+}
+
+impl BlockWidths {
+    /// Widths adjusted by the measured time per block.
+    pub(crate) fn new(width: I) -> Self {
+        Self {
+            ends: vec![],
+            width,
+            target_states: None,
+        }
+    }
+
+    /// Widths chosen from the band height at the start of each block, such
+    /// that a band of height `width` gives blocks of width `width`.
+    pub(crate) fn from_band(width: I) -> Self {
+        Self {
+            ends: vec![],
+            width,
+            target_states: Some(width * width),
+        }
+    }
+
+    /// The `i_range` of block `idx`, which starts at column `i`.
+    /// For band-based widths, `band` is the height of the preceding block.
+    pub(crate) fn i_range(&mut self, idx: usize, i: I, len_a: I, band: I) -> IRange {
+        if let Some(&end) = self.ends.get(idx) {
+            return IRange(i, end);
+        }
+        assert_eq!(idx, self.ends.len());
+        if let Some(target) = self.target_states {
+            // Round down to a power of two, to avoid many slightly different widths.
+            let width = (target / max(band, 1)).clamp(MIN_WIDTH, MAX_WIDTH);
+            self.width = 1 << width.ilog2();
+        }
+        let end = min(i + self.width, len_a);
+        self.ends.push(end);
+        IRange(i, end)
+    }
+
+    /// Update the width of new blocks, given the time to fill the last block
+    /// and the overhead of the rest of its iteration.
+    pub(crate) fn update(&mut self, fill: Duration, overhead: Duration) {
+        if self.target_states.is_some() {
+            return;
+        }
+        if overhead * GROW_RATIO > fill {
+            self.width = max(self.width, min(2 * self.width, MAX_WIDTH));
+        } else if overhead * SHRINK_RATIO < fill {
+            self.width = min(self.width, max(self.width / 2, MIN_WIDTH));
+        }
+    }
+}
@@ -1,8 +1,5 @@
 //!
 //! TODO: [fill_block] use a single allocation for all blocks in the block. Takes up to 2% of time.
-//! TODO: [fill_block] store horizontal deltas in blocks, so that `parent` is more
-//!       efficient and doesn't have to use relatively slow `block.index` operations.
-//!       (NOTE though that this doesn't actually seem that bad in practice.)
 //! TODO: Separate strong types for row `I` and 'block-row' `I*64`.
 
 pub mod trace;
@@ -11,14 +8,17 @@ use std::{
     cmp::{max, min},
     ops::{Index, IndexMut},
     ptr::read_unaligned,
+    sync::Arc,
     time::Duration,
 };
 
 use itertools::{izip, Itertools};
 use pa_bitpacking::{BitProfile, HEncoding, Profile, B, V};
+use pa_heuristic::{TraceEdge, TraceOrder};
 use pa_types::*;
 use pa_vis::VisualizerInstance;
 use serde::{Deserialize, Serialize};
+use tracing::{debug, trace};
 
 use super::*;
 use crate::block::*;
@@ -57,6 +57,12 @@ pub struct BlockParams {
     /// X-drop for dt-trace.
     #[serde(default)]
     pub fr_drop: I,
+
+    /// The preferred order of equal-cost parents during traceback.
+    /// `None` prefers insertions, then deletions, then substitutions, and
+    /// leaves ties in the dt-trace to the order of expansion.
+    #[serde(default)]
+    pub trace_order: Option<TraceOrder>,
 }
 
 impl Default for BlockParams {
@@ -69,6 +75,7 @@ impl Default for BlockParams {
             dt_trace: false,
             max_g: 40,
             fr_drop: 20,
+            trace_order: None,
         }
     }
 }
@@ -89,7 +96,7 @@ pub struct Blocks {
     params: BlockParams,
     trace: bool,
     a: Vec<PA>,
-    b: Vec<PA>,
+    b: Arc<[PB]>,
 
     // Pos.
     /// The list of blocks.
@@ -109,7 +116,12 @@ pub struct Blocks {
 
 impl BlockParams {
     pub fn new<'a>(&self, trace: bool, a: Seq<'a>, b: Seq<'a>) -> Blocks {
-        let (a, b) = BitProfile::build(a, b);
+        self.new_with_b_profile(trace, a, BitProfile::build_b(b).into())
+    }
+
+    /// Same as `new`, but shares a precomputed `BitProfile` of `b`.
+    pub fn new_with_b_profile<'a>(&self, trace: bool, a: Seq<'a>, b: Arc<[PB]>) -> Blocks {
+        let a = BitProfile::build_a(a);
         Blocks {
             params: *self,
             blocks: vec![],
@@ -169,6 +181,7 @@ impl Blocks {
                 top_val: 0,
                 bot_val: initial_j_range.1,
                 j_h: None,
+                word_vals: vec![],
             }
         };
         if self.blocks.is_empty() {
@@ -226,7 +239,7 @@ impl Blocks {
         }
 
         if DEBUG {
-            eprintln!("Compute block {:?} {:?}", i_range, j_range);
+            trace!("Compute block {:?} {:?}", i_range, j_range);
         }
 
         if self.trace && !self.params.sparse {
@@ -246,7 +259,7 @@ impl Blocks {
         let prev_top_val = self.last_block().index(j_range.0);
         let prev_bot_val = self.last_block().index(j_range.1);
         if DEBUG {
-            eprintln!("Prev top/bot: {prev_top_val}/{prev_bot_val}");
+            trace!("Prev top/bot: {prev_top_val}/{prev_bot_val}");
         }
 
         if !self.trace && !self.params.incremental_doubling {
@@ -299,6 +312,7 @@ impl Blocks {
         // Copy settings, but not the vector.
         let old_block = Block {
             v: vec![],
+            word_vals: vec![],
             ..*next_block
         };
         // Overwrite the nxt
@@ -314,6 +328,7 @@ impl Blocks {
             bot_val: prev_bot_val,
             // This will be set later based on whether incremental_doubling is enabled.
             j_h: None,
+            word_vals: vec![],
         };
 
         // If no incremental doubling or no fixed_j_range was set, just compute everything.
@@ -386,7 +401,7 @@ impl Blocks {
             let v_range_2 = JRange(new_j_h, j_range.1).assert_rounded().v_range();
             assert!(v_range_2.start <= v_range_2.end);
             if DEBUG {
-                eprintln!("INIT1: {:?}", next_block.v);
+                trace!("INIT1: {:?}", next_block.v);
             }
 
             // Compute the part before the fixed range without using input/output horizontal deltas.
@@ -473,7 +488,7 @@ impl Blocks {
         if (cfg!(test) || DEBUG) && !cfg!(feature = "example") {
             if let Some(old_j_h) = old_block.j_h {
                 // Check whether the fixed row has correct values.
-                eprintln!("DEBUG MODE: RECOMPUTE OLD FIXED H");
+                debug!("DEBUG MODE: RECOMPUTE OLD FIXED H");
                 let next_block_2 = &mut next_block.clone();
                 init_v_with_overlap(prev_block, next_block_2);
                 let h2 = self.h[i_slice.clone()].to_vec();
@@ -496,7 +511,7 @@ impl Blocks {
             }
             {
                 // Check whether the fixed row has correct values.
-                eprintln!("DEBUG MODE: RECOMPUTE UPDATED FIXED H");
+                debug!("DEBUG MODE: RECOMPUTE UPDATED FIXED H");
                 let next_block_2 = &mut next_block.clone();
                 init_v_with_overlap(prev_block, next_block_2);
                 let h2 = self.h[i_slice.clone()].to_vec();
@@ -516,10 +531,10 @@ impl Blocks {
                 );
                 assert_eq!(h2, self.h[i_slice]);
             }
-            eprintln!("DEBUG MODE: Recompute without incremental doubling");
+            debug!("DEBUG MODE: Recompute without incremental doubling");
             let next_block_2 = &mut next_block.clone();
             init_v_with_overlap(prev_block, next_block_2);
-            eprintln!("INIT2: {:?}", next_block_2.v);
+            trace!("INIT2: {:?}", next_block_2.v);
             let bot_diff = compute_block(
                 self.params,
                 &self.a,
@@ -533,11 +548,11 @@ impl Blocks {
                 viz,
             );
             next_block_2.bot_val = prev_bot_val + bot_diff;
-            eprintln!("Bot diff: {bot_diff}");
+            trace!("Bot diff: {bot_diff}");
             assert_eq!(next_block.top_val, next_block_2.top_val);
             assert_eq!(next_block.v, next_block_2.v);
             assert_eq!(next_block.bot_val, next_block_2.bot_val);
-            eprintln!("Check top bot val");
+            trace!("Check top bot val");
             next_block_2.check_top_bot_val();
             next_block.check_top_bot_val();
         }
@@ -598,6 +613,8 @@ impl Blocks {
             // bot_val: prev_block.index(j_range_rounded.1),
             // During traceback, we ignore any stored horizontal deltas.
             j_h: None,
+            // Set after filling.
+            word_vals: vec![],
         };
 
         init_v_with_overlap(prev_block, &mut next_block);
@@ -658,6 +675,7 @@ impl Blocks {
             block.v = vv;
             bot_val += h.value();
             block.bot_val = bot_val;
+            block.store_word_vals();
         }
     }
 }
@@ -704,7 +722,7 @@ fn compute_block(
     // Skipped during traceback.
     if i_range.len() > 1 {
         if DEBUG | cfg!(test) {
-            eprintln!("Compute i {i_range:?} x j {v_range:?} in mode {mode:?}");
+            trace!("Compute i {i_range:?} x j {v_range:?} in mode {mode:?}");
         }
 
         stats.computed_lanes += v_range.len();
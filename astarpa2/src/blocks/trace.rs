@@ -13,6 +13,121 @@ pub struct TraceStats {
     pub t_fill: Duration,
 }
 
+/// Run-length encoded cigar operations, packed into 4 bytes per run: the
+/// operation in the lower 2 bits and the count in the upper 30 bits.
+///
+/// The traceback pushes operations from the end of the alignment to the
+/// start, so runs are stored in reverse order.
+#[derive(Debug, Default, Clone)]
+pub struct PackedCigar {
+    runs: Vec<u32>,
+}
+
+impl PackedCigar {
+    const OP_BITS: u32 = 2;
+    const MAX_CNT: I = (u32::MAX >> Self::OP_BITS) as I;
+
+    fn encode(op: CigarOp, cnt: I) -> u32 {
+        let op = match op {
+            CigarOp::Match => 0,
+            CigarOp::Sub => 1,
+            CigarOp::Ins => 2,
+            CigarOp::Del => 3,
+        };
+        ((cnt as u32) << Self::OP_BITS) | op
+    }
+
+    fn decode(run: u32) -> CigarElem {
+        let op = match run & ((1 << Self::OP_BITS) - 1) {
+            0 => CigarOp::Match,
+            1 => CigarOp::Sub,
+            2 => CigarOp::Ins,
+            _ => CigarOp::Del,
+        };
+        CigarElem {
+            op,
+            cnt: (run >> Self::OP_BITS) as I,
+        }
+    }
+
+    /// Append `elem`, merging it with the last run when possible.
+    pub fn push_elem(&mut self, mut elem: CigarElem) {
+        if let Some(last) = self.runs.last_mut() {
+            let last_elem = Self::decode(*last);
+            if last_elem.op == elem.op && last_elem.cnt < Self::MAX_CNT {
+                let cnt = min(last_elem.cnt + elem.cnt, Self::MAX_CNT);
+                *last = Self::encode(elem.op, cnt);
+                elem.cnt -= cnt - last_elem.cnt;
+            }
+        }
+        while elem.cnt > 0 {
+            let cnt = min(elem.cnt, Self::MAX_CNT);
+            self.runs.push(Self::encode(elem.op, cnt));
+            elem.cnt -= cnt;
+        }
+    }
+
+    /// The number of runs.
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Remove all runs and return them in the order they were pushed.
+    pub fn drain(&mut self) -> impl Iterator<Item = CigarElem> + '_ {
+        self.runs.drain(..).map(Self::decode)
+    }
+
+    /// The runs in reverse order of pushing, i.e. in the forward direction
+    /// of a traced alignment.
+    pub fn iter_rev(&self) -> impl Iterator<Item = CigarElem> + '_ {
+        self.runs.iter().rev().map(|&run| Self::decode(run))
+    }
+
+    /// Convert a traced path into a cigar from start to end.
+    pub fn to_cigar_rev(&self) -> Cigar {
+        Cigar {
+            ops: self.iter_rev().collect(),
+        }
+    }
+}
+
+/// A lower bound on the row where a path of cost `g` to `to` enters the
+/// column at the end of `prev_block`.
+///
+/// Entering at row `j` costs at least `prev_block.index(j) + |di - dj|`, where
+/// `di` and `dj` are the horizontal and vertical distance to `to`. Once `dj >=
+/// di`, this bound does not decrease when going up, since vertical deltas are
+/// at least `-1`, so the scan can stop at the first row where it exceeds `g`.
+fn min_entry_row(prev_block: &Block, to: Pos, g: Cost) -> I {
+    let di = to.0 - prev_block.i_range.1;
+    let mut j = to.1;
+    let mut val = prev_block.index(j);
+    let mut entry_row = j;
+    while j > prev_block.j_range.0 {
+        let dj = to.1 - j;
+        if val + (dj - di).abs() as Cost <= g {
+            entry_row = j;
+        } else if dj >= di {
+            break;
+        }
+        // Rows below the range have vertical deltas of `1`, as in `index`.
+        val -= if j > prev_block.j_range.1 {
+            1
+        } else {
+            prev_block.get_diff(j - 1).unwrap()
+        };
+        j -= 1;
+    }
+    if j == prev_block.j_range.0 && val + (to.1 - j - di).abs() as Cost <= g {
+        entry_row = j;
+    }
+    entry_row
+}
+
 impl Blocks {
     /// Traceback the path from `from` to `to`.
     ///
@@ -23,25 +138,62 @@ impl Blocks {
         a: Seq,
         b: Seq,
         from: Pos,
-        mut to: Pos,
+        to: Pos,
         viz: &mut impl VisualizerInstance,
     ) -> (Cigar, TraceStats) {
+        let (cigar, stats) = self.trace_packed(a, b, from, to, viz);
+        (cigar.to_cigar_rev(), stats)
+    }
+
+    /// Same as `trace`, but returns the compact reversed cigar.
+    /// Use `PackedCigar::iter_rev` to iterate the operations from `from` to `to`.
+    pub fn trace_packed(
+        &mut self,
+        a: Seq,
+        b: Seq,
+        from: Pos,
+        to: Pos,
+        viz: &mut impl VisualizerInstance,
+    ) -> (PackedCigar, TraceStats) {
+        let mut cigar = PackedCigar::default();
+        let stats = self.trace_streaming(a, b, from, to, viz, |elem| cigar.push_elem(elem));
+        (cigar, stats)
+    }
+
+    /// Same as `trace`, but passes the operations to `emit` in reverse order,
+    /// from `to` back to `from`, as soon as each block has been traced.
+    ///
+    /// Only the operations of the current block are buffered, and blocks are
+    /// dropped once traced. Consecutive elements may have the same operation.
+    pub fn trace_streaming(
+        &mut self,
+        a: Seq,
+        b: Seq,
+        from: Pos,
+        mut to: Pos,
+        viz: &mut impl VisualizerInstance,
+        mut emit: impl FnMut(CigarElem),
+    ) -> TraceStats {
         assert!(self.trace);
         assert!(self.blocks.last().unwrap().i_range.1 == to.0);
-        let mut cigar = Cigar { ops: vec![] };
+        // The operations traced through the current block.
+        let mut cigar = PackedCigar::default();
         let mut g = self.blocks[self.last_block_idx].index(to.1);
 
         let mut stats = TraceStats::default();
 
         if DEBUG {
-            eprintln!("Trace from distance {g}");
+            debug!("Trace from distance {g}");
         }
 
         // Some allocated memory that can be reused.
         let dt_cache = &mut vec![BlockElem::default(); (self.params.max_g + 1).pow(2) as usize];
 
         while to != from {
-            // Remove blocks to the right of `to`.
+            // Remove blocks to the right of `to`, and emit the path through them.
+            if self.last_block_idx > 0 && self.blocks[self.last_block_idx].i_range.0 >= to.0 {
+                cigar.drain().for_each(&mut emit);
+            }
             while self.last_block_idx > 0 && self.blocks[self.last_block_idx].i_range.0 >= to.0 {
                 self.pop_last_block();
             }
@@ -76,23 +228,24 @@ impl Blocks {
                 if prev_block.i_range.1 < to.0 - 1 || block.i_range.1 > to.0 {
                     let start = std::time::Instant::now();
                     if DEBUG {
-                        eprintln!(
+                        trace!(
                             "Expand previous block from {:?} to {}",
-                            prev_block.i_range, to.0
+                            prev_block.i_range,
+                            to.0
                         );
                     }
                     let prev_j_range = prev_block.j_range;
                     let i_range = IRange(prev_block.i_range.1, to.0);
                     let j_range = JRange(block.j_range.0, to.1);
+                    let entry_row = min_entry_row(prev_block, to, g);
                     self.pop_last_block();
                     // NOTE: It's unlikely the full (large) `j_range` is needed to trace back through the current block.
                     // 1. We don't need states with `j > to.1`, because the path (in reverse direction) can never go down.
-                    // 2. It's unlikely we'll need all states starting at the (possibly much smaller) `j_range.0`.
-                    //    Instead, we do an exponential search for the start of the `j_range`, starting at `to.1-2*i_range.len()`.
+                    // 2. The path cannot enter the block above `entry_row`, so
+                    //    starting there suffices. Should the block still be too
+                    //    low, we fall back to an exponential search.
                     //    The block is high enough once the cost to `to` equals `g`.
-                    // TODO: Instead of computing increasingly large blocks in forward
-                    // direction we could also iterate backwards.
-                    let mut height = min(j_range.exclusive_len(), i_range.len() * 5 / 4);
+                    let mut height = max(j_range.1 - entry_row, WI);
                     loop {
                         let j_range =
                             JRange(max(j_range.1 - height, prev_j_range.0), j_range.1).round_out();
@@ -102,7 +255,7 @@ impl Blocks {
                         let size = Pos(i_range.len(), j_range.exclusive_len());
                         if DEBUG {
                             let br = tl + size;
-                            eprintln!("Expand block trace {tl}-{br} with size {size}");
+                            trace!("Expand block trace {tl}-{br} with size {size}");
                         }
                         viz.expand_block_trace(tl, size);
                         if self.blocks[self.last_block_idx].index(to.1) == g {
@@ -130,9 +283,9 @@ impl Blocks {
             cigar.push_elem(cigar_elem);
         }
         assert_eq!(g, 0);
-        cigar.reverse();
+        cigar.drain().for_each(&mut emit);
 
-        (cigar, stats)
+        stats
     }
 
     /// Find the parent of `st`.
@@ -169,57 +322,63 @@ impl Blocks {
             );
         }
 
-        // Vertical delta (insert).
-        // (This is first since it only needs a single delta bit, instead of an index() call.)
-        let vd = block.get_diff(st.1 - 1);
-        if vd == Some(1) {
+        // By default insertions come first, since they only need a single delta
+        // bit, instead of an index() call.
+        use TraceEdge::*;
+        let order = self
+            .params
+            .trace_order
+            .unwrap_or(TraceOrder([Ins, Del, Sub]));
+        for edge in order.edges() {
+            let (parent, op) = match edge {
+                // Vertical delta (insert).
+                TraceEdge::Ins => {
+                    if block.get_diff(st.1 - 1) != Some(1) {
+                        continue;
+                    }
+                    (Pos(st.0, st.1 - 1), CigarOp::Ins)
+                }
+                // Horizontal delta (delete).
+                TraceEdge::Del => {
+                    if self.hd(st, *g) != 1 {
+                        continue;
+                    }
+                    (Pos(st.0 - 1, st.1), CigarOp::Del)
+                }
+                // Diagonal delta (substitution).
+                TraceEdge::Sub => {
+                    let prev_block = self.prev_block(st);
+                    // This edge case happens when entering the previous block exactly in
+                    // the bottom-most row, where no vertical delta is available.
+                    let dd = if st.1 > prev_block.j_range.1 {
+                        assert_eq!(st.1, prev_block.j_range.1 + 1);
+                        1
+                    } else {
+                        prev_block.get_diff(st.1 - 1).unwrap() + self.hd(st, *g)
+                    };
+                    if dd != 1 {
+                        continue;
+                    }
+                    (Pos(st.0 - 1, st.1 - 1), CigarOp::Sub)
+                }
+            };
             *g -= 1;
-            return (
-                Pos(st.0, st.1 - 1),
-                CigarElem {
-                    op: CigarOp::Ins,
-                    cnt: 1,
-                },
-            );
+            return (parent, CigarElem { op, cnt: 1 });
         }
 
+        panic!("ERROR: PARENT OF {st:?} NOT FOUND IN TRACEBACK");
+    }
+
+    /// The block for column `st.0 - 1`, just before the last block.
+    fn prev_block(&self, st: Pos) -> &Block {
         let prev_block = &self.blocks[self.last_block_idx - 1];
         assert!(prev_block.i_range.1 == st.0 - 1);
+        prev_block
+    }
 
-        // Horizontal delta (delete).
-        let hd = *g - prev_block.index(st.1);
-        if hd == 1 {
-            *g -= 1;
-            return (
-                Pos(st.0 - 1, st.1),
-                CigarElem {
-                    op: CigarOp::Del,
-                    cnt: 1,
-                },
-            );
-        }
-
-        // Diagonal delta (substitution).
-        // This edge case happens when entering the previous block exactly in
-        // the bottom-most row, where no vertical delta is available.
-        let dd = if st.1 > prev_block.j_range.1 {
-            assert_eq!(st.1, prev_block.j_range.1 + 1);
-            1
-        } else {
-            prev_block.get_diff(st.1 - 1).unwrap() + hd
-        };
-        if dd == 1 {
-            *g -= 1;
-            return (
-                Pos(st.0 - 1, st.1 - 1),
-                CigarElem {
-                    op: CigarOp::Sub,
-                    cnt: 1,
-                },
-            );
-        }
-
-        panic!("ERROR: PARENT OF {st:?} NOT FOUND IN TRACEBACK");
+    /// The horizontal delta into `st`, which has distance `g`.
+    fn hd(&self, st: Pos, g: Cost) -> Cost {
+        g - self.prev_block(st).index(st.1)
     }
 
     /// Trace a path backwards from `st` until `i=block_start`.
@@ -230,12 +389,12 @@ impl Blocks {
         st: Pos,
         g_st: &mut Cost,
         prev_block: &Block,
-        cigar: &mut Cigar,
+        cigar: &mut PackedCigar,
         blocks: &mut Vec<BlockElem>,
         viz: &mut impl VisualizerInstance,
     ) -> Option<Pos> {
         if DEBUG {
-            eprintln!(
+            trace!(
                 "DT Trace from {st:?} with g={g_st} back to {:?}",
                 prev_block.i_range
             );
@@ -250,6 +409,15 @@ impl Blocks {
             parent_d: 0,
         };
 
+        /// The edge from a parent on relative diagonal `parent_d`.
+        fn edge(parent_d: I) -> TraceEdge {
+            match parent_d {
+                -1 => TraceEdge::Ins,
+                0 => TraceEdge::Sub,
+                1 => TraceEdge::Del,
+                _ => panic!(),
+            }
+        }
         fn index(g: Cost, d: I) -> usize {
             (g * g + g + d) as usize
         }
@@ -267,7 +435,7 @@ impl Blocks {
             st: Pos,
             g_st: &mut Cost,
             block_start: I,
-            cigar: &mut Cigar,
+            cigar: &mut PackedCigar,
         ) -> Pos {
             //eprintln!("TRACE");
             let new_st = Pos(block_start, st.1 - (st.0 - block_start) - d);
@@ -287,11 +455,10 @@ impl Blocks {
                 }
                 g -= 1;
                 d += fr.parent_d;
-                let op = match fr.parent_d {
-                    -1 => CigarOp::Ins,
-                    0 => CigarOp::Sub,
-                    1 => CigarOp::Del,
-                    _ => panic!(),
+                let op = match edge(fr.parent_d) {
+                    TraceEdge::Ins => CigarOp::Ins,
+                    TraceEdge::Sub => CigarOp::Sub,
+                    TraceEdge::Del => CigarOp::Del,
                 };
                 //eprintln!("Op: {:?}", op);
                 ops.push(CigarElem { op, cnt: 1 });
@@ -303,6 +470,7 @@ impl Blocks {
         }
 
         let mut g = 0 as Cost;
+        let order = self.params.trace_order;
 
         // Extend up to the start of the previous block and check if the distance is correct.
         let mut extend_left_simd_and_check =
@@ -346,16 +514,23 @@ impl Blocks {
             for d in d_range.0..=d_range.1 {
                 let fr = get(blocks, g, d);
                 //eprintln!("Expand g={} d={} i={}", g, d, fr.i);
-                fn update(x: &mut BlockElem, y: I, d: I) {
-                    if y < x.i {
+                fn update(x: &mut BlockElem, y: I, d: I, order: Option<TraceOrder>) {
+                    // Break ties on the rank of the edge from the parent.
+                    let better = match order {
+                        Some(order) if y == x.i => {
+                            order.rank(Some(edge(d))) < order.rank(Some(edge(x.parent_d)))
+                        }
+                        _ => y < x.i,
+                    };
+                    if better {
                         //eprintln!("update d={d} from {} to {}", x.i, y);
                         x.i = y;
                         x.parent_d = d;
                     }
                 }
-                update(&mut get_mut(blocks, ng, d - 1), fr.i - 1, 1);
-                update(&mut get_mut(blocks, ng, d), fr.i - 1, 0);
-                update(&mut get_mut(blocks, ng, d + 1), fr.i, -1);
+                update(&mut get_mut(blocks, ng, d - 1), fr.i - 1, 1, order);
+                update(&mut get_mut(blocks, ng, d), fr.i - 1, 0, order);
+                update(&mut get_mut(blocks, ng, d + 1), fr.i, -1, order);
             }
             g += 1;
             d_range.0 -= 1;
@@ -0,0 +1,179 @@
+//! Interrupting and resuming band doubling.
+//!
+//! Each band doubling iteration recomputes the fronts from the start, so the
+//! state between iterations is small: the state of the exponential search over
+//! `f_max`, and the blocks in which matches of the heuristic were pruned.
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use super::*;
+
+/// The state of a band doubling search between two iterations.
+///
+/// This can be serialized to disk, and passed to `AstarPa2::try_align_resumable`
+/// to continue the search, possibly in another process.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SearchState {
+    /// The lengths of `a` and `b`, to check that the same input is resumed.
+    pub len_a: usize,
+    pub len_b: usize,
+    /// `None` before the first iteration.
+    pub search: Option<ExponentialSearch>,
+    /// The `(i_range, j_range)` of blocks in which matches were pruned, in order.
+    /// Replaying these restores the state of the heuristic.
+    pub pruned_blocks: Vec<(Range<I>, Range<I>)>,
+}
+
+impl SearchState {
+    /// The current `f_max`, if the search has started.
+    pub fn f_max(&self) -> Option<Cost> {
+        self.search.map(|s| s.s())
+    }
+
+    /// Check that `self` was created for sequences of the given lengths.
+    pub(crate) fn check_input(&self, a: Seq, b: Seq) {
+        if self.search.is_some() {
+            assert_eq!(
+                (self.len_a, self.len_b),
+                (a.len(), b.len()),
+                "The search state was created for sequences of different lengths."
+            );
+        }
+    }
+}
+
+/// A snapshot of the progress of an alignment, as passed to the hook of
+/// `AstarPa2::checkpoint_every`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// The number of the current band doubling iteration, starting at 1.
+    pub iteration: usize,
+    /// The column `i` up to which the current iteration has computed the DP matrix.
+    pub i: I,
+    /// The length of `a`, i.e. the last column.
+    pub len_a: I,
+    /// The bound on `f` of the current iteration, if any.
+    pub f_max: Option<Cost>,
+    /// The band: the range of rows computed in the current block.
+    pub j_range: Range<I>,
+}
+
+/// Calls `f` at most once every `every` while computing blocks.
+pub(crate) struct CheckpointHook<'h> {
+    every: std::time::Duration,
+    last: std::time::Instant,
+    f: &'h dyn Fn(&Checkpoint),
+}
+
+impl<'h> CheckpointHook<'h> {
+    pub(crate) fn new(every: std::time::Duration, f: &'h dyn Fn(&Checkpoint)) -> Self {
+        Self {
+            every,
+            last: std::time::Instant::now(),
+            f,
+        }
+    }
+
+    /// Call the hook with the checkpoint, if `every` has passed since the last call.
+    pub(crate) fn tick(&mut self, checkpoint: impl FnOnce() -> Checkpoint) {
+        if self.last.elapsed() >= self.every {
+            (self.f)(&checkpoint());
+            self.last = std::time::Instant::now();
+        }
+    }
+}
+
+/// Optional hooks into band doubling.
+pub(crate) struct Hooks<'h> {
+    /// Continue band doubling from this state, and record the pruning so that
+    /// the state passed to `abort` can be resumed.
+    pub resume: Option<SearchState>,
+    /// Checked before each band doubling iteration.
+    pub abort: &'h mut dyn FnMut(&SearchState) -> bool,
+    /// Called periodically while computing blocks.
+    pub checkpoint: Option<CheckpointHook<'h>>,
+}
+
+impl<'h> Hooks<'h> {
+    pub(crate) fn abort(abort: &'h mut dyn FnMut(&SearchState) -> bool) -> Self {
+        Self {
+            resume: None,
+            abort,
+            checkpoint: None,
+        }
+    }
+}
+
+/// An aligner that periodically reports its progress. See `AstarPa2::checkpoint_every`.
+pub struct Checkpointed<'p, V: VisualizerT, H: Heuristic, F: Fn(&Checkpoint)> {
+    aligner: &'p AstarPa2<V, H>,
+    every: std::time::Duration,
+    f: F,
+}
+
+impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
+    /// Call `f` with a snapshot of the progress at most once every `every`.
+    /// This is checked after each block, so for large blocks calls may be
+    /// less frequent.
+    pub fn checkpoint_every<F: Fn(&Checkpoint)>(
+        &self,
+        every: std::time::Duration,
+        f: F,
+    ) -> Checkpointed<'_, V, H, F> {
+        Checkpointed {
+            aligner: self,
+            every,
+            f,
+        }
+    }
+}
+
+impl<'p, V: VisualizerT, H: Heuristic, F: Fn(&Checkpoint)> Checkpointed<'p, V, H, F> {
+    /// Ignores `max_divergence`, like `AstarPa2::align`.
+    pub fn align(&self, a: Seq, b: Seq) -> (Cost, Option<Cigar>) {
+        self.run(a, b, None, Cost::MAX, &mut |_| false)
+            .expect("Alignments without a bound or abort hook cannot fail.")
+    }
+
+    /// See `AstarPa2::try_align_resumable`. Resuming is only possible for
+    /// band doubling, but checkpoints are reported for all doubling types.
+    pub fn try_align_resumable(
+        &self,
+        a: Seq,
+        b: Seq,
+        resume: Option<SearchState>,
+        mut abort: impl FnMut(&SearchState) -> bool,
+    ) -> Result<(Cost, Option<Cigar>), AlignError> {
+        self.run(a, b, resume, self.aligner.max_cost(a, b), &mut abort)
+    }
+
+    fn run(
+        &self,
+        a: Seq,
+        b: Seq,
+        resume: Option<SearchState>,
+        max_cost: Cost,
+        abort: &mut dyn FnMut(&SearchState) -> bool,
+    ) -> Result<(Cost, Option<Cigar>), AlignError> {
+        let resume = self
+            .aligner
+            .doubling
+            .factor()
+            .map(|_| resume.unwrap_or_default());
+        self.aligner
+            .try_cost_or_align(
+                a,
+                b,
+                self.aligner.trace,
+                None,
+                max_cost,
+                Hooks {
+                    resume,
+                    abort,
+                    checkpoint: Some(CheckpointHook::new(self.every, &self.f)),
+                },
+            )
+            .map(|(cost, cigar, _stats)| (cost, cigar))
+    }
+}
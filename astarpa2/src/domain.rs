@@ -15,6 +15,7 @@
 mod local_doubling;
 
 use self::blocks::{trace::TraceStats, BlockStats};
+use pa_heuristic::{AlignStats, HeuristicStats};
 
 use super::*;
 use crate::{block::Block, blocks::Blocks};
@@ -26,6 +27,7 @@ use std::{
     cmp::{max, min},
     time::Duration,
 };
+use tracing::{debug, trace};
 use Domain::*;
 
 #[derive(Debug, Default, serde::Serialize, serde::Deserialize)]
@@ -40,6 +42,24 @@ pub struct AstarPa2Stats {
     pub t_fixed_j_range: Duration,
     pub t_pruning: Duration,
     pub t_contours_update: Duration,
+
+    /// Statistics of the heuristic, when the domain is `Astar`.
+    #[serde(skip)]
+    pub h: HeuristicStats,
+}
+
+impl AstarPa2Stats {
+    /// Convert to the statistics shared by all aligners.
+    /// `t_total` is not tracked here and left at 0.
+    pub fn to_align_stats(&self) -> AlignStats {
+        AlignStats {
+            h: self.h,
+            expanded: 0,
+            computed_rows: self.block_stats.computed_lanes * W,
+            t_total: Duration::ZERO,
+            t_precomp: self.t_precomp,
+        }
+    }
 }
 
 pub struct AstarPa2Instance<'a, V: VisualizerT, H: Heuristic> {
@@ -52,16 +72,40 @@ pub struct AstarPa2Instance<'a, V: VisualizerT, H: Heuristic> {
     /// The instantiated heuristic to use.
     pub domain: Domain<H::Instance<'a>>,
 
-    /// Hint for the heuristic, cached between `j_range` calls.
-    pub hint: <H::Instance<'a> as HeuristicInstance<'a>>::Hint,
+    /// Hints for the heuristic, cached per band of rows between `j_range` calls.
+    pub hints: HintCache<<H::Instance<'a> as HeuristicInstance<'a>>::Hint>,
 
     /// The instantiated visualizer to use.
     pub v: V::Instance,
 
+    /// Precomputed data for `b`, when aligning many sequences to the same reference.
+    pub reference: Option<&'a Reference<'a>>,
+
+    /// When set, the `(i_range, j_range)` of each `prune_block` call is
+    /// recorded, so that the pruning can be replayed when resuming.
+    pub pruned_blocks: Option<Vec<(std::ops::Range<I>, std::ops::Range<I>)>>,
+
+    /// Called periodically with the progress of `align_for_bounded_dist`.
+    pub(crate) checkpoint: Option<CheckpointHook<'a>>,
+
+    /// The blocks chosen so far, when `block_width_mode` is not `Fixed`.
+    pub(crate) block_widths: Option<BlockWidths>,
+
     pub stats: AstarPa2Stats,
 }
 
 impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
+    /// Allocate new blocks, reusing the profile of the reference when available.
+    pub fn new_blocks(&self, trace: bool) -> Blocks {
+        match self.reference.and_then(|r| r.profile.as_ref()) {
+            Some(profile) => self
+                .params
+                .block
+                .new_with_b_profile(trace, self.a, profile.clone()),
+            None => self.params.block.new(trace, self.a, self.b),
+        }
+    }
+
     /// The range of rows `j` to consider for columns `i_range.0 .. i_range.1`, when the cost is bounded by `f_bound`.
     ///
     /// For A*, this also returns the range of rows in column `i_range.0` that are 'fixed', ie have `f <= f_max`.
@@ -127,9 +171,9 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                     .fixed_j_range
                     .expect("With A* Domain, fixed_j_range should always be set.");
                 if DEBUG {
-                    eprintln!("j_range for   {i_range:?}");
-                    eprintln!("\told j_range {old_range:?}");
-                    eprintln!("\told fixed   {:?} @ {is}", prev.fixed_j_range.unwrap());
+                    trace!("j_range for   {i_range:?}");
+                    trace!("\told j_range {old_range:?}");
+                    trace!("\told fixed   {:?} @ {is}", prev.fixed_j_range.unwrap());
                 }
                 assert!(fixed_start <= fixed_end, "Fixed range must not be empty");
 
@@ -143,12 +187,8 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                 // i_range.1 that could possibly have `f(v) <= f_max`.
                 let mut v = u;
 
-                // Wrapper to use h with hint.
-                let mut h = |pos| {
-                    let (h, new_hint) = h.h_with_hint(pos, self.hint);
-                    self.hint = new_hint;
-                    h
-                };
+                // Wrapper to use h with cached hints.
+                let mut h = |pos| h.h_with_cached_hint(pos, &mut self.hints);
                 // A lower bound of `f` values estimated from `gu`, valid for states `v` below the diagonal of `u`.
                 let mut f = |v: Pos| {
                     assert!(v.1 - u.1 >= v.0 - u.0);
@@ -207,9 +247,9 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                                 v.1 = self.b.len() as I;
                             }
                         } else {
-                            // By consistency of `f`, it can only change value by at most `2` per step in the unit cost setting.
-                            // When `f(v) > f_max`, this means we have to make at least `ceil((fv - f_max)/2)` steps to possibly get at a cell with `f(v) <= f_max`.
-                            v.0 += (fv - f_max).div_ceil(2 * unit_cost.min_del_extend);
+                            // By consistency of `h`, `f` can only change value by at most `max_f_step` (`2` for unit costs) per step.
+                            // When `f(v) > f_max`, this means we have to make at least `ceil((fv - f_max)/max_f_step)` steps to possibly get at a cell with `f(v) <= f_max`.
+                            v.0 += (fv - f_max).div_ceil(unit_cost.max_f_step());
                             if v.0 > ie {
                                 v.0 = ie;
                                 break;
@@ -227,7 +267,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                         if fv <= f_max {
                             break;
                         } else {
-                            v.1 -= (fv - f_max).div_ceil(2 * unit_cost.min_ins_extend);
+                            v.1 -= (fv - f_max).div_ceil(unit_cost.max_f_step());
                         }
                     }
                 }
@@ -268,12 +308,8 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
             stats.t_fixed_j_range += t_start.elapsed();
         }
 
-        // Wrapper to use h with hint.
-        let mut h = |pos| {
-            let (h, new_hint) = h.h_with_hint(pos, self.hint);
-            self.hint = new_hint;
-            h
-        };
+        // Wrapper to use h with cached hints.
+        let mut h = |pos| h.h_with_cached_hint(pos, &mut self.hints);
 
         // Compute values at the end of each lane.
         let mut f = |j| {
@@ -309,7 +345,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                 break;
             }
             start += if self.params.sparse_h {
-                (f - f_max).div_ceil(2 * unit_cost.min_ins_extend)
+                (f - f_max).div_ceil(unit_cost.max_f_step())
             } else {
                 1
             };
@@ -321,15 +357,15 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                 break;
             }
             end -= if self.params.sparse_h {
-                (f - f_max).div_ceil(2 * unit_cost.min_ins_extend)
+                (f - f_max).div_ceil(unit_cost.max_f_step())
             } else {
                 1
             };
         }
         let mut fixed_j_range = JRange(start, end);
         if DEBUG {
-            eprintln!("initial fixed_j_range for {i} {fixed_j_range:?}");
-            eprintln!("old     fixed_j_range for {i} {:?}", block.fixed_j_range);
+            trace!("initial fixed_j_range for {i} {fixed_j_range:?}");
+            trace!("old     fixed_j_range for {i} {:?}", block.fixed_j_range);
         }
         if let Some(old_fixed_j_range) = block.fixed_j_range {
             if fixed_j_range.is_empty() {
@@ -339,7 +375,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
             }
         }
         if DEBUG {
-            eprintln!("updated fixed_j_range for {i} {fixed_j_range:?}");
+            trace!("updated fixed_j_range for {i} {fixed_j_range:?}");
         }
 
         if !fixed_j_range.is_empty() {
@@ -369,17 +405,17 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
             h.update_contours(Pos(0, 0));
             self.stats.t_contours_update += start.elapsed();
             if DEBUG {
-                eprintln!("\nTEST DIST {} h0 {}\n", f_max.unwrap_or(0), h.h(Pos(0, 0)));
+                debug!("Test dist {} h0 {}", f_max.unwrap_or(0), h.h(Pos(0, 0)));
             }
         } else {
             if DEBUG {
-                eprintln!("\nTEST DIST {}\n", f_max.unwrap_or(0));
+                debug!("Test dist {}", f_max.unwrap_or(0));
             }
         }
 
         // Make a local block variable if not passed in.
         let mut local_blocks = if blocks.is_none() {
-            Some(self.params.block.new(trace, self.a, self.b))
+            Some(self.new_blocks(trace))
         } else {
             None
         };
@@ -425,9 +461,22 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
 
         let mut all_blocks_reused = true;
 
-        for i in (0..self.a.len() as I).step_by(self.params.block_width as _) {
+        let mut i = 0;
+        let mut block_idx = 0;
+        while i < self.a.len() as I {
+            let block_start = std::time::Instant::now();
             // The i_range of the new block.
-            let i_range = IRange(i, min(i + self.params.block_width, self.a.len() as I));
+            let i_range = match &mut self.block_widths {
+                Some(widths) => widths.i_range(
+                    block_idx,
+                    i,
+                    self.a.len() as I,
+                    blocks.last_block().j_range.len(),
+                ),
+                None => IRange(i, min(i + self.params.block_width, self.a.len() as I)),
+            };
+            i = i_range.1;
+            block_idx += 1;
             // The j_range of the new block.
             let j_range = self.j_range(
                 i_range,
@@ -446,6 +495,16 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                 return None;
             }
 
+            if let Some(checkpoint) = &mut self.checkpoint {
+                checkpoint.tick(|| Checkpoint {
+                    iteration: self.stats.f_max_tries,
+                    i: i_range.0,
+                    len_a: self.a.len() as I,
+                    f_max,
+                    j_range: j_range.0..j_range.1,
+                });
+            }
+
             // If the new `j_range` is the same as the old one, and all previous
             // blocks were reused, we can also reuse this new block.
             let mut reuse = false;
@@ -466,6 +525,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
             }
 
             // Reuse or compute the next block.
+            let fill_start = std::time::Instant::now();
             if reuse {
                 blocks.reuse_next_block(i_range, j_range);
             } else {
@@ -474,6 +534,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                     self.v.new_layer(self.domain.h());
                 }
             }
+            let t_fill = fill_start.elapsed();
 
             // Compute the new range of fixed states.
             let next_fixed_j_range =
@@ -482,7 +543,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
             // If there are no fixed states, break.
             if next_fixed_j_range.is_some_and(|r| r.is_empty()) {
                 if DEBUG {
-                    eprintln!("fixed_j_range is empty! Increasing f_max!");
+                    debug!("fixed_j_range is empty! Increasing f_max!");
                 }
                 self.v.new_layer(self.domain.h());
                 return None;
@@ -510,9 +571,19 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                     JRange::intersection(prev_fixed_j_range.unwrap(), next_fixed_j_range.unwrap());
                 if !intersection.is_empty() {
                     h.prune_block(i_range.0..i_range.1, intersection.0..intersection.1);
+                    if let Some(pruned_blocks) = &mut self.pruned_blocks {
+                        pruned_blocks.push((i_range.0..i_range.1, intersection.0..intersection.1));
+                    }
                 }
                 self.stats.t_pruning += start.elapsed();
             }
+
+            // Reused blocks do not say anything about the time to fill them.
+            if !reuse {
+                if let Some(widths) = &mut self.block_widths {
+                    widths.update(t_fill, block_start.elapsed() - t_fill);
+                }
+            }
         }
 
         self.v.new_layer(self.domain.h());
@@ -9,7 +9,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
         // idx 0: i_range 0 .. 0
         // idx i: i_range (B-1)*i .. B*i
         // idx max: i_range (B-1)*max .. a.len()
-        let mut blocks = self.params.block.new(true, self.a, self.b);
+        let mut blocks = self.new_blocks(true);
 
         // Add the block for i_range 0..0
         {
@@ -119,7 +119,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
                 grow_to(&mut f_max[start_idx], f_target, &mut f_delta[start_idx]);
                 if f_max[start_idx] > last_grow {
                     if DEBUG {
-                        eprintln!(
+                        trace!(
                             "Grow  block idx {start_idx:>5} to {:>6} by {:>6} for {old_delta:>5?} and shortage {:>6}",
                             f_max[start_idx],
                             f_max[start_idx] - old_f,
@@ -134,7 +134,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
 
             if start_idx < last_idx {
                 if DEBUG {
-                    eprintln!("START block idx {start_idx:>5} to {:>6}", f_max[start_idx]);
+                    trace!("START block idx {start_idx:>5} to {:>6}", f_max[start_idx]);
                 }
                 let h = self.domain.h_mut().unwrap();
                 h.update_contours(Pos((start_idx as I - 1) * self.params.block_width, 0));
@@ -225,7 +225,7 @@ impl<'a, V: VisualizerT, H: Heuristic> AstarPa2Instance<'a, V, H> {
             for (idx, d) in f_delta.iter().enumerate() {
                 if delta != d.0 {
                     delta = d.0;
-                    eprintln!("Delta {idx:>6} => {delta:>5}");
+                    trace!("Delta {idx:>6} => {delta:>5}");
                 }
             }
         }
@@ -0,0 +1,52 @@
+use pa_types::Cost;
+
+/// Reasons an alignment can be aborted early.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AlignError {
+    /// The sequences are too long for the 32-bit positions and costs.
+    /// See `pa_heuristic::util::fits_index`.
+    TooLong { len_a: usize, len_b: usize },
+    /// The cost exceeds the configured `max_divergence`.
+    /// `lower_bound` is a lower bound on the actual cost.
+    TooDivergent { lower_bound: Cost },
+    /// The alignment was aborted, e.g. because of a timeout.
+    /// `lower_bound` is a lower bound on the actual cost, and `upper_bound` is
+    /// the cost of the best path found so far, if any.
+    Aborted {
+        lower_bound: Cost,
+        upper_bound: Option<Cost>,
+    },
+    /// The requested combination of options is not supported.
+    Unsupported { what: &'static str },
+}
+
+impl std::fmt::Display for AlignError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AlignError::TooLong { len_a, len_b } => write!(
+                f,
+                "Sequences of length {len_a} and {len_b} are too long for 32-bit positions."
+            ),
+            AlignError::TooDivergent { lower_bound } => {
+                write!(
+                    f,
+                    "Sequences are too divergent: cost is at least {lower_bound}."
+                )
+            }
+            AlignError::Aborted {
+                lower_bound,
+                upper_bound: Some(upper_bound),
+            } => write!(
+                f,
+                "Alignment was aborted: cost is between {lower_bound} and {upper_bound}."
+            ),
+            AlignError::Aborted {
+                lower_bound,
+                upper_bound: None,
+            } => write!(f, "Alignment was aborted: cost is at least {lower_bound}."),
+            AlignError::Unsupported { what } => write!(f, "{what} is not supported."),
+        }
+    }
+}
+
+impl std::error::Error for AlignError {}
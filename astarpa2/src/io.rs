@@ -0,0 +1,399 @@
+//! Reading input sequences from files.
+//!
+//! Fasta and Fastq files are parsed using `needletail`.
+//! Files with a `.seq` or `.txt` extension contain one sequence per line, and
+//! for `.seq` files lines start with `>` and `<`.
+//! All formats may be compressed with gzip or bgzip, e.g. `reads.fq.gz`.
+//! `MappedSeq` memory-maps a file containing a single raw sequence.
+mod gzip;
+
+use std::{
+    fs::File,
+    io::BufRead,
+    path::{Path, PathBuf},
+};
+
+use itertools::Itertools;
+use memmap2::Mmap;
+use needletail::{errors::ParseError, parse_fastx_reader, FastxReader};
+use pa_heuristic::{prepare_input, InvalidByte, NonAcgt};
+
+use super::*;
+
+pub use gzip::open;
+
+/// Errors while reading input files.
+#[derive(Debug)]
+pub enum InputError {
+    Io(std::io::Error),
+    Parse(ParseError),
+    /// A `.seq` line that does not start with the expected `>` or `<`.
+    SeqLine {
+        path: PathBuf,
+        line: usize,
+    },
+    InvalidByte(InvalidByte),
+    /// The two files passed to `align_files` have a different number of records.
+    Unpaired {
+        len_a: usize,
+        len_b: usize,
+    },
+}
+
+impl std::fmt::Display for InputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputError::Io(e) => write!(f, "{e}"),
+            InputError::Parse(e) => write!(f, "{e}"),
+            InputError::SeqLine { path, line } => write!(
+                f,
+                "Line {line} of {} does not start with '>' or '<'.",
+                path.display()
+            ),
+            InputError::InvalidByte(e) => write!(f, "{e}"),
+            InputError::Unpaired { len_a, len_b } => write!(
+                f,
+                "Files have a different number of records: {len_a} and {len_b}."
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<std::io::Error> for InputError {
+    fn from(e: std::io::Error) -> Self {
+        InputError::Io(e)
+    }
+}
+
+impl From<ParseError> for InputError {
+    fn from(e: ParseError) -> Self {
+        InputError::Parse(e)
+    }
+}
+
+impl From<InvalidByte> for InputError {
+    fn from(e: InvalidByte) -> Self {
+        InputError::InvalidByte(e)
+    }
+}
+
+/// An iterator over the sequences in a file.
+pub struct Records(RecordsInner);
+
+enum RecordsInner {
+    Fastx(Box<dyn FastxReader>),
+    Lines {
+        path: PathBuf,
+        lines: std::io::Lines<Box<dyn BufRead + Send>>,
+        /// Whether lines alternate between `>` and `<` prefixes.
+        seq: bool,
+        idx: usize,
+    },
+}
+
+impl Records {
+    /// The next record, together with its id.
+    ///
+    /// The id of a Fasta or Fastq record is the first word of its header, and
+    /// the id of a line is its 1-based line number.
+    fn next_named(&mut self) -> Option<Result<(String, Vec<u8>), InputError>> {
+        match &mut self.0 {
+            RecordsInner::Fastx(reader) => Some(
+                reader
+                    .next()?
+                    .map(|r| {
+                        let id = r.id().split(|c| c.is_ascii_whitespace()).next();
+                        let id = String::from_utf8_lossy(id.unwrap_or_default()).into_owned();
+                        (id, r.seq().into_owned())
+                    })
+                    .map_err(InputError::from),
+            ),
+            RecordsInner::Lines {
+                path,
+                lines,
+                seq,
+                idx,
+            } => {
+                let line = match lines.next()? {
+                    Ok(line) => line.into_bytes(),
+                    Err(e) => return Some(Err(e.into())),
+                };
+                *idx += 1;
+                let id = idx.to_string();
+                if !*seq {
+                    return Some(Ok((id, line)));
+                }
+                let prefix = if *idx % 2 == 1 { b'>' } else { b'<' };
+                Some(match line.split_first() {
+                    Some((&c, rest)) if c == prefix => Ok((id, rest.to_vec())),
+                    _ => Err(InputError::SeqLine {
+                        path: path.clone(),
+                        line: *idx,
+                    }),
+                })
+            }
+        }
+    }
+
+    /// Iterate over `(id, sequence)` pairs instead. See `next_named`.
+    pub fn named(mut self) -> impl Iterator<Item = Result<(String, Vec<u8>), InputError>> {
+        std::iter::from_fn(move || self.next_named())
+    }
+}
+
+impl Iterator for Records {
+    type Item = Result<Vec<u8>, InputError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_named()?.map(|(_id, seq)| seq))
+    }
+}
+
+/// The extension of `path` that determines its format, ignoring a trailing
+/// `.gz` or `.bgz`.
+fn format_extension(path: &Path) -> Option<&str> {
+    match path.extension()?.to_str()? {
+        "gz" | "bgz" => Path::new(path.file_stem()?).extension()?.to_str(),
+        ext => Some(ext),
+    }
+}
+
+/// Read all sequences in a `.seq`, `.txt`, Fasta, or Fastq file.
+/// Files may be compressed with gzip or bgzip.
+pub fn read_records(path: impl AsRef<Path>) -> Result<Records, InputError> {
+    let path = path.as_ref();
+    let reader = open(path)?;
+    Ok(Records(match format_extension(path) {
+        Some(ext @ ("seq" | "txt")) => RecordsInner::Lines {
+            path: path.to_owned(),
+            lines: reader.lines(),
+            seq: ext == "seq",
+            idx: 0,
+        },
+        _ => RecordsInner::Fastx(parse_fastx_reader(reader)?),
+    }))
+}
+
+/// Read pairs of consecutive sequences in a file, as accepted by `read_records`.
+/// A trailing unpaired sequence is ignored.
+pub fn read_pairs(
+    path: impl AsRef<Path>,
+) -> Result<impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), InputError>>, InputError> {
+    Ok(read_records(path)?
+        .tuples()
+        .map(|(a, b)| Ok::<_, InputError>((a?, b?))))
+}
+
+/// Like `read_pairs`, but also returns the ids of the records.
+pub fn read_named_pairs(
+    path: impl AsRef<Path>,
+) -> Result<impl Iterator<Item = Result<NamedPair, InputError>>, InputError> {
+    Ok(read_records(path)?
+        .named()
+        .tuples()
+        .map(|(a, b)| Ok::<_, InputError>((a?, b?))))
+}
+
+/// Two records with their ids, as returned by `read_named_pairs`.
+pub type NamedPair = ((String, Vec<u8>), (String, Vec<u8>));
+
+/// Align the `i`th record of `path_a` to the `i`th record of `path_b` using `aligner`,
+/// e.g. `AstarPa2Params::full().make_aligner(true)`.
+///
+/// Lowercase characters are converted to uppercase, and other characters than
+/// `ACGT` are an error. The aligner must return a cigar.
+pub fn align_files(
+    path_a: impl AsRef<Path>,
+    path_b: impl AsRef<Path>,
+    aligner: &mut (impl Aligner + ?Sized),
+) -> Result<Vec<(Cost, Cigar)>, InputError> {
+    let a = read_records(path_a)?.collect::<Result<Vec<_>, _>>()?;
+    let b = read_records(path_b)?.collect::<Result<Vec<_>, _>>()?;
+    if a.len() != b.len() {
+        return Err(InputError::Unpaired {
+            len_a: a.len(),
+            len_b: b.len(),
+        });
+    }
+    a.iter()
+        .zip(&b)
+        .map(|(a, b)| {
+            let (a, b) = prepare_input(a, b, NonAcgt::Error)?;
+            let (cost, cigar) = aligner.align(&a, &b);
+            Ok::<_, InputError>((cost, cigar.expect("The aligner must return a cigar.")))
+        })
+        .collect()
+}
+
+/// A sequence backed by a memory-mapped file, so that large references are
+/// shared between processes instead of being loaded and copied.
+///
+/// The file must contain only the raw sequence, optionally followed by a
+/// newline, and can not be compressed. Use with `Mapper::lazy` to avoid preprocessing the entire sequence.
+pub struct MappedSeq {
+    mmap: Mmap,
+}
+
+impl MappedSeq {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, InputError> {
+        let file = File::open(path)?;
+        // SAFETY: The file must not be modified while it is mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self { mmap })
+    }
+
+    /// The mapped sequence, without trailing newline.
+    pub fn seq(&self) -> Seq {
+        let mut seq = &self.mmap[..];
+        while let [rest @ .., b'\n' | b'\r'] = seq {
+            seq = rest;
+        }
+        seq
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression, GzBuilder};
+    use std::io::Write;
+
+    fn write(dir: &tempfile::TempDir, name: &str, content: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn read_formats() {
+        let dir = tempfile::tempdir().unwrap();
+        let expected = vec![(b"ACGT".to_vec(), b"AGT".to_vec())];
+        for (name, content) in [
+            ("pairs.seq", ">ACGT\n<AGT\n"),
+            ("pairs.txt", "ACGT\nAGT\n"),
+            ("pairs.fa", ">a\nAC\nGT\n>b\nAGT\n"),
+            ("pairs.fq", "@a\nACGT\n+\nIIII\n@b\nAGT\n+\nIII\n"),
+        ] {
+            let pairs = read_pairs(write(&dir, name, content))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(pairs, expected, "{name}");
+        }
+        assert!(matches!(
+            read_pairs(write(&dir, "bad.seq", "<ACGT\n>AGT\n"))
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>(),
+            Err(InputError::SeqLine { line: 1, .. })
+        ));
+    }
+
+    fn gzip(content: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(content).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    /// Bgzip blocks are gzip members with a `BC` extra field containing the block size.
+    fn bgzip(content: &[u8], block_size: usize) -> Vec<u8> {
+        let mut out = vec![];
+        for chunk in content.chunks(block_size).chain([&[][..]]) {
+            let mut encoder = GzBuilder::new()
+                .extra(vec![b'B', b'C', 2, 0, 0, 0])
+                .write(vec![], Compression::default());
+            encoder.write_all(chunk).unwrap();
+            let mut block = encoder.finish().unwrap();
+            let size = (block.len() - 1) as u16;
+            block[16..18].copy_from_slice(&size.to_le_bytes());
+            out.extend(block);
+        }
+        out
+    }
+
+    #[test]
+    fn read_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let ids = |name: &str, content: &str| {
+            read_named_pairs(write(&dir, name, content))
+                .unwrap()
+                .map(|pair| {
+                    let ((id_a, _), (id_b, _)) = pair.unwrap();
+                    (id_a, id_b)
+                })
+                .collect_vec()
+        };
+        assert_eq!(
+            ids("ids.fa", ">a1 first\nACGT\n>b1\nAGT\n"),
+            [("a1".to_string(), "b1".to_string())]
+        );
+        assert_eq!(
+            ids("ids.seq", ">ACGT\n<AGT\n>A\n<C\n")[1],
+            ("3".to_string(), "4".to_string())
+        );
+    }
+
+    #[test]
+    fn read_compressed() {
+        let dir = tempfile::tempdir().unwrap();
+        let fasta = (0..1000)
+            .map(|i| format!(">a{i}\nACGT\n>b{i}\nAGT\n"))
+            .collect::<String>();
+        let expected = vec![(b"ACGT".to_vec(), b"AGT".to_vec()); 1000];
+        for (name, content) in [
+            ("pairs.seq.gz", gzip(b">ACGT\n<AGT\n").repeat(1000)),
+            ("pairs.fa.gz", gzip(fasta.as_bytes())),
+            ("pairs.fa.bgz", bgzip(fasta.as_bytes(), 1000)),
+            // The compression is detected from the content, not the extension.
+            ("pairs.fa", bgzip(fasta.as_bytes(), 100)),
+        ] {
+            let path = write(&dir, name, "");
+            std::fs::write(&path, content).unwrap();
+            let pairs = read_pairs(path)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(pairs, expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn align() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = write(&dir, "a.fa", ">a1\nACGT\n>a2\nAAAA\n");
+        let b = write(&dir, "b.fa", ">b1\nAGT\n>b2\naaaa\n");
+        let mut aligner = AstarPa2Params::full().make_aligner(true);
+        let costs = align_files(&a, &b, aligner.as_mut())
+            .unwrap()
+            .into_iter()
+            .map(|(cost, _)| cost)
+            .collect_vec();
+        assert_eq!(costs, [1, 0]);
+        let c = write(&dir, "c.fa", ">c1\nACGT\n");
+        assert!(matches!(
+            align_files(&a, &c, aligner.as_mut()),
+            Err(InputError::Unpaired { len_a: 2, len_b: 1 })
+        ));
+    }
+
+    #[test]
+    fn mapped_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let (query, reference) = pa_generate::uniform_fixed(300, 0.1);
+        let mut content = vec![b'A'; 500];
+        content.extend_from_slice(&reference);
+        content.push(b'\n');
+        let path = write(&dir, "reference.txt", "");
+        std::fs::write(&path, &content).unwrap();
+
+        let mapped = MappedSeq::open(&path).unwrap();
+        assert_eq!(mapped.seq(), &content[..content.len() - 1]);
+        let mapper = Mapper::lazy(mapped.seq());
+        let (cost, cigar) = mapper.align_window(&query, 500..500 + reference.len());
+        assert_eq!(cost, astarpa2_full(&query, &reference).0);
+        cigar
+            .unwrap()
+            .verify(&CostModel::unit(), &query, &reference);
+    }
+}
@@ -0,0 +1,148 @@
+//! Transparent decompression of gzip and bgzip input files.
+//!
+//! Decompression runs on a background thread, so that it overlaps with
+//! alignment. Bgzip files consist of independent blocks of at most 64kB, which
+//! are additionally decompressed in parallel using rayon.
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Read},
+    path::Path,
+    sync::mpsc::{sync_channel, Receiver},
+    thread,
+};
+
+use flate2::read::{GzDecoder, MultiGzDecoder};
+use rayon::prelude::*;
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+/// Size of the bgzip header, including the `BC` extra subfield.
+const BGZF_HEADER: usize = 18;
+/// Number of bgzip blocks decompressed in parallel, i.e. up to 4MB of output.
+const BGZF_BATCH: usize = 64;
+/// Size of the chunks sent from the background thread for plain gzip files.
+const CHUNK: usize = 1 << 20;
+/// Number of decompressed chunks buffered ahead of the reader.
+const CHANNEL_SIZE: usize = 4;
+
+/// Open `path` for reading. Gzip and bgzip files are detected by their magic
+/// bytes and decompressed on the fly, independent of the file extension.
+pub fn open(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead + Send>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let header = file.fill_buf()?;
+    Ok(if is_bgzf(header) {
+        // Skip batches of empty blocks, so that an empty chunk means the end of the file.
+        Box::new(BufReader::new(ChunkReader::spawn(move || loop {
+            let chunk = read_bgzf_batch(&mut file)?;
+            if !chunk.is_empty() || file.fill_buf()?.is_empty() {
+                return Ok(chunk);
+            }
+        })))
+    } else if header.starts_with(&GZIP_MAGIC) {
+        let mut decoder = MultiGzDecoder::new(file);
+        Box::new(BufReader::new(ChunkReader::spawn(move || {
+            let mut chunk = Vec::with_capacity(CHUNK);
+            (&mut decoder).take(CHUNK as u64).read_to_end(&mut chunk)?;
+            Ok(chunk)
+        })))
+    } else {
+        Box::new(file)
+    })
+}
+
+/// A gzip header with the `FEXTRA` flag set and a `BC` subfield.
+fn is_bgzf(header: &[u8]) -> bool {
+    header.len() >= BGZF_HEADER
+        && header.starts_with(&GZIP_MAGIC)
+        && header[3] & 4 != 0
+        && &header[12..14] == b"BC"
+}
+
+/// Read up to `BGZF_BATCH` compressed blocks and decompress them in parallel.
+/// Returns an empty vector at the end of the file.
+fn read_bgzf_batch(file: &mut impl BufRead) -> io::Result<Vec<u8>> {
+    let mut blocks = vec![];
+    while blocks.len() < BGZF_BATCH && !file.fill_buf()?.is_empty() {
+        let mut block = vec![0; BGZF_HEADER];
+        file.read_exact(&mut block)?;
+        if !is_bgzf(&block) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid bgzip block header",
+            ));
+        }
+        let size = u16::from_le_bytes([block[16], block[17]]) as usize + 1;
+        if size < BGZF_HEADER {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid bgzip block size",
+            ));
+        }
+        block.resize(size, 0);
+        file.read_exact(&mut block[BGZF_HEADER..])?;
+        blocks.push(block);
+    }
+    let blocks = blocks
+        .par_iter()
+        .map(|block| {
+            let mut out = vec![];
+            GzDecoder::new(&block[..]).read_to_end(&mut out)?;
+            Ok(out)
+        })
+        .collect::<io::Result<Vec<_>>>()?;
+    Ok(blocks.concat())
+}
+
+/// Reads chunks produced by `next_chunk` on a background thread.
+/// An empty chunk marks the end of the input.
+struct ChunkReader {
+    rx: Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl ChunkReader {
+    fn spawn(mut next_chunk: impl FnMut() -> io::Result<Vec<u8>> + Send + 'static) -> Self {
+        let (tx, rx) = sync_channel(CHANNEL_SIZE);
+        thread::spawn(move || loop {
+            let chunk = next_chunk();
+            let last = !matches!(&chunk, Ok(chunk) if !chunk.is_empty());
+            // Stop when the reader was dropped.
+            if tx.send(chunk).is_err() || last {
+                break;
+            }
+        });
+        Self {
+            rx,
+            chunk: vec![],
+            pos: 0,
+            done: false,
+        }
+    }
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            if self.done {
+                return Ok(0);
+            }
+            match self.rx.recv() {
+                Ok(Ok(chunk)) => {
+                    self.done = chunk.is_empty();
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Ok(Err(e)) => {
+                    self.done = true;
+                    return Err(e);
+                }
+                Err(_) => self.done = true,
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
@@ -6,32 +6,65 @@
     type_changing_struct_update
 )]
 
+mod all_pairs;
+mod auto;
 mod band;
 mod block;
+mod block_width;
 mod blocks;
+mod checkpoint;
 mod domain;
+mod error;
+#[cfg(feature = "io")]
+pub mod io;
+mod mapper;
 mod params;
 mod ranges;
+#[cfg(feature = "tokio")]
+mod task;
+#[cfg(feature = "metrics")]
+mod telemetry;
 #[cfg(test)]
 mod tests;
+mod windows;
 
-pub use band::{DoublingStart, DoublingType};
+pub use all_pairs::{align_all_pairs, PairwiseCosts};
+pub use auto::AstarPa2Auto;
+pub use band::{DoublingStart, DoublingType, ExponentialSearch};
+pub use block_width::BlockWidthMode;
+use block_width::BlockWidths;
+pub use checkpoint::{Checkpoint, Checkpointed, SearchState};
+use checkpoint::{CheckpointHook, Hooks};
 use domain::AstarPa2Stats;
+pub use error::AlignError;
+pub use mapper::{Mapper, Reference};
 use pa_bitpacking::W;
+pub use pa_heuristic::estimate_divergence;
 pub use params::*;
+#[cfg(feature = "tokio")]
+pub use task::{AlignTask, CancelHandle};
+#[cfg(feature = "metrics")]
+pub use telemetry::describe_metrics;
+use tracing::debug;
+pub use windows::{window_profile, AlignmentWindow};
 
 pub use blocks::BlockParams;
-use pa_affine_types::AffineCigar;
-use pa_heuristic::{Heuristic, HeuristicInstance, NoCostI};
+use pa_affine_types::{AffineAligner, AffineCigar};
+use pa_heuristic::{AlignResult, Heuristic, HeuristicInstance, HintCache, NoCostI, ResultAligner};
 use pa_types::*;
 use pa_vis::{VisualizerInstance, VisualizerT};
 use ranges::*;
+use std::{
+    cmp::{max, min},
+    ops::ControlFlow,
+    time::Instant,
+};
 
 use crate::domain::AstarPa2Instance;
 
 const DEBUG: bool = false;
 
-/// Block height 64.
+/// Block height: 64, or 128 with the `wide_blocks` feature of `pa-bitpacking`.
 pub const WI: I = W as I;
 
 /// Align two sequences using A*PA2-simple.
@@ -46,6 +79,18 @@ pub fn astarpa2_full(a: Seq, b: Seq) -> (Cost, Cigar) {
     (cost, cigar.unwrap())
 }
 
+/// The result of `AstarPa2::align_approx`.
+#[derive(Debug, Clone)]
+pub struct Approximation {
+    /// The cost of the found alignment, an upper bound on the optimal cost.
+    pub cost: Cost,
+    /// The found alignment, when tracing is enabled and one was found.
+    pub cigar: Option<Cigar>,
+    /// A proven lower bound on the optimal cost.
+    /// When equal to `cost`, the alignment is optimal.
+    pub lower_bound: Cost,
+}
+
 /// Typed parameters for A*PA2 containing heuristic and visualizer.
 #[derive(Debug)]
 pub struct AstarPa2<V: VisualizerT, H: Heuristic> {
@@ -59,6 +104,10 @@ pub struct AstarPa2<V: VisualizerT, H: Heuristic> {
     /// computations.
     pub block_width: I,
 
+    /// How the width of each block is chosen from `block_width`.
+    /// Local doubling only supports `BlockWidthMode::Fixed`.
+    pub block_width_mode: BlockWidthMode,
+
     /// The visualizer to use.
     pub v: V,
 
@@ -75,11 +124,32 @@ pub struct AstarPa2<V: VisualizerT, H: Heuristic> {
 
     /// Whether pruning is enabled.
     pub prune: bool,
+
+    /// When set, the `try_*` methods give up with `AlignError::TooDivergent`
+    /// once the cost is known to exceed this fraction of the length of the
+    /// longest sequence. The infallible methods (`align`, `cost`, and the
+    /// `Aligner` impl) ignore it.
+    ///
+    /// Band doubling and two-phase doubling never compute bands beyond the
+    /// bound. The other doubling types only check it against a lower bound on
+    /// the cost before filling, and against the final cost.
+    pub max_divergence: Option<f32>,
 }
 
 impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
     pub fn build<'a>(&'a self, a: Seq<'a>, b: Seq<'a>) -> AstarPa2Instance<'a, V, H> {
+        self.build_with_reference(a, b, None)
+    }
+
+    /// Same as `build`, but reuses precomputed data of the reference `b` when given.
+    pub fn build_with_reference<'a>(
+        &'a self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        reference: Option<&'a Reference<'a>>,
+    ) -> AstarPa2Instance<'a, V, H> {
         use Domain::*;
+        pa_heuristic::util::assert_fits_index(a, b);
 
         // init V
         let v = self.v.build(a, b);
@@ -91,9 +161,12 @@ impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
             GapStart => GapStart,
             GapGap => GapGap,
             Astar(h) => {
-                let h = h.build(a, b);
+                let h = match reference.and_then(|r| r.index.as_ref()) {
+                    Some(index) => h.build_with_index(a, b, index),
+                    None => h.build(a, b),
+                };
                 if DEBUG {
-                    eprintln!("h0: {}", h.h(Pos(0, 0)));
+                    debug!("h0: {}", h.h(Pos(0, 0)));
                 }
                 Astar(h)
             }
@@ -104,8 +177,16 @@ impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
             b,
             params: self,
             domain,
-            hint: Default::default(),
+            hints: HintCache::new(self.block_width),
             v,
+            reference,
+            pruned_blocks: None,
+            checkpoint: None,
+            block_widths: match self.block_width_mode {
+                BlockWidthMode::Fixed => None,
+                BlockWidthMode::Adaptive => Some(BlockWidths::new(self.block_width)),
+                BlockWidthMode::Band => Some(BlockWidths::from_band(self.block_width)),
+            },
             stats: AstarPa2Stats {
                 t_precomp: start.elapsed(),
                 ..Default::default()
@@ -113,9 +194,88 @@ impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
         }
     }
 
-    fn cost_or_align(&self, a: Seq, b: Seq, trace: bool) -> (Cost, Option<Cigar>, AstarPa2Stats) {
-        let mut nw = self.build(a, b);
+    /// The maximum cost allowed by `max_divergence`.
+    pub(crate) fn max_cost(&self, a: Seq, b: Seq) -> Cost {
+        self.max_divergence.map_or(Cost::MAX, |d| {
+            (d * max(a.len(), b.len()) as f32).floor() as Cost
+        })
+    }
+
+    fn cost_or_align<'a>(
+        &'a self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        trace: bool,
+        reference: Option<&'a Reference<'a>>,
+    ) -> (Cost, Option<Cigar>, AstarPa2Stats) {
+        match self.try_cost_or_align(
+            a,
+            b,
+            trace,
+            reference,
+            Cost::MAX,
+            Hooks::abort(&mut |_| false),
+        ) {
+            Ok(r) => r,
+            Err(AlignError::TooLong { .. }) => {
+                pa_heuristic::util::assert_fits_index(a, b);
+                unreachable!()
+            }
+            Err(e) => {
+                unreachable!("Alignments without a bound or abort hook cannot fail, but got: {e}")
+            }
+        }
+    }
+
+    /// Returns `AlignError::TooDivergent` once the cost is known to exceed `max_cost`.
+    /// `hooks.abort` is checked before each band doubling iteration.
+    /// Other doubling types do not support aborting or resuming.
+    pub(crate) fn try_cost_or_align<'a>(
+        &'a self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        trace: bool,
+        reference: Option<&'a Reference<'a>>,
+        max_cost: Cost,
+        hooks: Hooks<'a>,
+    ) -> Result<(Cost, Option<Cigar>, AstarPa2Stats), AlignError> {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let r = self.try_cost_or_align_inner(a, b, trace, reference, max_cost, hooks);
+        #[cfg(feature = "metrics")]
+        telemetry::record(&r, start.elapsed());
+        r
+    }
+
+    fn try_cost_or_align_inner<'a>(
+        &'a self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        trace: bool,
+        reference: Option<&'a Reference<'a>>,
+        max_cost: Cost,
+        hooks: Hooks<'a>,
+    ) -> Result<(Cost, Option<Cigar>, AstarPa2Stats), AlignError> {
+        let Hooks {
+            resume,
+            abort,
+            checkpoint,
+        } = hooks;
+        if !pa_heuristic::util::fits_index(a, b) {
+            return Err(AlignError::TooLong {
+                len_a: a.len(),
+                len_b: b.len(),
+            });
+        }
+        let mut nw = self.build_with_reference(a, b, reference);
+        nw.checkpoint = checkpoint;
         let h0 = nw.domain.h().map_or(0, |h| h.h(Pos(0, 0)));
+        // Check the bound before filling anything. This is the only check
+        // before the final one for doubling types other than band doubling.
+        let lower_bound = max(h0, a.len().abs_diff(b.len()) as Cost);
+        if lower_bound > max_cost {
+            return Err(AlignError::TooDivergent { lower_bound });
+        }
         let (cost, cigar) = match self.doubling {
             DoublingType::None => {
                 // FIXME: Allow single-shot alignment with bounded dist.
@@ -124,7 +284,7 @@ impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
             }
             DoublingType::LinearSearch { start, delta } => {
                 let start_f = start.initial_values(a, b, h0).0;
-                let mut blocks = self.block.new(trace, a, b);
+                let mut blocks = nw.new_blocks(trace);
                 band::linear_search(start_f, delta as Cost, |s| {
                     nw.align_for_bounded_dist(Some(s), trace, Some(&mut blocks))
                         .map(|x @ (c, _)| (c, x))
@@ -142,19 +302,77 @@ impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
                 {
                     start_increment = si;
                 }
-                let mut blocks = self.block.new(trace, a, b);
-                let r = band::exponential_search(start_f, start_increment, factor, |s| {
-                    nw.align_for_bounded_dist(Some(s), trace, Some(&mut blocks))
-                        .map(|x @ (c, _)| (c, x))
-                })
-                .1;
+                let resumable = resume.is_some();
+                let mut state = resume.unwrap_or_default();
+                state.check_input(a, b);
+                if resumable {
+                    nw.pruned_blocks = Some(vec![]);
+                    if self.prune
+                        && let Domain::Astar(h) = &mut nw.domain
+                    {
+                        for (i_range, j_range) in &state.pruned_blocks {
+                            h.prune_block(i_range.clone(), j_range.clone());
+                        }
+                    }
+                }
+                let mut search = state.search.unwrap_or_else(|| {
+                    band::ExponentialSearch::new(start_f, start_increment, factor, max_cost)
+                });
+                state.len_a = a.len();
+                state.len_b = b.len();
+
+                let mut blocks = nw.new_blocks(trace);
+                let r = loop {
+                    state.search = Some(search);
+                    if abort(&state) {
+                        break Err(search.bounds());
+                    }
+                    let r = nw.align_for_bounded_dist(Some(search.s()), trace, Some(&mut blocks));
+                    if let Some(pruned_blocks) = &mut nw.pruned_blocks {
+                        state.pruned_blocks.append(pruned_blocks);
+                    }
+                    match search.step(r.as_ref().map(|&(cost, _)| cost)) {
+                        ControlFlow::Continue(()) => {}
+                        ControlFlow::Break(Ok(_)) => break Ok(r.unwrap()),
+                        ControlFlow::Break(Err(bounds)) => break Err(bounds),
+                    }
+                };
+                nw.stats.block_stats = blocks.stats;
+                match r {
+                    Ok(r) => r,
+                    Err((lower_bound, _)) if lower_bound > max_cost => {
+                        return Err(AlignError::TooDivergent { lower_bound })
+                    }
+                    Err((lower_bound, upper_bound)) => {
+                        return Err(AlignError::Aborted {
+                            lower_bound: max(lower_bound, h0),
+                            upper_bound,
+                        })
+                    }
+                }
+            }
+            DoublingType::TwoPhase => {
+                let f_max = min(band::greedy_upper_bound(a, b), max_cost);
+                let mut blocks = nw.new_blocks(trace);
+                let r = nw.align_for_bounded_dist(Some(f_max), trace, Some(&mut blocks));
                 nw.stats.block_stats = blocks.stats;
-                r
+                match r {
+                    Some(r @ (c, _)) if c <= f_max => r,
+                    _ => {
+                        return Err(AlignError::TooDivergent {
+                            lower_bound: f_max + 1,
+                        })
+                    }
+                }
             }
             // NOTE: This is not in the paper since it does not yet work much
             // better than (global) band doubling in practice.
             DoublingType::LocalDoubling => {
                 assert!(self.prune, "Local doubling requires pruning.");
+                assert!(
+                    self.block_width_mode == BlockWidthMode::Fixed,
+                    "Local doubling only supports a fixed block width."
+                );
                 let (cost, cigar) = nw.local_doubling();
                 (cost, Some(cigar))
             }
@@ -165,18 +383,236 @@ impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
             None,
         );
         assert!(h0 <= cost, "Heuristic at start {h0} > final cost {cost}.");
-        (cost, cigar, nw.stats)
+        if cost > max_cost {
+            return Err(AlignError::TooDivergent { lower_bound: cost });
+        }
+        if let Some(h) = nw.domain.h_mut() {
+            nw.stats.h = h.stats();
+        }
+        Ok((cost, cigar, nw.stats))
+    }
+
+    /// Same as `cost`, but returns an error when `max_divergence` is exceeded.
+    pub fn try_cost(&self, a: Seq, b: Seq) -> Result<Cost, AlignError> {
+        self.try_cost_or_align(
+            a,
+            b,
+            false,
+            None,
+            self.max_cost(a, b),
+            Hooks::abort(&mut |_| false),
+        )
+        .map(|r| r.0)
+    }
+
+    /// Same as `align`, but returns an error when `max_divergence` is exceeded.
+    pub fn try_align(&self, a: Seq, b: Seq) -> Result<(Cost, Option<Cigar>), AlignError> {
+        self.try_align_with_abort(a, b, || false)
+    }
+
+    /// Same as `try_align`, but returns `AlignError::Aborted` once `abort`
+    /// returns true. This is checked before each band doubling iteration, and
+    /// can be used for cancellation or to limit memory usage.
+    pub fn try_align_with_abort(
+        &self,
+        a: Seq,
+        b: Seq,
+        mut abort: impl FnMut() -> bool,
+    ) -> Result<(Cost, Option<Cigar>), AlignError> {
+        self.try_cost_or_align(
+            a,
+            b,
+            self.trace,
+            None,
+            self.max_cost(a, b),
+            Hooks::abort(&mut |_| abort()),
+        )
+        .map(|(cost, cigar, _stats)| (cost, cigar))
+    }
+
+    /// Same as `try_align_with_abort`, but `abort` also receives the state of
+    /// the band doubling search. This state can be stored, e.g. serialized to
+    /// disk, and passed as `resume` to continue an aborted alignment later.
+    ///
+    /// Only band doubling can be resumed.
+    pub fn try_align_resumable(
+        &self,
+        a: Seq,
+        b: Seq,
+        resume: Option<SearchState>,
+        mut abort: impl FnMut(&SearchState) -> bool,
+    ) -> Result<(Cost, Option<Cigar>), AlignError> {
+        assert!(
+            self.doubling.factor().is_some(),
+            "Only band doubling can be resumed."
+        );
+        self.try_cost_or_align(
+            a,
+            b,
+            self.trace,
+            None,
+            self.max_cost(a, b),
+            Hooks {
+                resume: Some(resume.unwrap_or_default()),
+                abort: &mut abort,
+                checkpoint: None,
+            },
+        )
+        .map(|(cost, cigar, _stats)| (cost, cigar))
+    }
+
+    /// Same as `try_align`, but aborts once `timeout` has passed.
+    /// Note that the iteration running at that time is finished first.
+    pub fn try_align_with_timeout(
+        &self,
+        a: Seq,
+        b: Seq,
+        timeout: std::time::Duration,
+    ) -> Result<(Cost, Option<Cigar>), AlignError> {
+        let start = std::time::Instant::now();
+        self.try_align_with_abort(a, b, || start.elapsed() >= timeout)
+    }
+
+    /// Approximate alignment that stops band doubling once `f_max` has been tried.
+    ///
+    /// When the cost is at most `f_max`, the optimal alignment is returned.
+    /// Otherwise, this returns the best alignment found in the last band, or
+    /// only the cost of a greedy alignment when no path was found at all,
+    /// together with a lower bound on the optimal cost of at least `f_max + 1`.
+    ///
+    /// Uses the start and factor of `doubling` when it is band doubling.
+    pub fn align_approx(&self, a: Seq, b: Seq, f_max: Cost) -> Approximation {
+        let mut nw = self.build(a, b);
+        let h0 = nw.domain.h().map_or(0, |h| h.h(Pos(0, 0)));
+        let (start, factor) = match self.doubling {
+            DoublingType::BandDoubling { start, factor }
+            | DoublingType::BandDoublingStartIncrement { start, factor, .. } => (start, factor),
+            _ => (DoublingStart::H0, 2.0),
+        };
+        let (start_f, start_increment) = start.initial_values(a, b, h0);
+        let start_increment = start_increment.max(self.block_width as i32);
+
+        let mut blocks = nw.new_blocks(self.trace);
+        // The cheapest path found for any of the tried bounds.
+        let mut best: Option<(Cost, Option<Cigar>)> = None;
+        let r = band::bounded_exponential_search(start_f, start_increment, factor, f_max, |s| {
+            let r = nw.align_for_bounded_dist(Some(s), self.trace, Some(&mut blocks));
+            if let Some((c, cigar)) = &r
+                && best.as_ref().map_or(true, |(best_c, _)| c < best_c)
+            {
+                best = Some((*c, cigar.clone()));
+            }
+            r.map(|x @ (c, _)| (c, x))
+        });
+        match r {
+            Ok((cost, (_, cigar))) => Approximation {
+                cost,
+                cigar,
+                lower_bound: cost,
+            },
+            Err(lower_bound) => {
+                let lower_bound = max(lower_bound, h0);
+                let (cost, cigar) = best.unwrap_or_else(|| (band::greedy_upper_bound(a, b), None));
+                Approximation {
+                    cost,
+                    cigar,
+                    lower_bound,
+                }
+            }
+        }
     }
 
     pub fn cost(&self, a: Seq, b: Seq) -> Cost {
-        self.cost_or_align(a, b, false).0
+        self.cost_or_align(a, b, false, None).0
+    }
+
+    /// Align and pass the cigar to `emit` one run at a time, instead of
+    /// returning a `Cigar`.
+    ///
+    /// Runs are emitted in reverse order, from the end of the alignment to the
+    /// start, as soon as the traceback leaves each block, so that alignments
+    /// of very long sequences do not hold a large `Vec<CigarElem>`.
+    /// Consecutive runs may have the same operation.
+    /// Only band doubling, and `DoublingType::None` with `Domain::Full`, are
+    /// supported; other combinations return `AlignError::Unsupported`.
+    pub fn align_streaming(
+        &self,
+        a: Seq,
+        b: Seq,
+        mut emit: impl FnMut(CigarElem),
+    ) -> Result<Cost, AlignError> {
+        if !pa_heuristic::util::fits_index(a, b) {
+            return Err(AlignError::TooLong {
+                len_a: a.len(),
+                len_b: b.len(),
+            });
+        }
+        let unsupported = match self.doubling {
+            DoublingType::None => !matches!(self.domain, Domain::Full),
+            DoublingType::BandDoubling { .. } | DoublingType::BandDoublingStartIncrement { .. } => {
+                false
+            }
+            _ => true,
+        };
+        if unsupported {
+            return Err(AlignError::Unsupported {
+                what: "Streaming alignment with this doubling type and domain",
+            });
+        }
+        let mut nw = self.build(a, b);
+        let h0 = nw.domain.h().map_or(0, |h| h.h(Pos(0, 0)));
+        let mut blocks = nw.new_blocks(true);
+        let cost = match self.doubling {
+            DoublingType::None => {
+                nw.align_for_bounded_dist(None, false, Some(&mut blocks))
+                    .unwrap()
+                    .0
+            }
+            DoublingType::BandDoubling { start, factor }
+            | DoublingType::BandDoublingStartIncrement { start, factor, .. } => {
+                let (start_f, start_increment) = start.initial_values(a, b, h0);
+                let start_increment = start_increment.max(self.block_width as i32);
+                band::exponential_search(start_f, start_increment, factor, |s| {
+                    nw.align_for_bounded_dist(Some(s), false, Some(&mut blocks))
+                        .map(|(c, _)| (c, ()))
+                })
+                .0
+            }
+            _ => unreachable!(),
+        };
+        blocks.trace_streaming(
+            nw.a,
+            nw.b,
+            Pos(0, 0),
+            Pos::target(nw.a, nw.b),
+            &mut nw.v,
+            &mut emit,
+        );
+        Ok(cost)
     }
 
     pub fn align(&self, a: Seq, b: Seq) -> (Cost, Option<Cigar>) {
-        let (cost, cigar, _stats) = self.cost_or_align(a, b, self.trace);
+        let (cost, cigar, _stats) = self.cost_or_align(a, b, self.trace, None);
         (cost, cigar)
     }
 
+    /// Align and additionally return the identity and cost in windows of
+    /// `window` alignment columns, starting every `step` columns.
+    /// See `window_profile`. The windows are empty when `trace` is disabled.
+    pub fn align_with_windows(
+        &self,
+        a: Seq,
+        b: Seq,
+        window: I,
+        step: I,
+    ) -> (Cost, Option<Cigar>, Vec<AlignmentWindow>) {
+        let (cost, cigar) = self.align(a, b);
+        let windows = cigar
+            .as_ref()
+            .map_or(vec![], |cigar| window_profile(cigar, window, step));
+        (cost, cigar, windows)
+    }
+
     pub fn cost_for_bounded_dist(&self, a: Seq, b: Seq, f_max: Cost) -> Option<Cost> {
         self.build(a, b)
             .align_for_bounded_dist(Some(f_max), false, None)
@@ -191,19 +627,51 @@ impl<V: VisualizerT, H: Heuristic> AstarPa2<V, H> {
 }
 
 /// Helper trait to erase the type of the heuristic that additionally returns alignment statistics.
-pub trait AstarPa2StatsAligner: Aligner {
+pub trait AstarPa2StatsAligner: ResultAligner {
     fn align_with_stats(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AstarPa2Stats);
+
+    /// Compute the cost if it is at most `f_max`, using a single fixed band.
+    fn cost_for_bounded_dist(&self, a: Seq, b: Seq, f_max: Cost) -> Option<Cost>;
+
+    /// See `AstarPa2::try_cost`.
+    fn try_cost(&self, a: Seq, b: Seq) -> Result<Cost, AlignError>;
 }
 
 impl<V: VisualizerT, H: Heuristic> AstarPa2StatsAligner for AstarPa2<V, H> {
     fn align_with_stats(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>, AstarPa2Stats) {
-        self.cost_or_align(a, b, self.trace)
+        self.cost_or_align(a, b, self.trace, None)
+    }
+
+    fn cost_for_bounded_dist(&self, a: Seq, b: Seq, f_max: Cost) -> Option<Cost> {
+        AstarPa2::cost_for_bounded_dist(self, a, b, f_max)
+    }
+
+    fn try_cost(&self, a: Seq, b: Seq) -> Result<Cost, AlignError> {
+        AstarPa2::try_cost(self, a, b)
+    }
+}
+
+impl<V: VisualizerT, H: Heuristic> ResultAligner for AstarPa2<V, H> {
+    fn align_result(&mut self, a: Seq, b: Seq) -> AlignResult {
+        let start = Instant::now();
+        let (cost, cigar, stats) = self.cost_or_align(a, b, self.trace, None);
+        let mut stats = stats.to_align_stats();
+        stats.t_total = start.elapsed();
+        AlignResult::new(cost, cigar, stats)
     }
 }
 
 impl<V: VisualizerT, H: Heuristic> Aligner for AstarPa2<V, H> {
     fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>) {
-        let (cost, cigar, _stats) = self.cost_or_align(a, b, self.trace);
+        let (cost, cigar, _stats) = self.cost_or_align(a, b, self.trace, None);
         (cost, cigar)
     }
 }
+
+/// Unit costs only, so that A*PA2 can be used wherever the affine aligners are.
+impl<V: VisualizerT, H: Heuristic> AffineAligner for AstarPa2<V, H> {
+    fn align_affine(&mut self, a: Seq, b: Seq) -> (Cost, Option<AffineCigar>) {
+        let (cost, cigar) = Aligner::align(self, a, b);
+        (cost, cigar.as_ref().map(AffineCigar::from))
+    }
+}
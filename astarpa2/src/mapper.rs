@@ -0,0 +1,154 @@
+//! Aligning many queries against a single fixed reference.
+//!
+//! The reference is the `b` sequence of each alignment. Its bit-profile and
+//! k-mer index are computed once and reused for every query.
+//!
+//! For very large references, e.g. a memory-mapped genome, use `Mapper::lazy`
+//! and `Mapper::align_window`: then nothing is precomputed, and only the
+//! profile of the aligned window is built.
+use super::*;
+use pa_bitpacking::{BitProfile, Profile};
+use pa_heuristic::{DefaultCSH, MatchConfig, ReferenceIndex, GCSH};
+use pa_vis::NoVis;
+use std::ops::Range;
+use std::sync::Arc;
+
+/// Precomputed data of a reference sequence `b`.
+pub struct Reference<'r> {
+    pub seq: Seq<'r>,
+    /// The bit-profile of the reference, as used by `Blocks`.
+    /// It is shared with the blocks of each alignment instead of copied.
+    /// `None` for lazy references, for which it is built for each alignment.
+    pub profile: Option<Arc<[<BitProfile as Profile>::B]>>,
+    /// The k-mer index of the reference, for heuristics that use matches.
+    pub index: Option<ReferenceIndex>,
+}
+
+impl<'r> Reference<'r> {
+    /// Preprocess `seq`. The k-mer index is only built when `match_config` is
+    /// given and supported by `ReferenceIndex`.
+    pub fn new(seq: Seq<'r>, match_config: Option<MatchConfig>) -> Self {
+        Self {
+            seq,
+            profile: Some(BitProfile::build_b(seq).into()),
+            index: match_config.and_then(|mc| ReferenceIndex::new(seq, mc).ok()),
+        }
+    }
+
+    /// Wrap `seq` without any preprocessing.
+    pub fn lazy(seq: Seq<'r>) -> Self {
+        Self {
+            seq,
+            profile: None,
+            index: None,
+        }
+    }
+}
+
+/// Align many queries against one reference, sharing the preprocessing of the reference.
+pub struct Mapper<'r, V: VisualizerT, H: Heuristic> {
+    pub aligner: AstarPa2<V, H>,
+    reference: Reference<'r>,
+}
+
+impl<'r> Mapper<'r, NoVis, DefaultCSH> {
+    /// A mapper using the A*PA2-full parameters.
+    pub fn new(reference: Seq<'r>) -> Self {
+        Self::with_aligner(Self::full_aligner(), reference)
+    }
+
+    /// A lazy mapper using the A*PA2-full parameters. See `lazy_with_aligner`.
+    pub fn lazy(reference: Seq<'r>) -> Self {
+        Self::lazy_with_aligner(Self::full_aligner(), reference)
+    }
+
+    fn full_aligner() -> AstarPa2<NoVis, DefaultCSH> {
+        let params = AstarPa2Params::full();
+        let h = &params.heuristic;
+        let h = GCSH::new(h.match_config(), h.pruning());
+        params.make_aligner_with_domain(Domain::Astar(h), true, NoVis)
+    }
+}
+
+impl<'r, V: VisualizerT, H: Heuristic> Mapper<'r, V, H> {
+    /// A mapper using the given aligner.
+    /// The k-mer index is only built when the heuristic uses fixed-length seeds.
+    pub fn with_aligner(aligner: AstarPa2<V, H>, reference: Seq<'r>) -> Self {
+        let match_config = aligner.domain.h().and_then(|h| h.match_config());
+        Self {
+            reference: Reference::new(reference, match_config),
+            aligner,
+        }
+    }
+
+    /// A mapper that does not preprocess the reference, which may be any
+    /// `&[u8]`, including a memory-mapped file.
+    pub fn lazy_with_aligner(aligner: AstarPa2<V, H>, reference: Seq<'r>) -> Self {
+        Self {
+            reference: Reference::lazy(reference),
+            aligner,
+        }
+    }
+
+    pub fn reference(&self) -> Seq<'r> {
+        self.reference.seq
+    }
+
+    pub fn cost(&self, query: Seq) -> Cost {
+        self.aligner
+            .cost_or_align(query, self.reference.seq, false, Some(&self.reference))
+            .0
+    }
+
+    /// Align `query` (as `a`) to the reference (as `b`).
+    pub fn align(&self, query: Seq) -> (Cost, Option<Cigar>) {
+        let (cost, cigar, _stats) = self.align_with_stats(query);
+        (cost, cigar)
+    }
+
+    /// Same as `cost`, but returns an error when `max_divergence` is exceeded.
+    pub fn try_cost(&self, query: Seq) -> Result<Cost, AlignError> {
+        self.try_cost_or_align(query, false).map(|r| r.0)
+    }
+
+    /// Same as `align`, but returns an error when `max_divergence` is exceeded.
+    pub fn try_align(&self, query: Seq) -> Result<(Cost, Option<Cigar>), AlignError> {
+        self.try_cost_or_align(query, self.aligner.trace)
+            .map(|(cost, cigar, _stats)| (cost, cigar))
+    }
+
+    fn try_cost_or_align(
+        &self,
+        query: Seq,
+        trace: bool,
+    ) -> Result<(Cost, Option<Cigar>, AstarPa2Stats), AlignError> {
+        self.aligner.try_cost_or_align(
+            query,
+            self.reference.seq,
+            trace,
+            Some(&self.reference),
+            self.aligner.max_cost(query, self.reference.seq),
+            Hooks::abort(&mut |_| false),
+        )
+    }
+
+    pub fn align_with_stats(&self, query: Seq) -> (Cost, Option<Cigar>, AstarPa2Stats) {
+        self.aligner.cost_or_align(
+            query,
+            self.reference.seq,
+            self.aligner.trace,
+            Some(&self.reference),
+        )
+    }
+
+    /// Align `query` (as `a`) to the window `range` of the reference (as `b`).
+    ///
+    /// Only the profile of the window is built, and the precomputed data of
+    /// the reference, if any, is not used.
+    pub fn align_window(&self, query: Seq, range: Range<usize>) -> (Cost, Option<Cigar>) {
+        let (cost, cigar, _stats) =
+            self.aligner
+                .cost_or_align(query, &self.reference.seq[range], self.aligner.trace, None);
+        (cost, cigar)
+    }
+}
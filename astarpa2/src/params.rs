@@ -25,6 +25,10 @@ pub struct AstarPa2Params {
     /// computations.
     pub block_width: I,
 
+    /// How the width of each block is chosen. See `AstarPa2::block_width_mode`.
+    #[serde(default)]
+    pub block_width_mode: BlockWidthMode,
+
     /// The front type to use.
     pub front: BlockParams,
 
@@ -36,6 +40,10 @@ pub struct AstarPa2Params {
     #[serde(default)]
     pub prune: bool,
 
+    /// Give up when the cost exceeds this fraction of the sequence length.
+    #[serde(default)]
+    pub max_divergence: Option<f32>,
+
     /// Whether the visualizer is enabled.
     #[serde(default)]
     pub viz: bool,
@@ -55,6 +63,7 @@ impl AstarPa2Params {
                 factor: 2.0,
             },
             block_width: 256,
+            block_width_mode: BlockWidthMode::Fixed,
             front: BlockParams {
                 sparse: true,
                 simd: true,
@@ -63,9 +72,11 @@ impl AstarPa2Params {
                 dt_trace: true,
                 max_g: 40,
                 fr_drop: 10,
+                trace_order: None,
             },
             sparse_h: true,
             prune: false,
+            max_divergence: None,
             viz: false,
         }
     }
@@ -87,6 +98,7 @@ impl AstarPa2Params {
                 factor: 2.0,
             },
             block_width: 256,
+            block_width_mode: BlockWidthMode::Fixed,
             front: BlockParams {
                 sparse: true,
                 simd: true,
@@ -95,9 +107,11 @@ impl AstarPa2Params {
                 dt_trace: true,
                 max_g: 40,
                 fr_drop: 10,
+                trace_order: None,
             },
             sparse_h: true,
             prune: true,
+            max_divergence: None,
             viz: false,
         }
     }
@@ -165,16 +179,10 @@ impl AstarPa2Params {
         impl<V: VisualizerT + 'static> HeuristicMapper for Mapper<V> {
             type R = Box<dyn AstarPa2StatsAligner>;
             fn call<H: Heuristic + 'static>(self, h: H) -> Box<dyn AstarPa2StatsAligner> {
-                Box::new(AstarPa2 {
-                    domain: Domain::Astar(h),
-                    doubling: self.params.doubling,
-                    block_width: self.params.block_width,
-                    v: self.v,
-                    block: self.params.front,
-                    trace: self.trace,
-                    sparse_h: self.params.sparse_h,
-                    prune: self.params.prune,
-                })
+                Box::new(
+                    self.params
+                        .make_aligner_with_domain(Domain::Astar(h), self.trace, self.v),
+                )
             }
         }
         match self.domain {
@@ -183,16 +191,29 @@ impl AstarPa2Params {
                 trace,
                 v,
             }),
-            d => Box::new(AstarPa2 {
-                domain: d.into(),
-                doubling: self.doubling,
-                block_width: self.block_width,
-                v,
-                block: self.front,
-                trace,
-                sparse_h: self.sparse_h,
-                prune: self.prune,
-            }),
+            d => Box::new(self.make_aligner_with_domain(d.into(), trace, v)),
+        }
+    }
+
+    /// Convert to an `AstarPa2` instance for the given domain, ignoring
+    /// `self.domain` and `self.heuristic`.
+    pub fn make_aligner_with_domain<V: VisualizerT, H: Heuristic>(
+        &self,
+        domain: Domain<H>,
+        trace: bool,
+        v: V,
+    ) -> AstarPa2<V, H> {
+        AstarPa2 {
+            domain,
+            doubling: self.doubling,
+            block_width: self.block_width,
+            block_width_mode: self.block_width_mode,
+            v,
+            block: self.front,
+            trace,
+            sparse_h: self.sparse_h,
+            prune: self.prune,
+            max_divergence: self.max_divergence,
         }
     }
 }
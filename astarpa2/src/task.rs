@@ -0,0 +1,176 @@
+//! Running alignments as futures on tokio's blocking thread pool.
+//!
+//! The alignment itself does not yield; it runs in `spawn_blocking` and checks
+//! for cancellation before each band doubling iteration, as
+//! `AstarPa2::try_align_with_abort` does.
+//! The running iteration is never interrupted, and is usually the most
+//! expensive one. Other doubling types do not check for cancellation, and are
+//! refused by `AstarPa2::spawn_align`.
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use tokio::task::JoinHandle;
+
+use super::*;
+
+/// Cancels the alignment of an `AlignTask`, which then returns
+/// `AlignError::Aborted` before its next band doubling iteration.
+#[derive(Debug, Clone, Default)]
+pub struct CancelHandle(Arc<AtomicBool>);
+
+impl CancelHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// An alignment running on tokio's blocking thread pool, as returned by
+/// `AstarPa2::spawn_align`. Resolves to the result of `AstarPa2::try_align`.
+///
+/// Dropping the task cancels the alignment, as `CancelHandle::cancel` does.
+/// E.g. `tokio::time::timeout` returns on time, but the blocking thread keeps
+/// running until the current band doubling iteration is done.
+#[derive(Debug)]
+pub struct AlignTask {
+    handle: JoinHandle<Result<(Cost, Option<Cigar>), AlignError>>,
+    cancel: CancelHandle,
+}
+
+impl AlignTask {
+    /// A handle to cancel the alignment without dropping the task.
+    pub fn cancel_handle(&self) -> CancelHandle {
+        self.cancel.clone()
+    }
+}
+
+impl Future for AlignTask {
+    type Output = Result<(Cost, Option<Cigar>), AlignError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.handle).poll(cx).map(|r| match r {
+            Ok(r) => r,
+            // Propagate panics of the aligner to the caller.
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => panic!("Alignment task failed: {e}"),
+        })
+    }
+}
+
+impl Drop for AlignTask {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+impl<V: VisualizerT + Send + 'static, H: Heuristic + Send + 'static> AstarPa2<V, H> {
+    /// Align `a` and `b` on tokio's blocking thread pool.
+    /// Must be called from within a tokio runtime.
+    ///
+    /// Only band doubling checks for cancellation, so other doubling types
+    /// return `AlignError::Unsupported` instead of a task that can not be
+    /// cancelled.
+    pub fn spawn_align(self, a: Vec<u8>, b: Vec<u8>) -> Result<AlignTask, AlignError> {
+        self.spawn_align_with_cancel(a, b, CancelHandle::default())
+    }
+
+    fn spawn_align_with_cancel(
+        self,
+        a: Vec<u8>,
+        b: Vec<u8>,
+        cancel: CancelHandle,
+    ) -> Result<AlignTask, AlignError> {
+        if !matches!(
+            self.doubling,
+            DoublingType::BandDoubling { .. } | DoublingType::BandDoublingStartIncrement { .. }
+        ) {
+            return Err(AlignError::Unsupported {
+                what: "Cancelling alignments with this doubling type",
+            });
+        }
+        let handle = {
+            let cancel = cancel.clone();
+            tokio::task::spawn_blocking(move || {
+                self.try_align_with_abort(&a, &b, || cancel.is_cancelled())
+            })
+        };
+        Ok(AlignTask { handle, cancel })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pa_generate::uniform_fixed;
+    use pa_heuristic::{MatchConfig, Pruning, GCSH};
+    use pa_vis::NoVis;
+
+    fn aligner() -> AstarPa2<NoVis, impl Heuristic + Send + 'static> {
+        AstarPa2 {
+            doubling: DoublingType::band_doubling(),
+            domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+            block_width: 256,
+            block_width_mode: BlockWidthMode::Fixed,
+            v: NoVis,
+            block: BlockParams::default(),
+            trace: true,
+            sparse_h: true,
+            prune: true,
+            max_divergence: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn spawn_align() {
+        let (a, b) = uniform_fixed(2000, 0.1);
+        let expected = aligner().align(&a, &b).0;
+        let (cost, cigar) = aligner().spawn_align(a, b).unwrap().await.unwrap();
+        assert_eq!(cost, expected);
+        assert!(cigar.is_some());
+    }
+
+    #[tokio::test]
+    async fn drop_cancels() {
+        let (a, b) = uniform_fixed(2000, 0.1);
+        let task = aligner().spawn_align(a, b).unwrap();
+        let cancel = task.cancel_handle();
+        assert!(!cancel.is_cancelled());
+        drop(task);
+        assert!(cancel.is_cancelled());
+    }
+
+    #[tokio::test]
+    async fn cancel_aborts() {
+        let (a, b) = uniform_fixed(2000, 0.1);
+        let cancel = CancelHandle::default();
+        cancel.cancel();
+        let r = aligner()
+            .spawn_align_with_cancel(a, b, cancel)
+            .unwrap()
+            .await;
+        assert!(matches!(r, Err(AlignError::Aborted { .. })), "{r:?}");
+    }
+
+    #[tokio::test]
+    async fn refuses_uncancellable() {
+        let (a, b) = uniform_fixed(100, 0.1);
+        let aligner = AstarPa2 {
+            doubling: DoublingType::TwoPhase,
+            ..aligner()
+        };
+        assert!(matches!(
+            aligner.spawn_align(a, b),
+            Err(AlignError::Unsupported { .. })
+        ));
+    }
+}
@@ -0,0 +1,147 @@
+//! Counters and histograms for each alignment, emitted via the `metrics` facade.
+//!
+//! Install any `metrics` recorder, e.g. `metrics-exporter-prometheus`, to
+//! collect them. Without a recorder, emitting is a no-op.
+use std::time::Duration;
+
+use metrics::{counter, describe_counter, describe_histogram, histogram, Unit};
+
+use super::*;
+use crate::blocks::BlockStats;
+
+const ALIGNMENTS: &str = "astarpa2_alignments_total";
+const ITERATIONS: &str = "astarpa2_band_doubling_iterations_total";
+const COMPUTED_LANES: &str = "astarpa2_computed_lanes_total";
+const BAND_SIZE: &str = "astarpa2_band_size";
+const DURATION: &str = "astarpa2_duration_seconds";
+
+/// Register descriptions of all emitted metrics with the installed recorder.
+pub fn describe_metrics() {
+    describe_counter!(
+        ALIGNMENTS,
+        Unit::Count,
+        "Number of alignments, by result: ok, too_long, too_divergent, aborted, or unsupported."
+    );
+    describe_counter!(
+        ITERATIONS,
+        Unit::Count,
+        "Number of band doubling iterations, i.e. tried values of f_max."
+    );
+    describe_counter!(
+        COMPUTED_LANES,
+        Unit::Count,
+        "Number of computed lanes of `W` rows, summed over all blocks and iterations."
+    );
+    describe_histogram!(
+        BAND_SIZE,
+        Unit::Count,
+        "Average number of computed rows per block."
+    );
+    describe_histogram!(
+        DURATION,
+        Unit::Seconds,
+        "Wall-clock duration of an alignment."
+    );
+}
+
+/// Emit the metrics of one alignment.
+pub(crate) fn record(
+    r: &Result<(Cost, Option<Cigar>, AstarPa2Stats), AlignError>,
+    duration: Duration,
+) {
+    let result = match r {
+        Ok(_) => "ok",
+        Err(AlignError::TooLong { .. }) => "too_long",
+        Err(AlignError::TooDivergent { .. }) => "too_divergent",
+        Err(AlignError::Aborted { .. }) => "aborted",
+        Err(AlignError::Unsupported { .. }) => "unsupported",
+    };
+    counter!(ALIGNMENTS, "result" => result).increment(1);
+    histogram!(DURATION).record(duration.as_secs_f64());
+
+    let Ok((_, _, stats)) = r else {
+        return;
+    };
+    counter!(ITERATIONS).increment(stats.f_max_tries as u64);
+    let BlockStats {
+        computed_lanes,
+        num_incremental_blocks,
+        ..
+    } = stats.block_stats;
+    counter!(COMPUTED_LANES).increment(computed_lanes as u64);
+    if num_incremental_blocks > 0 {
+        let band = (computed_lanes * W) as f64 / num_incremental_blocks as f64;
+        histogram!(BAND_SIZE).record(band);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use itertools::Itertools;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use pa_heuristic::{MatchConfig, Pruning, GCSH};
+    use pa_vis::NoVis;
+
+    #[test]
+    fn record_alignments() {
+        let aligner = AstarPa2 {
+            doubling: DoublingType::band_doubling(),
+            domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+            block_width: 256,
+            block_width_mode: BlockWidthMode::Fixed,
+            v: NoVis,
+            block: BlockParams::default(),
+            trace: true,
+            sparse_h: true,
+            prune: true,
+            max_divergence: Some(0.1),
+        };
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        metrics::with_local_recorder(&recorder, || {
+            let (a, b) = pa_generate::uniform_seeded(1000, 0.02, 31415);
+            assert!(aligner.try_align(&a, &b).is_ok());
+            let (a, b) = pa_generate::uniform_seeded(1000, 0.5, 31415);
+            assert!(aligner.try_align(&a, &b).is_err());
+        });
+
+        let metrics = snapshotter
+            .snapshot()
+            .into_vec()
+            .into_iter()
+            .map(|(key, _, _, value)| {
+                let (_, key) = key.into_parts();
+                let labels = key
+                    .labels()
+                    .map(|l| format!("{}={}", l.key(), l.value()))
+                    .join(",");
+                ((key.name().to_string(), labels), value)
+            })
+            .collect::<std::collections::HashMap<_, _>>();
+        let get = |name: &str, labels: &str| &metrics[&(name.to_string(), labels.to_string())];
+
+        assert_eq!(get(ALIGNMENTS, "result=ok"), &DebugValue::Counter(1));
+        assert_eq!(
+            get(ALIGNMENTS, "result=too_divergent"),
+            &DebugValue::Counter(1)
+        );
+        // Only successful alignments record their statistics.
+        let DebugValue::Counter(iterations) = get(ITERATIONS, "") else {
+            panic!("Expected a counter");
+        };
+        assert!(*iterations >= 1);
+        let DebugValue::Counter(lanes) = get(COMPUTED_LANES, "") else {
+            panic!("Expected a counter");
+        };
+        assert!(*lanes > 0);
+        let DebugValue::Histogram(bands) = get(BAND_SIZE, "") else {
+            panic!("Expected a histogram");
+        };
+        assert_eq!(bands.len(), 1);
+        let DebugValue::Histogram(durations) = get(DURATION, "") else {
+            panic!("Expected a histogram");
+        };
+        assert_eq!(durations.len(), 2);
+    }
+}
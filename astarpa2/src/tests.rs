@@ -8,11 +8,13 @@ fn nw() -> AstarPa2<NoVis, NoCost> {
         doubling: DoublingType::None,
         domain: Domain::full(),
         block_width: 1,
+        block_width_mode: BlockWidthMode::Fixed,
         v: NoVis,
         block: BlockParams::default(),
         trace: true,
         sparse_h: true,
         prune: true,
+        max_divergence: None,
     }
 }
 
@@ -45,6 +47,45 @@ fn dt_trace_gapgap() {
     })
 }
 
+#[test]
+fn trace_order() {
+    use TraceEdge::*;
+    for order in [[Sub, Ins, Del], [Del, Sub, Ins], [Ins, Del, Sub]] {
+        for dt_trace in [false, true] {
+            test_aligner(AstarPa2 {
+                doubling: DoublingType::band_doubling(),
+                domain: Domain::gap_gap(),
+                block_width: 64,
+                block: BlockParams {
+                    dt_trace,
+                    trace_order: Some(TraceOrder(order)),
+                    ..Default::default()
+                },
+                ..nw()
+            });
+        }
+    }
+}
+
+#[test]
+fn trace_order_breaks_ties() {
+    use TraceEdge::*;
+    let aligner = |order| AstarPa2 {
+        block: BlockParams {
+            trace_order: Some(TraceOrder(order)),
+            ..Default::default()
+        },
+        ..nw()
+    };
+    // Two substitutions and a deletion + insertion both cost 2.
+    let (a, b) = (b"AC", b"CA");
+    let (sub_cost, sub) = aligner([Sub, Ins, Del]).align(a, b);
+    let (del_cost, del) = aligner([Del, Ins, Sub]).align(a, b);
+    assert_eq!(sub_cost, 2);
+    assert_eq!(del_cost, 2);
+    assert_ne!(sub, del);
+}
+
 #[test]
 fn band_doubling_dijkstra() {
     test_aligner(AstarPa2 {
@@ -88,6 +129,39 @@ fn nw_prune() {
     })
 }
 
+#[test]
+fn adaptive_block_width() {
+    test_aligner(AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 32,
+        block_width_mode: BlockWidthMode::Adaptive,
+        ..nw()
+    })
+}
+
+#[test]
+fn band_block_width() {
+    test_aligner(AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 64,
+        block_width_mode: BlockWidthMode::Band,
+        ..nw()
+    })
+}
+
+#[test]
+fn band_block_width_gapgap() {
+    test_aligner(AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::gap_gap(),
+        block_width: 64,
+        block_width_mode: BlockWidthMode::Band,
+        ..nw()
+    })
+}
+
 #[test]
 fn dt_trace() {
     test_aligner(AstarPa2 {
@@ -118,6 +192,361 @@ fn incremental_doubling() {
     });
 }
 
+#[test]
+fn two_phase() {
+    test_aligner(AstarPa2 {
+        doubling: DoublingType::TwoPhase,
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 256,
+        block: BlockParams {
+            dt_trace: true,
+            ..Default::default()
+        },
+        ..nw()
+    });
+}
+
+#[test]
+fn too_divergent() {
+    let aligner = AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 256,
+        max_divergence: Some(0.1),
+        ..nw()
+    };
+    let (a, b) = pa_generate::uniform_seeded(1000, 0.02, 31415);
+    assert!(aligner.try_align(&a, &b).is_ok());
+    let (a, b) = pa_generate::uniform_seeded(1000, 0.5, 31415);
+    let Err(AlignError::TooDivergent { lower_bound }) = aligner.try_align(&a, &b) else {
+        panic!("Expected TooDivergent");
+    };
+    assert!(lower_bound > 100);
+    // The infallible methods ignore the bound.
+    let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+    assert_eq!(aligner.align(&a, &b).0, d);
+    assert_eq!(Mapper::with_aligner(aligner, &b).align(&a).0, d);
+}
+
+#[test]
+fn too_divergent_mapper() {
+    let aligner = AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 256,
+        max_divergence: Some(0.1),
+        ..nw()
+    };
+    let (a, b) = pa_generate::uniform_seeded(1000, 0.5, 31415);
+    let mapper = Mapper::with_aligner(aligner, &b);
+    assert!(matches!(
+        mapper.try_align(&a),
+        Err(AlignError::TooDivergent { .. })
+    ));
+    assert!(matches!(
+        mapper.try_cost(&a),
+        Err(AlignError::TooDivergent { .. })
+    ));
+}
+
+#[test]
+fn exponential_search_starts_at_most_s_max() {
+    let mut tried = vec![];
+    let r = band::bounded_exponential_search(10, 100, 2.0, 50, |s| {
+        tried.push(s);
+        None::<(Cost, ())>
+    });
+    assert_eq!(r.unwrap_err(), 51);
+    assert_eq!(tried, vec![50]);
+}
+
+#[test]
+fn too_divergent_before_filling() {
+    // Without doubling, the length difference is checked before filling.
+    let aligner = AstarPa2 {
+        max_divergence: Some(0.1),
+        ..nw()
+    };
+    let a = vec![b'A'; 1000];
+    let b = vec![b'A'; 500];
+    let Err(AlignError::TooDivergent { lower_bound }) = aligner.try_align(&a, &b) else {
+        panic!("Expected TooDivergent");
+    };
+    assert_eq!(lower_bound, 500);
+    assert_eq!(aligner.align(&a, &b).0, 500);
+}
+
+#[test]
+fn aborted() {
+    let aligner = AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 256,
+        ..nw()
+    };
+    let (a, b) = pa_generate::uniform_seeded(3000, 0.3, 31415);
+    let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+    assert_eq!(aligner.try_align_with_abort(&a, &b, || false).unwrap().0, d);
+    for rounds in 0..4 {
+        let mut calls = 0;
+        match aligner.try_align_with_abort(&a, &b, || {
+            calls += 1;
+            calls > rounds
+        }) {
+            Ok((cost, _)) => assert_eq!(cost, d),
+            Err(AlignError::Aborted {
+                lower_bound,
+                upper_bound,
+            }) => {
+                assert!(lower_bound <= d);
+                assert!(upper_bound.map_or(true, |u| d <= u));
+            }
+            Err(e) => panic!("Unexpected error {e}"),
+        }
+    }
+}
+
+#[test]
+fn resumable() {
+    let aligner = AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 256,
+        ..nw()
+    };
+    let (a, b) = pa_generate::uniform_seeded(3000, 0.3, 31415);
+    let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+    for rounds in 1..4 {
+        // Abort after `rounds` iterations, and resume from the last state.
+        let mut calls = 0;
+        let mut saved = None;
+        let r = aligner.try_align_resumable(&a, &b, None, |state| {
+            calls += 1;
+            saved = Some(state.clone());
+            calls > rounds
+        });
+        let state = saved.unwrap();
+        if r.is_ok() {
+            continue;
+        }
+        assert!(!state.pruned_blocks.is_empty());
+        let (cost, cigar) = aligner
+            .try_align_resumable(&a, &b, Some(state), |_| false)
+            .unwrap();
+        assert_eq!(cost, d);
+        assert_eq!(cigar.unwrap().verify(&CostModel::unit(), &a, &b), d);
+    }
+}
+
+#[test]
+fn checkpoint_every() {
+    let aligner = AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 256,
+        ..nw()
+    };
+    let (a, b) = pa_generate::uniform_seeded(3000, 0.3, 31415);
+    let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+    let checkpoints = std::cell::RefCell::new(vec![]);
+    let (cost, _) = aligner
+        .checkpoint_every(std::time::Duration::ZERO, |c| {
+            checkpoints.borrow_mut().push(c.clone())
+        })
+        .align(&a, &b);
+    assert_eq!(cost, d);
+    let checkpoints = checkpoints.into_inner();
+    assert!(!checkpoints.is_empty());
+    for (c1, c2) in checkpoints.iter().zip(&checkpoints[1..]) {
+        assert!(c1.iteration < c2.iteration || c1.i < c2.i);
+        assert!(c2.i < c2.len_a && !c2.j_range.is_empty());
+    }
+}
+
+#[test]
+fn align_approx() {
+    let aligner = AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 256,
+        ..nw()
+    };
+    for e in [0.01, 0.1, 0.3] {
+        let (a, b) = pa_generate::uniform_seeded(3000, e, 31415);
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        for f_max in [d / 4, d / 2, d, 2 * d] {
+            let approx = aligner.align_approx(&a, &b, f_max);
+            assert!(approx.lower_bound <= d && d <= approx.cost);
+            if let Some(cigar) = &approx.cigar {
+                assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), approx.cost);
+            }
+            if f_max >= d {
+                assert_eq!(approx.cost, d);
+                assert_eq!(approx.lower_bound, d);
+            } else {
+                assert!(approx.lower_bound > f_max);
+            }
+        }
+    }
+}
+
+#[test]
+fn windows() {
+    let aligner = AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::gap_gap(),
+        block_width: 64,
+        ..nw()
+    };
+    let (a, _) = pa_generate::uniform_seeded(1000, 0., 31415);
+    // Replace a region in the middle by unrelated sequence.
+    let (random, _) = pa_generate::uniform_seeded(100, 0., 27182);
+    let mut b = a.clone();
+    b[450..550].copy_from_slice(&random);
+
+    let (cost, cigar, windows) = aligner.align_with_windows(&a, &b, 100, 50);
+    let cigar = cigar.unwrap();
+    assert_eq!(windows.first().unwrap().start, Pos(0, 0));
+    assert_eq!(windows.last().unwrap().end, Pos::target(&a, &b));
+
+    // Non-overlapping windows add up to the total cost.
+    let tiles = window_profile(&cigar, 100, 100);
+    assert_eq!(tiles.iter().map(|w| w.cost).sum::<Cost>(), cost);
+    assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+    // The divergent region has the lowest identity.
+    let worst = windows
+        .iter()
+        .min_by(|x, y| x.identity().total_cmp(&y.identity()))
+        .unwrap();
+    assert!(worst.start.0 < 550 && worst.end.0 > 450);
+    assert!(worst.identity() < 0.9);
+}
+
+#[test]
+fn windows_step_larger_than_window() {
+    let (a, b) = pa_generate::uniform_seeded(50, 0., 31415);
+    let cigar = nw().align(&a, &b).1.unwrap();
+    let windows = window_profile(&cigar, 10, 100);
+    assert_eq!(windows.len(), 1);
+    assert_eq!(windows[0].start, Pos(0, 0));
+    assert_eq!(windows[0].end, Pos(10, 10));
+    assert_eq!(windows[0].matches, 10);
+
+    let windows = window_profile(&cigar, 10, 20);
+    assert_eq!(
+        windows.iter().map(|w| w.start.0).collect::<Vec<_>>(),
+        vec![0, 20, 40]
+    );
+    assert_eq!(windows.last().unwrap().end, Pos(50, 50));
+}
+
+#[test]
+fn align_streaming() {
+    let aligner = AstarPa2 {
+        doubling: DoublingType::band_doubling(),
+        domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+        block_width: 256,
+        ..nw()
+    };
+    for ((a, b), _) in gen_seqs() {
+        let mut cigar = Cigar { ops: vec![] };
+        let cost = aligner
+            .align_streaming(&a, &b, |elem| cigar.ops.push(elem))
+            .unwrap();
+        cigar.ops.reverse();
+        assert_eq!(cost, triple_accel::levenshtein_exp(&a, &b) as Cost);
+        assert_eq!(cigar.verify(&CostModel::unit(), &a, &b), cost);
+    }
+
+    let aligner = AstarPa2 {
+        doubling: DoublingType::LocalDoubling,
+        ..aligner
+    };
+    assert!(matches!(
+        aligner.align_streaming(b"ACGT", b"AGT", |_| {}),
+        Err(AlignError::Unsupported { .. })
+    ));
+
+    // Without doubling, only the full domain can be streamed.
+    let aligner = AstarPa2 {
+        doubling: DoublingType::None,
+        ..aligner
+    };
+    assert!(matches!(
+        aligner.align_streaming(b"ACGT", b"AGT", |_| {}),
+        Err(AlignError::Unsupported { .. })
+    ));
+    let aligner = AstarPa2 {
+        domain: Domain::full(),
+        ..nw()
+    };
+    assert_eq!(aligner.align_streaming(b"ACGT", b"AGT", |_| {}), Ok(1));
+}
+
+#[test]
+fn packed_cigar() {
+    use blocks::trace::PackedCigar;
+    let mut packed = PackedCigar::default();
+    for (op, cnt) in [
+        (CigarOp::Ins, 2),
+        (CigarOp::Match, 1 << 30),
+        (CigarOp::Match, 3),
+        (CigarOp::Del, 1),
+    ] {
+        packed.push_elem(CigarElem { op, cnt });
+    }
+    // Runs longer than 2^30-1 are split.
+    assert_eq!(
+        packed.iter_rev().map(|e| (e.op, e.cnt)).collect::<Vec<_>>(),
+        [
+            (CigarOp::Del, 1),
+            (CigarOp::Match, 4),
+            (CigarOp::Match, (1 << 30) - 1),
+            (CigarOp::Ins, 2)
+        ]
+    );
+}
+
+#[test]
+fn auto() {
+    test_aligner(AstarPa2Auto { trace: true });
+}
+
+#[test]
+fn auto_branches() {
+    let related = |e| pa_generate::uniform_seeded(10_000, e, 31415);
+    let unrelated = || {
+        let (a, _) = pa_generate::uniform_seeded(10_000, 0., 31415);
+        let (b, _) = pa_generate::uniform_seeded(10_000, 0., 27182);
+        (a, b)
+    };
+    for ((a, b), heuristic) in [
+        (related(0.0), HeuristicType::SH),
+        (related(0.04), HeuristicType::CSH),
+        (related(0.10), HeuristicType::GCSH),
+        (unrelated(), HeuristicType::None),
+    ] {
+        let params = AstarPa2Params::auto(&a, &b);
+        let domain = match heuristic {
+            HeuristicType::None => Domain::GapGap,
+            _ => Domain::Astar(()),
+        };
+        assert_eq!(params.domain, domain);
+        assert_eq!(params.heuristic.heuristic, heuristic);
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        let cost = params.make_aligner(true).align(&a, &b).0;
+        assert_eq!(cost, d, "{}", params.name);
+    }
+}
+
+#[test]
+fn greedy_upper_bound() {
+    for ((a, b), _) in gen_seqs() {
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        assert!(d <= band::greedy_upper_bound(&a, &b));
+    }
+}
+
 #[test]
 #[ignore = "local doubling is broken"]
 fn local_doubling() {
@@ -128,3 +557,99 @@ fn local_doubling() {
         ..nw()
     })
 }
+
+#[test]
+fn mapper() {
+    let (a, reference) = pa_generate::uniform_fixed(1000, 0.05);
+    let (random, _) = pa_generate::uniform_fixed(300, 0.);
+    let mut shifted = reference[100..].to_vec();
+    shifted.extend_from_slice(&a[..50]);
+    let queries = [a.clone(), reference[..250].to_vec(), shifted, random];
+
+    let mapper = Mapper::new(&reference);
+    for query in &queries {
+        let dist = triple_accel::levenshtein_exp(query, &reference) as Cost;
+        let (cost, cigar) = mapper.align(query);
+        assert_eq!(cost, dist);
+        assert_eq!(mapper.cost(query), dist);
+        cigar.unwrap().verify(&CostModel::unit(), query, &reference);
+    }
+}
+
+#[test]
+fn all_pairs() {
+    let seqs = (0..6)
+        .map(|i| pa_generate::uniform_seeded(200, 0.1, i).0)
+        .chain([pa_generate::uniform_fixed(200, 0.).0])
+        .collect::<Vec<_>>();
+    let seqs = seqs.iter().map(|s| s.as_slice()).collect::<Vec<_>>();
+    let params = AstarPa2Params::full();
+    for max_cost in [None, Some(20), Some(150)] {
+        let costs = align_all_pairs(&seqs, &params, max_cost).unwrap();
+        assert_eq!(costs.len(), seqs.len());
+        assert_eq!(costs.condensed().len(), seqs.len() * (seqs.len() - 1) / 2);
+        for i in 0..seqs.len() {
+            for j in 0..seqs.len() {
+                let dist = triple_accel::levenshtein_exp(seqs[i], seqs[j]) as Cost;
+                let expected = Some(dist).filter(|&d| max_cost.map_or(true, |m| d <= m));
+                assert_eq!(costs.get(i, j), expected, "pair {i} {j}");
+            }
+        }
+    }
+}
+
+#[test]
+fn all_pairs_max_divergence() {
+    // Pairs exceeding `max_divergence` are `None` rather than an error.
+    let (a, b) = pa_generate::uniform_seeded(200, 0.3, 31415);
+    let params = AstarPa2Params {
+        max_divergence: Some(0.05),
+        ..AstarPa2Params::full()
+    };
+    let costs = align_all_pairs(&[&a, &b, &a], &params, None).unwrap();
+    assert_eq!(costs.get(0, 1), None);
+    assert_eq!(costs.get(0, 2), Some(0));
+}
+
+#[test]
+#[should_panic]
+fn all_pairs_get_out_of_bounds() {
+    let seqs = [b"ACGT".as_slice(), b"ACT"];
+    let costs = align_all_pairs(&seqs, &AstarPa2Params::full(), None).unwrap();
+    costs.get(2, 2);
+}
+
+#[test]
+fn align_result() {
+    let (a, b) = pa_generate::uniform_seeded(5000, 0.05, 31415);
+    let dist = triple_accel::levenshtein_exp(&a, &b) as Cost;
+    let mut aligners: [Box<dyn AstarPa2StatsAligner>; 2] = [
+        AstarPa2Params::full().make_aligner(true),
+        AstarPa2Params::simple().make_aligner(true),
+    ];
+    for aligner in &mut aligners {
+        let r = aligner.align_result(&a, &b);
+        assert_eq!(r.cost, dist);
+        let path = r.path.unwrap();
+        assert_eq!(path.first(), Some(&Pos(0, 0)));
+        assert_eq!(path.last(), Some(&Pos::target(&a, &b)));
+        assert!(r.stats.computed_rows > 0);
+    }
+    // Only A*PA2-full uses seeds.
+    let r = aligners[0].align_result(&a, &b);
+    assert!(r.stats.h.num_seeds > 0);
+    assert!(r.stats.h.num_pruned > 0);
+    assert!(r.stats.h.contour_layers > 0);
+}
+
+#[test]
+fn affine_aligner() {
+    use pa_affine_types::{AffineAligner, AffineCost};
+
+    let (a, b) = pa_generate::uniform_seeded(2000, 0.05, 31415);
+    let dist = triple_accel::levenshtein_exp(&a, &b) as Cost;
+    let mut aligner: Box<dyn AffineAligner> = Box::new(AstarPa2Auto { trace: true });
+    let (cost, cigar) = aligner.align_affine(&a, &b);
+    assert_eq!(cost, dist);
+    assert_eq!(cigar.unwrap().verify(&AffineCost::unit(), &a, &b), dist);
+}
@@ -0,0 +1,72 @@
+//! Identity and cost in sliding windows along an alignment.
+use super::*;
+
+/// Statistics of a window of consecutive alignment columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlignmentWindow {
+    /// The position in the DP matrix at the start of the window.
+    pub start: Pos,
+    /// The position in the DP matrix at the end of the window.
+    pub end: Pos,
+    /// The number of alignment columns (cigar operations) in the window.
+    pub len: I,
+    /// The number of matches in the window.
+    pub matches: I,
+    /// The edit distance of the window.
+    pub cost: Cost,
+}
+
+impl AlignmentWindow {
+    /// The fraction of columns in the window that are matches.
+    pub fn identity(&self) -> f32 {
+        if self.len == 0 {
+            return 1.0;
+        }
+        self.matches as f32 / self.len as f32
+    }
+}
+
+/// Split the alignment into windows of `window` columns, starting every `step` columns.
+///
+/// The last window is truncated at the end of the alignment.
+/// When `step > window`, the columns between windows are skipped.
+/// Windows with a low `identity` indicate locally bad regions, such as
+/// breakpoints of structural variants.
+pub fn window_profile(cigar: &Cigar, window: I, step: I) -> Vec<AlignmentWindow> {
+    assert!(window > 0 && step > 0);
+    // Prefix sums over the alignment columns of (pos, matches, cost).
+    let mut prefix = vec![(Pos(0, 0), 0, 0)];
+    for elem in &cigar.ops {
+        for _ in 0..elem.cnt {
+            let (Pos(i, j), matches, cost) = *prefix.last().unwrap();
+            prefix.push(match elem.op {
+                CigarOp::Match => (Pos(i + 1, j + 1), matches + 1, cost),
+                CigarOp::Sub => (Pos(i + 1, j + 1), matches, cost + 1),
+                CigarOp::Del => (Pos(i + 1, j), matches, cost + 1),
+                CigarOp::Ins => (Pos(i, j + 1), matches, cost + 1),
+            });
+        }
+    }
+
+    let len = prefix.len() as I - 1;
+    let mut windows = vec![];
+    let mut start = 0;
+    loop {
+        let end = min(start + window, len);
+        let (start_pos, start_matches, start_cost) = prefix[start as usize];
+        let (end_pos, end_matches, end_cost) = prefix[end as usize];
+        windows.push(AlignmentWindow {
+            start: start_pos,
+            end: end_pos,
+            len: end - start,
+            matches: end_matches - start_matches,
+            cost: end_cost - start_cost,
+        });
+        start += step;
+        // With `step > window`, the last window may end before the alignment.
+        if end == len || start >= len {
+            break;
+        }
+    }
+    windows
+}
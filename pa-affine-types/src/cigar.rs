@@ -1,9 +1,12 @@
 use crate::cost_model::{AffineCost, AffineLayerType};
 use pa_types::*;
+use serde::{Deserialize, Serialize};
 use std::slice;
 
 /// A CigarOp with extra markers for affine indel layers.
-#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+///
+/// Serialized by name, e.g. `"Match"` or `{"AffineIns": 0}`.
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize, Deserialize)]
 pub enum AffineCigarOp {
     Match,
     Sub,
@@ -22,13 +25,14 @@ pub enum AffineCigarOp {
     AffineClose(usize),
 }
 
-#[derive(Debug, Eq, PartialEq)]
+#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub struct AffineCigarElem {
     pub op: AffineCigarOp,
     pub cnt: I,
 }
 
-#[derive(Default, Debug, PartialEq, Eq)]
+/// Serialized as `{"ops": [{"op": .., "cnt": ..}, ..]}`.
+#[derive(Default, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AffineCigar {
     ops: Vec<AffineCigarElem>,
 }
@@ -341,3 +345,31 @@ impl<'a> IntoIterator for &'a AffineCigar {
         self.ops.iter()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn serde() {
+        let mut cigar = AffineCigar::default();
+        cigar.match_push(2);
+        cigar.push_op(AffineCigarOp::AffineOpen(0));
+        cigar.push_elem(AffineCigarElem {
+            op: AffineCigarOp::AffineIns(0),
+            cnt: 3,
+        });
+        cigar.push_op(AffineCigarOp::AffineClose(0));
+        let json = serde_json::to_string(&cigar).unwrap();
+        assert_eq!(
+            json,
+            r#"{"ops":[{"op":"Match","cnt":2},{"op":{"AffineOpen":0},"cnt":1},{"op":{"AffineIns":0},"cnt":3},{"op":{"AffineClose":0},"cnt":1}]}"#
+        );
+        assert_eq!(serde_json::from_str::<AffineCigar>(&json).unwrap(), cigar);
+
+        let state = crate::State::new(1, 2, Some(0));
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(json, r#"{"i":1,"j":2,"layer":0}"#);
+        assert_eq!(serde_json::from_str::<crate::State>(&json).unwrap(), state);
+    }
+}
@@ -1,5 +1,6 @@
 //! This module contains the `AffineCost` and `LinearCost` cost models.
 
+use crate::cost_t::{CostT, IntCostT};
 use pa_types::*;
 use std::cmp::{max, min};
 
@@ -35,44 +36,47 @@ pub use AffineLayerType::*;
 
 /// An affine layer depends on its type, the open cost, and the extend cost.
 #[derive(Clone, Debug, Copy, PartialEq)]
-pub struct AffineLayerCosts {
+pub struct AffineLayerCosts<C = Cost> {
     pub affine_type: AffineLayerType,
-    pub open: Cost,
-    pub extend: Cost,
+    pub open: C,
+    pub extend: C,
 }
 
 /// A full cost model consists of linear substitution/insertion/delete costs,
 /// and zero or more (N) affine layers.
+///
+/// The type of costs `C` defaults to `Cost`. Only the naive DP and
+/// `AffineNwFronts` support other `CostT` types; see `cost_t`.
 // The constructure is private to this module.
 #[non_exhaustive]
 #[derive(Clone, Debug, Copy, PartialEq)]
-pub struct AffineCost<const N: usize> {
+pub struct AffineCost<const N: usize, C = Cost> {
     /// The substitution cost. Or None when substitutions are not allowed.
-    pub sub: Option<Cost>,
+    pub sub: Option<C>,
     /// The insertion cost. Or None when substitutions are not allowed.
-    pub ins: Option<Cost>,
+    pub ins: Option<C>,
     /// The deletion cost. Or None when substitutions are not allowed.
-    pub del: Option<Cost>,
+    pub del: Option<C>,
     /// `N` affine layers.
     /// TODO: Should we split this into `NI` insertion layers and `ND` deletion
     /// layers, so that matching on the type becomes a compile-time instead of
     /// run-time operation?
-    pub affine: [AffineLayerCosts; N],
+    pub affine: [AffineLayerCosts<C>; N],
 
     /// Extra fields derived from the affine layers.
     /// We store them so we do not have to recompute them all the time.
-    pub min_ins_open: Cost,
-    pub max_ins_open: Cost,
-    pub min_del_open: Cost,
-    pub max_del_open: Cost,
-    pub min_ins_extend: Cost,
-    pub max_ins_extend: Cost,
-    pub min_del_extend: Cost,
-    pub max_del_extend: Cost,
-    pub min_ins_open_extend: Cost,
-    pub max_ins_open_extend: Cost,
-    pub min_del_open_extend: Cost,
-    pub max_del_open_extend: Cost,
+    pub min_ins_open: C,
+    pub max_ins_open: C,
+    pub min_del_open: C,
+    pub max_del_open: C,
+    pub min_ins_extend: C,
+    pub max_ins_extend: C,
+    pub min_del_extend: C,
+    pub max_del_extend: C,
+    pub min_ins_open_extend: C,
+    pub max_ins_open_extend: C,
+    pub min_del_open_extend: C,
+    pub max_del_open_extend: C,
 }
 
 impl From<CostModel> for AffineCost<0> {
@@ -121,6 +125,12 @@ impl AffineCost<0> {
     pub fn linear_asymmetric(sub: Cost, ins: Cost, del: Cost) -> AffineCost<0> {
         Self::new(Some(sub), Some(ins), Some(del), [])
     }
+    /// Unit substitution cost, with separate insertion and deletion costs.
+    /// E.g. `unit_asymmetric(1, 2)` penalizes deletions (characters of `a`
+    /// missing from `b`) more than insertions.
+    pub fn unit_asymmetric(ins: Cost, del: Cost) -> AffineCost<0> {
+        Self::new(Some(1), Some(ins), Some(del), [])
+    }
 }
 
 impl AffineCost<2> {
@@ -189,6 +199,13 @@ impl AffineCost<2> {
     }
 }
 impl AffineCost<4> {
+    /// Two-piece affine gap costs, as used by e.g. minimap2.
+    ///
+    /// A gap of length `l` costs `min(open + l * extend, open2 + l * extend2)`.
+    /// Typically `open < open2` and `extend > extend2`, so that short gaps use
+    /// the first pair of layers and long gaps the second.
+    /// Layers `0` and `1` are the insertion and deletion layers of the first
+    /// piece, and layers `2` and `3` those of the second piece.
     pub fn double_affine(
         sub: Cost,
         open: Cost,
@@ -226,19 +243,19 @@ impl AffineCost<4> {
     }
 }
 
-impl<const N: usize> AffineCost<N> {
+impl<const N: usize, C: CostT> AffineCost<N, C> {
     pub fn new(
-        sub: Option<Cost>,
-        ins: Option<Cost>,
-        del: Option<Cost>,
-        affine: [AffineLayerCosts; N],
-    ) -> AffineCost<N> {
-        assert!(sub.unwrap_or(1) > 0);
-        assert!(ins.unwrap_or(1) > 0);
-        assert!(del.unwrap_or(1) > 0);
+        sub: Option<C>,
+        ins: Option<C>,
+        del: Option<C>,
+        affine: [AffineLayerCosts<C>; N],
+    ) -> AffineCost<N, C> {
+        assert!(sub.map_or(true, |c| c > C::ZERO));
+        assert!(ins.map_or(true, |c| c > C::ZERO));
+        assert!(del.map_or(true, |c| c > C::ZERO));
         for layer in &affine {
-            assert!(layer.open > 0);
-            assert!(layer.extend > 0);
+            assert!(layer.open > C::ZERO);
+            assert!(layer.extend > C::ZERO);
         }
 
         let layers = |affine_type| {
@@ -246,33 +263,27 @@ impl<const N: usize> AffineCost<N> {
                 .iter()
                 .filter(move |cm| cm.affine_type.base() == affine_type)
         };
-        let min_by = |affine_type, f: &dyn Fn(&AffineLayerCosts) -> Cost| {
-            let mut c = layers(affine_type).map(f).min().unwrap_or(Cost::MAX);
+        let min_by = |affine_type, f: &dyn Fn(&AffineLayerCosts<C>) -> C| {
+            let mut c = layers(affine_type).map(f).fold(C::MAX, C::min_cost);
             // Also include the linear layer in the affine maximums.
             if let Some(extend) = if affine_type.is_insert() { ins } else { del } {
-                c = min(
-                    c,
-                    f(&AffineLayerCosts {
-                        affine_type,
-                        open: 0,
-                        extend,
-                    }),
-                );
+                c = c.min_cost(f(&AffineLayerCosts {
+                    affine_type,
+                    open: C::ZERO,
+                    extend,
+                }));
             }
             c
         };
-        let max_by = |affine_type, f: &dyn Fn(&AffineLayerCosts) -> Cost| {
-            let mut c = layers(affine_type).map(f).max().unwrap_or(Cost::MIN);
+        let max_by = |affine_type, f: &dyn Fn(&AffineLayerCosts<C>) -> C| {
+            let mut c = layers(affine_type).map(f).fold(C::MIN, C::max_cost);
             // Also include the linear layer in the affine maximums.
             if let Some(extend) = if affine_type.is_insert() { ins } else { del } {
-                c = max(
-                    c,
-                    f(&AffineLayerCosts {
-                        affine_type,
-                        open: 0,
-                        extend,
-                    }),
-                );
+                c = c.max_cost(f(&AffineLayerCosts {
+                    affine_type,
+                    open: C::ZERO,
+                    extend,
+                }));
             }
             c
         };
@@ -309,9 +320,9 @@ impl<const N: usize> AffineCost<N> {
     }
 
     #[inline]
-    pub fn sub_cost(&self, a: u8, b: u8) -> Option<Cost> {
+    pub fn sub_cost(&self, a: u8, b: u8) -> Option<C> {
         if a == b {
-            Some(0)
+            Some(C::ZERO)
         } else {
             {
                 let ref this = self;
@@ -323,7 +334,7 @@ impl<const N: usize> AffineCost<N> {
     #[inline]
     pub fn sub_or<U, F>(&self, default: U, f: F) -> U
     where
-        F: FnOnce(Cost) -> U,
+        F: FnOnce(C) -> U,
     {
         self.sub.map_or(default, f)
     }
@@ -331,10 +342,10 @@ impl<const N: usize> AffineCost<N> {
     #[inline]
     pub fn sub_cost_or<U, F>(&self, a: u8, b: u8, default: U, f: F) -> U
     where
-        F: FnOnce(Cost) -> U,
+        F: FnOnce(C) -> U,
     {
         if a == b {
-            f(0)
+            f(C::ZERO)
         } else {
             self.sub_or(default, f)
         }
@@ -343,7 +354,7 @@ impl<const N: usize> AffineCost<N> {
     #[inline]
     pub fn ins_or<U, F>(&self, default: U, f: F) -> U
     where
-        F: FnOnce(Cost) -> U,
+        F: FnOnce(C) -> U,
     {
         self.ins.map_or(default, f)
     }
@@ -351,16 +362,16 @@ impl<const N: usize> AffineCost<N> {
     #[inline]
     pub fn del_or<U, F>(&self, default: U, f: F) -> U
     where
-        F: FnOnce(Cost) -> U,
+        F: FnOnce(C) -> U,
     {
         self.del.map_or(default, f)
     }
 
     /// NOTE: This also includes the linear insert cost.
     #[inline]
-    pub fn for_ins(&self, mut f: impl FnMut(Cost, Cost)) {
+    pub fn for_ins(&self, mut f: impl FnMut(C, C)) {
         if let Some(ins) = self.ins {
-            f(0, ins);
+            f(C::ZERO, ins);
         }
         for cm in &self.affine {
             if cm.affine_type.is_insert() {
@@ -371,9 +382,9 @@ impl<const N: usize> AffineCost<N> {
 
     /// NOTE: This also includes the linear delete cost.
     #[inline]
-    pub fn for_del(&self, mut f: impl FnMut(Cost, Cost)) {
+    pub fn for_del(&self, mut f: impl FnMut(C, C)) {
         if let Some(del) = self.del {
-            f(0, del);
+            f(C::ZERO, del);
         }
         for cm in &self.affine {
             if cm.affine_type.is_delete() {
@@ -381,6 +392,94 @@ impl<const N: usize> AffineCost<N> {
             }
         }
     }
+}
+
+impl<const N: usize, C: IntCostT> AffineCost<N, C> {
+    /// The cost of the cheapest gap between diagonals of `s` and `t`.
+    /// This is the gap-cost heuristic, which requires integer costs.
+    #[inline]
+    pub fn gap_cost(&self, s: Pos, t: Pos) -> C {
+        let delta = (t.0 - s.0) as isize - (t.1 - s.1) as isize;
+        match delta {
+            0 => C::ZERO,
+            d if d < 0 => {
+                let d = C::from_count((-d) as I);
+                let mut c = C::MAX;
+                if let Some(ins) = self.ins {
+                    c = min(c, d * ins);
+                }
+                for cm in &self.affine {
+                    if cm.affine_type.is_insert() {
+                        c = min(c, cm.open + d * cm.extend);
+                    }
+                }
+                assert!(c != C::MAX);
+                c
+            }
+            d if d > 0 => {
+                let d = C::from_count(d as I);
+                let mut c = C::MAX;
+                if let Some(del) = self.del {
+                    c = min(c, d * del);
+                }
+                for cm in &self.affine {
+                    if cm.affine_type.is_delete() {
+                        c = min(c, cm.open + d * cm.extend);
+                    }
+                }
+                assert!(c != C::MAX);
+                c
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<const N: usize> AffineCost<N> {
+    /// Piecewise-linear approximation of a convex gap cost function `f`,
+    /// using `N/2` pairs of insertion and deletion layers.
+    ///
+    /// `breakpoints` are `N/2+1` increasing gap lengths. Piece `k` is the
+    /// line through `f(breakpoints[k])` and `f(breakpoints[k+1])`, and the last
+    /// piece is extended to longer gaps. The cost of a gap is the minimum over
+    /// the pieces.
+    /// Extend costs are rounded to the nearest positive integer, and open costs
+    /// are chosen so that each piece is exact at its first breakpoint, when possible.
+    pub fn convex(sub: Cost, breakpoints: &[I], f: impl Fn(I) -> Cost) -> AffineCost<N> {
+        assert!(
+            N % 2 == 0,
+            "Convex gap costs need pairs of insertion and deletion layers."
+        );
+        assert_eq!(breakpoints.len(), N / 2 + 1, "Expected N/2+1 breakpoints.");
+        assert!(breakpoints[0] > 0);
+        assert!(
+            breakpoints.windows(2).all(|w| w[0] < w[1]),
+            "Breakpoints must be increasing."
+        );
+
+        let piece = |k: usize| {
+            let (l0, l1) = (breakpoints[k], breakpoints[k + 1]);
+            let (f0, f1) = (f(l0), f(l1));
+            assert!(f0 <= f1, "Gap cost function must be non-decreasing.");
+            let len = (l1 - l0) as Cost;
+            let extend = max(1, (f1 - f0 + len / 2) / len);
+            let open = max(1, f0 - l0 as Cost * extend);
+            (open, extend)
+        };
+        Self::new(
+            Some(sub),
+            None,
+            None,
+            std::array::from_fn(|i| {
+                let (open, extend) = piece(i / 2);
+                AffineLayerCosts {
+                    affine_type: if i % 2 == 0 { InsertLayer } else { DeleteLayer },
+                    open,
+                    extend,
+                }
+            }),
+        )
+    }
 
     /// Returns 0 when insertions are not possible.
     #[inline]
@@ -449,41 +548,15 @@ impl<const N: usize> AffineCost<N> {
         }
     }
 
+    /// An upper bound on how much `f(v) = g(u) + extend_cost(u, v) + h(v)`
+    /// decreases per step of `v` towards the diagonal of `u`, for consistent `h`.
+    ///
+    /// Each step removes one insertion from `extend_cost`, and changes `h` by at
+    /// most the cost of a single indel. Used to jump over states with
+    /// `f(v) > f_max` when `h` is only evaluated sparsely.
     #[inline]
-    pub fn gap_cost(&self, s: Pos, t: Pos) -> Cost {
-        let delta = (t.0 - s.0) as isize - (t.1 - s.1) as isize;
-        match delta {
-            0 => 0,
-            d if d < 0 => {
-                let d = (-d) as Cost;
-                let mut c = Cost::MAX;
-                if let Some(ins) = self.ins {
-                    c = min(c, d * ins);
-                }
-                for cm in &self.affine {
-                    if cm.affine_type.is_insert() {
-                        c = min(c, cm.open + d * cm.extend);
-                    }
-                }
-                assert!(c != Cost::MAX);
-                c
-            }
-            d if d > 0 => {
-                let d = d as Cost;
-                let mut c = Cost::MAX;
-                if let Some(del) = self.del {
-                    c = min(c, d * del);
-                }
-                for cm in &self.affine {
-                    if cm.affine_type.is_delete() {
-                        c = min(c, cm.open + d * cm.extend);
-                    }
-                }
-                assert!(c != Cost::MAX);
-                c
-            }
-            _ => unreachable!(),
-        }
+    pub fn max_f_step(&self) -> Cost {
+        self.min_ins_extend + max(self.max_ins_open_extend, self.max_del_open_extend)
     }
 
     #[inline]
@@ -0,0 +1,104 @@
+//! The `CostT` trait for types of costs other than `pa_types::Cost`.
+//!
+//! `AffineCost`, the naive DP, and the affine fronts in `pa_base_algos::nw`
+//! are generic over the cost type, so that e.g. `i64` costs for very long
+//! sequences or `f32` log-likelihood scores can be used.
+//! Heuristics need integer costs and require `IntCostT`, as does
+//! `AffineCost::gap_cost`. The other fronts and the heuristics in `pa_heuristic`
+//! only support `Cost`.
+use pa_types::{Cost, I};
+use std::fmt::Debug;
+use std::ops::{Add, Mul, Sub};
+
+pub trait CostT:
+    Copy
+    + Debug
+    + PartialEq
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Send
+    + Sync
+    + 'static
+{
+    const ZERO: Self;
+    /// The largest value, used when an operation is not possible.
+    const MAX: Self;
+    /// The smallest value.
+    const MIN: Self;
+    /// Larger than any reachable cost, but small enough that adding a cost does not overflow.
+    const INF: Self;
+
+    /// The cost corresponding to `n` times a unit cost, e.g. for gap lengths.
+    fn from_count(n: I) -> Self;
+
+    /// The minimum of two costs. For floats, `NaN`s are ignored.
+    #[inline]
+    fn min_cost(self, other: Self) -> Self {
+        if other < self {
+            other
+        } else {
+            self
+        }
+    }
+
+    /// The maximum of two costs. For floats, `NaN`s are ignored.
+    #[inline]
+    fn max_cost(self, other: Self) -> Self {
+        if other > self {
+            other
+        } else {
+            self
+        }
+    }
+}
+
+/// Integer costs, as required by the heuristics.
+pub trait IntCostT: CostT + Ord + Eq {}
+
+impl CostT for Cost {
+    const ZERO: Self = 0;
+    const MAX: Self = Cost::MAX;
+    const MIN: Self = Cost::MIN;
+    const INF: Self = Cost::MAX / 2;
+    #[inline]
+    fn from_count(n: I) -> Self {
+        n as Cost
+    }
+}
+impl IntCostT for Cost {}
+
+impl CostT for i64 {
+    const ZERO: Self = 0;
+    const MAX: Self = i64::MAX;
+    const MIN: Self = i64::MIN;
+    const INF: Self = i64::MAX / 2;
+    #[inline]
+    fn from_count(n: I) -> Self {
+        n as i64
+    }
+}
+impl IntCostT for i64 {}
+
+impl CostT for f32 {
+    const ZERO: Self = 0.;
+    const MAX: Self = f32::INFINITY;
+    const MIN: Self = f32::NEG_INFINITY;
+    const INF: Self = f32::INFINITY;
+    #[inline]
+    fn from_count(n: I) -> Self {
+        n as f32
+    }
+}
+
+impl CostT for f64 {
+    const ZERO: Self = 0.;
+    const MAX: Self = f64::INFINITY;
+    const MIN: Self = f64::NEG_INFINITY;
+    const INF: Self = f64::INFINITY;
+    #[inline]
+    fn from_count(n: I) -> Self {
+        n as f64
+    }
+}
@@ -2,15 +2,23 @@ use pa_types::{Cost, Pos, Seq, I};
 
 pub mod cigar;
 pub mod cost_model;
+pub mod cost_t;
+pub mod normalize;
+pub mod variants;
 
 // Re-export types for convenience of `use pa_affine_types::*;`.
 pub use cigar::*;
 pub use cost_model::*;
+pub use cost_t::*;
+pub use normalize::*;
+pub use variants::*;
 
 pub type Layer = Option<usize>;
 
 /// State in the edit graph during an affine alignment.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///
+/// Serialized as `{"i": .., "j": .., "layer": .. | null}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct State {
     pub i: I,
     pub j: I,
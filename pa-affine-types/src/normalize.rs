@@ -0,0 +1,156 @@
+//! Normalization of the position of indels in a cigar.
+//!
+//! Within a homopolymer or tandem repeat, an indel can be placed at multiple
+//! positions with the same cost. Variant callers (and VCF) expect indels to be
+//! shifted as far left as possible.
+use pa_types::*;
+
+/// The direction in which indels are shifted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum GapAlignment {
+    /// Shift indels towards the start of the sequences, as in VCF.
+    #[default]
+    Left,
+    /// Shift indels towards the end of the sequences.
+    Right,
+}
+
+/// Shift all insertions and deletions in `cigar` as far as possible in the
+/// given direction, by moving them past matches.
+///
+/// The cost of the alignment never increases: the shifted indels have the same
+/// lengths, and adjacent indels of the same type are merged.
+pub fn normalize_gaps(cigar: &Cigar, a: Seq, b: Seq, alignment: GapAlignment) -> Cigar {
+    let mut ops = cigar
+        .ops
+        .iter()
+        .flat_map(|el| std::iter::repeat(el.op).take(el.cnt as usize))
+        .collect::<Vec<_>>();
+    match alignment {
+        GapAlignment::Left => shift_left(&mut ops, a, b),
+        GapAlignment::Right => {
+            // Shifting right is shifting left in the reversed alignment.
+            let a = a.iter().rev().copied().collect::<Vec<_>>();
+            let b = b.iter().rev().copied().collect::<Vec<_>>();
+            ops.reverse();
+            shift_left(&mut ops, &a, &b);
+            ops.reverse();
+        }
+    }
+
+    let mut normalized = Cigar::default();
+    for op in ops {
+        match normalized.ops.last_mut() {
+            Some(el) if el.op == op => el.cnt += 1,
+            _ => normalized.ops.push(CigarElem { op, cnt: 1 }),
+        }
+    }
+    normalized
+}
+
+/// Shift each run of indels left over the matches before it, as long as the
+/// last character of the gap equals the character of the preceding match.
+/// Each step rotates the gapped sequence by one, so this also handles
+/// repeats longer than a single character.
+fn shift_left(ops: &mut [CigarOp], a: Seq, b: Seq) {
+    let (mut i, mut j) = (0, 0);
+    let mut k = 0;
+    while k < ops.len() {
+        let op = ops[k];
+        if op == CigarOp::Match || op == CigarOp::Sub {
+            i += 1;
+            j += 1;
+            k += 1;
+            continue;
+        }
+        let len = ops[k..].iter().take_while(|&&o| o == op).count();
+
+        // Rotate the run starting at `s`, which starts at `(si, sj)`.
+        let (mut s, mut si, mut sj) = (k, i, j);
+        while s > 0 && ops[s - 1] == CigarOp::Match {
+            let last = match op {
+                CigarOp::Del => a[si + len - 1],
+                _ => b[sj + len - 1],
+            };
+            if a[si - 1] != last {
+                break;
+            }
+            ops[s - 1] = op;
+            ops[s - 1 + len] = CigarOp::Match;
+            s -= 1;
+            si -= 1;
+            sj -= 1;
+        }
+
+        match op {
+            CigarOp::Del => i += len,
+            _ => j += len,
+        }
+        k += len;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use CigarOp::*;
+
+    fn cigar(ops: &[(CigarOp, I)]) -> Cigar {
+        Cigar {
+            ops: ops.iter().map(|&(op, cnt)| CigarElem { op, cnt }).collect(),
+        }
+    }
+
+    fn check(a: Seq, b: Seq, ops: &[(CigarOp, I)], left: &[(CigarOp, I)], right: &[(CigarOp, I)]) {
+        let c = cigar(ops);
+        let cost = c.verify(&CostModel::unit(), a, b);
+        for (alignment, expected) in [(GapAlignment::Left, left), (GapAlignment::Right, right)] {
+            let normalized = normalize_gaps(&c, a, b, alignment);
+            assert_eq!(normalized, cigar(expected), "{alignment:?}");
+            assert_eq!(normalized.verify(&CostModel::unit(), a, b), cost);
+        }
+    }
+
+    #[test]
+    fn homopolymer() {
+        // Delete one of the three `A`s.
+        check(
+            b"CAAAG",
+            b"CAAG",
+            &[(Match, 3), (Del, 1), (Match, 1)],
+            &[(Match, 1), (Del, 1), (Match, 3)],
+            &[(Match, 3), (Del, 1), (Match, 1)],
+        );
+        // Insert an `A`.
+        check(
+            b"CAAG",
+            b"CAAAG",
+            &[(Match, 2), (Ins, 1), (Match, 2)],
+            &[(Match, 1), (Ins, 1), (Match, 3)],
+            &[(Match, 3), (Ins, 1), (Match, 1)],
+        );
+    }
+
+    #[test]
+    fn repeat() {
+        // Delete one `CA` from `CACACA`.
+        check(
+            b"GCACACAT",
+            b"GCACAT",
+            &[(Match, 5), (Del, 2), (Match, 1)],
+            &[(Match, 1), (Del, 2), (Match, 5)],
+            &[(Match, 5), (Del, 2), (Match, 1)],
+        );
+    }
+
+    #[test]
+    fn stops_at_mismatch() {
+        check(
+            b"CTAAG",
+            b"CGAG",
+            &[(Match, 1), (Sub, 1), (Match, 1), (Del, 1), (Match, 1)],
+            &[(Match, 1), (Sub, 1), (Del, 1), (Match, 2)],
+            &[(Match, 1), (Sub, 1), (Match, 1), (Del, 1), (Match, 1)],
+        );
+    }
+}
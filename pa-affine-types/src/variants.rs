@@ -0,0 +1,218 @@
+//! Extracting variants from an alignment of a query against a reference.
+//!
+//! The reference is the first sequence `a` of the alignment, so that a
+//! deletion removes characters of the reference, and an insertion adds
+//! characters of the query `b`.
+use crate::normalize::{normalize_gaps, GapAlignment};
+use pa_types::*;
+
+/// A difference between the reference and the query.
+/// Positions are 0-based positions in the reference, including the offset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Variant {
+    /// `reference` at `pos` is replaced by `alt`.
+    Snv { pos: usize, reference: u8, alt: u8 },
+    /// `alt` is inserted before `pos`.
+    Insertion { pos: usize, alt: Vec<u8> },
+    /// `reference` starting at `pos` is deleted.
+    Deletion { pos: usize, reference: Vec<u8> },
+}
+
+impl Variant {
+    /// The 1-based position and the reference and alternative alleles as in VCF.
+    ///
+    /// Indels include the reference character before them, or after them when
+    /// they are at the start of the reference. When the reference has no
+    /// character after them either, e.g. when the entire reference is deleted
+    /// or it is empty, `N` is used instead.
+    /// `reference` is the full reference sequence, that positions index into.
+    pub fn to_vcf(&self, reference: Seq) -> (usize, Vec<u8>, Vec<u8>) {
+        // The position and alleles of an indel with the given inserted and deleted characters.
+        let anchored = |pos: usize, ins: &[u8], del: &[u8]| {
+            if pos > 0 {
+                let anchor = &reference[pos - 1..pos];
+                (pos, [anchor, del].concat(), [anchor, ins].concat())
+            } else {
+                let anchor = reference.get(del.len()..del.len() + 1).unwrap_or(b"N");
+                (pos + 1, [del, anchor].concat(), [ins, anchor].concat())
+            }
+        };
+        match self {
+            Variant::Snv {
+                pos,
+                reference,
+                alt,
+            } => (pos + 1, vec![*reference], vec![*alt]),
+            Variant::Insertion { pos, alt } => anchored(*pos, &alt[..], &[]),
+            Variant::Deletion { pos, reference } => anchored(*pos, &[], &reference[..]),
+        }
+    }
+}
+
+/// The variants of query `b` relative to the reference `a`, in order of position.
+///
+/// `offset` is the position of `a` in a larger reference, and is added to all positions.
+/// When `normalize` is set, indels are first shifted in the given direction,
+/// e.g. `GapAlignment::Left` for VCF.
+pub fn variants(
+    cigar: &Cigar,
+    a: Seq,
+    b: Seq,
+    offset: usize,
+    normalize: Option<GapAlignment>,
+) -> Vec<Variant> {
+    let normalized;
+    let cigar = match normalize {
+        Some(alignment) => {
+            normalized = normalize_gaps(cigar, a, b, alignment);
+            &normalized
+        }
+        None => cigar,
+    };
+
+    let mut variants = vec![];
+    let (mut i, mut j) = (0, 0);
+    for &CigarElem { op, cnt } in &cigar.ops {
+        let cnt = cnt as usize;
+        match op {
+            CigarOp::Match => {}
+            CigarOp::Sub => {
+                variants.extend((0..cnt).map(|k| Variant::Snv {
+                    pos: offset + i + k,
+                    reference: a[i + k],
+                    alt: b[j + k],
+                }));
+            }
+            CigarOp::Ins => variants.push(Variant::Insertion {
+                pos: offset + i,
+                alt: b[j..j + cnt].to_vec(),
+            }),
+            CigarOp::Del => variants.push(Variant::Deletion {
+                pos: offset + i,
+                reference: a[i..i + cnt].to_vec(),
+            }),
+        }
+        if op != CigarOp::Ins {
+            i += cnt;
+        }
+        if op != CigarOp::Del {
+            j += cnt;
+        }
+    }
+    variants
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use CigarOp::*;
+
+    #[test]
+    fn variants() {
+        // a: GCAAT-TAG
+        // b: GC-ATCTTG
+        let (a, b) = (b"GCAATTAG".as_slice(), b"GCATCTTG".as_slice());
+        let cigar = Cigar {
+            ops: [
+                (Match, 2),
+                (Del, 1),
+                (Match, 2),
+                (Ins, 1),
+                (Match, 1),
+                (Sub, 1),
+                (Match, 1),
+            ]
+            .into_iter()
+            .map(|(op, cnt)| CigarElem { op, cnt })
+            .collect(),
+        };
+        assert_eq!(cigar.verify(&CostModel::unit(), a, b), 3);
+
+        let vs = super::variants(&cigar, a, b, 100, None);
+        assert_eq!(
+            vs,
+            [
+                Variant::Deletion {
+                    pos: 102,
+                    reference: b"A".to_vec()
+                },
+                Variant::Insertion {
+                    pos: 105,
+                    alt: b"C".to_vec()
+                },
+                Variant::Snv {
+                    pos: 106,
+                    reference: b'A',
+                    alt: b'T'
+                },
+            ]
+        );
+        // The deletion is already left-aligned, but shifts right within `AA`.
+        assert_eq!(
+            super::variants(&cigar, a, b, 100, Some(GapAlignment::Left)),
+            vs
+        );
+        assert_eq!(
+            super::variants(&cigar, a, b, 100, Some(GapAlignment::Right))[0],
+            Variant::Deletion {
+                pos: 103,
+                reference: b"A".to_vec()
+            }
+        );
+
+        let reference = [b"N".repeat(100).as_slice(), a].concat();
+        assert_eq!(
+            vs[0].to_vcf(&reference),
+            (102, b"CA".to_vec(), b"C".to_vec())
+        );
+        assert_eq!(
+            vs[1].to_vcf(&reference),
+            (105, b"T".to_vec(), b"TC".to_vec())
+        );
+        assert_eq!(
+            vs[2].to_vcf(&reference),
+            (107, b"A".to_vec(), b"T".to_vec())
+        );
+    }
+
+    #[test]
+    fn vcf_at_start() {
+        let reference = b"ACGT".as_slice();
+        let deletion = Variant::Deletion {
+            pos: 0,
+            reference: b"A".to_vec(),
+        };
+        assert_eq!(
+            deletion.to_vcf(reference),
+            (1, b"AC".to_vec(), b"C".to_vec())
+        );
+        let insertion = Variant::Insertion {
+            pos: 0,
+            alt: b"TT".to_vec(),
+        };
+        assert_eq!(
+            insertion.to_vcf(reference),
+            (1, b"A".to_vec(), b"TTA".to_vec())
+        );
+    }
+
+    #[test]
+    fn vcf_without_anchor() {
+        // The entire reference is deleted.
+        let reference = b"ACGT".as_slice();
+        let deletion = Variant::Deletion {
+            pos: 0,
+            reference: reference.to_vec(),
+        };
+        assert_eq!(
+            deletion.to_vcf(reference),
+            (1, b"ACGTN".to_vec(), b"N".to_vec())
+        );
+        // An insertion into an empty reference.
+        let insertion = Variant::Insertion {
+            pos: 0,
+            alt: b"AC".to_vec(),
+        };
+        assert_eq!(insertion.to_vcf(b""), (1, b"N".to_vec(), b"ACN".to_vec()));
+    }
+}
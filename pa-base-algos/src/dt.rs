@@ -22,15 +22,18 @@
 //!
 //!
 use crate::edit_graph::{AffineCigarOps, EditGraph, StateT};
-use crate::exponential_search;
+use crate::{exponential_search, TraceOrder};
 use pa_affine_types::*;
+use pa_heuristic::coords::PosExt;
 use pa_heuristic::*;
 use pa_types::*;
 use pa_vis::*;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::cmp::{max, min};
-use std::iter::zip;
 use std::ops::RangeInclusive;
+use std::simd::prelude::*;
+use tracing::trace;
 
 /// The type for storing furthest reaching points.
 /// Sized, so that we can default them to -INF.
@@ -53,9 +56,12 @@ pub enum GapCostHeuristic {
     Disable,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
 pub enum PathTracingMethod {
+    /// When tracing back, prefer the parent furthest along its diagonal.
+    #[default]
     ForwardGreedy,
+    /// When tracing back, greedily walk back along matches first.
     ReverseGreedy,
 }
 
@@ -82,6 +88,14 @@ pub struct DiagonalTransition<const N: usize, V: VisualizerT, H: Heuristic> {
     pub local_doubling: bool,
 
     pub path_tracing_method: PathTracingMethod,
+
+    /// The preferred order of parents reaching equally far during traceback.
+    pub trace_order: TraceOrder,
+
+    /// When set to `Some(k)`, `align` only keeps every `k`th front (and the
+    /// fronts it depends on) in memory, and recomputes the fronts in between
+    /// during traceback.
+    pub sparse: Option<Cost>,
 }
 
 impl<const N: usize, V: VisualizerT, H: Heuristic> std::fmt::Debug for DiagonalTransition<N, V, H> {
@@ -92,6 +106,8 @@ impl<const N: usize, V: VisualizerT, H: Heuristic> std::fmt::Debug for DiagonalT
             .field("dc", &self.dc)
             .field("local_doubling", &self.local_doubling)
             .field("path_tracing_method", &self.path_tracing_method)
+            .field("trace_order", &self.trace_order)
+            .field("sparse", &self.sparse)
             .finish()
     }
 }
@@ -112,6 +128,8 @@ impl<const N: usize, V: VisualizerT, H: Heuristic> DiagonalTransition<N, V, H> {
             v,
             local_doubling: false,
             path_tracing_method: PathTracingMethod::ForwardGreedy,
+            trace_order: TraceOrder::default(),
+            sparse: None,
         }
     }
 
@@ -121,6 +139,7 @@ impl<const N: usize, V: VisualizerT, H: Heuristic> DiagonalTransition<N, V, H> {
         b: Seq<'a>,
         v: &'a RefCell<V::Instance>,
     ) -> DTInstance<'a, N, V, H> {
+        util::assert_fits_index(a, b);
         // The maximum cost we look back:
         let left_buf = EditGraph::max_edge_cost(&self.cm) as Fr;
 
@@ -154,6 +173,7 @@ impl<const N: usize, V: VisualizerT, H: Heuristic> DiagonalTransition<N, V, H> {
             left_buf,
             top_buf,
             bot_buf,
+            stats: DtStats::init(a, b),
         }
     }
 }
@@ -202,6 +222,71 @@ pub struct DTInstance<'a, const N: usize, V: VisualizerT, H: Heuristic> {
     /// FIXME: For affine GapClose costs, we add the max open cost to the substitution cost.
     top_buf: Fr,
     bot_buf: Fr,
+
+    /// Statistics of all fronts computed so far.
+    pub stats: DtStats,
+}
+
+/// Statistics of a single computed front.
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct FrontStats {
+    /// The cost `s` of the front.
+    pub s: Cost,
+    /// The number of diagonals in the range of the front.
+    pub diagonals: usize,
+    /// The largest furthest reaching point `i+j` on any diagonal, after extending.
+    pub max_fr: Fr,
+    /// The total number of matches extended over, summed over diagonals.
+    pub extended: usize,
+    /// The longest greedy extension on a single diagonal.
+    pub max_extension: usize,
+}
+
+/// Statistics of a DT run, similar to `AstarStats` for A*.
+///
+/// With band doubling, sparse fronts, or divide & conquer, fronts can be
+/// computed more than once, and each computation is counted.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct DtStats {
+    pub len_a: usize,
+    pub len_b: usize,
+    /// The computed distance.
+    pub distance: Cost,
+    /// The number of computed fronts.
+    pub fronts: usize,
+    /// The number of computed diagonals, summed over all fronts.
+    pub diagonals: usize,
+    /// The number of matches extended over, summed over all fronts.
+    pub extended: usize,
+    /// Per-front statistics, in the order the fronts were computed.
+    pub per_front: Vec<FrontStats>,
+}
+
+impl DtStats {
+    pub fn init(a: Seq, b: Seq) -> Self {
+        Self {
+            len_a: a.len(),
+            len_b: b.len(),
+            ..Default::default()
+        }
+    }
+
+    /// Convert to the statistics shared by all aligners. Each computed
+    /// diagonal of a front counts as one expanded state.
+    /// `t_total` is not tracked here and left at 0.
+    pub fn to_align_stats(&self) -> AlignStats {
+        AlignStats {
+            expanded: self.diagonals,
+            ..Default::default()
+        }
+    }
+
+    fn add_front(&mut self, front: FrontStats) {
+        self.fronts += 1;
+        self.diagonals += front.diagonals;
+        self.extended += front.extended;
+        self.per_front.push(front);
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -253,7 +338,7 @@ impl StateT for DtState {
 
     fn pos(&self) -> Pos {
         assert!((self.d + self.fr) % 2 == 0);
-        Pos(((self.fr + self.d) / 2) as I, ((self.fr - self.d) / 2) as I)
+        Pos::from_diagonals(self.d as I, self.fr as I)
     }
 }
 
@@ -266,16 +351,66 @@ fn fr_to_coords(d: Fr, fr: Fr) -> (Fr, Fr) {
 }
 #[inline]
 fn fr_to_pos(d: Fr, fr: Fr) -> Pos {
-    //assert!((d + fr) % 2 == 0);
-    Pos(((fr + d) / 2) as I, ((fr - d) / 2) as I)
+    Pos::from_diagonals(d as I, fr as I)
 }
 #[inline]
 pub fn pos_to_fr(p: Pos) -> (Fr, Fr) {
-    (p.0 as Fr - p.1 as Fr, p.0 as Fr + p.1 as Fr)
+    (p.diagonal() as Fr, p.antidiagonal() as Fr)
+}
+
+/// The number of diagonals processed at once by the SIMD parts of DT.
+const LANES: usize = 8;
+type FrSimd = Simd<Fr, LANES>;
+
+/// Load `LANES` values starting at `k`, padding with `Fr::MIN` past the end of `s`.
+#[inline]
+fn load(s: &[Fr], k: usize) -> FrSimd {
+    if k + LANES <= s.len() {
+        FrSimd::from_slice(&s[k..k + LANES])
+    } else {
+        let mut v = [Fr::MIN; LANES];
+        v[..s.len() - k].copy_from_slice(&s[k..]);
+        FrSimd::from_array(v)
+    }
+}
+
+/// Store the lanes of `v` that fall inside `s`, starting at `k`.
+#[inline]
+fn store(s: &mut [Fr], k: usize, v: FrSimd) {
+    let end = min(k + LANES, s.len());
+    s[k..end].copy_from_slice(&v.as_array()[..end - k]);
+}
+
+/// The diagonals `d..d+LANES`.
+#[inline]
+fn diagonals(d: Fr) -> FrSimd {
+    FrSimd::splat(d) + FrSimd::from_array(std::array::from_fn(|l| l as Fr))
+}
+
+/// For the diagonals `d..d+LANES` with f.r. points `frs[..LANES]`, a bitmask of
+/// the (forward) points that are followed by a match.
+///
+/// Most diagonals do not extend at all, so this filters them out with a single
+/// gather instead of one scalar comparison each.
+#[inline]
+fn followed_by_match(a: Seq, b: Seq, frs: &[Fr], d: Fr) -> u64 {
+    let fr = load(frs, 0);
+    let d = diagonals(d);
+    // Unreached diagonals get index -1, which is out of bounds as usize.
+    let unreached = fr.simd_lt(FrSimd::splat(0));
+    let i = unreached.select(FrSimd::splat(-1), (fr + d) / FrSimd::splat(2));
+    let j = unreached.select(FrSimd::splat(-1), (fr - d) / FrSimd::splat(2));
+    let (i, j) = (i.cast::<usize>(), j.cast::<usize>());
+    let in_bounds = i.simd_lt(Simd::splat(a.len())) & j.simd_lt(Simd::splat(b.len()));
+    let ca = Simd::<u8, LANES>::gather_or_default(a, i);
+    let cb = Simd::<u8, LANES>::gather_or_default(b, j);
+    (ca.simd_eq(cb) & in_bounds.cast::<i8>()).to_bitmask()
 }
 
 /// Given two sequences, a diagonal and point on it, expand it to a FR point.
 /// Returns the number of characters matched.
+///
+/// Compares 8 characters at a time. See `util::count_matches`.
 /// NOTE: `d` and `fr` must be in Forward domain here.
 fn extend_diagonal(direction: Direction, a: Seq, b: Seq, d: Fr, fr: Fr) -> Fr {
     let (i, j) = fr_to_coords(d, fr);
@@ -283,79 +418,10 @@ fn extend_diagonal(direction: Direction, a: Seq, b: Seq, d: Fr, fr: Fr) -> Fr {
         return 0;
     }
 
-    // TODO: The end check can be avoided by appending `#` and `$` to `a` and `b`.
     match direction {
-        Direction::Forward => zip(a[i as usize..].iter(), b[j as usize..].iter())
-            .take_while(|(ca, cb)| ca == cb)
-            .count() as Fr,
-        Direction::Backward => zip(a[..i as usize].iter().rev(), b[..j as usize].iter().rev())
-            .take_while(|(ca, cb)| ca == cb)
-            .count() as Fr,
-    }
-}
-
-/// Given two sequences, a diagonal and point on it, expand it to a FR point.
-///
-/// This version compares one usize at a time.
-/// NOTE: `d` and `fr` must be in Forward domain here.
-/// FIXME: This needs sentinels at the starts/ends of the sequences to finish correctly.
-/// FIXME: Dereferencing unaligned pointers is not good. Use this instead:
-/// https://doc.rust-lang.org/std/ptr/fn.read_unaligned.html
-/// Thanks @Daniel Liu!
-#[allow(unused)]
-fn extend_diagonal_packed(direction: Direction, a: Seq, b: Seq, d: Fr, mut fr: Fr) -> Fr {
-    let i = (fr + d) / 2;
-    let j = (fr - d) / 2;
-
-    // cast [u8] to *const usize, to compare 8 bytes at a time.
-    let mut a_ptr = a[i as usize..].as_ptr() as *const usize;
-    let mut b_ptr = b[j as usize..].as_ptr() as *const usize;
-    let a_ptr_original = a_ptr;
-    match direction {
-        Direction::Forward => {
-            let cmp = loop {
-                let cmp = unsafe { *a_ptr ^ *b_ptr };
-                // TODO: Make the break the `likely` case?
-                if cmp != 0 {
-                    break cmp;
-                }
-                unsafe {
-                    a_ptr = a_ptr.offset(1);
-                    b_ptr = b_ptr.offset(1);
-                }
-            };
-            fr += 2
-                * (unsafe { a_ptr.offset_from(a_ptr_original) } as Fr
-                    + (if cfg!(target_endian = "little") {
-                        cmp.trailing_zeros()
-                    } else {
-                        cmp.leading_zeros()
-                    } / u8::BITS) as Fr);
-        }
-        Direction::Backward => {
-            let cmp = loop {
-                unsafe {
-                    a_ptr = a_ptr.offset(-1);
-                    b_ptr = b_ptr.offset(-1);
-                }
-                let cmp = unsafe { *a_ptr ^ *b_ptr };
-                // TODO: Make the break the `likely` case?
-                if cmp != 0 {
-                    break cmp;
-                }
-            };
-            fr += 2
-                * (unsafe { a_ptr_original.offset_from(a_ptr) } as Fr - 1
-                    + (if cfg!(target_endian = "little") {
-                        // NOTE: this is reversed from the forward case.
-                        cmp.leading_zeros()
-                    } else {
-                        cmp.trailing_zeros()
-                    } / u8::BITS) as Fr);
-        }
+        Direction::Forward => util::count_matches(&a[i as usize..], &b[j as usize..]) as Fr,
+        Direction::Backward => util::count_matches_rev(&a[..i as usize], &b[..j as usize]) as Fr,
     }
-
-    fr
 }
 
 impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
@@ -369,7 +435,20 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
         offset: Pos,
         direction: Direction,
     ) -> bool {
-        for d in front.range().clone() {
+        let range = front.range().clone();
+        let mut matching = 0;
+        let mut front_stats = FrontStats {
+            s: g,
+            diagonals: range.clone().count(),
+            max_fr: Fr::MIN,
+            ..Default::default()
+        };
+        for d in range.clone() {
+            let lane = (d - range.start()) as usize % LANES;
+            if direction == Forward && lane == 0 {
+                let frs = front.m().slice(d..min(d + LANES as Fr, range.end() + 1));
+                matching = followed_by_match(&self.a, &self.b, frs, d);
+            }
             let fr = &mut front.m_mut()[d];
             if *fr < 0 {
                 continue;
@@ -377,7 +456,9 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
             let fr_old = *fr;
             match direction {
                 Direction::Forward => {
-                    *fr += 2 * extend_diagonal(direction, &self.a, &self.b, d, *fr);
+                    if matching >> lane & 1 != 0 {
+                        *fr += 2 * extend_diagonal(direction, &self.a, &self.b, d, *fr);
+                    }
                     for fr in (fr_old..*fr).step_by(2) {
                         self.v.borrow_mut().extend(
                             offset + fr_to_pos(d, fr),
@@ -422,7 +503,12 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                     );
                 }
             }
+            let extension = ((*fr - fr_old) / 2) as usize;
+            front_stats.extended += extension;
+            front_stats.max_extension = max(front_stats.max_extension, extension);
+            front_stats.max_fr = max(front_stats.max_fr, *fr);
         }
+        self.stats.add_front(front_stats);
 
         let target_d = self.a.len() as Fr - self.b.len() as Fr;
         if front.range().contains(&target_d)
@@ -559,6 +645,40 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
         }
     }
 
+    /// Remove diagonals from the front whose (extended) furthest reaching point
+    /// `u` has `g + h(u) > f_max`.
+    ///
+    /// This is the DT analogue of `fixed_j_range` in NW: unlike `d_range`, which
+    /// only shrinks the ends of the range before extending, this also removes
+    /// diagonals in the interior of the range.
+    ///
+    /// Only the main layer is trimmed, and only when a heuristic is used that
+    /// is consistent and not pruned. Then `h` does not decrease along the
+    /// matches of the extension, so testing after extending prunes at least as
+    /// much. With pruning, matches are removed during the search, which is not
+    /// accounted for here.
+    fn trim_front(&self, g: Cost, f_max: Option<Cost>, front: &mut Front<N>) {
+        let Some(f_max) = f_max else {
+            return;
+        };
+        if H::IS_DEFAULT || self.params.h.is_pruning() || !self.params.h.is_consistent() {
+            return;
+        }
+        for d in front.range().clone() {
+            let fr = &mut front.m_mut()[d];
+            if *fr < 0 {
+                continue;
+            }
+            let pos = fr_to_pos(d, *fr);
+            if pos.0 as usize <= self.a.len()
+                && pos.1 as usize <= self.b.len()
+                && util::add_cost(g, self.h.h(pos)) > f_max
+            {
+                *fr = Fr::MIN;
+            }
+        }
+    }
+
     /// Computes layer g from the previous ones.
     /// `ca` is the `i`th character of sequence `a`.
     ///
@@ -576,9 +696,9 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
     ) -> bool {
         // Loop over the entire dmin..=dmax range.
         // The boundaries are buffered so no boundary checks are needed.
-        // TODO: Vectorize this loop, or at least verify the compiler does this.
         // TODO: Loop over a positive range that does not need additional shifting?
         match direction {
+            Direction::Forward if N == 0 => self.next_front_linear(g, fronts),
             Direction::Forward => {
                 for d in fronts[g as Fr].range().clone() {
                     EditGraph::iterate_layers(&self.params.cm, |layer| {
@@ -653,6 +773,43 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
         self.extend(g, f_max, &mut fronts[g as Fr], offset, direction)
     }
 
+    /// `next_front` for linear costs in the forward direction, computing the
+    /// main layer for `LANES` diagonals at a time.
+    fn next_front_linear(&self, g: Cost, fronts: &mut Fronts<N>) {
+        let cm = &self.params.cm;
+        // Take out the front, so that the parent fronts can be borrowed.
+        let mut front = std::mem::take(&mut fronts[g as Fr]);
+        let range = front.range().clone();
+        if !range.is_empty() {
+            let (dmin, dmax) = (*range.start(), *range.end() + 1);
+            // For each edge: the parent f.r. points shifted to the diagonals
+            // of this front, and the increase of `fr` along the edge.
+            let parents =
+                [(cm.sub, 0, 2), (cm.ins, 1, 1), (cm.del, -1, 1)].map(|(cost, shift, step)| {
+                    cost.map(|cost| {
+                        let parent = fronts[g as Fr - cost as Fr].m();
+                        (parent.slice(dmin + shift..dmax + shift), step)
+                    })
+                });
+            let mut m = front.m_mut();
+            let m = &mut m[dmin..dmax];
+            for k in (0..m.len()).step_by(LANES) {
+                let d = diagonals(dmin + k as Fr);
+                let mut fr = load(m, k);
+                for &(parent, step) in parents.iter().flatten() {
+                    let p = load(parent, k) + FrSimd::splat(step);
+                    // The point must be reached and inside the grid, i.e. `i >= 0` and `j >= 0`.
+                    // Checking `p >= 0` first prevents overflow for unreached diagonals.
+                    let zero = FrSimd::splat(0);
+                    let valid = p.simd_ge(zero) & (p + d).simd_ge(zero) & (p - d).simd_ge(zero);
+                    fr = fr.simd_max(valid.select(p, FrSimd::splat(Fr::MIN)));
+                }
+                store(m, k, fr);
+            }
+        }
+        fronts[g as Fr] = front;
+    }
+
     // Returns None when the sequences are equal.
     fn init_fronts(
         &mut self,
@@ -917,6 +1074,9 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
         &mut self,
         f_max: Option<Cost>,
     ) -> Option<(Cost, AffineCigar)> {
+        if let Some(k) = self.params.sparse {
+            return self.align_for_bounded_dist_sparse(f_max, k);
+        }
         self.v
             .borrow_mut()
             .expand(Pos(0, 0), 0, f_max.unwrap_or(0), Some(&self.h));
@@ -956,6 +1116,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
             ) {
                 break;
             }
+            self.trim_front(s, f_max, &mut fronts[s as Fr]);
             self.v.borrow_mut().new_layer(Some(&self.h));
         }
 
@@ -969,6 +1130,84 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
         Some((s, cigar))
     }
 
+    /// Same as `align_for_bounded_dist`, but only stores every `k`th front.
+    ///
+    /// The fronts are computed in a rotating buffer as in `cost_for_bounded_dist`.
+    /// Every `k` steps, a copy of the buffer is kept as a checkpoint.
+    /// The traceback recomputes the fronts between consecutive checkpoints.
+    /// When a visualizer is attached, all fronts are additionally kept so that
+    /// the tree of parents can be drawn in the last frame.
+    fn align_for_bounded_dist_sparse(
+        &mut self,
+        f_max: Option<Cost>,
+        k: Cost,
+    ) -> Option<(Cost, AffineCigar)> {
+        assert!(k > 0);
+        self.v
+            .borrow_mut()
+            .expand(Pos(0, 0), 0, f_max.unwrap_or(0), Some(&self.h));
+        let mut fronts = match self.init_fronts(
+            f_max.unwrap_or(0),
+            Pos(0, 0),
+            None,
+            None,
+            Direction::Forward,
+        ) {
+            Ok(fronts) => fronts,
+            Err(r) => return Some(r),
+        };
+        let mut checkpoints = vec![fronts.clone()];
+        let mut all_fronts =
+            (!<V::Instance as VisualizerInstance>::IS_DEFAULT).then(|| fronts.clone());
+
+        let mut s = 0;
+        loop {
+            s += 1;
+            if let Some(f_max) = f_max
+                && s > f_max
+            {
+                return None;
+            }
+
+            let range = self.d_range(s, f_max, &fronts);
+            if range.is_empty() {
+                return None;
+            }
+            fronts.rotate(range);
+            let done = self.next_front(
+                s,
+                f_max.unwrap_or(0),
+                &mut fronts,
+                Pos(0, 0),
+                None,
+                Direction::Forward,
+            );
+            if !done {
+                self.trim_front(s, f_max, &mut fronts[s as Fr]);
+            }
+            if let Some(all_fronts) = &mut all_fronts {
+                all_fronts.push_front(fronts[s as Fr].clone());
+            }
+            if done {
+                break;
+            }
+            if s % k == 0 {
+                checkpoints.push(fronts.clone());
+            }
+            self.v.borrow_mut().new_layer(Some(&self.h));
+        }
+
+        let cigar = self.trace_sparse(&checkpoints, DtState::target(&self.a, &self.b, s), f_max);
+        if let Some(all_fronts) = all_fronts {
+            self.visualize_last_frame(all_fronts, &cigar);
+        } else {
+            self.v
+                .borrow_mut()
+                .last_frame(Some(&cigar), None, Some(&self.h));
+        }
+        Some((s, cigar))
+    }
+
     pub fn align_local_band_doubling<'b>(&mut self) -> (Cost, AffineCigar) {
         const D: bool = false;
 
@@ -1010,7 +1249,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
             fronts.push_default_front(range);
 
             if D {
-                println!("Compute {g} up to {f_tip}");
+                trace!("Compute {g} up to {f_tip}");
             }
 
             // Grow previous front sizes as long as their f_max is not large enough.
@@ -1030,15 +1269,15 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                     let e = fr_to_pos(ke, front.m()[ke]);
 
                     if D && false {
-                        println!("{start_g} {ks} {s}");
-                        println!(
+                        trace!("{start_g} {ks} {s}");
+                        trace!(
                             "Diagonal {ks}\t g {} + h {} > f_next {} (f_cur {})",
                             start_g,
                             self.h.h(s),
                             f_max[start_g + 1],
                             f_max[start_g]
                         );
-                        println!(
+                        trace!(
                             "Diagonal {ke}\t g {} + h {} > f_next {} (f_cur {})",
                             start_g,
                             self.h.h(e),
@@ -1052,7 +1291,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                     {
                         start_g += 1;
                         if D && false {
-                            println!(
+                            trace!(
                                 "Stop. Front {} is last to reuse. Col {start_g} is recomputed",
                                 start_g - 1
                             );
@@ -1065,7 +1304,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                 let delta = &mut f_delta[start_g];
                 f_max[start_g] = (f_max[start_g + 1] + *delta - 1) / *delta * *delta;
                 if D && false {
-                    println!(
+                    trace!(
                         "Grow {start_g} from {before} by {delta} to {}",
                         f_max[start_g]
                     );
@@ -1073,7 +1312,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                 if f_max[start_g] > last_grow {
                     last_grow = f_max[start_g];
                     if D {
-                        println!(
+                        trace!(
                             "Grow {start_g} from {before} by {delta} to {}",
                             f_max[start_g]
                         );
@@ -1104,9 +1343,10 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                     Direction::Forward,
                 );
                 if D && false {
-                    println!(
+                    trace!(
                         "New front {g} at {}: {:?}",
-                        f_max[g as usize], fronts[g as Fr]
+                        f_max[g as usize],
+                        fronts[g as Fr]
                     );
                 }
                 // PRUNING
@@ -1137,7 +1377,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                 }
                 let h_after = self.h.h(Pos(0, 0));
                 if D && false {
-                    println!("Pruning: {h_before} => {h_after}");
+                    trace!("Pruning: {h_before} => {h_after}");
                 }
 
                 if done {
@@ -1231,6 +1471,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                 self.v.borrow_mut().last_frame(None, None, Some(&self.h));
                 return Some(s);
             }
+            self.trim_front(s, f_max, &mut fronts[s as Fr]);
             self.v.borrow_mut().new_layer(Some(&self.h));
         }
 
@@ -1247,6 +1488,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
             return None;
         }
         let mut max_fr = Fr::MIN;
+        let mut best_rank = usize::MAX;
         let mut parent = None;
         let mut cigar_ops = [None, None];
 
@@ -1289,9 +1531,12 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                     },
                     |di, dj, i, j, layer, edge_cost, ops| {
                         let fr = (i + j) as Fr;
-                        // Prefer indel edges over substitution edges.
-                        if fr > max_fr || (fr == max_fr && (di != dj || layer != None)) {
+                        // Break ties using the trace order.
+                        let rank =
+                            EditGraph::trace_rank(&self.params.cm, &self.params.trace_order, ops);
+                        if fr > max_fr || (fr == max_fr && rank < best_rank) {
                             max_fr = fr;
+                            best_rank = rank;
                             parent = Some(DtState {
                                 d: st.d + (di - dj),
                                 fr: st.fr + (di + dj),
@@ -1339,8 +1584,11 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
                     },
                     |di, dj, i, j, layer, edge_cost, ops| {
                         let fr = mirror_fr((i + j) as Fr) + (di + dj) as Fr;
-                        if fr > max_fr {
+                        let rank =
+                            EditGraph::trace_rank(&self.params.cm, &self.params.trace_order, ops);
+                        if fr > max_fr || (fr == max_fr && rank < best_rank) {
                             max_fr = fr;
+                            best_rank = rank;
                             parent = Some(DtState {
                                 d: st.d - (di - dj),
                                 fr: st.fr - (di + dj),
@@ -1387,6 +1635,56 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic> DTInstance<'a, N, V, H> {
         cigar.reverse();
         cigar
     }
+
+    /// Trace a path from the start to `to`, given checkpoints of the fronts at
+    /// increasing costs.
+    ///
+    /// Going backwards over the checkpoints, the fronts from the checkpoint up to
+    /// the current state are recomputed, and the path is traced until it
+    /// leaves this range.
+    fn trace_sparse(
+        &mut self,
+        checkpoints: &[Fronts<N>],
+        mut to: DtState,
+        f_max: Option<Cost>,
+    ) -> AffineCigar {
+        let from = DtState::start();
+        let mut cigar = AffineCigar::default();
+
+        for checkpoint in checkpoints.iter().rev() {
+            let s_c = *checkpoint.range().end();
+            if to == from || (to.s as Fr) < s_c {
+                continue;
+            }
+            let mut fronts = checkpoint.clone();
+            for s in s_c + 1..=to.s as Fr {
+                let range = self.d_range(s as Cost, f_max, &fronts);
+                fronts.push_default_front(range);
+                if !self.next_front(
+                    s as Cost,
+                    f_max.unwrap_or(0),
+                    &mut fronts,
+                    Pos(0, 0),
+                    None,
+                    Direction::Forward,
+                ) {
+                    self.trim_front(s as Cost, f_max, &mut fronts[s]);
+                }
+            }
+            while to != from && to.s as Fr >= s_c {
+                let (parent, cigar_ops) = self.parent(&fronts, to, Direction::Forward).unwrap();
+                to = parent;
+                for op in cigar_ops {
+                    if let Some(op) = op {
+                        cigar.push_op(op);
+                    }
+                }
+            }
+        }
+        assert_eq!(to, from);
+        cigar.reverse();
+        cigar
+    }
 }
 
 impl<const N: usize, V: VisualizerT, H: Heuristic> DiagonalTransition<N, V, H> {
@@ -1406,6 +1704,18 @@ impl<const N: usize, V: VisualizerT, H: Heuristic> DiagonalTransition<N, V, H> {
     }
 
     pub fn align(&mut self, a: Seq, b: Seq) -> (Cost, AffineCigar) {
+        let (cost, cigar, _stats) = self.align_with_stats(a, b);
+        (cost, cigar)
+    }
+
+    /// Same as `align`, but returns an error when a cost addition overflowed.
+    /// Overflows are only detected with the `checked_cost` feature.
+    pub fn try_align(&mut self, a: Seq, b: Seq) -> Result<(Cost, AffineCigar), util::CostOverflow> {
+        util::check_cost_overflow(|| self.align(a, b))
+    }
+
+    /// Same as `align`, but also returns statistics on the computed fronts.
+    pub fn align_with_stats(&mut self, a: Seq, b: Seq) -> (Cost, AffineCigar, DtStats) {
         let v = &RefCell::new(self.v.build(a, b));
         let mut dt = self.build(a, b, v);
         if self.dc {
@@ -1419,7 +1729,8 @@ impl<const N: usize, V: VisualizerT, H: Heuristic> DiagonalTransition<N, V, H> {
             let (cost, cigar) = dt.path_between_dc(Pos(0, 0), None, None);
             dt.v.borrow_mut()
                 .last_frame::<NoCostI>(Some(&cigar), None, None);
-            (cost, cigar)
+            dt.stats.distance = cost;
+            (cost, cigar, dt.stats)
         } else {
             let cc;
             if self.local_doubling {
@@ -1440,17 +1751,25 @@ impl<const N: usize, V: VisualizerT, H: Heuristic> DiagonalTransition<N, V, H> {
             } else {
                 cc = dt.align_for_bounded_dist(None).unwrap();
             };
-            cc
+            let (cost, cigar) = cc;
+            dt.stats.distance = cost;
+            (cost, cigar, dt.stats)
         }
     }
 
-    pub fn cost_for_bounded_dist(&mut self, a: Seq, b: Seq, f_max: Cost) -> Option<Cost> {
+    /// Compute the cost if it is at most `f_max`.
+    ///
+    /// Fronts are computed for increasing `s`, and `None` is returned as soon as
+    /// `s` exceeds `f_max`. This makes DT usable as the inner step of
+    /// exponential search or to check a given upper bound.
+    pub fn cost_for_bounded_dist(&self, a: Seq, b: Seq, f_max: Cost) -> Option<Cost> {
         self.build(a, b, &RefCell::new(self.v.build(a, b)))
             .cost_for_bounded_dist(Some(f_max))
     }
 
+    /// Same as `cost_for_bounded_dist`, but also returns an alignment.
     pub fn align_for_bounded_dist(
-        &mut self,
+        &self,
         a: Seq,
         b: Seq,
         f_max: Cost,
@@ -1473,3 +1792,150 @@ impl<const N: usize, V: VisualizerT, H: Heuristic> Aligner for DiagonalTransitio
         (cost, Some(cigar.into()))
     }
 }
+
+impl<const N: usize, V: VisualizerT, H: Heuristic> ResultAligner for DiagonalTransition<N, V, H> {
+    fn align_result(&mut self, a: Seq, b: Seq) -> AlignResult {
+        let start = std::time::Instant::now();
+        let (cost, cigar, stats) = self.align_with_stats(a, b);
+        let mut stats = stats.to_align_stats();
+        stats.t_total = start.elapsed();
+        AlignResult::new(cost, Some(cigar.into()), stats)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pa_affine_types::AffineCost;
+    use pa_heuristic::{MatchConfig, NoCost, Pruning, GCSH};
+    use pa_types::Cost;
+    use pa_vis::NoVis;
+
+    use super::{DiagonalTransition, GapCostHeuristic};
+
+    #[test]
+    fn stats() {
+        use pa_heuristic::ResultAligner;
+
+        let (a, b) =
+            pa_generate::generate_model(1000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::unit();
+        let mut dt = DiagonalTransition::new(cm, GapCostHeuristic::Disable, NoCost, false, NoVis);
+        let (cost, _, stats) = dt.align_with_stats(&a, &b);
+        assert_eq!(stats.distance, cost);
+        // Without band doubling, each front is computed once.
+        assert_eq!(stats.fronts, cost as usize + 1);
+        assert_eq!(stats.per_front.len(), stats.fronts);
+        for (s, front) in stats.per_front.iter().enumerate() {
+            assert_eq!(front.s, s as Cost);
+        }
+        let diagonals: usize = stats.per_front.iter().map(|f| f.diagonals).sum();
+        assert_eq!(stats.diagonals, diagonals);
+        // Matches on the path are extended over, and the last front reaches the end.
+        assert!(stats.extended >= a.len() - cost as usize);
+        let last = stats.per_front.last().unwrap();
+        assert_eq!(last.max_fr as usize, a.len() + b.len());
+
+        let r = dt.align_result(&a, &b);
+        assert_eq!(r.cost, cost);
+        assert_eq!(r.stats.expanded, stats.diagonals);
+    }
+
+    /// Unit costs use the SIMD front computation. Check lengths around multiples of the lane count.
+    #[test]
+    fn simd_fronts() {
+        let cm = AffineCost::unit();
+        let mut dt = DiagonalTransition::new(cm, GapCostHeuristic::Disable, NoCost, false, NoVis);
+        for n in (1..40).chain([1000]) {
+            for e in [0.05, 0.3] {
+                let (a, b) =
+                    pa_generate::generate_model(n, e, pa_generate::ErrorModel::Uniform, 31415);
+                let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+                let (cost, cigar) = dt.align(&a, &b);
+                assert_eq!(cost, d, "n={n} e={e}");
+                assert_eq!(cigar.verify(&cm, &a, &b), cost, "n={n} e={e}");
+            }
+        }
+    }
+
+    #[test]
+    fn sparse() {
+        let (a, b) =
+            pa_generate::generate_model(3000, 0.2, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::affine(1, 2, 1);
+        let mut dt = DiagonalTransition::new(cm, GapCostHeuristic::Disable, NoCost, false, NoVis);
+        let (cost, _) = dt.align(&a, &b);
+        for k in [1, 7, 64, 10000] {
+            dt.sparse = Some(k);
+            let (sparse_cost, cigar) = dt.align(&a, &b);
+            assert_eq!(sparse_cost, cost, "k={k}");
+            assert_eq!(cigar.verify(&cm, &a, &b), cost, "k={k}");
+        }
+    }
+
+    #[test]
+    fn astar_trim() {
+        let (a, b) =
+            pa_generate::generate_model(5000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::unit();
+        let cost = DiagonalTransition::new(cm, GapCostHeuristic::Disable, NoCost, false, NoVis)
+            .align(&a, &b)
+            .0;
+        let mut dt = DiagonalTransition::new(
+            cm,
+            GapCostHeuristic::Disable,
+            GCSH::new(MatchConfig::exact(10), Pruning::disabled()),
+            false,
+            NoVis,
+        );
+        for sparse in [None, Some(16)] {
+            dt.sparse = sparse;
+            let (astar_cost, cigar) = dt.align(&a, &b);
+            assert_eq!(astar_cost, cost);
+            assert_eq!(cigar.verify(&cm, &a, &b), cost);
+            assert_eq!(dt.cost(&a, &b), cost);
+        }
+    }
+
+    /// With pruning, `h` is not fixed during the search, so fronts are not
+    /// trimmed, and the result must still be optimal.
+    #[test]
+    fn astar_trim_pruning() {
+        use pa_heuristic::{Heuristic, Prune};
+
+        let (a, b) =
+            pa_generate::generate_model(5000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::unit();
+        let cost = DiagonalTransition::new(cm, GapCostHeuristic::Disable, NoCost, false, NoVis)
+            .align(&a, &b)
+            .0;
+        let diagonals = |prune| {
+            let h = GCSH::new(MatchConfig::exact(10), Pruning::new(prune));
+            assert_eq!(h.is_pruning(), prune != Prune::None);
+            let mut dt = DiagonalTransition::new(cm, GapCostHeuristic::Disable, h, false, NoVis);
+            let (astar_cost, cigar, stats) = dt.align_with_stats(&a, &b);
+            assert_eq!(astar_cost, cost, "{prune:?}");
+            assert_eq!(cigar.verify(&cm, &a, &b), cost, "{prune:?}");
+            stats.diagonals
+        };
+        let trimmed = diagonals(Prune::None);
+        for prune in [Prune::Start, Prune::Both] {
+            assert!(diagonals(prune) >= trimmed);
+        }
+    }
+
+    #[test]
+    fn bounded_dist() {
+        let (a, b) =
+            pa_generate::generate_model(3000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::unit();
+        let mut dt = DiagonalTransition::new(cm, GapCostHeuristic::Enable, NoCost, false, NoVis);
+        let cost = dt.cost(&a, &b);
+        assert_eq!(dt.cost_for_bounded_dist(&a, &b, cost - 1), None);
+        assert_eq!(dt.cost_for_bounded_dist(&a, &b, cost), Some(cost));
+        assert_eq!(dt.cost_for_bounded_dist(&a, &b, 2 * cost), Some(cost));
+        assert!(dt.align_for_bounded_dist(&a, &b, cost - 1).is_none());
+        let (c, cigar) = dt.align_for_bounded_dist(&a, &b, cost).unwrap();
+        assert_eq!(c, cost);
+        assert_eq!(cigar.verify(&cm, &a, &b), cost);
+    }
+}
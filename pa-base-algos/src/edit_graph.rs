@@ -7,6 +7,7 @@ use pa_types::*;
 use std::cmp::max;
 
 use super::dt::Fr;
+use crate::{TraceEdge, TraceOrder};
 
 pub type AffineCigarOps = [Option<AffineCigarOp>; 2];
 
@@ -72,6 +73,31 @@ impl EditGraph {
         e
     }
 
+    /// The rank in `order` of the edge with the given cigar ops, where lower is preferred.
+    /// Edges into or out of an affine layer count as the gap type of that layer.
+    pub fn trace_rank<const N: usize, C: CostT>(
+        cm: &AffineCost<N, C>,
+        order: &TraceOrder,
+        ops: AffineCigarOps,
+    ) -> usize {
+        let layer_edge = |layer: usize| match cm.affine[layer].affine_type {
+            AffineLayerType::InsertLayer => TraceEdge::Ins,
+            AffineLayerType::DeleteLayer => TraceEdge::Del,
+        };
+        order.rank(match ops[0] {
+            None | Some(AffineCigarOp::Match) => None,
+            Some(AffineCigarOp::Sub) => Some(TraceEdge::Sub),
+            Some(AffineCigarOp::Ins) => Some(TraceEdge::Ins),
+            Some(AffineCigarOp::Del) => Some(TraceEdge::Del),
+            Some(
+                AffineCigarOp::AffineOpen(layer)
+                | AffineCigarOp::AffineClose(layer)
+                | AffineCigarOp::AffineIns(layer)
+                | AffineCigarOp::AffineDel(layer),
+            ) => Some(layer_edge(layer)),
+        })
+    }
+
     /// Iterate over the states/layers at the given position in 'the right'
     /// order, making sure dependencies within the states at the given position
     /// come first.
@@ -79,7 +105,10 @@ impl EditGraph {
     /// I.e., in the normal case affine layers are iterated before the main
     /// layer, to ensure that the ends of the gap-close edges within this
     /// position are visited first.
-    pub fn iterate_layers<const N: usize>(_cm: &AffineCost<N>, mut f: impl FnMut(Layer)) {
+    pub fn iterate_layers<const N: usize, C: CostT>(
+        _cm: &AffineCost<N, C>,
+        mut f: impl FnMut(Layer),
+    ) {
         for layer in 0..N {
             f(Some(layer));
         }
@@ -92,13 +121,13 @@ impl EditGraph {
     /// `iterate_children` may be needed as well at some point, but currently we
     /// use a 'pull-based' DP, meaning that in each state we look back, and
     /// never 'push' to the children.
-    pub fn iterate_parents<const N: usize>(
+    pub fn iterate_parents<const N: usize, C: CostT>(
         a: Seq,
         b: Seq,
-        cm: &AffineCost<N>,
+        cm: &AffineCost<N, C>,
         greedy_matching: bool,
         State { i, j, layer }: State,
-        mut f: impl FnMut(I, I, Layer, Cost, AffineCigarOps),
+        mut f: impl FnMut(I, I, Layer, C, AffineCigarOps),
     ) {
         match layer {
             None => {
@@ -112,7 +141,7 @@ impl EditGraph {
                 // match / substitution
                 let is_match = i > 0 && j > 0 && a[i as usize - 1] == b[j as usize - 1];
                 if is_match {
-                    f(-1, -1, None, 0, [Some(AffineCigarOp::Match), None]);
+                    f(-1, -1, None, C::ZERO, [Some(AffineCigarOp::Match), None]);
                     if greedy_matching {
                         return;
                     }
@@ -59,6 +59,7 @@ where
 
 /// `Fronts` is a vector of fronts, possibly with a buffer layer at the top.
 /// TODO: Add `fronts.rotate()` and `fronts.grow()` functions to add a new front.
+#[derive(Clone)]
 pub struct Fronts<const N: usize, T, I> {
     pub fronts: Vec<Front<N, T, I>>,
     /// The default value.
@@ -131,6 +132,15 @@ where
         self.range = *self.range.start()..=*self.range.end() + I::one();
     }
 
+    /// Push a copy of a front computed elsewhere, e.g. in a rotating buffer.
+    pub fn push_front(&mut self, front: Front<N, T, I>)
+    where
+        for<'l> &'l I: RefNum<I>,
+    {
+        self.fronts.push(front);
+        self.range = *self.range.start()..=*self.range.end() + I::one();
+    }
+
     pub fn rotate(&mut self, range: RangeInclusive<I>)
     where
         for<'l> &'l I: RefNum<I>,
@@ -354,6 +364,13 @@ where
         self.l
             .get((index + self.buffers.0 - self.range.start()).as_())
     }
+
+    /// Same as indexing by a range, but borrows from the front instead of this layer.
+    #[inline]
+    pub fn slice(&self, index: Range<I>) -> &'a [T] {
+        &self.l[(index.start + self.buffers.0 - self.range.start()).as_()
+            ..(index.end + self.buffers.0 - self.range.start()).as_()]
+    }
 }
 impl<'a, T, I> Index<I> for Layer<'a, T, I>
 where
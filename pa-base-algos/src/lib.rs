@@ -10,6 +10,7 @@ mod front;
 pub mod cli;
 pub mod dt;
 pub mod nw;
+pub mod poa;
 
 // #[cfg(test)]
 // mod tests;
@@ -55,9 +56,21 @@ fn exponential_search<T>(
     offset: Cost,
     s0: Cost,
     factor: f32,
+    f: impl FnMut(Cost) -> Option<(Cost, T)>,
+) -> (Cost, T) {
+    growth_search(offset, s0, Growth::Factor(factor), f)
+}
+
+/// Same as `exponential_search`, but grows `s - offset` following `growth`.
+fn growth_search<T>(
+    offset: Cost,
+    s0: Cost,
+    growth: Growth,
     mut f: impl FnMut(Cost) -> Option<(Cost, T)>,
 ) -> (Cost, T) {
     let mut last_s = -1;
+    // The previous increment, used by `Growth::Fibonacci`.
+    let mut last_d = s0;
     let mut s = offset + s0;
     let mut maxs = Cost::MAX;
     // TODO: Fix the potential infinite loop here.
@@ -86,7 +99,9 @@ fn exponential_search<T>(
             );
         }
         last_s = s;
-        s = max((factor * (s - offset) as f32).ceil() as Cost, 1) + offset;
+        let d = s - offset;
+        s = growth.next(last_d, d) + offset;
+        last_d = d;
         s = min(s, maxs);
     }
 }
@@ -130,6 +145,7 @@ fn linear_search<T>(
 }
 
 use pa_heuristic::{GapCost, NoCost};
+pub use pa_heuristic::{TraceEdge, TraceOrder};
 
 /// Enum for the various computational domain types.
 /// See Ukkonen, Scrooge, O(NP), Papamichail, A*PA
@@ -214,20 +230,70 @@ pub enum DoublingStart {
     Zero,
     Gap,
     H0,
+    /// The maximum of the gap cost and `h(0,0)`.
+    Max,
+    /// A user-provided initial bound, e.g. from a previous alignment.
+    Fixed(Cost),
+    /// The cost implied by `estimate_divergence`, but at least `h(0,0)`.
+    Estimate,
+}
+
+/// The schedule by which band doubling grows the threshold `s - offset`.
+///
+/// The best choice depends on the data: a large factor wastes work when the
+/// last iteration overshoots the distance, while slow growth needs many
+/// iterations when the start value is far below the distance.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Growth {
+    /// Multiply by the given factor.
+    Factor(f32),
+    /// Add a constant.
+    Additive(Cost),
+    /// Add the previous value, giving `s0`, `2*s0`, `3*s0`, `5*s0`, ...
+    /// This grows by the golden ratio asymptotically.
+    Fibonacci,
+    /// Given the previous and current value, return the next value.
+    /// Values not larger than the current value are increased to `current + 1`.
+    #[serde(skip)]
+    Custom(fn(Cost, Cost) -> Cost),
+}
+
+impl Growth {
+    /// The value to try after `d`, where `last_d` was tried before `d`.
+    fn next(&self, last_d: Cost, d: Cost) -> Cost {
+        match *self {
+            Growth::Factor(factor) => max((factor * d as f32).ceil() as Cost, 1),
+            Growth::Additive(delta) => d + max(delta, 1),
+            Growth::Fibonacci => d + max(last_d, 1),
+            Growth::Custom(f) => max(f(last_d, d), d + 1),
+        }
+    }
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Growth::Factor(2.)
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Strategy {
     None,
-    BandDoubling { start: DoublingStart, factor: f32 },
-    LinearSearch { start: DoublingStart, delta: f32 },
+    BandDoubling {
+        start: DoublingStart,
+        growth: Growth,
+    },
+    LinearSearch {
+        start: DoublingStart,
+        delta: f32,
+    },
     LocalDoubling,
 }
 impl Strategy {
     pub fn band_doubling() -> Strategy {
         Self::BandDoubling {
             start: DoublingStart::H0,
-            factor: 2.0,
+            growth: Growth::Factor(2.0),
         }
     }
 }
@@ -235,7 +301,7 @@ impl Default for Strategy {
     fn default() -> Self {
         Strategy::BandDoubling {
             start: DoublingStart::H0,
-            factor: 2.,
+            growth: Growth::Factor(2.),
         }
     }
 }
@@ -16,23 +16,33 @@
 mod affine;
 mod bitpacking;
 mod front;
+mod matrix;
+mod oracle;
 
+use crate::dt::{DiagonalTransition, GapCostHeuristic, PathTracingMethod};
 use crate::nw::front::{IRange, JRange, NwFront, NwFronts};
-use crate::{exponential_search, Strategy, PRINT};
-use crate::{linear_search, Domain};
+use crate::{growth_search, Strategy, PRINT};
+use crate::{linear_search, Domain, TraceOrder};
 use pa_affine_types::*;
 use pa_heuristic::*;
 use pa_types::*;
 use pa_vis::*;
 use serde::{Deserialize, Serialize};
 use std::cmp::{max, min};
+use tracing::{debug, trace};
 
 use self::affine::AffineNwFrontsTag;
-use self::front::NwFrontsTag;
+use self::front::{NwFrontsRevTag, NwFrontsTag};
+use self::oracle::Oracle;
+
+pub use self::affine::{generic_align, generic_align_banded, FrontCostT};
+pub use self::oracle::generic_cost;
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
 pub enum FrontType {
     Affine,
+    /// `Affine`, with linear-memory traceback.
+    AffineHirschberg,
     Bit(BitFront),
 }
 
@@ -43,8 +53,10 @@ impl Default for FrontType {
 }
 
 // TODO: Fix these names to be the same.
+pub use affine::AffineHirschbergFrontsTag as AffineHirschbergFront;
 pub use affine::AffineNwFrontsTag as AffineFront;
 pub use bitpacking::BitFrontsTag as BitFront;
+pub use matrix::{compute_full_matrix, f_landscape_svg, write_matrix_csv};
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
 pub struct AstarNwParams {
@@ -76,6 +88,25 @@ pub struct AstarNwParams {
     #[serde(default)]
     pub prune: bool,
 
+    /// Use diagonal transition instead of NW.
+    /// `strategy`, `block_width`, `front` and `sparse_h_calls` are ignored in this case.
+    #[serde(default)]
+    pub dt: bool,
+
+    /// Use divide & conquer for diagonal transition (like BiWFA).
+    /// Only supported without a heuristic.
+    #[serde(default)]
+    pub dc: bool,
+
+    /// The method used to trace the diagonal transition alignment.
+    #[serde(default)]
+    pub path_tracing: PathTracingMethod,
+
+    /// The preferred order of equal-cost parents during traceback.
+    /// Overrides the order set in `front`, so that NW and DT give the same cigars.
+    #[serde(default)]
+    pub trace_order: TraceOrder,
+
     /// Whether the visualizer is enabled.
     #[serde(default)]
     pub viz: bool,
@@ -83,7 +114,7 @@ pub struct AstarNwParams {
 
 impl AstarNwParams {
     /// Build an `AstarStatsAligner` instance from
-    pub fn make_aligner(&self, trace: bool) -> Box<dyn Aligner> {
+    pub fn make_aligner(&self, trace: bool) -> Result<Box<dyn Aligner>, InvalidParams> {
         #[cfg(feature = "example")]
         if self.viz {
             use pa_vis::visualizer::{Gradient, When};
@@ -119,12 +150,16 @@ impl AstarNwParams {
     }
 
     /// Build a type-erased aligner object from parameters.
+    /// Fails for combinations of parameters that are not supported.
     /// FIXME: Add costmodel support.
     pub fn make_aligner_with_visualizer<V: VisualizerT + 'static>(
         &self,
         trace: bool,
         v: V,
-    ) -> Box<dyn Aligner> {
+    ) -> Result<Box<dyn Aligner>, InvalidParams> {
+        if self.dt {
+            return self.make_dt_aligner(v);
+        }
         struct Mapper<V: VisualizerT, F: NwFrontsTag<0>> {
             params: AstarNwParams,
             trace: bool,
@@ -147,18 +182,31 @@ impl AstarNwParams {
                 })
             }
         }
-        match (self.domain, self.front) {
+        Ok(match (self.domain, self.front) {
             (Domain::Astar(()), FrontType::Affine) => self.heuristic.map(Mapper {
                 params: self.clone(),
                 trace,
                 v,
-                front: AffineFront,
+                front: AffineFront {
+                    trace_order: self.trace_order,
+                },
+            }),
+            (Domain::Astar(()), FrontType::AffineHirschberg) => self.heuristic.map(Mapper {
+                params: self.clone(),
+                trace,
+                v,
+                front: AffineHirschbergFront {
+                    trace_order: self.trace_order,
+                },
             }),
             (Domain::Astar(()), FrontType::Bit(front)) => self.heuristic.map(Mapper {
                 params: self.clone(),
                 trace,
                 v,
-                front,
+                front: BitFront {
+                    trace_order: self.trace_order,
+                    ..front
+                },
             }),
             (d, FrontType::Affine) => Box::new(NW {
                 cm: AffineCost::unit(),
@@ -166,7 +214,22 @@ impl AstarNwParams {
                 strategy: self.strategy,
                 block_width: self.block_width,
                 v,
-                front: AffineFront,
+                front: AffineFront {
+                    trace_order: self.trace_order,
+                },
+                trace,
+                sparse_h: self.sparse_h_calls,
+                prune: self.prune,
+            }),
+            (d, FrontType::AffineHirschberg) => Box::new(NW {
+                cm: AffineCost::unit(),
+                domain: d.into(),
+                strategy: self.strategy,
+                block_width: self.block_width,
+                v,
+                front: AffineHirschbergFront {
+                    trace_order: self.trace_order,
+                },
                 trace,
                 sparse_h: self.sparse_h_calls,
                 prune: self.prune,
@@ -177,18 +240,84 @@ impl AstarNwParams {
                 strategy: self.strategy,
                 block_width: self.block_width,
                 v,
-                front,
+                front: BitFront {
+                    trace_order: self.trace_order,
+                    ..front
+                },
                 trace,
                 sparse_h: self.sparse_h_calls,
                 prune: self.prune,
             }),
+        })
+    }
+}
+
+impl AstarNwParams {
+    /// Build a type-erased diagonal transition aligner from parameters.
+    fn make_dt_aligner<V: VisualizerT + 'static>(
+        &self,
+        v: V,
+    ) -> Result<Box<dyn Aligner>, InvalidParams> {
+        const DC_WITH_HEURISTIC: InvalidParams =
+            InvalidParams("Divide & conquer is only supported without a heuristic.");
+        struct Mapper<V: VisualizerT> {
+            params: AstarNwParams,
+            v: V,
+        }
+        impl<V: VisualizerT + 'static> HeuristicMapper for Mapper<V> {
+            type R = Result<Box<dyn Aligner>, InvalidParams>;
+            fn call<H: Heuristic + 'static>(self, h: H) -> Self::R {
+                if self.params.dc && !H::IS_DEFAULT {
+                    return Err(DC_WITH_HEURISTIC);
+                }
+                let mut dt = DiagonalTransition::new(
+                    AffineCost::unit(),
+                    GapCostHeuristic::Disable,
+                    h,
+                    self.params.dc,
+                    self.v,
+                );
+                dt.path_tracing_method = self.params.path_tracing;
+                dt.trace_order = self.params.trace_order;
+                Ok(Box::new(dt))
+            }
         }
+        let gap_cost_heuristic = match self.domain {
+            Domain::Astar(()) => {
+                return self.heuristic.map(Mapper {
+                    params: self.clone(),
+                    v,
+                })
+            }
+            Domain::GapGap if self.dc => return Err(DC_WITH_HEURISTIC),
+            Domain::GapGap => GapCostHeuristic::Enable,
+            Domain::Full | Domain::GapStart => GapCostHeuristic::Disable,
+        };
+        let mut dt =
+            DiagonalTransition::new(AffineCost::unit(), gap_cost_heuristic, NoCost, self.dc, v);
+        dt.path_tracing_method = self.path_tracing;
+        dt.trace_order = self.trace_order;
+        Ok(Box::new(dt))
+    }
+}
+
+/// A combination of `AstarNwParams` that is not supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidParams(pub &'static str);
+
+impl std::fmt::Display for InvalidParams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid parameters: {}", self.0)
     }
 }
 
+impl std::error::Error for InvalidParams {}
+
 /// Needleman-Wunsch aligner.
 ///
 /// NOTE: Heuristics only support unit cost graph for now.
+/// They remain admissible for cost models where all edit costs are at least 1,
+/// such as asymmetric insertion and deletion costs.
 pub struct NW<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> {
     /// The cost model to use.
     pub cm: AffineCost<N>,
@@ -222,7 +351,6 @@ pub struct NW<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> {
 }
 
 impl<const N: usize> NW<N, NoVis, NoCost, AffineNwFrontsTag<N>> {
-    // TODO: This is only used in tests.
     pub fn new(cm: AffineCost<N>, use_gap_cost_heuristic: bool, exponential_search: bool) -> Self {
         Self {
             cm,
@@ -240,7 +368,7 @@ impl<const N: usize> NW<N, NoVis, NoCost, AffineNwFrontsTag<N>> {
             // TODO: Make this more general.
             block_width: 32,
             v: NoVis,
-            front: AffineNwFrontsTag::<N>,
+            front: AffineNwFrontsTag::<N>::default(),
             trace: true,
             sparse_h: true,
             prune: true,
@@ -251,6 +379,7 @@ impl<const N: usize> NW<N, NoVis, NoCost, AffineNwFrontsTag<N>> {
 impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> NW<N, V, H, F> {
     pub fn build<'a>(&'a self, a: Seq<'a>, b: Seq<'a>) -> NWInstance<'a, N, V, H, F> {
         use Domain::*;
+        util::assert_fits_index(a, b);
         NWInstance {
             a,
             b,
@@ -262,13 +391,15 @@ impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> NW<N, V, H
                 Astar(h) => {
                     let h = h.build(a, b);
                     if PRINT {
-                        eprintln!("h0: {}", h.h(Pos(0, 0)));
+                        debug!("h0: {}", h.h(Pos(0, 0)));
                     }
                     Astar(h)
                 }
             },
-            hint: Default::default(),
+            hints: HintCache::new(self.block_width),
             v: self.v.build(a, b),
+            new_rev: None,
+            computed_rows: 0,
         }
     }
 
@@ -293,12 +424,37 @@ impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> NW<N, V, H
                 }
                 Domain::Astar(_) => (nw.domain.h().unwrap().h(Pos(0, 0)), 1),
             },
+            crate::DoublingStart::Max => {
+                let x = self.cm.gap_cost(Pos(0, 0), Pos::target(a, b));
+                let h0 = nw.domain.h().map_or(0, |h| h.h(Pos(0, 0)));
+                (max(x, h0), 1)
+            }
+            crate::DoublingStart::Fixed(f) => (f, 1),
+            crate::DoublingStart::Estimate => {
+                let e = estimate_divergence(a, b);
+                let h0 = nw.domain.h().map_or(0, |h| h.h(Pos(0, 0)));
+                (max((e * max(a.len(), b.len()) as f32) as Cost, h0), 1)
+            }
         };
         (start_f, max(start_increment, F::BLOCKSIZE))
     }
 
     fn cost_or_align(&self, a: Seq, b: Seq, trace: bool) -> (Cost, Option<AffineCigar>) {
+        let (cost, cigar, _stats) = self.cost_or_align_with_stats(a, b, trace);
+        (cost, cigar)
+    }
+
+    /// Same as `cost_or_align`, but also returns the statistics shared by all
+    /// aligners. `t_total` is left at 0.
+    fn cost_or_align_with_stats(
+        &self,
+        a: Seq,
+        b: Seq,
+        trace: bool,
+    ) -> (Cost, Option<AffineCigar>, AlignStats) {
+        let start = std::time::Instant::now();
         let mut nw = self.build(a, b);
+        let t_precomp = start.elapsed();
         let h0 = nw.domain.h().map_or(0, |h| h.h(Pos(0, 0)));
         let (cost, cigar) = match self.strategy {
             Strategy::LocalDoubling => {
@@ -306,10 +462,10 @@ impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> NW<N, V, H
                 let (cost, cigar) = nw.local_doubling();
                 (cost, Some(cigar))
             }
-            Strategy::BandDoubling { start, factor } => {
+            Strategy::BandDoubling { start, growth } => {
                 let (start_f, start_increment) = self.band_doubling_params(start, a, b, &nw);
                 let mut fronts = self.front.new(trace, a, b, &self.cm);
-                exponential_search(start_f, start_increment, factor, |s| {
+                growth_search(start_f, start_increment, growth, |s| {
                     nw.align_for_bounded_dist(Some(s), trace, Some(&mut fronts))
                         .map(|x @ (c, _)| (c, x))
                 })
@@ -332,7 +488,16 @@ impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> NW<N, V, H
         };
         nw.v.last_frame(cigar.as_ref(), None, nw.domain.h());
         assert!(h0 <= cost, "Heuristic at start {h0} > final cost {cost}.");
-        (cost, cigar)
+        let stats = AlignStats {
+            h: match &mut nw.domain {
+                Domain::Astar(h) => h.stats(),
+                _ => Default::default(),
+            },
+            computed_rows: nw.computed_rows,
+            t_precomp,
+            ..Default::default()
+        };
+        (cost, cigar, stats)
     }
 
     pub fn cost(&self, a: Seq, b: Seq) -> Cost {
@@ -344,6 +509,22 @@ impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> NW<N, V, H
         (cost, cigar)
     }
 
+    /// Same as `cost`, but returns an error when a cost addition overflowed.
+    /// Overflows are only detected with the `checked_cost` feature.
+    pub fn try_cost(&self, a: Seq, b: Seq) -> Result<Cost, util::CostOverflow> {
+        util::check_cost_overflow(|| self.cost(a, b))
+    }
+
+    /// Same as `align`, but returns an error when a cost addition overflowed.
+    /// Overflows are only detected with the `checked_cost` feature.
+    pub fn try_align(
+        &self,
+        a: Seq,
+        b: Seq,
+    ) -> Result<(Cost, Option<AffineCigar>), util::CostOverflow> {
+        util::check_cost_overflow(|| self.align(a, b))
+    }
+
     pub fn cost_for_bounded_dist(&self, a: Seq, b: Seq, f_max: Cost) -> Option<Cost> {
         self.build(a, b)
             .align_for_bounded_dist(Some(f_max), false, None)
@@ -360,6 +541,127 @@ impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> NW<N, V, H
             .align_for_bounded_dist(Some(f_max), true, None)
             .map(|(c, cigar)| (c, cigar.unwrap()))
     }
+
+    /// Returns the distance and up to `max_count` distinct optimal alignments.
+    ///
+    /// Different tools break ties differently, so this can be used to check
+    /// whether a given alignment is one of the co-optimal ones.
+    /// `Strategy::LocalDoubling`, pruning, and `Strategy::None` with a domain
+    /// other than `Domain::Full` are not supported and return an error.
+    /// All fronts must be stored, i.e. `BitFront` must not be `sparse`.
+    /// Alignments are only enumerated inside the computed band, which
+    /// contains all optimal paths when the heuristic is admissible.
+    pub fn align_all(
+        &self,
+        a: Seq,
+        b: Seq,
+        max_count: usize,
+    ) -> Result<(Cost, Vec<AffineCigar>), UnsupportedStrategy> {
+        let unsupported = |reason| UnsupportedStrategy {
+            strategy: self.strategy,
+            reason,
+        };
+        if self.prune && matches!(self.domain, Domain::Astar(_)) {
+            return Err(unsupported("pruning"));
+        }
+        match self.strategy {
+            Strategy::None if !matches!(self.domain, Domain::Full) => {
+                return Err(unsupported("a domain other than Full"));
+            }
+            Strategy::LocalDoubling => return Err(unsupported("local doubling")),
+            _ => {}
+        }
+        let mut nw = self.build(a, b);
+        let mut fronts = self.front.new(true, a, b, &self.cm);
+        let cost = match self.strategy {
+            Strategy::BandDoubling { start, growth } => {
+                let (start_f, start_increment) = self.band_doubling_params(start, a, b, &nw);
+                growth_search(start_f, start_increment, growth, |s| {
+                    nw.align_for_bounded_dist(Some(s), false, Some(&mut fronts))
+                        .map(|(c, _)| (c, ()))
+                })
+                .0
+            }
+            Strategy::LinearSearch { start, delta } => {
+                let start_f = self.band_doubling_params(start, a, b, &nw).0;
+                linear_search(start_f, delta as Cost, |s| {
+                    nw.align_for_bounded_dist(Some(s), false, Some(&mut fronts))
+                        .map(|(c, _)| (c, ()))
+                })
+                .0
+            }
+            Strategy::None => {
+                nw.align_for_bounded_dist(None, false, Some(&mut fronts))
+                    .unwrap()
+                    .0
+            }
+            Strategy::LocalDoubling => unreachable!(),
+        };
+        let cigars = fronts.trace_all(
+            a,
+            b,
+            State {
+                i: 0,
+                j: 0,
+                layer: None,
+            },
+            State {
+                i: a.len() as I,
+                j: b.len() as I,
+                layer: None,
+            },
+            max_count,
+        );
+        Ok((cost, cigars))
+    }
+}
+
+/// A configuration that is not supported by `NW::align_all`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UnsupportedStrategy {
+    pub strategy: Strategy,
+    /// The unsupported part of the configuration.
+    pub reason: &'static str,
+}
+
+impl std::fmt::Display for UnsupportedStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Enumerating alignments is not supported with {} (strategy {:?})",
+            self.reason, self.strategy
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedStrategy {}
+
+impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsRevTag<N>> NW<N, V, H, F> {
+    /// Same as `build`, but the instance aligns the reverse of `a` and `b`,
+    /// filling the fronts from right to left. Column `i` then corresponds to
+    /// the suffix `a[a.len()-i..]`.
+    ///
+    /// The sequences are not copied; only the front type needs to support
+    /// this via `NwFrontsRevTag`.
+    /// Only supported for domains without a heuristic, since the heuristic is
+    /// built for the forward direction. Traceback is not supported.
+    pub fn build_rev<'a>(&'a self, a: Seq<'a>, b: Seq<'a>) -> NWInstance<'a, N, V, H, F> {
+        assert!(
+            self.domain.h().is_none(),
+            "Reverse alignment does not support A*."
+        );
+        let mut nw = self.build(a, b);
+        nw.new_rev = Some(F::new_rev);
+        nw
+    }
+
+    /// Same as `cost_for_bounded_dist`, but fills the fronts from the end of
+    /// the sequences. See `build_rev`.
+    pub fn cost_rev_for_bounded_dist(&self, a: Seq, b: Seq, f_max: Cost) -> Option<Cost> {
+        self.build_rev(a, b)
+            .align_for_bounded_dist(Some(f_max), false, None)
+            .map(|c| c.0)
+    }
 }
 
 impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> AffineAligner
@@ -370,13 +672,24 @@ impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> AffineAlig
     }
 }
 
-impl<V: VisualizerT, H: Heuristic, F: NwFrontsTag<0>> Aligner for NW<0, V, H, F> {
+impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> Aligner for NW<N, V, H, F> {
     fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>) {
         let (cost, cigar) = NW::align(self, a, b);
         (cost, cigar.map(|c| c.into()))
     }
 }
 
+impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> ResultAligner
+    for NW<N, V, H, F>
+{
+    fn align_result(&mut self, a: Seq, b: Seq) -> AlignResult {
+        let start = std::time::Instant::now();
+        let (cost, cigar, mut stats) = self.cost_or_align_with_stats(a, b, self.trace);
+        stats.t_total = start.elapsed();
+        AlignResult::new(cost, cigar.map(|c| c.into()), stats)
+    }
+}
+
 impl<const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> std::fmt::Debug
     for NW<N, V, H, F>
 {
@@ -395,11 +708,19 @@ pub struct NWInstance<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFro
     /// The instantiated heuristic to use.
     domain: Domain<H::Instance<'a>>,
 
-    /// Hint for the heuristic, cached between `j_range` calls.
-    hint: <H::Instance<'a> as HeuristicInstance<'a>>::Hint,
+    /// Hints for the heuristic, cached per band of rows between `j_range` calls.
+    hints: HintCache<<H::Instance<'a> as HeuristicInstance<'a>>::Hint>,
 
     /// The instantiated visualizer to use.
     v: V::Instance,
+
+    /// Set when the reverse of `a` and `b` is aligned, to construct the
+    /// reverse fronts. See `NW::build_rev`.
+    new_rev: Option<fn(&F, Seq<'a>, Seq<'a>, &'a AffineCost<N>) -> F::Fronts<'a>>,
+
+    /// Number of rows in the `j_range` of all computed columns, summed over
+    /// all tried values of `f_max`.
+    computed_rows: usize,
 }
 
 impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> Drop
@@ -408,7 +729,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> Drop
     fn drop(&mut self) {
         if PRINT {
             if let Domain::Astar(h) = &mut self.domain {
-                eprintln!("h0 end: {}", h.h(Pos(0, 0)));
+                debug!("h0 end: {}", h.h(Pos(0, 0)));
             }
         }
     }
@@ -417,6 +738,38 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>> Drop
 impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
     NWInstance<'a, N, V, H, F>
 {
+    /// For the `GapStart` and `GapGap` domains, the range of diagonals `j - i`
+    /// that paths of cost at most `f_max` can visit.
+    fn diagonals(&self, f_max: Cost) -> Option<JRange> {
+        match &self.domain {
+            Domain::GapStart => {
+                // range: the max number of diagonals we can move up/down from the start with cost f.
+                Some(JRange(
+                    -(self.params.cm.max_del_for_cost(f_max) as I),
+                    self.params.cm.max_ins_for_cost(f_max) as I,
+                ))
+            }
+            Domain::GapGap => {
+                let d = self.b.len() as I - self.a.len() as I;
+                // We subtract the cost needed to bridge the gap from the start to the end.
+                let s = f_max
+                    - self
+                        .params
+                        .cm
+                        .gap_cost(Pos(0, 0), Pos::target(&self.a, &self.b));
+                // Each extra diagonal costs one insertion and one deletion.
+                let extra_diagonals =
+                    s / (self.params.cm.min_ins_extend + self.params.cm.min_del_extend);
+                // NOTE: The range could be reduced slightly further by considering gap open costs.
+                Some(JRange(
+                    min(d, 0) - extra_diagonals as I,
+                    max(d, 0) + extra_diagonals as I,
+                ))
+            }
+            Domain::Full | Domain::Astar(_) => None,
+        }
+    }
+
     /// The range of rows `j` to consider for columns `i_range.0 .. i_range.1`, when the cost is bounded by `f_bound`.
     ///
     /// For A*, this also returns the range of rows in column `i_range.0` that are 'fixed', ie have `f <= f_max`.
@@ -454,35 +807,8 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
 
         match &self.domain {
             Domain::Full => JRange(0, self.b.len() as I),
-            Domain::GapStart => {
-                // range: the max number of diagonals we can move up/down from the start with cost f.
-                let range = JRange(
-                    -(self.params.cm.max_del_for_cost(f_max) as I),
-                    self.params.cm.max_ins_for_cost(f_max) as I,
-                );
-                // crop
-                JRange(
-                    max(is + 1 + range.0, 0),
-                    min(ie + range.1, self.b.len() as I),
-                )
-            }
-            Domain::GapGap => {
-                let d = self.b.len() as I - self.a.len() as I;
-                // We subtract the cost needed to bridge the gap from the start to the end.
-                let s = f_max
-                    - self
-                        .params
-                        .cm
-                        .gap_cost(Pos(0, 0), Pos::target(&self.a, &self.b));
-                // Each extra diagonal costs one insertion and one deletion.
-                let extra_diagonals =
-                    s / (self.params.cm.min_ins_extend + self.params.cm.min_del_extend);
-                // NOTE: The range could be reduced slightly further by considering gap open costs.
-                let range = JRange(
-                    min(d, 0) - extra_diagonals as I,
-                    max(d, 0) + extra_diagonals as I,
-                );
-
+            Domain::GapStart | Domain::GapGap => {
+                let range = self.diagonals(f_max).unwrap();
                 // crop
                 JRange(
                     max(is + 1 + range.0, 0),
@@ -499,7 +825,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
                 };
 
                 if PRINT {
-                    eprintln!("j_range for {i_range:?}\t\told {old_range:?}\t\t fixed @ {is}\t {fixed_start}..{fixed_end}");
+                    trace!("j_range for {i_range:?}\t\told {old_range:?}\t\t fixed @ {is}\t {fixed_start}..{fixed_end}");
                 }
 
                 // Early return for empty range.
@@ -522,10 +848,9 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
                 // i_range.1 that could possibly have `f(v) <= f_max`.
                 let mut v = u;
 
-                // Wrapper to use h with hint.
+                // Wrapper to use h with cached hints.
                 let mut h = |pos| {
-                    let (h, new_hint) = h.h_with_hint(pos, self.hint);
-                    self.hint = new_hint;
+                    let h = h.h_with_cached_hint(pos, &mut self.hints);
                     self.v.h_call(pos);
                     h
                 };
@@ -533,7 +858,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
                 let mut f = |v: Pos| {
                     assert!(v.1 - u.1 >= v.0 - u.0);
                     // eprintln!("f({})", v);
-                    gu + self.params.cm.extend_cost(u, v) + h(v)
+                    util::add_cost(util::add_cost(gu, self.params.cm.extend_cost(u, v)), h(v))
                 };
 
                 // Extend `v` diagonally one column at a time towards `ie`.
@@ -564,7 +889,9 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
                             // we can make bigger jumps.
                             v.1 += 1;
                         } else {
-                            v.0 += (fv - f_max).div_ceil(2 * self.params.cm.min_del_extend);
+                            // Moving `v` right shrinks its vertical gap to `u`,
+                            // which consists of insertions. See `max_f_step`.
+                            v.0 += (fv - f_max).div_ceil(self.params.cm.max_f_step());
                         }
                     }
                     v.0 = ie;
@@ -580,7 +907,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
                         if fv <= f_max {
                             break;
                         } else {
-                            v.1 -= (fv - f_max).div_ceil(2 * self.params.cm.min_ins_extend);
+                            v.1 -= (fv - f_max).div_ceil(self.params.cm.max_f_step());
                             // Don't go above the diagonal.
                             // This could happen after pruning we if don't check explicitly.
                             if v.1 < v.0 - u.0 + u.1 {
@@ -623,13 +950,9 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
             return None;
         };
 
-        // Wrapper to use h with hint.
-        let mut h = |pos| {
-            let (h, new_hint) = h.h_with_hint(pos, self.hint);
-            self.hint = new_hint;
-            h
-        };
-        let mut f = |j| front.index(j) + h(Pos(i, j));
+        // Wrapper to use h with cached hints.
+        let mut h = |pos| h.h_with_cached_hint(pos, &mut self.hints);
+        let mut f = |j| util::add_cost(front.index(j), h(Pos(i, j)));
 
         // Start: increment the start of the range until f<=f_max is satisfied.
         // End: decrement the end of the range until f<=f_max is satisfied.
@@ -653,7 +976,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
             }
             start += if self.params.sparse_h {
                 // TODO: Increase by steps of 64.
-                (f - f_max).div_ceil(2 * self.params.cm.min_ins_extend)
+                (f - f_max).div_ceil(self.params.cm.max_f_step())
             } else {
                 1
             };
@@ -666,7 +989,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
             }
             end -= if self.params.sparse_h {
                 // TODO: Decrease by steps of 64.
-                (f - f_max).div_ceil(2 * self.params.cm.min_ins_extend)
+                (f - f_max).div_ceil(self.params.cm.max_f_step())
             } else {
                 1
             };
@@ -691,17 +1014,24 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
         {
             h.update_contours(Pos(0, 0));
             if PRINT {
-                eprintln!("Test dist {} h0 {}", f_max.unwrap_or(0), h.h(Pos(0, 0)));
+                debug!("Test dist {} h0 {}", f_max.unwrap_or(0), h.h(Pos(0, 0)));
             }
         }
 
+        assert!(
+            !(self.new_rev.is_some() && trace),
+            "Traceback is not supported for reverse alignment."
+        );
+
         // Make a local front variable if not passed in.
         let mut local_fronts = if fronts.is_none() {
-            Some(
+            Some(if let Some(new_rev) = self.new_rev {
+                new_rev(&self.params.front, self.a, self.b, &self.params.cm)
+            } else {
                 self.params
                     .front
-                    .new(trace, self.a, self.b, &self.params.cm),
-            )
+                    .new(trace, self.a, self.b, &self.params.cm)
+            })
         } else {
             None
         };
@@ -721,10 +1051,17 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
         if initial_j_range.is_empty() {
             return None;
         }
-        // eprintln!("Bound: {f_max:?} {initial_j_range:?}");
+        debug!("Bound: {f_max:?} {initial_j_range:?}");
         fronts.init(initial_j_range);
         fronts.set_last_front_fixed_j_range(Some(initial_j_range));
 
+        let oracle = (cfg!(feature = "validate_fronts") && self.new_rev.is_none())
+            .then(|| Oracle::new(self.a, self.b, &self.params.cm))
+            .flatten();
+        if let Some(oracle) = &oracle {
+            oracle.check_front(0, fronts.last_front());
+        }
+
         self.v.expand_block(
             Pos(0, fronts.last_front().j_range_rounded().0),
             Pos(1, fronts.last_front().j_range_rounded().len()),
@@ -764,7 +1101,13 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
                 fronts.reuse_next_block(i_range, j_range);
             } else {
                 // eprintln!("{i}: compute block {i_range:?} {j_range:?}");
-                fronts.compute_next_block(i_range, j_range, &mut self.v);
+                self.computed_rows += i_range.len() as usize * j_range.len() as usize;
+                match f_max.and_then(|f_max| self.diagonals(f_max)) {
+                    Some(diagonals) => {
+                        fronts.compute_next_block_banded(i_range, j_range, diagonals, &mut self.v)
+                    }
+                    None => fronts.compute_next_block(i_range, j_range, &mut self.v),
+                }
                 if self.params.strategy == Strategy::None {
                     self.v.new_layer(self.domain.h());
                 }
@@ -776,6 +1119,9 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
             // }
             fronts.set_last_front_fixed_j_range(next_fixed_j_range);
             let next_fixed_j_range = fronts.last_front().fixed_j_range();
+            if let Some(oracle) = &oracle {
+                oracle.check_front(i_range.1, fronts.last_front());
+            }
 
             // Prune matches in the fixed range.
             if self.params.prune
@@ -938,7 +1284,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
                 grow_to(&mut f_max[start_idx], f_target, &mut f_delta[start_idx]);
                 if f_max[start_idx] > last_grow {
                     if PRINT {
-                        eprintln!(
+                        trace!(
                             "Grow  front idx {start_idx:>5} to {:>6} by {:>6} for {old_delta:>5?} and shortage {:>6}",
                             f_max[start_idx],
                             f_max[start_idx] - old_f,
@@ -953,7 +1299,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
 
             if start_idx < last_idx {
                 if PRINT {
-                    eprintln!("START front idx {start_idx:>5} to {:>6}", f_max[start_idx]);
+                    trace!("START front idx {start_idx:>5} to {:>6}", f_max[start_idx]);
                 }
                 let h = self.domain.h_mut().unwrap();
                 h.update_contours(Pos((start_idx as I - 1) * self.params.block_width, 0));
@@ -1008,6 +1354,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
                     fronts.reuse_next_block(i_range, j_range);
                 } else {
                     // eprintln!("Compute front idx {idx} i {i_range:?} j {j_range:?} f {f_max:?}");
+                    self.computed_rows += i_range.len() as usize * j_range.len() as usize;
                     fronts.compute_next_block(i_range, j_range, &mut self.v);
                 }
                 // Compute the range of fixed states.
@@ -1039,7 +1386,7 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
             for (idx, d) in f_delta.iter().enumerate() {
                 if delta != d.0 {
                     delta = d.0;
-                    eprintln!("Delta {idx:>6} => {delta:>5}");
+                    trace!("Delta {idx:>6} => {delta:>5}");
                 }
             }
         }
@@ -1067,14 +1414,28 @@ impl<'a, const N: usize, V: VisualizerT, H: Heuristic, F: NwFrontsTag<N>>
 
 #[cfg(test)]
 mod test {
-    use pa_affine_types::AffineCost;
+    use pa_affine_types::{AffineCigar, AffineCost};
     use pa_heuristic::{MatchConfig, Pruning, GCSH};
+    use pa_types::Cost;
     use pa_vis::NoVis;
 
     use crate::{Domain, Strategy};
 
     use super::{BitFront, NW};
 
+    #[test]
+    #[cfg(feature = "checked_cost")]
+    fn cost_overflow() {
+        let nw = NW::new(AffineCost::linear(1, 1), false, false);
+        assert_eq!(nw.try_cost(b"ACG", b"CGA"), Ok(2));
+        // Paths through the corners cost 3 * (MAX / 2).
+        let m = Cost::MAX / 2;
+        let nw = NW::new(AffineCost::linear(m, m), false, false);
+        assert!(nw.try_cost(b"AC", b"CA").is_err());
+        // The next check starts without the overflow.
+        assert!(nw.try_cost(b"A", b"A").is_ok());
+    }
+
     #[test]
     fn nw() {
         let (a, b) =
@@ -1096,6 +1457,31 @@ mod test {
         assert_eq!(d, d2);
     }
 
+    #[test]
+    fn reverse() {
+        let (a, b) =
+            pa_generate::generate_model(10000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let d = triple_accel::levenshtein_exp(&a, &b) as _;
+        for domain in [Domain::full(), Domain::gap_gap()] {
+            let nw = NW {
+                cm: AffineCost::unit(),
+                strategy: Strategy::band_doubling(),
+                domain,
+                block_width: 256,
+                v: NoVis,
+                front: BitFront::default(),
+                trace: false,
+                sparse_h: true,
+                prune: false,
+            };
+            assert_eq!(nw.cost_rev_for_bounded_dist(&a, &b, d), Some(d));
+            // With a too small bound, at most an upper bound is returned.
+            assert!(nw
+                .cost_rev_for_bounded_dist(&a, &b, d - 1)
+                .map_or(true, |c| c >= d));
+        }
+    }
+
     #[test]
     fn nw_prune() {
         let (a, b) =
@@ -1117,6 +1503,31 @@ mod test {
         assert_eq!(d, d2);
     }
 
+    #[test]
+    fn align_result() {
+        use pa_heuristic::ResultAligner;
+
+        let (a, b) =
+            pa_generate::generate_model(10000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let mut nw = NW {
+            cm: AffineCost::unit(),
+            strategy: Strategy::band_doubling(),
+            domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+            block_width: 256,
+            v: NoVis,
+            front: BitFront::default(),
+            trace: true,
+            sparse_h: true,
+            prune: true,
+        };
+        let r = nw.align_result(&a, &b);
+        assert_eq!(r.cost, triple_accel::levenshtein_exp(&a, &b) as Cost);
+        assert!(r.cigar.is_some());
+        assert!(r.stats.computed_rows >= a.len());
+        assert!(r.stats.h.num_seeds > 0);
+        assert!(r.stats.h.num_pruned > 0);
+    }
+
     #[test]
     fn local_doubling() {
         let (a, b) =
@@ -1138,6 +1549,370 @@ mod test {
         assert_eq!(d, d2);
     }
 
+    #[test]
+    fn reset_fronts() {
+        use super::front::{NwFrontsReset, NwFrontsTag};
+
+        let nw = NW {
+            cm: AffineCost::unit(),
+            strategy: Strategy::None,
+            domain: Domain::full(),
+            block_width: 256,
+            v: NoVis,
+            front: BitFront::default(),
+            trace: true,
+            sparse_h: true,
+            prune: false,
+        };
+        let pairs = [
+            pa_generate::generate_model(3000, 0.1, pa_generate::ErrorModel::Uniform, 31415),
+            pa_generate::generate_model(1000, 0.2, pa_generate::ErrorModel::Uniform, 2718),
+            pa_generate::generate_model(5000, 0.05, pa_generate::ErrorModel::Uniform, 1618),
+        ];
+        let mut fronts = nw.front.new(true, &pairs[0].0, &pairs[0].1, &nw.cm);
+        for (a, b) in &pairs {
+            fronts.reset(true, a, b);
+            let (cost, cigar) = nw
+                .build(a, b)
+                .align_for_bounded_dist(None, true, Some(&mut fronts))
+                .unwrap();
+            assert_eq!(cost, triple_accel::levenshtein_exp(a, b) as Cost);
+            assert_eq!(cigar.unwrap().verify(&nw.cm, a, b), cost);
+        }
+    }
+
+    #[test]
+    fn iter_values() {
+        use super::{JRange, NwFront, NwFrontsTag};
+
+        let (a, b) =
+            pa_generate::generate_model(1000, 0.2, pa_generate::ErrorModel::Uniform, 31415);
+        let nw = NW {
+            cm: AffineCost::unit(),
+            strategy: Strategy::None,
+            domain: Domain::gap_gap(),
+            block_width: 100,
+            v: NoVis,
+            front: BitFront::default(),
+            trace: true,
+            sparse_h: true,
+            prune: false,
+        };
+        let mut fronts = nw.front.new(true, &a, &b, &nw.cm);
+        nw.build(&a, &b)
+            .align_for_bounded_dist(Some(300), false, Some(&mut fronts))
+            .unwrap();
+        for idx in 0..=a.len().div_ceil(100) {
+            let front = &fronts[idx];
+            let range = front.j_range_rounded();
+            for r in [range, JRange(range.0 + 3, range.1 - 70)] {
+                let values: Vec<_> = front.iter_values(r).collect();
+                assert_eq!(values.len(), (r.1 - r.0 + 1) as usize);
+                for (j, cost) in values {
+                    assert_eq!(cost, front.index(j), "front {idx} row {j}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn growth() {
+        use crate::{DoublingStart, Growth};
+
+        let (a, b) =
+            pa_generate::generate_model(10000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        for growth in [
+            Growth::Factor(1.5),
+            Growth::Additive(100),
+            Growth::Fibonacci,
+            Growth::Custom(|_, d| d + d / 4),
+        ] {
+            let nw = NW {
+                cm: AffineCost::unit(),
+                strategy: Strategy::BandDoubling {
+                    start: DoublingStart::H0,
+                    growth,
+                },
+                domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+                block_width: 256,
+                v: NoVis,
+                front: BitFront::default(),
+                trace: true,
+                sparse_h: true,
+                prune: true,
+            };
+            assert_eq!(nw.align(&a, &b).0, d, "{growth:?}");
+        }
+    }
+
+    /// Incremental doubling reuses the fixed range of fronts between band
+    /// doubling iterations. Test the edge cases of `resize_v_with_fixed`:
+    /// block widths and lengths that are not multiples of 64, and long indels
+    /// that shift the start of the band between iterations.
+    #[test]
+    fn incremental_doubling() {
+        let (a, b) =
+            pa_generate::generate_model(3001, 0.15, pa_generate::ErrorModel::Uniform, 31415);
+        let (a2, mut b2) =
+            pa_generate::generate_model(3001, 0.05, pa_generate::ErrorModel::Uniform, 31415);
+        b2.drain(1000..1300);
+        b2.splice(2000..2000, a2[500..700].iter().copied());
+        for (a, b) in [(&a, &b), (&a2, &b2)] {
+            let d = triple_accel::levenshtein_exp(a, b) as Cost;
+            for block_width in [64, 100, 256] {
+                for incremental_doubling in [false, true] {
+                    let mut front = BitFront::default();
+                    front.incremental_doubling = incremental_doubling;
+                    front.validate_incremental = true;
+                    let (cost, cigar) = NW {
+                        cm: AffineCost::unit(),
+                        strategy: Strategy::band_doubling(),
+                        domain: Domain::Astar(GCSH::new(
+                            MatchConfig::exact(15),
+                            Pruning::disabled(),
+                        )),
+                        block_width,
+                        v: NoVis,
+                        front,
+                        trace: true,
+                        sparse_h: true,
+                        prune: false,
+                    }
+                    .align(a, b);
+                    assert_eq!(
+                        cost, d,
+                        "block_width {block_width} incremental {incremental_doubling}"
+                    );
+                    assert_eq!(cigar.unwrap().verify(&AffineCost::unit(), a, b), d);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn oracle() {
+        use super::oracle::Oracle;
+
+        let (a, b) =
+            pa_generate::generate_model(1000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let oracle = Oracle::new(&a, &b, &AffineCost::unit()).unwrap();
+        let dist = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        assert_eq!(oracle.get(a.len() as _, b.len() as _), dist);
+
+        let cm = AffineCost::affine(1, 4, 2);
+        let oracle = Oracle::new(&a, &b, &cm).unwrap();
+        let cost = NW::new(cm, false, false).cost(&a, &b);
+        assert_eq!(oracle.get(a.len() as _, b.len() as _), cost);
+
+        let cm = AffineCost::double_affine(1, 4, 2, 10, 1);
+        let oracle = Oracle::new(&a, &b, &cm).unwrap();
+        let cost = NW::new(cm, false, false).cost(&a, &b);
+        assert_eq!(oracle.get(a.len() as _, b.len() as _), cost);
+    }
+
+    #[test]
+    fn generic_cost() {
+        use super::generic_cost;
+        use pa_affine_types::{AffineLayerCosts, AffineLayerType, DeleteLayer, InsertLayer};
+
+        let (a, b) =
+            pa_generate::generate_model(1000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::affine(1, 4, 2);
+        let cost = NW::new(cm, false, false).cost(&a, &b);
+        assert_eq!(generic_cost(&a, &b, &cm), cost);
+
+        fn layer<C>(affine_type: AffineLayerType, open: C, extend: C) -> AffineLayerCosts<C> {
+            AffineLayerCosts {
+                affine_type,
+                open,
+                extend,
+            }
+        }
+        // The same costs, as i64.
+        let cm64 = AffineCost::<2, i64>::new(
+            Some(1),
+            None,
+            None,
+            [layer(InsertLayer, 4, 2), layer(DeleteLayer, 4, 2)],
+        );
+        assert_eq!(generic_cost(&a, &b, &cm64), cost as i64);
+
+        // And scaled by 0.5, as f32.
+        let cmf = AffineCost::<2, f32>::new(
+            Some(0.5),
+            None,
+            None,
+            [layer(InsertLayer, 2., 1.), layer(DeleteLayer, 2., 1.)],
+        );
+        assert_eq!(generic_cost(&a, &b, &cmf), cost as f32 / 2.);
+        assert_eq!(cmf.min_ins_open_extend, 3.);
+    }
+
+    #[test]
+    fn generic_align() {
+        use super::{generic_align, generic_align_banded};
+        use pa_affine_types::{AffineLayerCosts, AffineLayerType, DeleteLayer, InsertLayer};
+
+        let (a, b) =
+            pa_generate::generate_model(1000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::affine(1, 4, 2);
+        let cost = NW::new(cm, false, false).cost(&a, &b);
+
+        fn layer<C>(affine_type: AffineLayerType, open: C, extend: C) -> AffineLayerCosts<C> {
+            AffineLayerCosts {
+                affine_type,
+                open,
+                extend,
+            }
+        }
+        let cm64 = AffineCost::<2, i64>::new(
+            Some(1),
+            None,
+            None,
+            [layer(InsertLayer, 4, 2), layer(DeleteLayer, 4, 2)],
+        );
+        let cmf = AffineCost::<2, f32>::new(
+            Some(0.5),
+            None,
+            None,
+            [layer(InsertLayer, 2., 1.), layer(DeleteLayer, 2., 1.)],
+        );
+
+        let (c, cigar) = generic_align(&a, &b, &cm64);
+        assert_eq!(c, cost as i64);
+        assert_eq!(cigar.verify(&cm, &a, &b), cost);
+
+        let (c, cigar) = generic_align(&a, &b, &cmf);
+        assert_eq!(c, cost as f32 / 2.);
+        assert_eq!(cigar.verify(&cm, &a, &b), cost);
+
+        // Integer costs can use the gap-cost heuristic.
+        let (c, cigar) = generic_align_banded(&a, &b, &cm64);
+        assert_eq!(c, cost as i64);
+        assert_eq!(cigar.verify(&cm, &a, &b), cost);
+        let (c, cigar) = generic_align_banded(&a, &b, &cm);
+        assert_eq!(c, cost);
+        assert_eq!(cigar.verify(&cm, &a, &b), cost);
+    }
+
+    #[test]
+    fn double_affine() {
+        use crate::dt::{DiagonalTransition, GapCostHeuristic};
+        use pa_heuristic::NoCost;
+
+        // Small errors, and one long deletion that is cheaper in the second piece.
+        let (a, mut b) =
+            pa_generate::generate_model(2000, 0.02, pa_generate::ErrorModel::Uniform, 31415);
+        b.drain(800..900);
+        let cm = AffineCost::double_affine(1, 4, 2, 10, 1);
+
+        let (cost, cigar) = NW::new(cm, false, false).align(&a, &b);
+        assert_eq!(cigar.unwrap().verify(&cm, &a, &b), cost);
+        assert!(cost < NW::new(AffineCost::affine(1, 4, 2), false, false).cost(&a, &b));
+
+        let (band_cost, band_cigar) = NW::new(cm, true, true).align(&a, &b);
+        assert_eq!(band_cost, cost);
+        assert_eq!(band_cigar.unwrap().verify(&cm, &a, &b), cost);
+
+        let (dt_cost, dt_cigar) =
+            DiagonalTransition::new(cm, GapCostHeuristic::Enable, NoCost, false, NoVis)
+                .align(&a, &b);
+        assert_eq!(dt_cost, cost);
+        assert_eq!(dt_cigar.verify(&cm, &a, &b), cost);
+    }
+
+    #[test]
+    fn convex() {
+        use crate::dt::{DiagonalTransition, GapCostHeuristic};
+        use pa_heuristic::NoCost;
+        use pa_types::{Cost, Pos};
+
+        let f = |l: i32| 4 + 4 * (l as f32).log2().round() as Cost;
+        let cm = AffineCost::<6>::convex(1, &[1, 4, 16, 64], f);
+        // Each piece is exact at its first breakpoint, so the minimum is at most `f` there.
+        assert_eq!(cm.gap_cost(Pos(0, 0), Pos(1, 0)), f(1));
+        for l in [4, 16] {
+            assert!(cm.gap_cost(Pos(0, 0), Pos(l, 0)) <= f(l));
+        }
+
+        let (a, mut b) =
+            pa_generate::generate_model(2000, 0.02, pa_generate::ErrorModel::Uniform, 31415);
+        b.drain(800..900);
+        b.drain(300..305);
+
+        let (cost, cigar) = NW::new(cm, false, false).align(&a, &b);
+        assert_eq!(cigar.unwrap().verify(&cm, &a, &b), cost);
+
+        let (dt_cost, dt_cigar) =
+            DiagonalTransition::new(cm, GapCostHeuristic::Enable, NoCost, false, NoVis)
+                .align(&a, &b);
+        assert_eq!(dt_cost, cost);
+        assert_eq!(dt_cigar.verify(&cm, &a, &b), cost);
+    }
+
+    /// A*PA2-style alignment with asymmetric costs in both directions, where
+    /// the sparse `h` jumps must use `max_f_step` rather than a single side.
+    #[test]
+    fn asymmetric_astar() {
+        use super::AffineFront;
+
+        for (ins, del) in [(1, 2), (2, 1), (1, 3), (3, 1)] {
+            for model in [
+                pa_generate::ErrorModel::NoisyDelete,
+                pa_generate::ErrorModel::NoisyInsert,
+            ] {
+                let (a, b) = pa_generate::generate_model(5000, 0.1, model, 31415);
+                let cm = AffineCost::unit_asymmetric(ins, del);
+                let d = NW {
+                    cm,
+                    strategy: Strategy::band_doubling(),
+                    domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+                    block_width: 256,
+                    v: NoVis,
+                    front: AffineFront::<0>::default(),
+                    trace: true,
+                    sparse_h: true,
+                    prune: false,
+                }
+                .align(&a, &b);
+                let (cost, cigar) = NW::new(cm, false, false).align(&a, &b);
+                assert_eq!(d.0, cost, "ins {ins} del {del} {model:?}");
+                assert_eq!(d.1.unwrap().verify(&cm, &a, &b), cost);
+                assert_eq!(cigar.unwrap().verify(&cm, &a, &b), cost);
+            }
+        }
+    }
+
+    #[test]
+    fn hirschberg() {
+        use super::AffineHirschbergFront;
+
+        let (a, mut b) =
+            pa_generate::generate_model(2000, 0.05, pa_generate::ErrorModel::Uniform, 31415);
+        b.drain(800..900);
+        let cm = AffineCost::double_affine(1, 4, 2, 10, 1);
+        for (strategy, domain) in [
+            (Strategy::None, Domain::full()),
+            (Strategy::band_doubling(), Domain::gap_gap()),
+        ] {
+            let nw = NW {
+                cm,
+                strategy,
+                domain,
+                block_width: 1,
+                v: NoVis,
+                front: AffineHirschbergFront::default(),
+                trace: true,
+                sparse_h: true,
+                prune: false,
+            };
+            let (cost, cigar) = nw.align(&a, &b);
+            assert_eq!(cost, NW::new(cm, false, false).cost(&a, &b));
+            assert_eq!(cigar.unwrap().verify(&cm, &a, &b), cost);
+        }
+    }
+
     #[test]
     fn dt_trace() {
         let (a, b) =
@@ -1162,4 +1937,300 @@ mod test {
         let d2 = triple_accel::levenshtein_exp(&a, &b) as _;
         assert_eq!(d, d2);
     }
+    /// `BitFront::delta_trace` traces through each block of sparse fronts
+    /// using recorded differences.
+    #[test]
+    fn delta_trace() {
+        let (a, b) =
+            pa_generate::generate_model(5000, 0.15, pa_generate::ErrorModel::Uniform, 31415);
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        let mut front = BitFront::default();
+        front.delta_trace = true;
+        for block_width in [64, 100, 256] {
+            let nw = NW {
+                cm: AffineCost::unit(),
+                strategy: Strategy::band_doubling(),
+                domain: Domain::gap_gap(),
+                block_width,
+                v: NoVis,
+                front,
+                trace: true,
+                sparse_h: true,
+                prune: false,
+            };
+            let (cost, cigar) = nw.align(&a, &b);
+            assert_eq!(cost, d, "GapGap block_width {block_width}");
+            assert_eq!(cigar.unwrap().verify(&AffineCost::unit(), &a, &b), d);
+
+            let nw = NW {
+                cm: AffineCost::unit(),
+                strategy: Strategy::band_doubling(),
+                domain: Domain::Astar(GCSH::new(MatchConfig::exact(15), Pruning::start())),
+                block_width,
+                v: NoVis,
+                front,
+                trace: true,
+                sparse_h: true,
+                prune: true,
+            };
+            let (cost, cigar) = nw.align(&a, &b);
+            assert_eq!(cost, d, "A* block_width {block_width}");
+            assert_eq!(cigar.unwrap().verify(&AffineCost::unit(), &a, &b), d);
+        }
+    }
+
+    /// `BitFront::banded` only computes the diagonal band of each column in
+    /// cost mode, and is ignored when tracing.
+    #[test]
+    fn banded() {
+        let (a, b) =
+            pa_generate::generate_model(5000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let d = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        for domain in [Domain::gap_start(), Domain::gap_gap()] {
+            for block_width in [64, 100, 256] {
+                for simd in [false, true] {
+                    let mut front = BitFront::default();
+                    front.banded = true;
+                    front.simd = simd;
+                    let nw = NW {
+                        cm: AffineCost::unit(),
+                        strategy: Strategy::band_doubling(),
+                        domain,
+                        block_width,
+                        v: NoVis,
+                        front,
+                        trace: false,
+                        sparse_h: true,
+                        prune: false,
+                    };
+                    assert_eq!(
+                        nw.cost(&a, &b),
+                        d,
+                        "{domain:?} block_width {block_width} simd {simd}"
+                    );
+                    let nw = NW { trace: true, ..nw };
+                    assert_eq!(nw.align(&a, &b).0, d);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn align_all() {
+        use super::UnsupportedStrategy;
+        use crate::DoublingStart;
+
+        // All 6 ways to delete two of the four characters.
+        let (a, b) = (b"AAAA".as_slice(), b"AA".as_slice());
+        let cm = AffineCost::unit();
+        let check = |(cost, cigars): (Cost, Vec<AffineCigar>)| {
+            assert_eq!(cost, 2);
+            assert_eq!(cigars.len(), 6);
+            for (i, cigar) in cigars.iter().enumerate() {
+                assert_eq!(cigar.verify(&cm, a, b), cost);
+                assert!(!cigars[..i].contains(cigar));
+            }
+        };
+
+        let mut front = BitFront::default();
+        front.sparse = false;
+        let nw = NW {
+            cm,
+            strategy: Strategy::band_doubling(),
+            domain: Domain::full(),
+            block_width: 256,
+            v: NoVis,
+            front,
+            trace: true,
+            sparse_h: true,
+            prune: false,
+        };
+        check(nw.align_all(a, b, usize::MAX).unwrap());
+        assert_eq!(nw.align_all(a, b, 2).unwrap().1.len(), 2);
+        let linear = NW {
+            strategy: Strategy::LinearSearch {
+                start: DoublingStart::H0,
+                delta: 1.,
+            },
+            ..nw
+        };
+        check(linear.align_all(a, b, usize::MAX).unwrap());
+        let local = NW {
+            strategy: Strategy::LocalDoubling,
+            ..nw
+        };
+        let err = local.align_all(a, b, usize::MAX).unwrap_err();
+        assert_eq!(err.strategy, Strategy::LocalDoubling);
+        let full_only = NW {
+            strategy: Strategy::None,
+            domain: Domain::gap_gap(),
+            ..nw
+        };
+        let err = full_only.align_all(a, b, usize::MAX).unwrap_err();
+        assert_eq!(err.strategy, Strategy::None);
+        let pruning = NW {
+            cm,
+            strategy: Strategy::band_doubling(),
+            domain: Domain::Astar(GCSH::new(MatchConfig::exact(5), Pruning::start())),
+            block_width: 256,
+            v: NoVis,
+            front,
+            trace: true,
+            sparse_h: true,
+            prune: true,
+        };
+        assert!(matches!(
+            pruning.align_all(a, b, usize::MAX),
+            Err(UnsupportedStrategy { .. })
+        ));
+
+        check(
+            NW::new(cm, false, false)
+                .align_all(a, b, usize::MAX)
+                .unwrap(),
+        );
+        check(NW::new(cm, true, true).align_all(a, b, usize::MAX).unwrap());
+    }
+
+    #[test]
+    fn trace_order() {
+        use super::AffineFront;
+        use crate::{TraceEdge::*, TraceOrder};
+
+        let (a, b) =
+            pa_generate::generate_model(2000, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::unit();
+        let mut cigars = vec![];
+        for order in [[Sub, Del, Ins], [Ins, Del, Sub], [Del, Ins, Sub]] {
+            let trace_order = TraceOrder(order);
+            let mut front = BitFront::default();
+            front.trace_order = trace_order;
+            let bit = NW {
+                cm,
+                strategy: Strategy::None,
+                domain: Domain::full(),
+                block_width: 256,
+                v: NoVis,
+                front,
+                trace: true,
+                sparse_h: true,
+                prune: false,
+            };
+            let affine = NW {
+                cm,
+                strategy: Strategy::None,
+                domain: Domain::full(),
+                block_width: 1,
+                v: NoVis,
+                front: AffineFront::<0> { trace_order },
+                trace: true,
+                sparse_h: true,
+                prune: false,
+            };
+            let (cost, cigar) = bit.align(&a, &b);
+            let cigar = cigar.unwrap();
+            assert_eq!(cigar.verify(&cm, &a, &b), cost);
+            // Both front types pick the same parents.
+            let (affine_cost, affine_cigar) = affine.align(&a, &b);
+            assert_eq!(affine_cost, cost);
+            assert_eq!(affine_cigar.as_ref(), Some(&cigar));
+            cigars.push(cigar);
+        }
+        assert!(cigars[1..].iter().any(|cigar| *cigar != cigars[0]));
+    }
+
+    #[test]
+    fn full_matrix() {
+        use super::{compute_full_matrix, oracle::Oracle, write_matrix_csv};
+
+        let (a, b) = pa_generate::generate_model(100, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let cm = AffineCost::double_affine(1, 4, 2, 10, 1);
+        let matrix = compute_full_matrix(&a, &b, &cm);
+        assert_eq!(matrix.len(), a.len() + 1);
+        let oracle = Oracle::new(&a, &b, &cm).unwrap();
+        for (i, column) in matrix.iter().enumerate() {
+            assert_eq!(column.len(), b.len() + 1);
+            for (j, &cost) in column.iter().enumerate() {
+                assert_eq!(cost, oracle.get(i as _, j as _), "({i}, {j})");
+            }
+        }
+        assert_eq!(
+            matrix[a.len()][b.len()],
+            NW::new(cm, false, false).cost(&a, &b)
+        );
+
+        let mut csv = vec![];
+        write_matrix_csv(&matrix, &mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        assert_eq!(csv.lines().count(), b.len() + 1);
+        assert_eq!(csv.lines().next().unwrap().split(',').count(), a.len() + 1);
+    }
+
+    #[test]
+    fn f_landscape() {
+        use super::compute_full_matrix;
+        use pa_heuristic::HeuristicParams;
+        use pa_vis::f_landscape::FLandscape;
+
+        let (a, b) = pa_generate::generate_model(500, 0.1, pa_generate::ErrorModel::Uniform, 31415);
+        let g = compute_full_matrix(&a, &b, &AffineCost::unit());
+        let h = HeuristicParams::default().build_query(&a, &b);
+        let landscape = FLandscape::new(&g, h.as_ref(), 100).unwrap();
+        assert!(landscape.width.max(landscape.height) <= 101);
+        // The heuristic is admissible, so `f` is at most the distance at the start.
+        assert!(landscape.get(0, 0) <= landscape.distance);
+        let svg = landscape.to_svg();
+        assert_eq!(
+            svg.matches("<rect").count(),
+            landscape.width * landscape.height
+        );
+    }
+    #[test]
+    fn dt_params() {
+        use super::{AstarNwParams, PathTracingMethod};
+        use pa_heuristic::{HeuristicParams, HeuristicType};
+        use pa_types::CostModel;
+
+        let (a, b) = pa_generate::uniform_fixed(1000, 0.1);
+        let dist = triple_accel::levenshtein_exp(&a, &b) as Cost;
+        let params = |domain, heuristic, dc, path_tracing| AstarNwParams {
+            domain,
+            heuristic: HeuristicParams {
+                heuristic,
+                ..Default::default()
+            },
+            dt: true,
+            dc,
+            path_tracing,
+            ..Default::default()
+        };
+        for path_tracing in [
+            PathTracingMethod::ForwardGreedy,
+            PathTracingMethod::ReverseGreedy,
+        ] {
+            for (domain, heuristic, dc) in [
+                (Domain::Full, HeuristicType::None, false),
+                (Domain::Full, HeuristicType::None, true),
+                (Domain::GapGap, HeuristicType::None, false),
+                (Domain::Astar(()), HeuristicType::None, true),
+                (Domain::Astar(()), HeuristicType::GCSH, false),
+            ] {
+                let mut aligner = params(domain, heuristic, dc, path_tracing)
+                    .make_aligner(true)
+                    .unwrap();
+                let (cost, cigar) = aligner.align(&a, &b);
+                assert_eq!(cost, dist);
+                assert_eq!(cigar.unwrap().verify(&CostModel::unit(), &a, &b), dist);
+            }
+            // Divide & conquer does not support a heuristic.
+            for (domain, heuristic) in [
+                (Domain::GapGap, HeuristicType::None),
+                (Domain::Astar(()), HeuristicType::GCSH),
+            ] {
+                assert!(params(domain, heuristic, true, path_tracing)
+                    .make_aligner(true)
+                    .is_err());
+            }
+        }
+    }
 }
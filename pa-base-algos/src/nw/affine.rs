@@ -1,33 +1,75 @@
 //! TODO: Feature parity with BitFront:
-//! - sparse memory/traceback
+//! - sparse memory/traceback (`AffineHirschbergFrontsTag` uses linear memory instead)
 //! - reuse memory between runs
 //! - incremental doubling
+use super::front::trace_all_paths;
 use super::*;
 use crate::edit_graph::{AffineCigarOps, EditGraph};
+use pa_heuristic::util::add_cost;
 use std::{
     array::from_fn,
     ops::{Index, IndexMut},
 };
 
-// TODO: Instead use saturating add everywhere?
-const INF: Cost = Cost::MAX / 2;
+/// The types of costs `AffineNwFronts` can store.
+pub trait FrontCostT: CostT {
+    /// Add an edge cost to the cost of a state.
+    fn add_edge(self, edge: Self) -> Self;
+}
+
+/// `Cost` sums go through `add_cost`, so that overflows are recorded with the
+/// `checked_cost` feature.
+impl FrontCostT for Cost {
+    #[inline(always)]
+    fn add_edge(self, edge: Self) -> Self {
+        add_cost(self, edge)
+    }
+}
+
+impl FrontCostT for i64 {
+    #[inline(always)]
+    fn add_edge(self, edge: Self) -> Self {
+        self + edge
+    }
+}
+
+impl FrontCostT for f32 {
+    #[inline(always)]
+    fn add_edge(self, edge: Self) -> Self {
+        self + edge
+    }
+}
+
+impl FrontCostT for f64 {
+    #[inline(always)]
+    fn add_edge(self, edge: Self) -> Self {
+        self + edge
+    }
+}
 
 /// Costs per affine layer
-pub struct AffineNwFront<const N: usize> {
+pub struct AffineNwFront<const N: usize, C = Cost> {
     /// The main layer.
-    m: Vec<Cost>,
+    m: Vec<C>,
     /// The affine layers.
-    affine: [Vec<Cost>; N],
+    affine: [Vec<C>; N],
     j_range: JRange,
     fixed_j_range: Option<JRange>,
 }
 
-pub struct AffineNwFronts<'a, const N: usize> {
+/// Fronts of any `FrontCostT` type. `NW` only uses `Cost`; other types are
+/// aligned using `generic_align` and `generic_align_banded`.
+pub struct AffineNwFronts<'a, const N: usize, C = Cost> {
+    /// Whether all fronts are stored for traceback.
     trace: bool,
+    /// Traceback by divide & conquer instead of using stored fronts.
+    hirschberg: bool,
+    /// The preferred order of parents during traceback.
+    trace_order: TraceOrder,
     a: Seq<'a>,
     b: Seq<'a>,
-    cm: &'a AffineCost<N>,
-    fronts: Vec<AffineNwFront<N>>,
+    cm: &'a AffineCost<N, C>,
+    fronts: Vec<AffineNwFront<N, C>>,
     i_range: IRange,
 }
 
@@ -45,10 +87,30 @@ impl<'a, const N: usize> Index<usize> for AffineNwFronts<'a, N> {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct AffineNwFrontsTag<const N: usize>;
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AffineNwFrontsTag<const N: usize> {
+    /// The preferred order of parents during traceback.
+    pub trace_order: TraceOrder,
+}
+
+/// Same as `AffineNwFrontsTag`, but only ever stores two fronts, and traces
+/// back using divide & conquer (Hirschberg) in `O(|b|)` memory.
+///
+/// Traceback recomputes all states between the start and end, regardless of
+/// the band used to compute the distance, taking roughly twice the time of
+/// computing the full rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AffineHirschbergFrontsTag<const N: usize> {
+    /// The preferred order of parents during traceback.
+    pub trace_order: TraceOrder,
+}
+
+/// Index of `(layer, j)` in a flat vector over the rows of `j_range` and all layers.
+fn flat_index<const N: usize>(j_range: JRange, layer: Layer, j: I) -> usize {
+    (j - j_range.0) as usize * (N + 1) + layer.unwrap_or(N)
+}
 
-impl<const N: usize> Default for AffineNwFront<N> {
+impl<const N: usize, C> Default for AffineNwFront<N, C> {
     fn default() -> Self {
         Self {
             m: vec![],
@@ -74,22 +136,22 @@ impl<const N: usize> NwFront for AffineNwFront<N> {
         self.m.get((j - self.j_range.0) as usize).copied()
     }
 }
-impl<const N: usize> AffineNwFront<N> {
+impl<const N: usize, C: FrontCostT> AffineNwFront<N, C> {
     fn new(j_range: JRange) -> Self {
         Self {
-            m: vec![INF; j_range.len() as usize],
-            affine: from_fn(|_| vec![INF; j_range.len() as usize]),
+            m: vec![C::INF; j_range.len() as usize],
+            affine: from_fn(|_| vec![C::INF; j_range.len() as usize]),
             j_range,
             fixed_j_range: None,
         }
     }
-    fn first_col(cm: &AffineCost<N>, j_range: JRange) -> Self {
+    fn first_col(cm: &AffineCost<N, C>, j_range: JRange) -> Self {
         let mut next = Self::new(j_range);
         next.fixed_j_range = Some(j_range);
-        next.m[0] = 0;
+        next.m[0] = C::ZERO;
         for j in next.j_range.0..=next.j_range.1 {
             EditGraph::iterate_layers(cm, |layer| {
-                let mut best = INF;
+                let mut best = C::INF;
                 EditGraph::iterate_parents(
                     b"",
                     b"",
@@ -99,7 +161,7 @@ impl<const N: usize> AffineNwFront<N> {
                     |di, dj, layer, edge_cost, _cigar_ops| {
                         if di == 0 {
                             if let Some(cost) = next.get(layer, j + dj) {
-                                best = min(best, cost + edge_cost);
+                                best = best.min_cost(cost.add_edge(edge_cost));
                             }
                         }
                     },
@@ -111,21 +173,21 @@ impl<const N: usize> AffineNwFront<N> {
         }
         next
     }
-    fn index(&self, layer: Option<usize>, j: I) -> Cost {
+    fn index(&self, layer: Option<usize>, j: I) -> C {
         let l = match layer {
             None => &self.m,
             Some(layer) => &self.affine[layer],
         };
         l[(j - self.j_range.0) as usize]
     }
-    fn get(&self, layer: Option<usize>, j: I) -> Option<Cost> {
+    fn get(&self, layer: Option<usize>, j: I) -> Option<C> {
         let l = match layer {
             None => &self.m,
             Some(layer) => &self.affine[layer],
         };
         l.get((j - self.j_range.0) as usize).copied()
     }
-    fn index_mut(&mut self, layer: Option<usize>, j: I) -> &mut Cost {
+    fn index_mut(&mut self, layer: Option<usize>, j: I) -> &mut C {
         let l = match layer {
             None => &mut self.m,
             Some(layer) => &mut self.affine[layer],
@@ -134,12 +196,12 @@ impl<const N: usize> AffineNwFront<N> {
     }
 }
 
-impl<'a, const N: usize> AffineNwFronts<'a, N> {
+impl<'a, const N: usize, C: FrontCostT> AffineNwFronts<'a, N, C> {
     /// Computes the next front (front `i`) from the current one.
-    fn next_front(&self, i: I, prev: &AffineNwFront<N>, next: &mut AffineNwFront<N>) {
+    fn next_front(&self, i: I, prev: &AffineNwFront<N, C>, next: &mut AffineNwFront<N, C>) {
         for j in next.j_range.0..=next.j_range.1 {
             EditGraph::iterate_layers(&self.cm, |layer| {
-                let mut best = INF;
+                let mut best = C::INF;
                 EditGraph::iterate_parents(
                     &self.a,
                     &self.b,
@@ -153,7 +215,7 @@ impl<'a, const N: usize> AffineNwFronts<'a, N> {
                             prev.get(layer, j + dj)
                         };
                         if let Some(cost) = parent_cost {
-                            best = min(best, cost + edge_cost);
+                            best = best.min_cost(cost.add_edge(edge_cost));
                         }
                     },
                 );
@@ -163,9 +225,27 @@ impl<'a, const N: usize> AffineNwFronts<'a, N> {
     }
 
     fn parent(&self, st: State, _g: &mut Cost) -> Option<(State, AffineCigarOps)> {
-        let cur_cost = self.fronts[st.i as usize].index(st.layer, st.j);
+        let prev = if st.i > 0 {
+            Some(&self.fronts[st.i as usize - 1])
+        } else {
+            None
+        };
+        self.parent_in(st, prev, &self.fronts[st.i as usize])
+    }
+
+    /// The parent of `st` on a shortest path that comes first in `trace_order`,
+    /// where `cur` is the front of column `st.i`, and `prev` the front of column
+    /// `st.i - 1`, if any.
+    fn parent_in(
+        &self,
+        st: State,
+        prev: Option<&AffineNwFront<N, C>>,
+        cur: &AffineNwFront<N, C>,
+    ) -> Option<(State, AffineCigarOps)> {
+        let cur_cost = cur.index(st.layer, st.j);
         let mut parent = None;
         let mut cigar_ops: AffineCigarOps = [None, None];
+        let mut best_rank = usize::MAX;
         EditGraph::iterate_parents(
             &self.a,
             &self.b,
@@ -173,19 +253,136 @@ impl<'a, const N: usize> AffineNwFronts<'a, N> {
             /*greedy_matching=*/ false,
             st,
             |di, dj, new_layer, cost, ops| {
-                if parent.is_none()
+                let front = if di == 0 { Some(cur) } else { prev };
+                let rank = EditGraph::trace_rank(self.cm, &self.trace_order, ops);
+                if rank < best_rank
                         // We use `get` to handle possible out-of-bound lookups.
                         && let Some(parent_cost) =
-                            self.fronts[(st.i + di) as usize].get(new_layer, st.j + dj)
-                        && cur_cost == parent_cost + cost
+                            front.and_then(|front| front.get(new_layer, st.j + dj))
+                        && cur_cost == parent_cost.add_edge(cost)
                 {
                     parent = Some(State::new(st.i + di, st.j + dj, new_layer));
                     cigar_ops = ops;
+                    best_rank = rank;
                 }
             },
         );
         Some((parent?, cigar_ops))
     }
+
+    /// The front of column `from.i` for rows `from.j ..= j_end`, for paths
+    /// starting in `from`.
+    fn start_front(&self, from: State, j_end: I) -> AffineNwFront<N, C> {
+        let mut front = AffineNwFront::new(JRange(from.j, j_end));
+        *front.index_mut(from.layer, from.j) = C::ZERO;
+        for j in from.j..=j_end {
+            EditGraph::iterate_layers(self.cm, |layer| {
+                if (layer, j) == (from.layer, from.j) {
+                    return;
+                }
+                let mut best = C::INF;
+                EditGraph::iterate_parents(
+                    &self.a,
+                    &self.b,
+                    &self.cm,
+                    /*greedy_matching=*/ false,
+                    State::new(from.i, j, layer),
+                    |di, dj, layer, edge_cost, _cigar_ops| {
+                        if di == 0 {
+                            if let Some(cost) = front.get(layer, j + dj) {
+                                best = best.min_cost(cost.add_edge(edge_cost));
+                            }
+                        }
+                    },
+                );
+                *front.index_mut(layer, j) = best;
+            });
+        }
+        front
+    }
+
+    /// A state in column `(from.i + to.i) / 2` on an optimal path from `from` to `to`.
+    ///
+    /// Computes the fronts from `from` to `to` column by column, and tracks for
+    /// each state the state where its optimal path leaves the middle column.
+    fn mid_state(&self, from: State, to: State) -> State {
+        let mid = (from.i + to.i) / 2;
+        let j_range = JRange(from.j, to.j);
+        let len = j_range.len() as usize * (N + 1);
+        let mut prev = self.start_front(from, to.j);
+        let mut prev_mid: Vec<Option<State>> = vec![None; len];
+        let mut next_mid: Vec<Option<State>> = vec![None; len];
+        for i in from.i + 1..=to.i {
+            let mut next = AffineNwFront::new(j_range);
+            self.next_front(i, &prev, &mut next);
+            if i >= mid {
+                for j in j_range.0..=j_range.1 {
+                    EditGraph::iterate_layers(self.cm, |layer| {
+                        let st = State::new(i, j, layer);
+                        let st_mid = if i == mid {
+                            Some(st)
+                        } else {
+                            self.parent_in(st, Some(&prev), &next).and_then(|(p, _)| {
+                                let mids = if p.i == i { &next_mid } else { &prev_mid };
+                                mids[flat_index::<N>(j_range, p.layer, p.j)]
+                            })
+                        };
+                        next_mid[flat_index::<N>(j_range, layer, j)] = st_mid;
+                    });
+                }
+                std::mem::swap(&mut prev_mid, &mut next_mid);
+            }
+            prev = next;
+        }
+        prev_mid[flat_index::<N>(j_range, to.layer, to.j)].unwrap()
+    }
+
+    /// Push the operations of an optimal path from `from` to `to` in reverse
+    /// order, using divide & conquer on the middle column.
+    fn trace_dc(&self, from: State, mut to: State, ops: &mut Vec<AffineCigarOp>) {
+        if to.i - from.i > 1 {
+            let mid = self.mid_state(from, to);
+            self.trace_dc(mid, to, ops);
+            self.trace_dc(from, mid, ops);
+            return;
+        }
+        // At most two columns remain, which are traced directly.
+        let first = self.start_front(from, to.j);
+        let mut last = AffineNwFront::new(JRange(from.j, to.j));
+        if to.i > from.i {
+            self.next_front(to.i, &first, &mut last);
+        }
+        while to != from {
+            let (prev, cur) = if to.i == from.i {
+                (None, &first)
+            } else {
+                (Some(&first), &last)
+            };
+            let (parent, cigar_ops) = self.parent_in(to, prev, cur).unwrap();
+            to = parent;
+            ops.extend(cigar_ops.into_iter().flatten());
+        }
+    }
+
+    /// An optimal path from `from` to `to`, using the stored fronts or divide & conquer.
+    fn trace_path(&self, from: State, mut to: State) -> AffineCigar {
+        let mut ops = vec![];
+        if self.hirschberg {
+            self.trace_dc(from, to, &mut ops);
+        } else {
+            while to != from {
+                let (parent, cigar_ops) = self.parent(to, &mut 0).unwrap();
+                to = parent;
+                ops.extend(cigar_ops.into_iter().flatten());
+            }
+        }
+        let mut cigar = AffineCigar::default();
+        for op in ops {
+            cigar.push_op(op);
+        }
+        cigar.reverse();
+        cigar
+    }
 }
 
 impl<const N: usize> NwFrontsTag<N> for AffineNwFrontsTag<N> {
@@ -201,6 +398,31 @@ impl<const N: usize> NwFrontsTag<N> for AffineNwFrontsTag<N> {
         Self::Fronts {
             fronts: vec![],
             trace,
+            hirschberg: false,
+            trace_order: self.trace_order,
+            a,
+            b,
+            cm,
+            i_range: IRange(-1, 0),
+        }
+    }
+}
+
+impl<const N: usize> NwFrontsTag<N> for AffineHirschbergFrontsTag<N> {
+    type Fronts<'a> = AffineNwFronts<'a, N>;
+    const BLOCKSIZE: I = 1;
+    fn new<'a>(
+        &self,
+        _trace: bool,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        cm: &'a AffineCost<N>,
+    ) -> Self::Fronts<'a> {
+        Self::Fronts {
+            fronts: vec![],
+            trace: false,
+            hirschberg: true,
+            trace_order: self.trace_order,
             a,
             b,
             cm,
@@ -272,7 +494,7 @@ impl<'a, const N: usize> NwFronts<N> for AffineNwFronts<'a, N> {
         }
         for i in i_range.start..i_range.end {
             let next = &mut self.fronts[i];
-            next.reset(INF, j_range.clone());
+            next.reset(Cost::INF, j_range.clone());
             let mut next = std::mem::take(&mut self.fronts[i]);
             self.next_front(i + 1, &self.fronts[i], &mut next);
             self.fronts[i] = next;
@@ -289,21 +511,125 @@ impl<'a, const N: usize> NwFronts<N> for AffineNwFronts<'a, N> {
         _a: Seq,
         _b: Seq,
         from: State,
-        mut to: State,
+        to: State,
         _viz: &mut impl VisualizerInstance,
     ) -> AffineCigar {
-        let mut cigar = AffineCigar::default();
+        self.trace_path(from, to)
+    }
 
-        while to != from {
-            let (parent, cigar_ops) = self.parent(to, &mut 0).unwrap();
-            to = parent;
-            for op in cigar_ops {
-                if let Some(op) = op {
-                    cigar.push_op(op);
-                }
-            }
+    fn trace_all(
+        &mut self,
+        _a: Seq,
+        _b: Seq,
+        from: State,
+        to: State,
+        max_count: usize,
+    ) -> Vec<AffineCigar> {
+        assert!(self.trace);
+        trace_all_paths(from, to, max_count, |st, push| {
+            let cur_cost = self.fronts[st.i as usize].index(st.layer, st.j);
+            EditGraph::iterate_parents(
+                &self.a,
+                &self.b,
+                &self.cm,
+                /*greedy_matching=*/ false,
+                st,
+                |di, dj, new_layer, cost, ops| {
+                    if st.i + di >= 0
+                        && let Some(parent_cost) =
+                            self.fronts[(st.i + di) as usize].get(new_layer, st.j + dj)
+                        && cur_cost == add_cost(parent_cost, cost)
+                    {
+                        push(State::new(st.i + di, st.j + dj, new_layer), ops);
+                    }
+                },
+            );
+        })
+    }
+}
+
+/// Align `a` and `b` for any type of costs.
+///
+/// Computes the full rectangle, and traces back using divide & conquer in
+/// `O(|b|)` memory, as `AffineHirschbergFrontsTag` does.
+pub fn generic_align<const N: usize, C: FrontCostT>(
+    a: Seq,
+    b: Seq,
+    cm: &AffineCost<N, C>,
+) -> (C, AffineCigar) {
+    let fronts = AffineNwFronts {
+        trace: false,
+        hirschberg: true,
+        trace_order: TraceOrder::default(),
+        a,
+        b,
+        cm,
+        fronts: vec![],
+        i_range: IRange(-1, 0),
+    };
+    let j_range = JRange(0, b.len() as I);
+    let mut prev = AffineNwFront::first_col(cm, j_range);
+    for i in 1..=a.len() as I {
+        let mut next = AffineNwFront::new(j_range);
+        fronts.next_front(i, &prev, &mut next);
+        prev = next;
+    }
+    let cost = prev.index(None, b.len() as I);
+    let cigar = fronts.trace_path(
+        State::new(0, 0, None),
+        State::new(a.len() as I, b.len() as I, None),
+    );
+    (cost, cigar)
+}
+
+/// Same as `generic_align`, but for integer costs, using the gap-cost
+/// heuristic to limit the computed band.
+///
+/// Starting at the gap cost from start to end, `f_max` is doubled until the
+/// distance is at most `f_max`. Only diagonals `d` with
+/// `gap_cost(start, d) + gap_cost(d, end) <= f_max` are computed, and all
+/// fronts are stored for traceback.
+pub fn generic_align_banded<const N: usize, C: FrontCostT + IntCostT>(
+    a: Seq,
+    b: Seq,
+    cm: &AffineCost<N, C>,
+) -> (C, AffineCigar) {
+    let (n, m) = (a.len() as I, b.len() as I);
+    // The gap-cost heuristic of a state on diagonal `d = j - i`.
+    let gap = |d: I| cm.gap_cost(Pos(0, 0), Pos(0, d)) + cm.gap_cost(Pos(0, d), Pos(0, m - n));
+    let mut f_max = max(gap(0), C::from_count(1));
+    loop {
+        // The range of diagonals with f at most `f_max`. Between the start
+        // and end diagonal, all diagonals are included.
+        let (mut d_min, mut d_max) = (min(0, m - n), max(0, m - n));
+        while d_min > -n && gap(d_min - 1) <= f_max {
+            d_min -= 1;
         }
-        cigar.reverse();
-        cigar
+        while d_max < m && gap(d_max + 1) <= f_max {
+            d_max += 1;
+        }
+        let j_range = |i: I| JRange(max(0, i + d_min), min(m, i + d_max));
+
+        let mut fronts = AffineNwFronts {
+            trace: true,
+            hirschberg: false,
+            trace_order: TraceOrder::default(),
+            a,
+            b,
+            cm,
+            fronts: vec![AffineNwFront::first_col(cm, j_range(0))],
+            i_range: IRange(-1, n),
+        };
+        for i in 1..=n {
+            let mut next = AffineNwFront::new(j_range(i));
+            fronts.next_front(i, fronts.fronts.last().unwrap(), &mut next);
+            fronts.fronts.push(next);
+        }
+        let cost = fronts.fronts.last().unwrap().index(None, m);
+        if cost <= f_max {
+            let cigar = fronts.trace_path(State::new(0, 0, None), State::new(n, m, None));
+            return (cost, cigar);
+        }
+        f_max = f_max + f_max;
     }
 }
@@ -4,10 +4,14 @@
 //!       efficient and doesn't have to use relatively slow `front.index` operations.
 //!       (NOTE though that this doesn't actually seem that bad in practice.)
 //! TODO: Separate strong types for row `I` and 'block-row' `I*64`.
+use super::front::trace_all_paths;
 use super::*;
+use crate::TraceEdge;
 use itertools::{izip, Itertools};
 use pa_bitpacking::{BitProfile, HEncoding, Profile, B, V, W};
+use std::fmt::Write;
 use std::ops::{Index, IndexMut};
+use tracing::{error, Level};
 
 const DEBUG: bool = false;
 
@@ -25,15 +29,38 @@ pub struct BitFrontsTag {
     pub sparse: bool,
     #[serde(default)]
     pub simd: bool,
+    /// In `cost` mode, only compute the words of each column that intersect
+    /// the diagonal band of the `GapStart` and `GapGap` domains, instead of
+    /// the full rounded `j_range` of the block.
     #[serde(default)]
+    pub banded: bool,
+    /// Reuse the fixed part of each front from the previous band doubling
+    /// iteration, and only recompute the states below the stored row `j_h`.
+    #[serde(default = "default_incremental_doubling")]
     pub incremental_doubling: bool,
+    /// Recompute incrementally doubled blocks from scratch and assert that the
+    /// results are equal. Slow; only for debugging.
+    #[serde(default)]
+    pub validate_incremental: bool,
     #[serde(default)]
     pub dt_trace: bool,
+    /// In the DP traceback of sparse fronts, recompute each block once while
+    /// recording all horizontal and vertical differences, and trace through
+    /// them directly, instead of storing a front for every column.
+    #[serde(default)]
+    pub delta_trace: bool,
     /// Do traceback up to this g. 0 disables the limit.
     #[serde(default)]
     pub max_g: Cost,
     #[serde(default)]
     pub drop: I,
+    /// The preferred order of equal-cost parents in the DP traceback.
+    #[serde(default)]
+    pub trace_order: TraceOrder,
+}
+
+fn default_incremental_doubling() -> bool {
+    true
 }
 
 impl Default for BitFrontsTag {
@@ -41,10 +68,14 @@ impl Default for BitFrontsTag {
         Self {
             sparse: true,
             simd: true,
+            banded: false,
             incremental_doubling: true,
+            validate_incremental: false,
             dt_trace: false,
+            delta_trace: false,
             max_g: 40,
             drop: 20,
+            trace_order: TraceOrder::default(),
         }
     }
 }
@@ -71,6 +102,9 @@ pub struct BitFronts {
     /// The distribution of number of rows in `compute` calls.
     computed_rows: Vec<usize>,
     unique_rows: usize,
+
+    /// `v` vectors of fronts dropped by `reset`, to be reused by new fronts.
+    free_v: Vec<Vec<V>>,
 }
 
 pub struct BitFront {
@@ -78,7 +112,7 @@ pub struct BitFront {
     v: Vec<V>,
     /// The column of this front.
     i: I,
-    /// The 'input' range, that is rounded to `W=64` bits in practice.
+    /// The 'input' range, that is rounded to `W` bits in practice.
     j_range: JRange,
     /// Helper for `NW`: the range of rows in this column with `f(u) <= f_max`.
     fixed_j_range: Option<JRange>,
@@ -222,6 +256,29 @@ fn round_inward(j_range: JRange) -> JRange {
 }
 
 impl BitFront {
+    /// Iterate over `(j, cost)` for all `j` in the inclusive `range`, which
+    /// must lie inside the rounded `j_range`.
+    /// Unlike calling `index` for each row, this only counts bits once.
+    pub fn iter_values(&self, range: JRange) -> impl Iterator<Item = (I, Cost)> + '_ {
+        let rounded = round(self.j_range);
+        assert!(
+            rounded.0 <= range.0 && range.1 <= rounded.1,
+            "Range {range:?} is not inside the range {rounded:?} of front {}",
+            self.i
+        );
+        let mut val = self.index(range.0);
+        (range.0..=range.1).map(move |j| {
+            let cur = val;
+            // Add the vertical delta from `j` to `j+1`.
+            if j < range.1 {
+                let k = (j - self.offset) as usize;
+                let (p, m) = self.v[k / W].pm();
+                val += ((p >> (k % W)) & 1) as Cost - ((m >> (k % W)) & 1) as Cost;
+            }
+            (j, cur)
+        })
+    }
+
     fn first_col(j_range: JRange) -> Self {
         assert!(j_range.0 == 0);
         let rounded = round(j_range);
@@ -270,7 +327,7 @@ impl BitFront {
 
 impl NwFrontsTag<0usize> for BitFrontsTag {
     type Fronts<'a> = BitFronts;
-    const BLOCKSIZE: I = 64;
+    const BLOCKSIZE: I = WI;
     fn new<'a>(
         &self,
         trace: bool,
@@ -279,7 +336,24 @@ impl NwFrontsTag<0usize> for BitFrontsTag {
         cm: &'a AffineCost<0>,
     ) -> Self::Fronts<'a> {
         assert_eq!(*cm, AffineCost::unit());
-        let (a, b) = BitProfile::build(a, b);
+        self.from_profiles(trace, BitProfile::build(a, b), cm)
+    }
+}
+
+impl NwFrontsRevTag<0usize> for BitFrontsTag {
+    fn new_rev<'a>(&self, a: Seq<'a>, b: Seq<'a>, cm: &'a AffineCost<0>) -> Self::Fronts<'a> {
+        assert_eq!(*cm, AffineCost::unit());
+        self.from_profiles(false, BitProfile::build_rev(a, b), cm)
+    }
+}
+
+impl BitFrontsTag {
+    fn from_profiles(
+        &self,
+        trace: bool,
+        (a, b): (Vec<PA>, Vec<PB>),
+        cm: &AffineCost<0>,
+    ) -> BitFronts {
         BitFronts {
             params: *self,
             fronts: vec![],
@@ -296,31 +370,36 @@ impl NwFrontsTag<0usize> for BitFrontsTag {
             b,
             computed_rows: vec![],
             unique_rows: 0,
+            free_v: vec![],
         }
     }
 }
 
 impl Drop for BitFronts {
     fn drop(&mut self) {
-        if !PRINT {
+        if !tracing::enabled!(Level::DEBUG) {
             return;
         }
         let mut cnt = 0;
         let mut total = 0;
+        let mut histogram = String::new();
         for (i, c) in self.computed_rows.iter().enumerate() {
             cnt += c;
             total += i * c;
             if i % 10 == 0 {
-                eprint!("\n{i:>4}");
+                write!(histogram, "\n{i:>4}").unwrap();
             }
-            eprint!("{c:>7}");
+            write!(histogram, "{c:>7}").unwrap();
         }
-        eprintln!();
-        eprintln!("Num blocks: {cnt}");
+        trace!("Computed rows per block:{histogram}");
         // FIXME: Hardcoded blocksize.
         let num_blocks = self.a.len() / 256;
-        eprintln!("Total band: {}", total / num_blocks);
-        eprintln!("Uniq. band: {}", self.unique_rows / num_blocks);
+        debug!(
+            blocks = cnt,
+            total_band = total / num_blocks.max(1),
+            unique_band = self.unique_rows / num_blocks.max(1),
+            "BitFronts"
+        );
     }
 }
 
@@ -338,6 +417,25 @@ impl Index<usize> for BitFronts {
     }
 }
 
+impl NwFrontsReset<0usize> for BitFronts {
+    fn reset(&mut self, trace: bool, a: Seq, b: Seq) {
+        (self.a, self.b) = BitProfile::build(a, b);
+        self.trace = trace;
+        self.i_range = IRange(-1, 0);
+        self.last_front_idx = 0;
+        if self.params.incremental_doubling {
+            self.h.clear();
+            self.h.resize(self.a.len(), (0, 0));
+        }
+        // Old fronts must not be reused as-is, since their ranges and values
+        // are for the previous pair. Only keep their memory.
+        self.free_v
+            .extend(self.fronts.drain(..).map(|front| front.v));
+        self.computed_rows.clear();
+        self.unique_rows = 0;
+    }
+}
+
 impl NwFronts<0usize> for BitFronts {
     type Front = BitFront;
 
@@ -439,7 +537,10 @@ impl NwFronts<0usize> for BitFronts {
             // Reuse memory from an existing front if possible.
             // Otherwise, push a new front.
             if self.last_front_idx + 1 == self.fronts.len() {
-                self.fronts.push(BitFront::default());
+                self.fronts.push(BitFront {
+                    v: self.free_v.pop().unwrap_or_default(),
+                    ..BitFront::default()
+                });
             } else {
                 let next_front = &mut self.fronts[self.last_front_idx + 1];
                 assert_eq!(
@@ -581,7 +682,7 @@ impl NwFronts<0usize> for BitFronts {
                 next_front.j_h = Some(new_j_h);
                 next_front.offset = new_range.0;
 
-                if cfg!(test) || DEBUG {
+                if self.params.validate_incremental || cfg!(test) || DEBUG {
                     // Redo the computation without the fixed range and test if they give the same results.
                     let mut v2 = Vec::default();
                     initialize_next_v(prev_front, j_range_rounded, &mut v2);
@@ -602,7 +703,7 @@ impl NwFronts<0usize> for BitFronts {
                     if v != v2 {
                         for (i, (a, b)) in izip!(&v, &v2).enumerate() {
                             if a != b {
-                                println!("{}+{}={}: {:?} != {:?}", i, offset, i + offset, a, b);
+                                error!("{}+{}={}: {:?} != {:?}", i, offset, i + offset, a, b);
                             }
                         }
 
@@ -665,6 +766,87 @@ impl NwFronts<0usize> for BitFronts {
         }
     }
 
+    fn compute_next_block_banded(
+        &mut self,
+        i_range: IRange,
+        j_range: JRange,
+        diagonals: JRange,
+        viz: &mut impl VisualizerInstance,
+    ) {
+        // Bands are only used for the single front in `cost` mode: the values
+        // below the band are upper bounds, which the traceback can not use.
+        if !self.params.banded || self.trace {
+            return self.compute_next_block(i_range, j_range, viz);
+        }
+
+        assert_eq!(i_range.0, self.i_range.1);
+        self.i_range.1 = i_range.1;
+
+        let j_range_rounded = round(j_range);
+        let v_range = j_range_rounded.0 as usize / W..j_range_rounded.1 as usize / W;
+        self.unique_rows += v_range.len();
+
+        // Column `i` needs the words up to row `i + diagonals.1`. The rows
+        // above the band are cheap to keep, and keep `top_val` exact. The
+        // last column is computed fully, so that `v` is the front at `i_range.1`.
+        let bands = (i_range.0 + 1..=i_range.1)
+            .map(|i| {
+                let end = if i == i_range.1 {
+                    v_range.len()
+                } else {
+                    let end = (i + diagonals.1).clamp(j_range_rounded.0, j_range_rounded.1);
+                    (end - j_range_rounded.0).div_ceil(WI) as usize
+                };
+                0..end
+            })
+            .collect_vec();
+
+        viz.expand_block_simple(
+            Pos(i_range.0 + 1, j_range_rounded.0),
+            Pos(i_range.len(), j_range_rounded.exclusive_len()),
+        );
+        if i_range.len() > 1 {
+            let words = bands.iter().map(|band| band.len()).sum::<usize>() / bands.len();
+            if !(words < self.computed_rows.len()) {
+                self.computed_rows.resize(words + 1, 0);
+            }
+            self.computed_rows[words] += 1;
+        }
+
+        let front = &mut self.fronts[self.last_front_idx];
+        let top_val = front.index(j_range_rounded.0) + i_range.len();
+        let mut v = std::mem::take(&mut front.v);
+        let a = &self.a[i_range.0 as usize..i_range.1 as usize];
+        let h = &mut vec![H::one(); a.len()];
+        if self.params.simd {
+            pa_bitpacking::simd::compute_banded::<2, H, 4>(
+                a,
+                &self.b[v_range.clone()],
+                h,
+                &mut v[v_range.clone()],
+                &bands,
+            );
+        } else {
+            pa_bitpacking::scalar::row_banded::<BitProfile, H>(
+                a,
+                &self.b[v_range.clone()],
+                h,
+                &mut v[v_range.clone()],
+                &bands,
+            );
+        }
+        // `h` is not the bottom row when bands end early, so sum `v` instead.
+        let bot_val = top_val + v[v_range].iter().map(|v| v.value()).sum::<Cost>();
+
+        let next_front = &mut self.fronts[self.last_front_idx];
+        next_front.v = v;
+        next_front.i = i_range.1;
+        next_front.j_range = j_range;
+        next_front.top_val = top_val;
+        next_front.bot_val = bot_val;
+        next_front.check_top_bot_val();
+    }
+
     fn cm(&self) -> &AffineCost<0> {
         &self.cm
     }
@@ -699,7 +881,7 @@ impl NwFronts<0usize> for BitFronts {
         let mut g = self.fronts[self.last_front_idx].index(to.j);
 
         if PRINT {
-            eprintln!("Trace from distance {g}");
+            debug!("Trace from distance {g}");
         }
 
         let mut dt_trace_tries = 0;
@@ -713,7 +895,7 @@ impl NwFronts<0usize> for BitFronts {
             // Remove fronts to the right of `to`.
             while self.last_front_idx > 0 && self.fronts[self.last_front_idx - 1].i >= to.i {
                 if PRINT {
-                    eprintln!(
+                    trace!(
                         "to {to:?} Pop front at i={}",
                         self.fronts[self.last_front_idx].i
                     );
@@ -753,9 +935,29 @@ impl NwFronts<0usize> for BitFronts {
                 let prev_front = &self.fronts[self.last_front_idx - 1];
                 assert!(prev_front.i < to.i && to.i <= front.i);
                 // If the previous front is the correct one, no need for further recomputation.
+                if (prev_front.i < to.i - 1 || front.i > to.i) && self.params.delta_trace {
+                    let i_range = IRange(prev_front.i, to.i);
+                    let top = prev_front.j_range_rounded().0;
+                    // Same exponential search for the height as below.
+                    let mut height = max(to.j - front.j_range.0, i_range.len() * 5 / 4);
+                    to = loop {
+                        let j_range = JRange(max(to.j - height, top), to.j);
+                        if let Some(parent) =
+                            self.delta_trace_block(i_range, j_range, to, &mut g, &mut cigar, viz)
+                        {
+                            break parent;
+                        }
+                        assert!(
+                            j_range.0 > top,
+                            "Traceback did not reach the previous front."
+                        );
+                        height *= 2;
+                    };
+                    continue;
+                }
                 if prev_front.i < to.i - 1 || front.i > to.i {
                     if PRINT {
-                        eprintln!("Expand previous front from {} to {}", prev_front.i, to.i);
+                        trace!("Expand previous front from {} to {}", prev_front.i, to.i);
                     }
                     let i_range = IRange(prev_front.i, to.i);
                     let j_range = JRange(front.j_range.0, to.j);
@@ -769,7 +971,7 @@ impl NwFronts<0usize> for BitFronts {
                     loop {
                         let j_range = JRange(max(j_range.1 - height, 0), j_range.1);
                         if PRINT {
-                            eprintln!("Fill block {:?} {:?}", i_range, j_range);
+                            trace!("Fill block {:?} {:?}", i_range, j_range);
                         }
                         self.fill_block(i_range, j_range, viz);
                         if self.fronts[self.last_front_idx].index(to.j) == g {
@@ -786,7 +988,7 @@ impl NwFronts<0usize> for BitFronts {
             }
 
             if PRINT && to.i % 256 == 0 {
-                eprintln!(
+                trace!(
                     "Parent of {to:?} at distance {g} with range {:?}",
                     self.fronts[self.last_front_idx].j_range
                 );
@@ -795,16 +997,131 @@ impl NwFronts<0usize> for BitFronts {
             to = parent;
             cigar.push_elem(cigar_elem);
         }
-        if PRINT {
-            eprintln!("dt_trace_tries:    {:>7}", dt_trace_tries);
-            eprintln!("dt_trace_success:  {:>7}", dt_trace_success);
-            eprintln!("dt_trace_fallback: {:>7}", dt_trace_fallback);
-        }
+        debug!(
+            tries = dt_trace_tries,
+            success = dt_trace_success,
+            fallback = dt_trace_fallback,
+            "DT traceback"
+        );
         assert_eq!(g, 0);
         cigar.reverse();
         cigar
     }
 
+    /// Trace from `to` back to the front before the last one, at column
+    /// `i_range.0`, by recomputing the block `i_range` x `j_range` once using
+    /// `pa_bitpacking::trace`.
+    ///
+    /// Returns `None` when the block is not high enough, i.e. the value at
+    /// `to` differs from `g`. Otherwise, the path is appended to `cigar`, `g` is
+    /// updated, and the state where the path reaches column `i_range.0` is returned.
+    fn delta_trace_block(
+        &self,
+        i_range: IRange,
+        j_range: JRange,
+        to: State,
+        g: &mut Cost,
+        cigar: &mut AffineCigar,
+        viz: &mut impl VisualizerInstance,
+    ) -> Option<State> {
+        let prev_front = &self.fronts[self.last_front_idx - 1];
+        assert!(prev_front.i == i_range.0 && to.i == i_range.1);
+        let j_range_rounded = round(j_range);
+        let v_range = j_range_rounded.0 as usize / W..j_range_rounded.1 as usize / W;
+        let a = &self.a[i_range.0 as usize..i_range.1 as usize];
+        let b = &self.b[v_range];
+
+        let mut v0 = Vec::default();
+        initialize_next_v(prev_front, j_range_rounded, &mut v0);
+        let mut v = v0.clone();
+        let h = &mut vec![H::one(); a.len()];
+        let deltas = &mut vec![vec![]; a.len()];
+        viz.expand_block_simple(
+            Pos(i_range.0 + 1, j_range_rounded.0),
+            Pos(i_range.len(), j_range_rounded.exclusive_len()),
+        );
+        pa_bitpacking::trace::fill_deltas::<BitProfile, H>(a, b, h, &mut v, deltas);
+
+        // Along the top row, horizontal deltas are 1.
+        let j = (to.j - j_range_rounded.0) as usize;
+        let mut val = prev_front.index(j_range_rounded.0) + i_range.len();
+        val += v[..j / W].iter().map(|v| v.value()).sum::<Cost>();
+        if j % W > 0 {
+            val += v[j / W].value_of_prefix((j % W) as I);
+        }
+        if val != *g {
+            return None;
+        }
+
+        let (ops, (i, j)) =
+            pa_bitpacking::trace::trace_block::<BitProfile>(a, b, &v0, deltas, (a.len(), j));
+        for op in ops {
+            cigar.push_op(match op {
+                CigarOp::Match => AffineCigarOp::Match,
+                CigarOp::Sub => AffineCigarOp::Sub,
+                CigarOp::Ins => AffineCigarOp::Ins,
+                CigarOp::Del => AffineCigarOp::Del,
+            });
+            if op != CigarOp::Match {
+                *g -= 1;
+            }
+        }
+        // The path left through the top row, where all values are reached by
+        // deletions from the top left corner.
+        for _ in 0..i {
+            cigar.push_op(AffineCigarOp::Del);
+            *g -= 1;
+        }
+        let parent = State {
+            i: i_range.0,
+            j: j_range_rounded.0 + j as I,
+            layer: None,
+        };
+        debug_assert_eq!(prev_front.index(parent.j), *g);
+        Some(parent)
+    }
+
+    /// Enumerate co-optimal paths from `from` to `to`.
+    ///
+    /// This requires `self.trace` to be `true` and non-sparse fronts, so that
+    /// front `i` is stored at index `i`. Unlike `trace`, no greedy matching
+    /// is done, since that would skip alternative paths.
+    fn trace_all(
+        &mut self,
+        a: Seq,
+        b: Seq,
+        from: State,
+        to: State,
+        max_count: usize,
+    ) -> Vec<AffineCigar> {
+        assert!(self.trace);
+        assert!(
+            !self.params.sparse,
+            "Enumerating co-optimal alignments requires non-sparse fronts."
+        );
+        assert!(self.fronts[self.last_front_idx].i == to.i);
+        let fronts = &self.fronts[..=self.last_front_idx];
+        trace_all_paths(from, to, max_count, |st, push| {
+            let g = fronts[st.i as usize].index(st.j);
+            let mut push_if = |i: I, j: I, cost: Cost, op: AffineCigarOp| {
+                if i >= 0
+                    && j >= 0
+                    && let Some(parent_g) = fronts[i as usize].get(j)
+                    && parent_g + cost == g
+                {
+                    push(State { i, j, layer: None }, [Some(op), None]);
+                }
+            };
+            if st.i > 0 && st.j > 0 && a[st.i as usize - 1] == b[st.j as usize - 1] {
+                push_if(st.i - 1, st.j - 1, 0, AffineCigarOp::Match);
+            } else {
+                push_if(st.i - 1, st.j - 1, 1, AffineCigarOp::Sub);
+            }
+            push_if(st.i, st.j - 1, 1, AffineCigarOp::Ins);
+            push_if(st.i - 1, st.j, 1, AffineCigarOp::Del);
+        })
+    }
+
     // Update the fixed range, and make sure it only grows.
     fn set_last_front_fixed_j_range(&mut self, fixed_j_range: Option<JRange>) {
         assert!(fixed_j_range.is_some());
@@ -938,66 +1255,85 @@ impl BitFronts {
             );
         }
 
-        // Vertical delta (insert).
-        // (This is first since it only needs a single delta bit, instead of an index() call.)
-        let vd = front.get_diff(st.j - 1);
-        if vd == Some(1) {
-            *g -= 1;
-            return (
-                State {
-                    i: st.i,
-                    j: st.j - 1,
-                    layer: None,
-                },
-                AffineCigarElem {
-                    op: AffineCigarOp::Ins,
-                    cnt: 1,
-                },
-            );
-        }
-
-        let prev_front = &self.fronts[self.last_front_idx - 1];
-        assert!(prev_front.i == st.i - 1);
-
-        // Horizontal delta (delete).
-        let hd = *g - prev_front.index(st.j);
-        if hd == 1 {
-            *g -= 1;
-            return (
-                State {
-                    i: st.i - 1,
-                    j: st.j,
-                    layer: None,
-                },
-                AffineCigarElem {
-                    op: AffineCigarOp::Del,
-                    cnt: 1,
-                },
-            );
-        }
-
-        // Diagonal delta (substitution).
-        // This edge case happens when entering the previous front exactly in
-        // the bottom-most row, where no vertical delta is available.
-        let dd = if st.j > prev_front.j_range.1 {
-            assert_eq!(st.j, prev_front.j_range.1 + 1);
-            1
-        } else {
-            prev_front.get_diff(st.j - 1).unwrap() + hd
-        };
-        if dd == 1 {
-            *g -= 1;
-            return (
-                State {
-                    i: st.i - 1,
-                    j: st.j - 1,
-                    layer: None,
-                },
-                AffineCigarElem {
-                    op: AffineCigarOp::Sub,
-                    cnt: 1,
-                },
-            );
+        // When `st` is in the first stored front, only insertions remain.
+        let prev_front = (self.last_front_idx > 0).then(|| {
+            let prev_front = &self.fronts[self.last_front_idx - 1];
+            assert!(prev_front.i == st.i - 1);
+            prev_front
+        });
+
+        for edge in self.params.trace_order.0 {
+            match edge {
+                // Vertical delta (insert).
+                // This only needs a single delta bit, instead of an index() call.
+                TraceEdge::Ins => {
+                    if front.get_diff(st.j - 1) == Some(1) {
+                        *g -= 1;
+                        return (
+                            State {
+                                i: st.i,
+                                j: st.j - 1,
+                                layer: None,
+                            },
+                            AffineCigarElem {
+                                op: AffineCigarOp::Ins,
+                                cnt: 1,
+                            },
+                        );
+                    }
+                }
+                // Horizontal delta (delete).
+                TraceEdge::Del => {
+                    if let Some(prev_front) = prev_front
+                        && *g - prev_front.index(st.j) == 1
+                    {
+                        *g -= 1;
+                        return (
+                            State {
+                                i: st.i - 1,
+                                j: st.j,
+                                layer: None,
+                            },
+                            AffineCigarElem {
+                                op: AffineCigarOp::Del,
+                                cnt: 1,
+                            },
+                        );
+                    }
+                }
+                // Diagonal delta (substitution).
+                TraceEdge::Sub => {
+                    let Some(prev_front) = prev_front else {
+                        continue;
+                    };
+                    if st.j - 1 < prev_front.j_range.0 {
+                        continue;
+                    }
+                    // Below the previous front, no vertical delta is available
+                    // and we index the diagonal parent directly.
+                    let dd = if st.j <= prev_front.j_range.1
+                        && let Some(vd) = prev_front.get_diff(st.j - 1)
+                    {
+                        vd + *g - prev_front.index(st.j)
+                    } else {
+                        *g - prev_front.index(st.j - 1)
+                    };
+                    if dd == 1 {
+                        *g -= 1;
+                        return (
+                            State {
+                                i: st.i - 1,
+                                j: st.j - 1,
+                                layer: None,
+                            },
+                            AffineCigarElem {
+                                op: AffineCigarOp::Sub,
+                                cnt: 1,
+                            },
+                        );
+                    }
+                }
+            }
         }
 
         panic!("ERROR: PARENT OF {st:?} NOT FOUND IN TRACEBACK");
@@ -1213,7 +1549,9 @@ impl BitFronts {
             next_front.top_val += 1;
             self.last_front_idx += 1;
             if self.last_front_idx == self.fronts.len() {
-                self.fronts.push(next_front.clone());
+                let mut front = next_front.clone();
+                front.v = self.free_v.pop().unwrap_or_default();
+                self.fronts.push(front);
             } else {
                 self.fronts[self.last_front_idx].clone_from(&next_front);
             }
@@ -1303,8 +1641,8 @@ fn compute_columns(
         computed_rows[v_range.len()] += 1;
     }
 
-    if i_range.len() > 1 && (cfg!(test) || DEBUG) {
-        eprintln!("Compute i {i_range:?} x j {v_range:?} in mode {mode:?}");
+    if i_range.len() > 1 {
+        trace!("Compute i {i_range:?} x j {v_range:?} in mode {mode:?}");
     }
 
     let run = |h, exact_end| {
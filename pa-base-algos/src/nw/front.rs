@@ -1,9 +1,12 @@
+use std::collections::HashSet;
 use std::ops::{IndexMut, Range, RangeInclusive};
 
 use pa_affine_types::{AffineCigar, AffineCost, State};
 use pa_types::*;
 use pa_vis::VisualizerInstance;
 
+use crate::edit_graph::AffineCigarOps;
+
 /// Left-exclusive range of columns to compute.
 /// (-1, 0): the first column
 /// (i, i+W): Compute column W given column i.
@@ -95,6 +98,16 @@ pub trait NwFrontsTag<const N: usize>: Copy + PartialEq {
     ) -> Self::Fronts<'a>;
 }
 
+/// Front types that can align the reverse of the sequences. See `NW::build_rev`.
+pub trait NwFrontsRevTag<const N: usize>: NwFrontsTag<N> {
+    /// Constructs a new front that aligns the reverse of `a` and `b`, without
+    /// copying the sequences. Column `i` of these fronts corresponds to the
+    /// suffix `a[a.len()-i..]`.
+    ///
+    /// Only computing costs is supported; traceback is not.
+    fn new_rev<'a>(&self, a: Seq<'a>, b: Seq<'a>, cm: &'a AffineCost<N>) -> Self::Fronts<'a>;
+}
+
 pub trait NwFronts<const N: usize>: IndexMut<usize, Output = Self::Front> {
     type Front: NwFront;
 
@@ -113,6 +126,22 @@ pub trait NwFronts<const N: usize>: IndexMut<usize, Output = Self::Front> {
         v: &mut impl VisualizerInstance,
     );
 
+    /// Same as `compute_next_block`, but the caller guarantees that in column
+    /// `i`, only rows `i + diagonals.0 ..= i + diagonals.1` can be on a path of
+    /// cost at most the current bound. Values outside this band may be upper
+    /// bounds.
+    ///
+    /// By default, the full `j_range` is computed.
+    fn compute_next_block_banded(
+        &mut self,
+        i_range: IRange,
+        j_range: JRange,
+        _diagonals: JRange,
+        v: &mut impl VisualizerInstance,
+    ) {
+        self.compute_next_block(i_range, j_range, v)
+    }
+
     fn reuse_next_block(&mut self, _i_range: IRange, _j_range: JRange) {
         unimplemented!();
     }
@@ -149,4 +178,70 @@ pub trait NwFronts<const N: usize>: IndexMut<usize, Output = Self::Front> {
         _to: State,
         _viz: &mut impl VisualizerInstance,
     ) -> AffineCigar;
+
+    /// Traceback up to `max_count` distinct co-optimal paths from `from` to `to`.
+    ///
+    /// Unlike `trace`, this does not break ties but branches on every parent
+    /// on a shortest path. Requires that all fronts are stored.
+    fn trace_all(
+        &mut self,
+        a: Seq,
+        b: Seq,
+        from: State,
+        to: State,
+        max_count: usize,
+    ) -> Vec<AffineCigar>;
+}
+
+/// Fronts that can be reused for another pair of sequences.
+pub trait NwFrontsReset<const N: usize>: NwFronts<N> {
+    /// Prepare the fronts for aligning a new pair of sequences `a` and `b`,
+    /// reusing the existing allocations. Call `init` afterwards as usual.
+    fn reset(&mut self, trace: bool, a: Seq, b: Seq);
+}
+
+/// Depth-first search backwards from `to` to `from`, collecting up to
+/// `max_count` distinct cigars.
+///
+/// `parents(st, push)` must call `push(parent, ops)` for every parent of `st`
+/// on a shortest path, where `ops` are the cigar operations of the edge.
+/// Parents are visited in the order they are pushed.
+pub fn trace_all_paths(
+    from: State,
+    to: State,
+    max_count: usize,
+    mut parents: impl FnMut(State, &mut dyn FnMut(State, AffineCigarOps)),
+) -> Vec<AffineCigar> {
+    let mut cigars = vec![];
+    // The ops of the cigars found so far, since distinct paths can give the
+    // same cigar.
+    let mut seen = HashSet::new();
+    // The ops of the current path, in reverse order.
+    let mut ops = vec![];
+    // (state, length of `ops` at its child, ops of the edge to its child)
+    let mut stack = vec![(to, 0, [None, None])];
+    let mut next = vec![];
+    while let Some((st, len, edge_ops)) = stack.pop() {
+        ops.truncate(len);
+        ops.extend(edge_ops.into_iter().flatten());
+        if st == from {
+            if seen.insert(ops.clone()) {
+                let mut cigar = AffineCigar::default();
+                for &op in &ops {
+                    cigar.push_op(op);
+                }
+                cigar.reverse();
+                cigars.push(cigar);
+                if cigars.len() >= max_count {
+                    break;
+                }
+            }
+            continue;
+        }
+        let len = ops.len();
+        parents(st, &mut |p, p_ops| next.push((p, len, p_ops)));
+        // Reverse, so that the first parent is explored first.
+        stack.extend(next.drain(..).rev());
+    }
+    cigars
 }
@@ -0,0 +1,60 @@
+//! The full DP matrix of the main layer, for teaching, debugging, and testing
+//! new cost models on small inputs.
+use super::affine::AffineNwFrontsTag;
+use super::front::{IRange, JRange, NwFront, NwFronts, NwFrontsTag};
+use pa_affine_types::AffineCost;
+use pa_heuristic::HeuristicParams;
+use pa_types::*;
+use pa_vis::{dot_plot::PlotError, f_landscape::FLandscape, NoVis};
+use std::io;
+
+/// The cost `matrix[i][j]` of aligning `a[..i]` to `b[..j]`, for all
+/// `0 <= i <= |a|` and `0 <= j <= |b|`.
+///
+/// This computes all fronts with `AffineFront`, and takes `O(|a||b|)` time and memory.
+pub fn compute_full_matrix<const N: usize>(a: Seq, b: Seq, cm: &AffineCost<N>) -> Vec<Vec<Cost>> {
+    let j_range = JRange(0, b.len() as I);
+    fn column(front: &impl NwFront, j_range: JRange) -> Vec<Cost> {
+        (j_range.0..=j_range.1).map(|j| front.index(j)).collect()
+    }
+
+    let mut fronts = AffineNwFrontsTag::<N>::default().new(true, a, b, cm);
+    fronts.init(j_range);
+    let mut matrix = vec![column(fronts.last_front(), j_range)];
+    for i in 0..a.len() as I {
+        fronts.compute_next_block(IRange(i, i + 1), j_range, &mut NoVis);
+        matrix.push(column(fronts.last_front(), j_range));
+    }
+    matrix
+}
+
+/// Write `matrix` as CSV, with one line per row `j` and one column per column `i`,
+/// so that the layout matches the visualizer.
+pub fn write_matrix_csv(matrix: &[Vec<Cost>], out: &mut impl io::Write) -> io::Result<()> {
+    let height = matrix.first().map_or(0, |column| column.len());
+    for j in 0..height {
+        let row = matrix
+            .iter()
+            .map(|column| column[j].to_string())
+            .collect::<Vec<_>>();
+        writeln!(out, "{}", row.join(","))?;
+    }
+    Ok(())
+}
+
+/// An SVG heatmap of `f(u) = g(u) + h(u)` for unit costs, where `h` is the
+/// heuristic built from `params`, using at most `max_size` cells along either axis.
+/// States in gray have `f` larger than the distance, and are never computed by A*.
+///
+/// Matches are not pruned; use `FLandscape` directly with a pruned heuristic instead.
+/// Returns an error when `max_size` is 0.
+pub fn f_landscape_svg(
+    a: Seq,
+    b: Seq,
+    params: &HeuristicParams,
+    max_size: usize,
+) -> Result<String, PlotError> {
+    let g = compute_full_matrix(a, b, &AffineCost::unit());
+    let h = params.build_query(a, b);
+    Ok(FLandscape::new(&g, h.as_ref(), max_size)?.to_svg())
+}
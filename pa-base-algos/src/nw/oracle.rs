@@ -0,0 +1,130 @@
+//! A naive full DP used to cross-check the values of `NwFronts` on small
+//! inputs, with the `validate_fronts` feature.
+//!
+//! Only checking the final cost against the edit distance hides many bugs;
+//! this instead reports the first cell where a front differs from the DP.
+use super::front::{JRange, NwFront};
+use pa_affine_types::{AffineCost, AffineLayerType, CostT};
+use pa_types::*;
+
+/// Inputs with more DP cells than this are not checked.
+pub const MAX_CELLS: usize = 1 << 22;
+
+/// The main layer of the full DP matrix, computed with the plain recurrence.
+///
+/// This is generic over the type of costs, and also supports e.g. `f32` scores.
+pub struct Oracle<C = Cost> {
+    /// Column-major costs, indexed by `i * (b.len() + 1) + j`.
+    costs: Vec<C>,
+    height: usize,
+}
+
+/// Column `i` of the main layer and of each affine layer.
+struct Column<C> {
+    main: Vec<C>,
+    layers: Vec<Vec<C>>,
+}
+
+/// Compute column `i` of the DP from column `i-1`, which is `None` for `i=0`.
+fn next_column<const N: usize, C: CostT>(
+    a: Seq,
+    b: Seq,
+    cm: &AffineCost<N, C>,
+    i: usize,
+    prev: Option<&Column<C>>,
+) -> Column<C> {
+    let h = b.len() + 1;
+    let inf = C::INF;
+    let mut main = vec![inf; h];
+    // One column per affine layer, for the cost of ending in that layer.
+    let mut layers = vec![vec![inf; h]; N];
+    for j in 0..h {
+        let mut c = if i == 0 && j == 0 { C::ZERO } else { inf };
+        if let Some(prev) = prev {
+            if j > 0 {
+                if a[i - 1] == b[j - 1] {
+                    c = c.min_cost(prev.main[j - 1]);
+                } else if let Some(sub) = cm.sub {
+                    c = c.min_cost(prev.main[j - 1] + sub);
+                }
+            }
+            if let Some(del) = cm.del {
+                c = c.min_cost(prev.main[j] + del);
+            }
+        }
+        if j > 0
+            && let Some(ins) = cm.ins
+        {
+            c = c.min_cost(main[j - 1] + ins);
+        }
+        for (l, layer) in cm.affine.iter().enumerate() {
+            let (prev_main, prev_layer) = match (layer.affine_type, prev) {
+                (AffineLayerType::InsertLayer, _) if j > 0 => (main[j - 1], layers[l][j - 1]),
+                (AffineLayerType::DeleteLayer, Some(prev)) => (prev.main[j], prev.layers[l][j]),
+                _ => continue,
+            };
+            layers[l][j] = (prev_main + layer.open).min_cost(prev_layer) + layer.extend;
+            c = c.min_cost(layers[l][j]);
+        }
+        main[j] = c;
+    }
+    Column { main, layers }
+}
+
+impl<C: CostT> Oracle<C> {
+    /// `None` when the input has more than `MAX_CELLS` cells.
+    pub fn new<const N: usize>(a: Seq, b: Seq, cm: &AffineCost<N, C>) -> Option<Self> {
+        let (w, h) = (a.len() + 1, b.len() + 1);
+        if w * h > MAX_CELLS {
+            return None;
+        }
+        let mut costs = Vec::with_capacity(w * h);
+        let mut column: Option<Column<C>> = None;
+        for i in 0..w {
+            let next = next_column(a, b, cm, i, column.as_ref());
+            costs.extend_from_slice(&next.main);
+            column = Some(next);
+        }
+        Some(Oracle { costs, height: h })
+    }
+
+    pub fn get(&self, i: I, j: I) -> C {
+        self.costs[i as usize * self.height + j as usize]
+    }
+}
+
+/// The cost of aligning `a` and `b` using the plain recurrence, for any type of costs.
+///
+/// This takes `O(|a||b|)` time and `O(|b|)` memory.
+pub fn generic_cost<const N: usize, C: CostT>(a: Seq, b: Seq, cm: &AffineCost<N, C>) -> C {
+    let mut column = next_column(a, b, cm, 0, None);
+    for i in 1..=a.len() {
+        column = next_column(a, b, cm, i, Some(&column));
+    }
+    column.main[b.len()]
+}
+
+impl Oracle {
+    /// Check the front for column `i`.
+    ///
+    /// Values can exceed the true distance when the optimal path leaves the
+    /// band, but must never be smaller. Values in the fixed range are exact.
+    /// Panics at the first cell that violates this.
+    pub fn check_front(&self, i: I, front: &impl NwFront) {
+        let fixed = front.fixed_j_range();
+        let JRange(start, end) = front.j_range();
+        for j in start.max(0)..=end.min(self.height as I - 1) {
+            let (value, expected) = (front.index(j), self.get(i, j));
+            assert!(
+                value >= expected,
+                "Front value {value} at ({i}, {j}) is below the true distance {expected}."
+            );
+            if fixed.is_some_and(|f| f.contains(j)) {
+                assert_eq!(
+                    value, expected,
+                    "Fixed front value at ({i}, {j}) differs from the true distance."
+                );
+            }
+        }
+    }
+}
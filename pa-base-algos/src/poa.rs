@@ -0,0 +1,526 @@
+//! Alignment of a sequence to a directed acyclic graph (partial order alignment).
+//!
+//! Each node of the graph holds a single character, and nodes are added in
+//! topological order. The query is aligned globally, starting before any
+//! source node and ending in a sink node, using unit costs.
+//!
+//! Like band doubling for NW, the computed domain is bounded by the gap-cost
+//! from the start and to the end. In a graph, the number of nodes before and
+//! after a node is not fixed, so the gap-cost is taken to the closest
+//! value in the range of path lengths along the topological order.
+//!
+//! Seed heuristics are not supported: matches would have to be found along
+//! paths in the graph, so only the gap-cost bound restricts the domain.
+use crate::exponential_search;
+use pa_affine_types::{AffineCigar, AffineCigarOp};
+use pa_types::*;
+use std::cmp::{max, min};
+
+/// A DAG with a character on each node.
+#[derive(Debug, Clone, Default)]
+pub struct PoaGraph {
+    chars: Vec<u8>,
+    /// Predecessors of each node. Always smaller than the node itself.
+    preds: Vec<Vec<usize>>,
+    /// Whether each node has a successor.
+    has_succ: Vec<bool>,
+}
+
+impl PoaGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A linear graph for a single sequence.
+    pub fn from_seq(seq: Seq) -> Self {
+        let mut g = Self::new();
+        for (i, &c) in seq.iter().enumerate() {
+            if i == 0 {
+                g.add_node(c, &[]);
+            } else {
+                g.add_node(c, &[i - 1]);
+            }
+        }
+        g
+    }
+
+    /// Add a node with the given predecessors and return its id.
+    /// Predecessors must already exist, so that ids are a topological order.
+    pub fn add_node(&mut self, c: u8, preds: &[usize]) -> usize {
+        let id = self.chars.len();
+        for &p in preds {
+            assert!(p < id, "Predecessor {p} of node {id} does not exist yet.");
+            self.has_succ[p] = true;
+        }
+        self.chars.push(c);
+        self.preds.push(preds.to_vec());
+        self.has_succ.push(false);
+        id
+    }
+
+    /// Add a new path for `seq` that branches off after node `from` and
+    /// joins again before node `to`. Returns the ids of the new nodes.
+    ///
+    /// To keep ids in topological order, the new nodes are inserted just
+    /// before `to`, so that `to` and all later nodes are shifted by `seq.len()`.
+    /// Fails when `to` does not exist or `from` is not smaller than `to`.
+    pub fn add_path(
+        &mut self,
+        seq: Seq,
+        from: Option<usize>,
+        to: Option<usize>,
+    ) -> Result<Vec<usize>, InvalidPath> {
+        let invalid = InvalidPath { from, to };
+        let pos = match to {
+            None => self.len(),
+            Some(to) => {
+                if to >= self.len() {
+                    return Err(invalid);
+                }
+                to
+            }
+        };
+        if let Some(from) = from
+            && from >= pos
+        {
+            return Err(invalid);
+        }
+
+        // Shift all nodes from `pos` onwards to make room for the new path.
+        let n = seq.len();
+        for preds in &mut self.preds[pos..] {
+            for p in preds {
+                if *p >= pos {
+                    *p += n;
+                }
+            }
+        }
+        let ids: Vec<usize> = (pos..pos + n).collect();
+        if n > 0
+            && let Some(from) = from
+        {
+            self.has_succ[from] = true;
+        }
+        self.chars.splice(pos..pos, seq.iter().copied());
+        self.preds.splice(
+            pos..pos,
+            ids.iter().map(|&id| {
+                if id == pos {
+                    from.as_slice().to_vec()
+                } else {
+                    vec![id - 1]
+                }
+            }),
+        );
+        self.has_succ
+            .splice(pos..pos, ids.iter().map(|&id| id + 1 < pos + n));
+
+        // Join the last node of the path, or `from` for an empty path, into `to`.
+        if to.is_some()
+            && let Some(last) = ids.last().copied().or(from)
+        {
+            self.preds[pos + n].push(last);
+            self.has_succ[last] = true;
+        }
+        Ok(ids)
+    }
+
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+
+    pub fn char(&self, v: usize) -> u8 {
+        self.chars[v]
+    }
+
+    pub fn preds(&self, v: usize) -> &[usize] {
+        &self.preds[v]
+    }
+
+    pub fn is_source(&self, v: usize) -> bool {
+        self.preds[v].is_empty()
+    }
+
+    pub fn is_sink(&self, v: usize) -> bool {
+        !self.has_succ[v]
+    }
+
+    /// For each node, the min and max number of nodes on a path from a source up to and including it.
+    fn depth_range(&self) -> Vec<(I, I)> {
+        let mut d = vec![(0, 0); self.len()];
+        for v in 0..self.len() {
+            d[v] = if self.is_source(v) {
+                (1, 1)
+            } else {
+                let lo = self.preds[v].iter().map(|&p| d[p].0).min().unwrap();
+                let hi = self.preds[v].iter().map(|&p| d[p].1).max().unwrap();
+                (lo + 1, hi + 1)
+            };
+        }
+        d
+    }
+
+    /// For each node, the min and max number of nodes on a path after it to a sink.
+    fn remaining_range(&self) -> Vec<(I, I)> {
+        let mut r = vec![(I::MAX, I::MIN); self.len()];
+        for v in (0..self.len()).rev() {
+            if self.is_sink(v) {
+                r[v] = (0, 0);
+            }
+            let (lo, hi) = r[v];
+            for &p in &self.preds[v] {
+                r[p].0 = min(r[p].0, lo + 1);
+                r[p].1 = max(r[p].1, hi + 1);
+            }
+        }
+        r
+    }
+}
+
+/// A path passed to `PoaGraph::add_path` would break the topological order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InvalidPath {
+    pub from: Option<usize>,
+    pub to: Option<usize>,
+}
+
+impl std::fmt::Display for InvalidPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot add a path from {:?} to {:?}: `from` must come before `to` and both must exist.",
+            self.from, self.to
+        )
+    }
+}
+
+impl std::error::Error for InvalidPath {}
+
+/// Distance from `x` to the interval `[lo, hi]`.
+fn dist_to_range(x: I, (lo, hi): (I, I)) -> Cost {
+    max(0, max(lo - x, x - hi)) as Cost
+}
+
+/// The computed values of a single node, for rows `j_range.0 ..= j_range.1`.
+struct Column {
+    j_range: (I, I),
+    g: Vec<Cost>,
+}
+
+impl Column {
+    fn get(&self, j: I) -> Cost {
+        if j < self.j_range.0 || j > self.j_range.1 {
+            Cost::MAX
+        } else {
+            self.g[(j - self.j_range.0) as usize]
+        }
+    }
+}
+
+/// Align a query sequence against a `PoaGraph`.
+#[derive(Debug, Clone, Copy)]
+pub struct PoaAligner {
+    /// The growth factor of the band.
+    pub factor: f32,
+}
+
+impl Default for PoaAligner {
+    fn default() -> Self {
+        Self { factor: 2.0 }
+    }
+}
+
+/// The result of aligning to a graph.
+#[derive(Debug)]
+pub struct PoaAlignment {
+    pub cost: Cost,
+    /// The nodes on the aligned path through the graph.
+    pub path: Vec<usize>,
+    /// Cigar with the graph path as `a` and the query as `b`.
+    pub cigar: Cigar,
+}
+
+impl PoaAligner {
+    /// Return the cost and the path of the alignment.
+    pub fn align(&self, graph: &PoaGraph, query: Seq) -> PoaAlignment {
+        let m = query.len() as I;
+        if graph.is_empty() {
+            let mut cigar = AffineCigar::default();
+            for _ in 0..m {
+                cigar.push_op(AffineCigarOp::Ins);
+            }
+            return PoaAlignment {
+                cost: m as Cost,
+                path: vec![],
+                cigar: cigar.into(),
+            };
+        }
+
+        let depth = graph.depth_range();
+        let remaining = graph.remaining_range();
+        let min_len = (0..graph.len())
+            .filter(|&v| graph.is_source(v))
+            .map(|v| remaining[v].0 + 1)
+            .min()
+            .unwrap();
+        let max_len = (0..graph.len())
+            .filter(|&v| graph.is_source(v))
+            .map(|v| remaining[v].1 + 1)
+            .max()
+            .unwrap();
+        let start = dist_to_range(m, (min_len, max_len));
+
+        let (cost, (sink, columns)) = exponential_search(start, 1, self.factor, |f_max| {
+            let columns = self.compute(graph, query, &depth, &remaining, f_max);
+            let (cost, sink) = (0..graph.len())
+                .filter(|&v| graph.is_sink(v))
+                .map(|v| (columns[v].get(m), v))
+                .min()
+                .unwrap();
+            if cost == Cost::MAX {
+                return None;
+            }
+            Some((cost, (sink, columns)))
+        });
+        let (path, cigar) = self.traceback(graph, query, &columns, sink);
+        PoaAlignment { cost, path, cigar }
+    }
+
+    /// Compute all states with `gap(s, u) + gap(u, t) <= f_max`.
+    fn compute(
+        &self,
+        graph: &PoaGraph,
+        query: Seq,
+        depth: &[(I, I)],
+        remaining: &[(I, I)],
+        f_max: Cost,
+    ) -> Vec<Column> {
+        let m = query.len() as I;
+        let f = f_max as I;
+        let mut columns: Vec<Column> = Vec::with_capacity(graph.len());
+        for v in 0..graph.len() {
+            // Intersect the bands from the start and to the end.
+            let j_start = max(0, max(depth[v].0 - f, m - remaining[v].1 - f));
+            let j_end = min(m, min(depth[v].1 + f, m - remaining[v].0 + f));
+            if j_start > j_end {
+                columns.push(Column {
+                    j_range: (0, -1),
+                    g: vec![],
+                });
+                continue;
+            }
+            let mut g = vec![Cost::MAX; (j_end - j_start + 1) as usize];
+            // The value of the virtual source row, or of a predecessor.
+            let pred_get = |j: I| -> Cost {
+                if graph.is_source(v) {
+                    j as Cost
+                } else {
+                    graph
+                        .preds(v)
+                        .iter()
+                        .map(|&p| columns[p].get(j))
+                        .min()
+                        .unwrap()
+                }
+            };
+            for j in j_start..=j_end {
+                if dist_to_range(j, depth[v]) + dist_to_range(m - j, remaining[v]) > f_max {
+                    continue;
+                }
+                let mut best = pred_get(j).saturating_add(1);
+                if j > 0 {
+                    let sub = (graph.char(v) != query[j as usize - 1]) as Cost;
+                    best = min(best, pred_get(j - 1).saturating_add(sub));
+                    if j > j_start {
+                        best = min(best, g[(j - 1 - j_start) as usize].saturating_add(1));
+                    }
+                }
+                g[(j - j_start) as usize] = best;
+            }
+            columns.push(Column {
+                j_range: (j_start, j_end),
+                g,
+            });
+        }
+        columns
+    }
+
+    fn traceback(
+        &self,
+        graph: &PoaGraph,
+        query: Seq,
+        columns: &[Column],
+        sink: usize,
+    ) -> (Vec<usize>, Cigar) {
+        let mut cigar = AffineCigar::default();
+        let mut path = vec![];
+        // `None` is the virtual source.
+        let mut v = Some(sink);
+        let mut j = query.len() as I;
+        while let Some(u) = v {
+            let g = columns[u].get(j);
+            let preds = graph.preds(u);
+            let pred_get = |p: Option<usize>, j: I| match p {
+                None => j as Cost,
+                Some(p) => columns[p].get(j),
+            };
+            let candidates: Vec<Option<usize>> = if preds.is_empty() {
+                vec![None]
+            } else {
+                preds.iter().map(|&p| Some(p)).collect()
+            };
+            if j > 0 && columns[u].get(j - 1).saturating_add(1) == g {
+                cigar.push_op(AffineCigarOp::Ins);
+                j -= 1;
+                continue;
+            }
+            if j > 0 {
+                let is_match = graph.char(u) == query[j as usize - 1];
+                if let Some(&p) = candidates
+                    .iter()
+                    .find(|&&p| pred_get(p, j - 1).saturating_add(!is_match as Cost) == g)
+                {
+                    cigar.push_op(if is_match {
+                        AffineCigarOp::Match
+                    } else {
+                        AffineCigarOp::Sub
+                    });
+                    path.push(u);
+                    v = p;
+                    j -= 1;
+                    continue;
+                }
+            }
+            let &p = candidates
+                .iter()
+                .find(|&&p| pred_get(p, j).saturating_add(1) == g)
+                .expect("No parent found during traceback.");
+            cigar.push_op(AffineCigarOp::Del);
+            path.push(u);
+            v = p;
+        }
+        for _ in 0..j {
+            cigar.push_op(AffineCigarOp::Ins);
+        }
+        cigar.reverse();
+        path.reverse();
+        (path, cigar.into())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn path_seq(graph: &PoaGraph, path: &[usize]) -> Vec<u8> {
+        path.iter().map(|&v| graph.char(v)).collect()
+    }
+
+    #[test]
+    fn linear_graph() {
+        for n in [0, 1, 10, 100, 1000] {
+            for e in [0.0, 0.05, 0.2, 0.5] {
+                let (a, b) = pa_generate::uniform_fixed(n, e);
+                let graph = PoaGraph::from_seq(&a);
+                let aln = PoaAligner::default().align(&graph, &b);
+                assert_eq!(aln.cost, triple_accel::levenshtein_exp(&a, &b) as Cost);
+                assert_eq!(path_seq(&graph, &aln.path), a);
+                aln.cigar.verify(&CostModel::unit(), &a, &b);
+            }
+        }
+    }
+
+    #[test]
+    fn bubble_graph() {
+        for e in [0.0, 0.1, 0.3] {
+            let (a, b) = pa_generate::uniform_fixed(200, e);
+            // Add an alternative path with the middle of `b` next to the middle of `a`.
+            let mut graph = PoaGraph::from_seq(&a[..80]);
+            let main = graph.add_path(&a[80..120], Some(79), None).unwrap();
+            let alt = graph.add_path(&b[80..120], Some(79), None).unwrap();
+            let join = graph.add_node(a[120], &[*main.last().unwrap(), *alt.last().unwrap()]);
+            graph.add_path(&a[121..], Some(join), None).unwrap();
+
+            let mut alt_seq = a[..80].to_vec();
+            alt_seq.extend_from_slice(&b[80..120]);
+            alt_seq.extend_from_slice(&a[120..]);
+            let dist = min(
+                triple_accel::levenshtein_exp(&a, &b),
+                triple_accel::levenshtein_exp(&alt_seq, &b),
+            ) as Cost;
+
+            let aln = PoaAligner::default().align(&graph, &b);
+            assert_eq!(aln.cost, dist);
+            let p = path_seq(&graph, &aln.path);
+            assert_eq!(triple_accel::levenshtein_exp(&p, &b) as Cost, dist);
+            aln.cigar.verify(&CostModel::unit(), &p, &b);
+        }
+    }
+
+    #[test]
+    fn add_path_joining() {
+        for e in [0.0, 0.1, 0.3] {
+            let (a, b) = pa_generate::uniform_fixed(200, e);
+            // Add the middle of `b` as an alternative to the middle of `a`
+            // after the main path already exists.
+            let mut graph = PoaGraph::from_seq(&a);
+            let alt = graph.add_path(&b[80..120], Some(79), Some(120)).unwrap();
+            assert_eq!(alt, (120..160).collect::<Vec<_>>());
+            assert_eq!(graph.len(), 240);
+            // The old node 120 is now 160, and is joined from both paths.
+            assert_eq!(graph.preds(160), &[119, 159]);
+            assert_eq!(graph.preds(161), &[160]);
+            for v in 0..graph.len() {
+                assert!(graph.preds(v).iter().all(|&p| p < v));
+            }
+            assert!(graph.is_sink(graph.len() - 1));
+            assert!((0..graph.len() - 1).all(|v| !graph.is_sink(v)));
+
+            let mut alt_seq = a[..80].to_vec();
+            alt_seq.extend_from_slice(&b[80..120]);
+            alt_seq.extend_from_slice(&a[120..]);
+            let dist = min(
+                triple_accel::levenshtein_exp(&a, &b),
+                triple_accel::levenshtein_exp(&alt_seq, &b),
+            ) as Cost;
+
+            let aln = PoaAligner::default().align(&graph, &b);
+            assert_eq!(aln.cost, dist);
+            let p = path_seq(&graph, &aln.path);
+            aln.cigar.verify(&CostModel::unit(), &p, &b);
+        }
+    }
+
+    #[test]
+    fn add_path_empty_seq() {
+        // An empty path adds a single edge, which skips the middle here.
+        let mut graph = PoaGraph::from_seq(b"ACGT");
+        assert_eq!(graph.add_path(b"", Some(0), Some(3)), Ok(vec![]));
+        assert_eq!(graph.preds(3), &[2, 0]);
+        let aln = PoaAligner::default().align(&graph, b"AT");
+        assert_eq!(aln.cost, 0);
+        assert_eq!(aln.path, vec![0, 3]);
+    }
+
+    #[test]
+    fn add_path_invalid() {
+        let mut graph = PoaGraph::from_seq(b"ACGT");
+        for seq in [b"".as_slice(), b"GG"] {
+            for (from, to) in [
+                (Some(2), Some(2)),
+                (Some(3), Some(1)),
+                (Some(4), None),
+                (None, Some(4)),
+            ] {
+                assert_eq!(graph.add_path(seq, from, to), Err(InvalidPath { from, to }));
+            }
+        }
+        // Failed calls leave the graph untouched.
+        assert_eq!(graph.len(), 4);
+        assert_eq!(graph.preds(3), &[2]);
+    }
+}
@@ -208,7 +208,7 @@ mod nw_band_doubling_sh {
                 }),
                 block_width: 1,
                 v: NoVis,
-                front: AffineFront,
+                front: AffineFront::default(),
                 trace: true,
                 sparse_h: true,
                 prune: true,
@@ -36,7 +36,7 @@ fn main() {
                     domain: pa_base_algos::Domain::gap_start(),
                     block_width: 1,
                     v: NoVis,
-                    front: AffineFront,
+                    front: AffineFront::default(),
                     trace: true,
                     sparse_h: false,
                     prune: false,
@@ -73,7 +73,7 @@ fn main() {
             domain: Domain::full(),
             block_width: 1,
             v: vis("01-nw"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -85,6 +85,7 @@ fn main() {
             dt: false,
             h: NoCost,
             v: vis("02-dijkstra"),
+            trace_order: None,
         };
         aligner.align(a, b);
     }
@@ -105,7 +106,7 @@ fn main() {
             domain: Domain::gap_start(),
             block_width: 1,
             v: vis("04-nw_doubling"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -119,7 +120,7 @@ fn main() {
             domain: Domain::gap_gap(),
             block_width: 1,
             v: vis("05-nw_gapcost"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -136,6 +137,7 @@ fn main() {
             dt: false,
             h: sh,
             v: vis("06-a*pa-sh"),
+            trace_order: None,
         };
         aligner.align(a, b);
     }
@@ -144,6 +146,7 @@ fn main() {
             dt: false,
             h: sh_prune,
             v: vis("07-a*pa-sh-prune"),
+            trace_order: None,
         };
         aligner.align(a, b);
     }
@@ -152,6 +155,7 @@ fn main() {
             dt: false,
             h: csh_prune,
             v: vis("08-a*pa-csh-prune"),
+            trace_order: None,
         };
         aligner.align(a, b);
     }
@@ -160,6 +164,7 @@ fn main() {
             dt: false,
             h: gcsh_prune,
             v: vis("09-a*pa-gcsh-prune"),
+            trace_order: None,
         };
         aligner.align(a, b);
     }
@@ -173,6 +178,7 @@ fn main() {
             dt: true,
             h: gcsh_prune,
             v: vis("10-a*pa-gcsh-prune-dt"),
+            trace_order: None,
         };
         aligner.align(a, b);
     }
@@ -189,12 +195,12 @@ fn main() {
             cm: cm.clone(),
             strategy: pa_base_algos::Strategy::BandDoubling {
                 start: pa_base_algos::DoublingStart::H0,
-                factor: 1.5,
+                growth: pa_base_algos::Growth::Factor(1.5),
             },
             domain: Domain::astar(gcsh_prune),
             block_width: 1,
             v: vis("11-nw-gcsh-prune"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: true,
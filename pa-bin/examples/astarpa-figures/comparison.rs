@@ -79,6 +79,7 @@ fn main() {
                     dt,
                     h: NoCost,
                     v: config.with_filename(&("dijkstra".to_string() + suf1 + suf2)),
+                    trace_order: None,
                 }
                 .align(a, b);
             }
@@ -86,18 +87,21 @@ fn main() {
                 dt,
                 h: SH::new(MatchConfig::inexact(k), Pruning::new(pruning)),
                 v: config.with_filename(&("sh".to_string() + suf1 + suf2)),
+                trace_order: None,
             }
             .align(a, b);
             AstarPa {
                 dt,
                 h: CSH::new(MatchConfig::inexact(k), Pruning::new(pruning)),
                 v: config.with_filename(&("csh".to_string() + suf1 + suf2)),
+                trace_order: None,
             }
             .align(a, b);
             AstarPa {
                 dt,
                 h: GCSH::new(MatchConfig::inexact(k), Pruning::new(pruning)),
                 v: config.with_filename(&("gcsh".to_string() + suf1 + suf2)),
+                trace_order: None,
             }
             .align(a, b);
         }
@@ -49,7 +49,7 @@ fn main() {
             domain: pa_base_algos::Domain::gap_gap(),
             block_width: 1,
             v: config.with_filename("1_ukkonen"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -58,6 +58,7 @@ fn main() {
             dt: false,
             h: NoCost,
             v: config.with_filename("2_dijkstra"),
+            trace_order: None,
         }),
         Box::new(DiagonalTransition::new(
             cm,
@@ -81,6 +82,7 @@ fn main() {
             h: GCSH::new(MatchConfig::exact(5), Pruning::both()),
             dt: true,
             v: config.with_filename("5_astarpa"),
+            trace_order: None,
         }),
     ];
     for aligner in aligners {
@@ -49,18 +49,21 @@ fn main() {
             dt: false,
             h: SH::new(MatchConfig::exact(k), Pruning::new(pruning)),
             v: config.with_filename(&("sh".to_string() + suf)),
+            trace_order: None,
         }
         .align(a, b);
         AstarPa {
             dt: false,
             h: CSH::new(MatchConfig::exact(k), Pruning::new(pruning)),
             v: config.with_filename(&("csh".to_string() + suf)),
+            trace_order: None,
         }
         .align(a, b);
         AstarPa {
             dt: false,
             h: GCSH::new(MatchConfig::exact(k), Pruning::new(pruning)),
             v: config.with_filename(&("gcsh".to_string() + suf)),
+            trace_order: None,
         }
         .align(a, b);
     }
@@ -53,6 +53,7 @@ fn main() {
             dt: false,
             h,
             v: config.clone(),
+            trace_order: None,
         };
         a_star.align(a, b);
     }
@@ -58,6 +58,7 @@ fn main() {
             factor: 2.0,
         },
         block_width: 1,
+        block_width_mode: BlockWidthMode::Fixed,
         front: astarpa2::BlockParams {
             sparse: true,
             simd: false,
@@ -68,6 +69,7 @@ fn main() {
         },
         sparse_h: false,
         prune: false,
+        max_divergence: None,
         viz: false,
     };
 
@@ -78,7 +80,7 @@ fn main() {
             domain: pa_base_algos::Domain::full(),
             block_width: 1,
             v: config.with_filename("0_full"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -89,7 +91,7 @@ fn main() {
             domain: pa_base_algos::Domain::dijkstra(),
             block_width: 1,
             v: config.with_filename("0_g"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -100,7 +102,7 @@ fn main() {
             domain: pa_base_algos::Domain::gap_start(),
             block_width: 1,
             v: config.with_filename("0_gap-start"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -111,7 +113,7 @@ fn main() {
             domain: pa_base_algos::Domain::gap_gap(),
             block_width: 1,
             v: config.with_filename("0_gap-gap"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -122,7 +124,7 @@ fn main() {
             domain: pa_base_algos::Domain::dist_gap(),
             block_width: 1,
             v: config.with_filename("0_g-gap"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -147,7 +149,7 @@ fn main() {
             domain: pa_base_algos::Domain::dist_gap(),
             block_width: 1,
             v: config.with_filename("1_edlib"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -156,6 +158,7 @@ fn main() {
             dt: false,
             h: NoCost,
             v: config.with_filename("2_dijkstra"),
+            trace_order: None,
         }),
         Box::new(DiagonalTransition::new(
             cm,
@@ -179,11 +182,13 @@ fn main() {
             h: GCSH::new(MatchConfig::exact(5), Pruning::disabled()),
             dt: false,
             v: config.with_filename("5_astarpa"),
+            trace_order: None,
         }),
         Box::new(AstarPa {
             h: GCSH::new(MatchConfig::exact(5), Pruning::both()),
             dt: false,
             v: config.with_filename("5_astarpa-prune"),
+            trace_order: None,
         }),
         astarpa2::AstarPa2Params::simple()
             .make_aligner_with_visualizer(true, config.with_filename("6_astarpa2_simple")),
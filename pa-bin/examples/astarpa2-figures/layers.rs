@@ -59,18 +59,21 @@ fn main() {
             dt: false,
             h: SH::new(match_config, Pruning::new(pruning)),
             v: config.with_filename(&("sh".to_string() + suf)),
+            trace_order: None,
         }
         .align(a, b);
         AstarPa {
             dt: false,
             h: CSH::new(match_config, Pruning::new(pruning)),
             v: config.with_filename(&("csh".to_string() + suf)),
+            trace_order: None,
         }
         .align(a, b);
         AstarPa {
             dt: false,
             h: GCSH::new(match_config, Pruning::new(pruning)),
             v: config.with_filename(&("gcsh".to_string() + suf)),
+            trace_order: None,
         }
         .align(a, b);
     }
@@ -81,7 +81,7 @@ fn main() {
         cm,
         strategy: Strategy::BandDoubling {
             start: pa_base_algos::DoublingStart::H0,
-            factor: 2.,
+            growth: pa_base_algos::Growth::Factor(2.),
         },
         domain: Domain::Astar(GCSH::new(MatchConfig::exact(5), Pruning::start())),
         block_width: 256,
@@ -45,6 +45,7 @@ fn main() {
             dt: true,
             h,
             v: config.clone(),
+            trace_order: None,
         };
         a_star.align(a, b);
     }
@@ -59,7 +59,7 @@ fn main() {
             domain: Domain::gap_gap(),
             block_width: 1,
             v: config.with_filename("1_ukkonen"),
-            front: AffineFront,
+            front: AffineFront::default(),
             trace: true,
             sparse_h: false,
             prune: false,
@@ -68,6 +68,7 @@ fn main() {
             dt: false,
             h: NoCost,
             v: config.with_filename("2_dijkstra"),
+            trace_order: None,
         }),
         Box::new(DiagonalTransition::new(
             cm,
@@ -87,6 +88,7 @@ fn main() {
             h: GCSH::new(MatchConfig::exact(5), Pruning::both()),
             dt: true,
             v: config.with_filename("5_astarpa"),
+            trace_order: None,
         }),
         {
             let mut config = config.with_filename("6_astarpa2");
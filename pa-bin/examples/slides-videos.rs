@@ -41,26 +41,31 @@ fn main() {
             dt: false,
             h: SH::new(MatchConfig::exact(5), Pruning::disabled()),
             v: config.with_filename("sh-noprune"),
+            trace_order: None,
         }),
         Box::new(AstarPa {
             dt: false,
             h: SH::new(MatchConfig::exact(5), Pruning::both()),
             v: config.with_filename("sh"),
+            trace_order: None,
         }),
         Box::new(AstarPa {
             dt: false,
             h: GCSH::new(MatchConfig::exact(5), Pruning::disabled()),
             v: config.with_filename("gcsh-noprune"),
+            trace_order: None,
         }),
         Box::new(AstarPa {
             dt: false,
             h: GCSH::new(MatchConfig::exact(5), Pruning::both()),
             v: config.with_filename("gcsh"),
+            trace_order: None,
         }),
         Box::new(AstarPa {
             dt: true,
             h: GCSH::new(MatchConfig::exact(5), Pruning::both()),
             v: config.with_filename("gcsh-dt"),
+            trace_order: None,
         }),
     ];
     for aligner in aligners {
@@ -0,0 +1,175 @@
+//! Alignments in which a prefix and suffix of the query are clipped, as
+//! produced by semi-global and extension alignment, and their SAM and PAF
+//! representations.
+//!
+//! `pa_types::Cigar` has no clip operations, so the clipped lengths are stored
+//! next to it, and the start of the alignment in the reference is tracked
+//! explicitly.
+use pa_types::{Cigar, CigarOp};
+
+/// The alignment of a query (`b`) against the reference (`a`) region starting
+/// at `ref_start`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClippedAlignment {
+    /// 0-based start of the aligned region in the reference.
+    pub ref_start: usize,
+    /// Lengths of the clipped prefix and suffix of the query that are not
+    /// stored, i.e. `H` operations.
+    pub hard_clip: (usize, usize),
+    /// Lengths of the clipped prefix and suffix of the stored query, i.e. `S`
+    /// operations.
+    pub soft_clip: (usize, usize),
+    /// The alignment of the unclipped part of the query.
+    pub cigar: Cigar,
+}
+
+impl ClippedAlignment {
+    /// A global alignment of the query against the region starting at `ref_start`.
+    pub fn new(ref_start: usize, cigar: Cigar) -> Self {
+        Self {
+            ref_start,
+            hard_clip: (0, 0),
+            soft_clip: (0, 0),
+            cigar,
+        }
+    }
+
+    /// Turn leading and trailing gaps into clips, as for ends-free alignment.
+    ///
+    /// Inserted query characters become soft clips, and leading deleted
+    /// reference characters move `ref_start`.
+    pub fn clip_end_gaps(&mut self) {
+        let ops = &mut self.cigar.ops;
+        let mut start = 0;
+        for e in ops.iter() {
+            match e.op {
+                CigarOp::Ins => self.soft_clip.0 += e.cnt as usize,
+                CigarOp::Del => self.ref_start += e.cnt as usize,
+                _ => break,
+            }
+            start += 1;
+        }
+        ops.drain(..start);
+        while let Some(e) = ops.last() {
+            match e.op {
+                CigarOp::Ins => self.soft_clip.1 += e.cnt as usize,
+                CigarOp::Del => {}
+                _ => break,
+            }
+            ops.pop();
+        }
+    }
+
+    fn count(&self, ops: &[CigarOp]) -> usize {
+        self.cigar
+            .ops
+            .iter()
+            .filter(|e| ops.contains(&e.op))
+            .map(|e| e.cnt as usize)
+            .sum()
+    }
+
+    /// The length of the aligned reference region.
+    pub fn ref_len(&self) -> usize {
+        self.count(&[CigarOp::Match, CigarOp::Sub, CigarOp::Del])
+    }
+
+    /// The length of the aligned part of the query.
+    pub fn aligned_query_len(&self) -> usize {
+        self.count(&[CigarOp::Match, CigarOp::Sub, CigarOp::Ins])
+    }
+
+    /// The length of the full query, including all clips.
+    pub fn query_len(&self) -> usize {
+        self.hard_clip.0
+            + self.soft_clip.0
+            + self.aligned_query_len()
+            + self.soft_clip.1
+            + self.hard_clip.1
+    }
+
+    /// The edit distance of the aligned part.
+    pub fn edit_distance(&self) -> usize {
+        self.count(&[CigarOp::Sub, CigarOp::Ins, CigarOp::Del])
+    }
+
+    /// The 1-based SAM `POS`.
+    pub fn sam_pos(&self) -> usize {
+        self.ref_start + 1
+    }
+
+    /// The SAM `CIGAR`, including clips. With `eqx`, matches and mismatches
+    /// are written as `=` and `X` instead of `M`.
+    pub fn sam_cigar(&self, eqx: bool) -> String {
+        let clip = |cnt: usize, op: u8| (cnt > 0).then_some((cnt, op));
+        let ops = clip(self.hard_clip.0, b'H')
+            .into_iter()
+            .chain(clip(self.soft_clip.0, b'S'))
+            .chain(self.aligned_ops(eqx))
+            .chain(clip(self.soft_clip.1, b'S'))
+            .chain(clip(self.hard_clip.1, b'H'));
+        write_sam_cigar(ops)
+    }
+
+    /// The operations of the aligned part, as `(count, op)` with SAM operations.
+    fn aligned_ops(&self, eqx: bool) -> impl Iterator<Item = (usize, u8)> + '_ {
+        self.cigar.ops.iter().map(move |e| {
+            let op = match e.op {
+                CigarOp::Match if eqx => b'=',
+                CigarOp::Sub if eqx => b'X',
+                CigarOp::Match | CigarOp::Sub => b'M',
+                CigarOp::Ins => b'I',
+                CigarOp::Del => b'D',
+            };
+            (e.cnt as usize, op)
+        })
+    }
+
+    /// A PAF line for this alignment.
+    ///
+    /// As in SAM, the query is stored in the orientation of the reference, so
+    /// for `reverse` alignments the query coordinates are mirrored. The cigar
+    /// is written in the `cg` tag without clips.
+    pub fn paf_line(
+        &self,
+        qname: &str,
+        tname: &str,
+        tlen: usize,
+        reverse: bool,
+        mapq: u8,
+    ) -> String {
+        let qlen = self.query_len();
+        let qstart = self.hard_clip.0 + self.soft_clip.0;
+        let qend = qstart + self.aligned_query_len();
+        let (qstart, qend) = if reverse {
+            (qlen - qend, qlen - qstart)
+        } else {
+            (qstart, qend)
+        };
+        let matches = self.count(&[CigarOp::Match]);
+        let block_len = self.count(&[CigarOp::Match, CigarOp::Sub, CigarOp::Ins, CigarOp::Del]);
+        format!(
+            "{qname}\t{qlen}\t{qstart}\t{qend}\t{}\t{tname}\t{tlen}\t{}\t{}\t{matches}\t{block_len}\t{mapq}\tNM:i:{}\tcg:Z:{}",
+            if reverse { '-' } else { '+' },
+            self.ref_start,
+            self.ref_start + self.ref_len(),
+            self.edit_distance(),
+            write_sam_cigar(self.aligned_ops(false)),
+        )
+    }
+}
+
+/// Write `(count, op)` pairs, merging consecutive equal operations.
+pub fn write_sam_cigar(ops: impl Iterator<Item = (usize, u8)>) -> String {
+    let mut merged: Vec<(usize, u8)> = vec![];
+    for (cnt, op) in ops {
+        match merged.last_mut() {
+            Some((last_cnt, last_op)) if *last_op == op => *last_cnt += cnt,
+            _ => merged.push((cnt, op)),
+        }
+    }
+    merged
+        .iter()
+        .map(|&(cnt, op)| format!("{cnt}{}", op as char))
+        .collect()
+}
@@ -1,20 +1,30 @@
 #![feature(trait_upcasting)]
 
+pub mod clip;
+mod realign;
+
+pub use realign::{RealignArgs, RealignCounts};
+
 use astarpa::{make_aligner, HeuristicParams};
-use astarpa2::AstarPa2Params;
-use bio::io::fasta;
+use astarpa2::{io::read_named_pairs, AstarPa2Params, BlockWidthMode, Domain};
 use clap::{value_parser, Parser};
 use itertools::Itertools;
-use pa_types::{Aligner, Seq};
+use pa_affine_types::AffineCost;
+use pa_base_algos::nw::NW;
+use pa_bitpacking::{
+    wide::{self, WideCost},
+    AlphabetProfile, BitProfile,
+};
+use pa_generate::{generate_model, ErrorModel};
+use pa_heuristic::{
+    estimate_divergence, is_acgt, prepare_input, util::fits_index, AlignResult, MatchCost, NonAcgt,
+    Prune, ResultAligner,
+};
+use pa_types::{Aligner, CigarOp, Cost, Seq, I};
 use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
-use std::{
-    fs::File,
-    io::{BufRead, BufReader},
-    ops::ControlFlow,
-    path::PathBuf,
-};
+use std::{fmt::Write, io, ops::ControlFlow, path::PathBuf, time::Instant};
 
 #[derive(clap::ValueEnum, Default, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum AlignerType {
@@ -25,13 +35,158 @@ pub enum AlignerType {
 }
 
 impl AlignerType {
-    pub fn build(&self) -> Box<dyn Aligner> {
+    pub fn build(&self) -> Box<dyn ResultAligner> {
         match self {
             AlignerType::Astarpa => make_aligner(true, &HeuristicParams::default()),
             AlignerType::Astarpa2Simple => AstarPa2Params::simple().make_aligner(true),
             AlignerType::Astarpa2Full => AstarPa2Params::full().make_aligner(true),
         }
     }
+
+    /// Build the aligner with the given visualizer attached.
+    #[cfg(feature = "vis")]
+    pub fn build_with_visualizer<V: pa_vis::VisualizerT + 'static>(
+        &self,
+        v: V,
+    ) -> Box<dyn Aligner> {
+        match self {
+            AlignerType::Astarpa => {
+                astarpa::make_aligner_with_visualizer(true, &HeuristicParams::default(), v)
+            }
+            AlignerType::Astarpa2Simple => {
+                AstarPa2Params::simple().make_aligner_with_visualizer(true, v)
+            }
+            AlignerType::Astarpa2Full => {
+                AstarPa2Params::full().make_aligner_with_visualizer(true, v)
+            }
+        }
+    }
+
+    /// The parameters of the heuristic used by the aligner.
+    pub fn heuristic_params(&self) -> HeuristicParams {
+        match self {
+            AlignerType::Astarpa => HeuristicParams::default(),
+            AlignerType::Astarpa2Simple => AstarPa2Params::simple().heuristic,
+            AlignerType::Astarpa2Full => AstarPa2Params::full().heuristic,
+        }
+    }
+
+    /// A human readable description of the configuration used to align `a` and `b`.
+    pub fn explain(&self, a: Seq, b: Seq) -> String {
+        let mut s = String::new();
+        writeln!(s, "aligner:     {self:?}").unwrap();
+        writeln!(s, "input:       {} x {}", a.len(), b.len()).unwrap();
+        let e = estimate_divergence(a, b);
+        writeln!(s, "divergence:  {e:.3} (estimated)").unwrap();
+        let params = match self {
+            AlignerType::Astarpa => {
+                let h = HeuristicParams::default();
+                write_heuristic(&mut s, &h);
+                writeln!(s, "strategy:    A* with diagonal transition").unwrap();
+                return s;
+            }
+            AlignerType::Astarpa2Simple => AstarPa2Params::simple(),
+            AlignerType::Astarpa2Full => AstarPa2Params::full(),
+        };
+        writeln!(s, "domain:      {:?}", params.domain).unwrap();
+        if params.domain == Domain::Astar(()) {
+            write_heuristic(&mut s, &params.heuristic);
+        }
+        writeln!(s, "strategy:    {:?}", params.doubling).unwrap();
+        writeln!(
+            s,
+            "block width: {}{}",
+            params.block_width,
+            match params.block_width_mode {
+                BlockWidthMode::Fixed => "",
+                BlockWidthMode::Adaptive => " (adaptive)",
+                BlockWidthMode::Band => " (band)",
+            }
+        )
+        .unwrap();
+        writeln!(s, "front:       {:?}", params.front).unwrap();
+        writeln!(s, "prune:       {}", params.prune).unwrap();
+        if let Some(d) = params.max_divergence {
+            writeln!(s, "max div.:    {d}").unwrap();
+        }
+        writeln!(
+            s,
+            "memory:      {:.1} MiB (estimated)",
+            astarpa2_memory(&params, a, b, e) as f32 / (1 << 20) as f32
+        )
+        .unwrap();
+        s
+    }
+}
+
+fn write_heuristic(s: &mut String, h: &HeuristicParams) {
+    writeln!(s, "heuristic:   {}", heuristic_label(h)).unwrap();
+}
+
+/// A one-line description of the heuristic parameters.
+fn heuristic_label(h: &HeuristicParams) -> String {
+    format!(
+        "{:?} k={} r={} p={} prune={:?}",
+        h.heuristic, h.k, h.r, h.p, h.prune
+    )
+}
+
+/// A rough estimate of the memory used by A*PA2 in bytes.
+///
+/// Assumes that the last band covers twice the estimated distance (or all of
+/// `b` for the full domain), and that one front of 16 bytes per 64 rows is
+/// stored per block.
+fn astarpa2_memory(params: &AstarPa2Params, a: Seq, b: Seq, e: f32) -> usize {
+    let dist = (e * a.len().max(b.len()) as f32) as usize;
+    let rows = match params.domain {
+        Domain::Full => b.len() + 1,
+        _ => (2 * dist + params.block_width as usize).min(b.len() + 1),
+    };
+    let fronts = if params.front.sparse {
+        a.len().div_ceil(params.block_width as usize) + 1
+    } else {
+        a.len() + 1
+    };
+    // Stored horizontal differences for incremental doubling.
+    let h = if params.front.incremental_doubling {
+        16 * a.len()
+    } else {
+        0
+    };
+    fronts * rows.div_ceil(64) * 16 + h
+}
+
+/// The columns of the `--summary` output.
+pub const SUMMARY_HEADER: &str =
+    "id_a\tid_b\tlen_a\tlen_b\tcost\tidentity\tt_s\texpanded\tcomputed_rows";
+
+/// A row of the `--summary` output.
+///
+/// The identity is the fraction of matches among all columns of the alignment.
+/// `expanded` and `computed_rows` are the fields of `AlignStats`.
+/// Statistics that do not apply to the aligner are 0.
+pub fn summary_row((id_a, id_b): (&str, &str), a: Seq, b: Seq, result: &AlignResult) -> String {
+    let identity = result.cigar.as_ref().map_or(0., |cigar| {
+        let cnt = |f: fn(CigarOp) -> bool| {
+            cigar
+                .ops
+                .iter()
+                .filter(|e| f(e.op))
+                .map(|e| e.cnt as usize)
+                .sum::<usize>()
+        };
+        let matches = cnt(|op| op == CigarOp::Match);
+        matches as f32 / cnt(|_| true).max(1) as f32
+    });
+    format!(
+        "{id_a}\t{id_b}\t{}\t{}\t{}\t{identity:.4}\t{}\t{}\t{}",
+        a.len(),
+        b.len(),
+        result.cost,
+        result.stats.t_total.as_secs_f32(),
+        result.stats.expanded,
+        result.stats.computed_rows,
+    )
 }
 
 /// Globally align pairs of sequences using A*PA.
@@ -43,13 +198,19 @@ impl AlignerType {
 #[clap(mut_arg("size", |a| a.hide_short_help(true)))]
 #[clap(mut_arg("error_model", |a| a.hide_short_help(true)))]
 #[clap(mut_arg("error_model", |a| a.hide_short_help(true)))]
+// Subcommands that need input check this group themselves.
+#[clap(subcommand_negates_reqs(true))]
 #[clap(group(
     clap::ArgGroup::new("input_type")
         .required(true)
         .args(&["input", "length"]),
 ))]
 pub struct Cli {
-    /// A .seq, .txt, or Fasta file with sequence pairs to align.
+    #[clap(subcommand)]
+    #[serde(default)]
+    pub command: Option<Command>,
+
+    /// A .seq, .txt, Fasta, or Fastq file (optionally gzip or bgzip compressed) with sequence pairs to align.
     #[clap(short, long, value_parser = value_parser!(PathBuf), display_order = 1)]
     pub input: Option<PathBuf>,
 
@@ -61,14 +222,450 @@ pub struct Cli {
     #[clap(long, default_value = "astarpa2-full")]
     pub aligner: AlignerType,
 
+    /// How to handle characters other than `ACGT`.
+    /// The aligners use a 2-bit encoding, so with `to-n` or `mismatch`, pairs
+    /// containing other characters are aligned using band doubling NW, which
+    /// compares bytes directly. Only supported for aligning and `distances`.
+    #[clap(long, value_enum, default_value_t)]
+    #[serde(default)]
+    pub non_acgt: NonAcgt,
+
+    /// Write a .tsv of the seeds and matches of the aligner's heuristic for each pair.
+    #[clap(long, value_parser = value_parser!(PathBuf))]
+    pub dump_matches: Option<PathBuf>,
+
+    /// Write a .tsv with one row per aligned pair, with the ids, lengths, cost,
+    /// identity, runtime, and the number of expanded states and computed cells.
+    #[clap(long, value_parser = value_parser!(PathBuf))]
+    pub summary: Option<PathBuf>,
+
     /// Options to generate an input pair.
     #[clap(flatten, next_help_heading = "Generated input")]
     pub generate: pa_generate::DatasetGenerator,
 }
 
+#[derive(clap::Subcommand, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum Command {
+    /// Print the resolved aligner configuration for each input pair, without aligning.
+    Explain,
+    /// Align each input pair with A*PA2-full for every combination of the given
+    /// parameters, and write a CSV with one line per run.
+    Evals(EvalsArgs),
+    /// Compute only the edit distance of each input pair, and write one cost
+    /// per line to `--output` or stdout. Many short pairs are computed at once,
+    /// one per SIMD lane, which is faster than aligning pairs of a few hundred bp
+    /// one by one.
+    Distances,
+    /// Generate a random sequence pair and write it as Fasta, without aligning.
+    Generate(GenerateArgs),
+    /// Re-align the mapped records of a SAM file to their reference region,
+    /// and write them with updated cigars.
+    Realign(RealignArgs),
+    /// Align the first input pair with the visualizer attached and save the result.
+    #[cfg(feature = "vis")]
+    Vis(VisArgs),
+    /// Render a recording made with `vis --record` into images.
+    #[cfg(feature = "vis")]
+    Render(RenderArgs),
+}
+
+impl Command {
+    /// Whether the subcommand reads `--input` or generated pairs.
+    pub fn needs_input(&self) -> bool {
+        match self {
+            Command::Generate(_) | Command::Realign(_) => false,
+            #[cfg(feature = "vis")]
+            Command::Render(_) => false,
+            _ => true,
+        }
+    }
+}
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FrontType {
+    /// Store a front for every column.
+    Dense,
+    /// Only store fronts at block boundaries.
+    Sparse,
+    /// Sparse fronts that reuse computed columns when doubling.
+    Incremental,
+}
+
+/// A grid of parameters for A*PA2-full. Each option takes a comma separated list.
+#[derive(clap::Args, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct EvalsArgs {
+    /// Seed lengths.
+    #[clap(long, value_delimiter = ',', default_value = "12")]
+    pub k: Vec<I>,
+
+    /// Costs of the matches, i.e. 1 for exact and 2 for inexact matches.
+    #[clap(long, value_delimiter = ',', default_value = "1")]
+    pub r: Vec<MatchCost>,
+
+    /// Pruning types.
+    #[clap(long, value_enum, value_delimiter = ',', default_value = "start")]
+    pub prune: Vec<Prune>,
+
+    /// Block widths.
+    #[clap(long, value_delimiter = ',', default_value = "256")]
+    pub block_width: Vec<I>,
+
+    /// Front types.
+    #[clap(long, value_enum, value_delimiter = ',', default_value = "incremental")]
+    pub front: Vec<FrontType>,
+
+    /// The CSV file to write. Defaults to stdout.
+    #[clap(short, long, value_parser = value_parser!(PathBuf))]
+    pub output: Option<PathBuf>,
+}
+
+impl EvalsArgs {
+    pub const CSV_HEADER: &'static str = "k,r,prune,block_width,front,pair,len_a,len_b,\
+        cost,t_s,memory_estimate_b,num_blocks,computed_lanes,f_max_tries";
+
+    /// All parameter combinations, together with their CSV columns.
+    pub fn grid(&self) -> Vec<(String, AstarPa2Params)> {
+        itertools::iproduct!(
+            &self.k,
+            &self.r,
+            &self.prune,
+            &self.block_width,
+            &self.front
+        )
+        .map(|(&k, &r, &prune, &block_width, &front)| {
+            let mut params = AstarPa2Params::full();
+            params.heuristic.k = k;
+            params.heuristic.r = r;
+            params.heuristic.prune = prune;
+            params.prune = prune.is_enabled();
+            params.block_width = block_width;
+            params.front.sparse = front != FrontType::Dense;
+            params.front.incremental_doubling = front == FrontType::Incremental;
+            let columns = format!("{k},{r},{prune:?},{block_width},{front:?}");
+            (columns, params)
+        })
+        .collect()
+    }
+
+    /// Run every parameter combination on every input pair of `cli`.
+    pub fn run(&self, cli: &Cli, out: &mut impl io::Write) -> io::Result<()> {
+        writeln!(out, "{}", Self::CSV_HEADER)?;
+        let mut aligners = self
+            .grid()
+            .into_iter()
+            .map(|(columns, params)| {
+                let aligner = params.make_aligner(true);
+                (columns, params, aligner)
+            })
+            .collect_vec();
+        let mut pair = 0;
+        let mut result = Ok(());
+        cli.process_input_pairs(|a: Seq, b: Seq| {
+            let e = estimate_divergence(a, b);
+            for (columns, params, aligner) in &mut aligners {
+                let start = Instant::now();
+                let (cost, _cigar, stats) = aligner.align_with_stats(a, b);
+                let t = start.elapsed().as_secs_f32();
+                result = writeln!(
+                    out,
+                    "{columns},{pair},{},{},{cost},{t},{},{},{},{}",
+                    a.len(),
+                    b.len(),
+                    astarpa2_memory(params, a, b, e),
+                    stats.block_stats.num_blocks,
+                    stats.block_stats.computed_lanes,
+                    stats.f_max_tries,
+                );
+                if result.is_err() {
+                    return ControlFlow::Break(());
+                }
+            }
+            pair += 1;
+            ControlFlow::Continue(())
+        });
+        result
+    }
+}
+
+#[derive(clap::Args, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GenerateArgs {
+    /// Length of the first sequence.
+    #[clap(long)]
+    pub n: usize,
+
+    /// Error rate between the sequences.
+    #[clap(long)]
+    pub e: f32,
+
+    /// The error model.
+    #[clap(long, value_enum, default_value_t = ErrorModel::Uniform)]
+    pub model: ErrorModel,
+
+    /// Seed of the first pair. Pair `i` uses seed `seed + i`.
+    /// Chosen randomly and printed when not given.
+    #[clap(long)]
+    pub seed: Option<u64>,
+
+    /// The number of pairs to generate.
+    #[clap(long, default_value_t = 1)]
+    pub cnt: usize,
+
+    /// The Fasta file to write. Defaults to stdout.
+    #[clap(short, long, value_parser = value_parser!(PathBuf))]
+    pub output: Option<PathBuf>,
+}
+
+impl GenerateArgs {
+    /// Write the generated pairs as consecutive Fasta records `>{i}a` and `>{i}b`.
+    pub fn write(&self, out: &mut impl io::Write) -> io::Result<()> {
+        let seed = self.seed.unwrap_or_else(|| {
+            let seed = ChaCha8Rng::from_entropy().gen_range(0..1_000);
+            tracing::info!("Seed: {seed}");
+            seed
+        });
+        for i in 0..self.cnt {
+            let (a, b) = generate_model(self.n, self.e, self.model, seed + i as u64);
+            for (name, s) in [("a", a), ("b", b)] {
+                writeln!(out, ">{i}{name}")?;
+                out.write_all(&s)?;
+                writeln!(out)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "vis")]
+#[derive(clap::Args, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct VisArgs {
+    /// The visualizer style.
+    #[clap(long, value_enum, default_value_t = pa_vis::visualizer::VisualizerStyle::Paper)]
+    pub style: pa_vis::visualizer::VisualizerStyle,
+
+    /// The color palette, overriding the colors of the style.
+    #[clap(long, value_enum, default_value_t)]
+    pub palette: pa_vis::visualizer::Palette,
+
+    /// Where to save the image. The final frame is written as `<output>.bmp`.
+    #[clap(long, value_parser = value_parser!(PathBuf))]
+    pub output: PathBuf,
+
+    /// Also write one frame per layer to `<output>/<frame>.bmp`, e.g. to
+    /// convert into a gif using `ffmpeg`.
+    #[clap(long)]
+    pub frames: bool,
+
+    /// Instead of drawing, write all events to `<output>.rec`, to be drawn
+    /// later with any style using the `render` subcommand.
+    #[clap(long)]
+    pub record: bool,
+
+    /// Draw axes with sequence coordinates, a legend, and a title with the
+    /// aligner parameters.
+    #[clap(long)]
+    pub annotate: bool,
+}
+
+#[cfg(feature = "vis")]
+impl VisArgs {
+    /// The visualizer config that saves to `output` without opening a window.
+    ///
+    /// The title and parameters describe `aligner`.
+    pub fn config(&self, aligner: AlignerType) -> pa_vis::visualizer::Config {
+        let mut config = render_config(
+            self.style,
+            self.palette,
+            &self.output,
+            self.frames,
+            self.annotate,
+        );
+        config.title = Some(format!("{aligner:?}"));
+        config.params = Some(heuristic_label(&aligner.heuristic_params()));
+        config
+    }
+
+    /// The recorder that writes to `<output>.rec`.
+    pub fn recorder(&self) -> pa_vis::recording::Recorder {
+        pa_vis::recording::Recorder::new(self.output.with_extension("rec"))
+    }
+}
+
+#[cfg(feature = "vis")]
+#[derive(clap::Args, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RenderArgs {
+    /// The recording written by `vis --record`.
+    #[clap(value_parser = value_parser!(PathBuf))]
+    pub recording: PathBuf,
+
+    /// The visualizer style.
+    #[clap(long, value_enum, default_value_t = pa_vis::visualizer::VisualizerStyle::Paper)]
+    pub style: pa_vis::visualizer::VisualizerStyle,
+
+    /// The color palette, overriding the colors of the style.
+    #[clap(long, value_enum, default_value_t)]
+    pub palette: pa_vis::visualizer::Palette,
+
+    /// Where to save the image. The final frame is written as `<output>.bmp`.
+    #[clap(long, value_parser = value_parser!(PathBuf))]
+    pub output: PathBuf,
+
+    /// Also write one frame per layer to `<output>/<frame>.bmp`.
+    #[clap(long)]
+    pub frames: bool,
+
+    /// Draw axes with sequence coordinates, a legend, and the title.
+    #[clap(long)]
+    pub annotate: bool,
+
+    /// The title drawn above the image with --annotate.
+    #[clap(long)]
+    pub title: Option<String>,
+}
+
+#[cfg(feature = "vis")]
+impl RenderArgs {
+    pub fn run(&self) -> std::io::Result<()> {
+        let recording = pa_vis::recording::Recording::read(&self.recording)?;
+        let mut config = render_config(
+            self.style,
+            self.palette,
+            &self.output,
+            self.frames,
+            self.annotate,
+        );
+        config.title = self.title.clone();
+        recording.replay(&config)
+    }
+}
+
+/// A visualizer config that saves to `output` without opening a window.
+#[cfg(feature = "vis")]
+fn render_config(
+    style: pa_vis::visualizer::VisualizerStyle,
+    palette: pa_vis::visualizer::Palette,
+    output: &std::path::Path,
+    frames: bool,
+    annotate: bool,
+) -> pa_vis::visualizer::Config {
+    use pa_vis::visualizer::{Config, When};
+    let mut config = Config::new(style);
+    config.style.palette = palette;
+    config.style.draw_axes = annotate;
+    config.style.draw_legend = annotate;
+    config.style.draw_title = annotate;
+    config.draw = When::None;
+    config.paused = false;
+    config.save_last = true;
+    if frames {
+        config.save = When::Layers;
+    }
+    config.filepath = output.with_extension("");
+    config
+}
+
+/// Aligns pairs containing characters other than `ACGT` using an aligner that
+/// compares bytes directly, and all other pairs using `two_bit`.
+pub struct NonAcgtAligner {
+    pub two_bit: Box<dyn ResultAligner>,
+    pub bytes: Box<dyn ResultAligner>,
+}
+
+impl NonAcgtAligner {
+    pub fn new(two_bit: Box<dyn ResultAligner>) -> Self {
+        // Unit cost band doubling with the gap-gap heuristic.
+        let bytes = NW::new(AffineCost::unit(), true, true);
+        Self {
+            two_bit,
+            bytes: Box::new(bytes),
+        }
+    }
+
+    fn aligner(&mut self, a: Seq, b: Seq) -> &mut dyn ResultAligner {
+        if is_acgt(a) && is_acgt(b) {
+            self.two_bit.as_mut()
+        } else {
+            self.bytes.as_mut()
+        }
+    }
+}
+
+impl Aligner for NonAcgtAligner {
+    fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<pa_types::Cigar>) {
+        self.aligner(a, b).align(a, b)
+    }
+}
+
+impl ResultAligner for NonAcgtAligner {
+    fn align_result(&mut self, a: Seq, b: Seq) -> AlignResult {
+        self.aligner(a, b).align_result(a, b)
+    }
+}
+
 impl Cli {
+    /// The `--aligner`, additionally supporting characters other than `ACGT`
+    /// when `--non-acgt` is not `error`.
+    pub fn build_aligner(&self) -> Box<dyn ResultAligner> {
+        let aligner = self.aligner.build();
+        if self.non_acgt.supports_two_bit() {
+            aligner
+        } else {
+            Box::new(NonAcgtAligner::new(aligner))
+        }
+    }
+
     /// Call the given function for each pair in the input.
+    ///
+    /// Lowercase characters are converted to uppercase. Other characters than
+    /// `ACGT` are handled as set by `--non-acgt`.
     pub fn process_input_pairs(&self, mut run_pair: impl FnMut(Seq, Seq) -> ControlFlow<()>) {
+        self.process_named_input_pairs(|_ids, a, b| run_pair(a, b))
+    }
+
+    /// The edit distance of each input pair, computed in batches using
+    /// `pa_bitpacking::batch`. Pairs too long for 32-bit costs use
+    /// `pa_bitpacking::wide` instead, and pairs with characters other than
+    /// `ACGT` use `wide` with an `AlphabetProfile` that compares bytes.
+    pub fn input_distances(&self) -> Vec<WideCost> {
+        let mut pairs = vec![];
+        self.process_input_pairs(|a, b| {
+            pairs.push((a.to_vec(), b.to_vec()));
+            ControlFlow::Continue(())
+        });
+        let is_short = |(a, b): &(Vec<u8>, Vec<u8>)| fits_index(a, b) && is_acgt(a) && is_acgt(b);
+        let (short, long): (Vec<_>, Vec<_>) = (0..pairs.len()).partition(|&p| is_short(&pairs[p]));
+        let mut dists = vec![0; pairs.len()];
+        let short_pairs = short
+            .iter()
+            .map(|&p| (&pairs[p].0[..], &pairs[p].1[..]))
+            .collect_vec();
+        for (p, d) in short
+            .into_iter()
+            .zip(pa_bitpacking::batch::edit_distances(&short_pairs))
+        {
+            dists[p] = d as WideCost;
+        }
+        for p in long {
+            let (a, b) = (&pairs[p].0, &pairs[p].1);
+            dists[p] = if is_acgt(a) && is_acgt(b) {
+                wide::edit_distance::<BitProfile>(a, b)
+            } else {
+                wide::edit_distance::<AlphabetProfile<8>>(a, b)
+            };
+        }
+        dists
+    }
+
+    /// Like `process_input_pairs`, but also passes the ids of the two sequences.
+    ///
+    /// Generated pairs are named `{i}a` and `{i}b`, as written by `generate`.
+    pub fn process_named_input_pairs(
+        &self,
+        mut run_pair: impl FnMut((&str, &str), Seq, Seq) -> ControlFlow<()>,
+    ) {
+        let mut run_pair = |ids: (&str, &str), a: Seq, b: Seq| {
+            let (a, b) = prepare_input(a, b, self.non_acgt).unwrap_or_else(|e| panic!("{e}"));
+            run_pair(ids, &a, &b)
+        };
         if let Some(input) = &self.input {
             // Parse file
             let files = if input.is_file() {
@@ -81,51 +678,28 @@ impl Cli {
                     .collect_vec()
             };
 
-            'outer: for f in files {
-                match f.extension().expect("Unknown file extension") {
-                    ext if ext == "seq" || ext == "txt" => {
-                        let f = std::fs::File::open(&f).unwrap();
-                        let f = BufReader::new(f);
-                        for (mut a, mut b) in f.lines().map(|l| l.unwrap().into_bytes()).tuples() {
-                            if ext == "seq" {
-                                assert_eq!(a.remove(0), '>' as u8);
-                                assert_eq!(b.remove(0), '<' as u8);
-                            }
-                            if let ControlFlow::Break(()) = run_pair(&a, &b) {
-                                break 'outer;
-                            }
-                        }
-                    }
-                    ext if ext == "fna" || ext == "fa" || ext == "fasta" => {
-                        for (a, b) in fasta::Reader::new(BufReader::new(File::open(&f).unwrap()))
-                            .records()
-                            .tuples()
-                        {
-                            if let ControlFlow::Break(()) =
-                                run_pair(a.unwrap().seq(), b.unwrap().seq())
-                            {
-                                break 'outer;
-                            }
-                        }
+            for f in files {
+                let pairs = read_named_pairs(&f).unwrap_or_else(|e| panic!("{}: {e}", f.display()));
+                for pair in pairs {
+                    let ((id_a, a), (id_b, b)) =
+                        pair.unwrap_or_else(|e| panic!("{}: {e}", f.display()));
+                    if let ControlFlow::Break(()) = run_pair((&id_a, &id_b), &a, &b) {
+                        return;
                     }
-                    ext => {
-                        unreachable!(
-                            "Unknown file extension {ext:?}. Must be in {{seq,txt,fna,fa,fasta}}."
-                        )
-                    }
-                };
+                }
             }
         } else {
             // Generate random input.
             let seed = self.generate.seed.unwrap_or_else(|| {
                 let seed = ChaCha8Rng::from_entropy().gen_range(0..1_000);
-                eprintln!("Seed: {seed}");
+                tracing::info!("Seed: {seed}");
                 seed
             });
             let ref mut rng = ChaCha8Rng::seed_from_u64(seed);
-            for _ in 0..self.generate.cnt.unwrap() {
+            for i in 0..self.generate.cnt.unwrap() {
                 let (a, b) = self.generate.settings.generate(rng);
-                if let ControlFlow::Break(()) = run_pair(&a, &b) {
+                let ids = (format!("{i}a"), format!("{i}b"));
+                if let ControlFlow::Break(()) = run_pair((&ids.0, &ids.1), &a, &b) {
                     break;
                 }
             }
@@ -1,7 +1,7 @@
 #![feature(let_chains, trait_upcasting)]
 
-use clap::Parser;
-use pa_bin::Cli;
+use clap::{error::ErrorKind, CommandFactory, FromArgMatches};
+use pa_bin::{Cli, Command};
 use pa_types::*;
 use std::{
     io::{BufWriter, Write},
@@ -9,7 +9,120 @@ use std::{
 };
 
 fn main() {
-    let args = Cli::parse();
+    // Internal diagnostics of the aligners, e.g. `RUST_LOG=astarpa2=debug`.
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let matches = Cli::command().get_matches();
+    let args = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+
+    if let Some(Command::Generate(generate)) = &args.command {
+        match &generate.output {
+            Some(o) => generate.write(&mut BufWriter::new(std::fs::File::create(o).unwrap())),
+            None => generate.write(&mut std::io::stdout().lock()),
+        }
+        .unwrap();
+        return;
+    }
+    if args.command.as_ref().is_some_and(Command::needs_input) && !matches.contains_id("input_type")
+    {
+        Cli::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "one of --input or --length is required",
+            )
+            .exit();
+    }
+    if !args.non_acgt.supports_two_bit()
+        && let Some(command) = &args.command
+        && *command != Command::Distances
+    {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "--non-acgt is only supported for aligning and `distances`",
+            )
+            .exit();
+    }
+
+    if let Some(Command::Evals(evals)) = &args.command {
+        match &evals.output {
+            Some(o) => evals.run(
+                &args,
+                &mut BufWriter::new(std::fs::File::create(o).unwrap()),
+            ),
+            None => evals.run(&args, &mut std::io::stdout().lock()),
+        }
+        .unwrap();
+        return;
+    }
+
+    if let Some(Command::Realign(realign)) = &args.command {
+        let mut aligner = args.build_aligner();
+        let counts = match &realign.output {
+            Some(o) => realign.run(
+                aligner.as_mut(),
+                &mut BufWriter::new(std::fs::File::create(o).unwrap()),
+            ),
+            None => realign.run(aligner.as_mut(), &mut std::io::stdout().lock()),
+        }
+        .unwrap_or_else(|e| panic!("{}: {e}", realign.sam.display()));
+        eprintln!(
+            "Realigned: {} (improved: {}), skipped: {}",
+            counts.realigned, counts.improved, counts.skipped
+        );
+        return;
+    }
+
+    if let Some(Command::Distances) = args.command {
+        let dists = args.input_distances();
+        let mut out: Box<dyn Write> = match &args.output {
+            Some(o) => Box::new(BufWriter::new(std::fs::File::create(o).unwrap())),
+            None => Box::new(std::io::stdout().lock()),
+        };
+        for d in dists {
+            writeln!(out, "{d}").unwrap();
+        }
+        return;
+    }
+
+    if let Some(Command::Explain) = args.command {
+        args.process_input_pairs(|a: Seq, b: Seq| {
+            println!("{}", args.aligner.explain(a, b));
+            ControlFlow::Continue(())
+        });
+        return;
+    }
+
+    #[cfg(feature = "vis")]
+    if let Some(Command::Render(render)) = &args.command {
+        render
+            .run()
+            .unwrap_or_else(|e| panic!("{}: {e}", render.recording.display()));
+        return;
+    }
+
+    #[cfg(feature = "vis")]
+    if let Some(Command::Vis(vis)) = &args.command {
+        let recorder = vis.record.then(|| vis.recorder());
+        let mut aligner = if let Some(recorder) = &recorder {
+            args.aligner.build_with_visualizer(recorder.clone())
+        } else {
+            args.aligner.build_with_visualizer(vis.config(args.aligner))
+        };
+        args.process_input_pairs(|a: Seq, b: Seq| {
+            aligner.align(a, b);
+            ControlFlow::Break(())
+        });
+        if let Some(recorder) = recorder {
+            recorder
+                .finish()
+                .unwrap_or_else(|e| panic!("{}: {e}", recorder.path.display()));
+        }
+        return;
+    }
 
     let mut aligner = args.aligner.build();
 
@@ -18,14 +131,36 @@ fn main() {
         .as_ref()
         .map(|o| BufWriter::new(std::fs::File::create(o).unwrap()));
 
+    let mut matches_file = args.dump_matches.as_ref().map(|o| {
+        let mut f = BufWriter::new(std::fs::File::create(o).unwrap());
+        writeln!(f, "{}", pa_heuristic::SEEDS_AND_MATCHES_HEADER).unwrap();
+        f
+    });
+
+    let mut summary_file = args.summary.as_ref().map(|o| {
+        let mut f = BufWriter::new(std::fs::File::create(o).unwrap());
+        writeln!(f, "{}", pa_bin::SUMMARY_HEADER).unwrap();
+        f
+    });
+
     let mut done = 0;
 
     eprint!("Done: {done:>3}\r");
 
     // Process the input.
-    args.process_input_pairs(|a: Seq, b: Seq| {
+    args.process_named_input_pairs(|ids, a: Seq, b: Seq| {
         // Run the pair.
-        let (cost, cigar) = aligner.align(a, b);
+        let result = aligner.align_result(a, b);
+        let (cost, cigar) = (result.cost, result.cigar.as_ref());
+
+        if let Some(f) = &mut summary_file {
+            writeln!(f, "{}", pa_bin::summary_row(ids, a, b, &result)).unwrap();
+        }
+
+        if let Some(f) = &mut matches_file {
+            let h = args.aligner.heuristic_params().build_query(a, b);
+            pa_heuristic::write_seeds_and_matches(h.as_ref(), done, f).unwrap();
+        }
 
         done += 1;
         eprint!("Done: {done:>3}\r");
@@ -40,8 +175,281 @@ fn main() {
 
 #[cfg(test)]
 mod test {
+    use pa_types::Cost;
+
     #[test]
     fn cli_test() {
         <super::Cli as clap::CommandFactory>::command().debug_assert();
     }
+
+    #[test]
+    fn explain() {
+        use clap::Parser;
+
+        let args = super::Cli::parse_from([
+            "pa-bin", "--length", "1000", "--cnt", "1", "--seed", "1", "explain",
+        ]);
+        assert_eq!(args.command, Some(super::Command::Explain));
+        args.process_input_pairs(|a, b| {
+            let s = args.aligner.explain(a, b);
+            assert!(s.contains("block width: 256"), "{s}");
+            std::ops::ControlFlow::Continue(())
+        });
+    }
+
+    #[test]
+    fn distances() {
+        use clap::Parser;
+
+        let args = super::Cli::parse_from(
+            "pa-bin --length 200 --cnt 20 --seed 1 distances".split_whitespace(),
+        );
+        let dists = args.input_distances();
+        let mut aligner = args.aligner.build();
+        let mut costs = vec![];
+        args.process_input_pairs(|a, b| {
+            costs.push(aligner.align(a, b).0 as pa_bitpacking::wide::WideCost);
+            std::ops::ControlFlow::Continue(())
+        });
+        assert_eq!(dists, costs);
+    }
+
+    /// Costs of aligning and of `distances` for a pair with `N`s, with the
+    /// given `--non-acgt` mode.
+    fn non_acgt_costs(mode: &str) -> (Vec<Cost>, Vec<pa_bitpacking::wide::WideCost>) {
+        use clap::Parser;
+
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in.fa");
+        std::fs::write(&input, ">a\nACGTNACGT\n>b\nACGTnACGA\n").unwrap();
+        let args =
+            super::Cli::parse_from(["pa-bin", "-i", input.to_str().unwrap(), "--non-acgt", mode]);
+        let mut aligner = args.build_aligner();
+        let mut costs = vec![];
+        args.process_input_pairs(|a, b| {
+            costs.push(aligner.align(a, b).0);
+            std::ops::ControlFlow::Continue(())
+        });
+        (costs, args.input_distances())
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected byte 'N'")]
+    fn non_acgt_error() {
+        non_acgt_costs("error");
+    }
+
+    #[test]
+    fn non_acgt_to_n() {
+        // `N` matches `N`.
+        assert_eq!(non_acgt_costs("to-n"), (vec![1], vec![1]));
+    }
+
+    #[test]
+    fn non_acgt_mismatch() {
+        // The `N`s never match.
+        assert_eq!(non_acgt_costs("mismatch"), (vec![2], vec![2]));
+    }
+
+    #[test]
+    fn generate() {
+        use clap::Parser;
+
+        let args = super::Cli::parse_from(
+            "pa-bin generate --n 1000 --e 0.05 --model noisy-insert --seed 31415 --cnt 2"
+                .split_whitespace(),
+        );
+        let Some(super::Command::Generate(generate)) = args.command else {
+            panic!("Expected the generate subcommand.");
+        };
+        let mut out = vec![];
+        generate.write(&mut out).unwrap();
+        let mut again = vec![];
+        generate.write(&mut again).unwrap();
+        assert_eq!(out, again);
+        assert_eq!(out.iter().filter(|&&c| c == b'>').count(), 4);
+    }
+
+    #[test]
+    fn realign() {
+        use clap::Parser;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let reference = dir.join("ref.fa");
+        std::fs::write(&reference, ">chr1\nTTTTACGTACGTAAAACCCC\n").unwrap();
+        let sam = dir.join("in.sam");
+        std::fs::write(
+            &sam,
+            "@SQ\tSN:chr1\tLN:20\n\
+             r1\t0\tchr1\t5\t60\t2S4M1I4M\t*\t0\t0\tGGACGTCACGT\t*\tNM:i:3\tMD:Z:1A5\n\
+             r2\t4\t*\t0\t0\t*\t*\t0\t0\tACGT\t*\n",
+        )
+        .unwrap();
+
+        let args = super::Cli::parse_from([
+            "pa-bin",
+            "realign",
+            sam.to_str().unwrap(),
+            "--reference",
+            reference.to_str().unwrap(),
+            "--eqx",
+        ]);
+        assert!(!args.command.as_ref().unwrap().needs_input());
+        let Some(super::Command::Realign(realign)) = &args.command else {
+            panic!("Expected the realign subcommand.");
+        };
+        let mut out = vec![];
+        let counts = realign
+            .run(args.aligner.build().as_mut(), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 4, "{out}");
+        assert!(lines[1].starts_with("@PG"));
+        // ACGTCACGT against ACGTACGT: a single insertion, keeping the clip.
+        assert_eq!(
+            lines[2],
+            "r1\t0\tchr1\t5\t60\t2S4=1I4=\t*\t0\t0\tGGACGTCACGT\t*\tNM:i:1"
+        );
+        assert_eq!(lines[3], "r2\t4\t*\t0\t0\t*\t*\t0\t0\tACGT\t*");
+        assert_eq!(
+            counts,
+            pa_bin::RealignCounts {
+                realigned: 1,
+                improved: 1,
+                skipped: 1
+            }
+        );
+    }
+
+    #[test]
+    fn realign_clip() {
+        use clap::Parser;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let reference = dir.join("ref.fa");
+        std::fs::write(&reference, ">chr1\nTTTTACGTACGTAAAACCCC\n").unwrap();
+        let sam = dir.join("in.sam");
+        std::fs::write(
+            &sam,
+            "r1\t16\tchr1\t4\t60\t9M\t*\t0\t0\tACGTACGTG\t*\tNM:i:2\n",
+        )
+        .unwrap();
+        let paf = dir.join("out.paf");
+
+        let args = super::Cli::parse_from([
+            "pa-bin",
+            "realign",
+            sam.to_str().unwrap(),
+            "--reference",
+            reference.to_str().unwrap(),
+            "--clip",
+            "--paf",
+            paf.to_str().unwrap(),
+        ]);
+        let Some(super::Command::Realign(realign)) = &args.command else {
+            panic!("Expected the realign subcommand.");
+        };
+        let mut out = vec![];
+        let counts = realign
+            .run(args.aligner.build().as_mut(), &mut out)
+            .unwrap();
+        // Clipping the gaps is not an improvement of the alignment.
+        assert_eq!(counts.improved, 0);
+        let out = String::from_utf8(out).unwrap();
+        // TACGTACGT against ACGTACGTG: the leading deletion moves POS, and the
+        // trailing insertion is clipped.
+        assert_eq!(
+            out.lines().nth(1).unwrap(),
+            "r1\t16\tchr1\t5\t60\t8M1S\t*\t0\t0\tACGTACGTG\t*\tNM:i:0"
+        );
+        // The query coordinates of the reverse strand alignment are mirrored.
+        assert_eq!(
+            std::fs::read_to_string(&paf).unwrap(),
+            "r1\t9\t1\t9\t-\tchr1\t20\t4\t12\t8\t8\t60\tNM:i:0\tcg:Z:8M\n"
+        );
+    }
+
+    #[test]
+    fn realign_header_only() {
+        use clap::Parser;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let reference = dir.join("ref.fa");
+        std::fs::write(&reference, ">chr1\nACGT\n").unwrap();
+        let sam = dir.join("in.sam");
+        std::fs::write(&sam, "@HD\tVN:1.6\n@SQ\tSN:chr1\tLN:4\n").unwrap();
+
+        let args = super::Cli::parse_from([
+            "pa-bin",
+            "realign",
+            sam.to_str().unwrap(),
+            "--reference",
+            reference.to_str().unwrap(),
+        ]);
+        let Some(super::Command::Realign(realign)) = &args.command else {
+            panic!("Expected the realign subcommand.");
+        };
+        let mut out = vec![];
+        realign
+            .run(args.aligner.build().as_mut(), &mut out)
+            .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3, "{out}");
+        assert!(lines[2].starts_with("@PG"));
+    }
+
+    #[test]
+    fn summary() {
+        use clap::Parser;
+
+        let args =
+            super::Cli::parse_from("pa-bin --length 500 --cnt 2 --seed 1".split_whitespace());
+        let mut aligner = args.aligner.build();
+        let mut rows = vec![];
+        args.process_named_input_pairs(|ids, a, b| {
+            let result = aligner.align_result(a, b);
+            rows.push(pa_bin::summary_row(ids, a, b, &result));
+            std::ops::ControlFlow::Continue(())
+        });
+        let columns = pa_bin::SUMMARY_HEADER.split('\t').count();
+        assert_eq!(rows.len(), 2);
+        assert!(rows[1].starts_with("1a\t1b\t500\t"), "{}", rows[1]);
+        for row in &rows {
+            let fields = row.split('\t').collect::<Vec<_>>();
+            assert_eq!(fields.len(), columns);
+            let identity = fields[5].parse::<f32>().unwrap();
+            assert!(0.5 < identity && identity <= 1., "{row}");
+            assert!(fields[8].parse::<usize>().unwrap() > 0, "{row}");
+        }
+    }
+
+    #[test]
+    fn evals() {
+        use clap::Parser;
+
+        let args = super::Cli::parse_from(
+            "pa-bin --length 1000 --cnt 2 --seed 1 evals --k 10,12 --block-width 64,256 --front dense,incremental"
+                .split_whitespace(),
+        );
+        let Some(super::Command::Evals(evals)) = &args.command else {
+            panic!("Expected the evals subcommand.");
+        };
+        let mut out = vec![];
+        evals.run(&args, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], pa_bin::EvalsArgs::CSV_HEADER);
+        // 2 pairs, 8 parameter combinations.
+        assert_eq!(lines.len(), 1 + 2 * 8);
+        // All configurations find the same cost for each pair.
+        let cost = |l: &str| l.split(',').nth(8).unwrap().to_string();
+        for pair in lines[1..].chunks(8) {
+            assert!(pair.iter().all(|l| cost(l) == cost(pair[0])), "{out}");
+        }
+    }
 }
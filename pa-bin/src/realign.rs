@@ -0,0 +1,271 @@
+//! The `realign` subcommand: re-align the records of a SAM file exactly.
+//!
+//! For each mapped record, the aligned part of the read (excluding clips) is
+//! globally aligned to the reference region it is mapped to. `POS` and the
+//! clips are kept, the cigar is replaced, `NM` is updated, and the stale `MD`
+//! tag is removed. All other records and fields are copied unchanged.
+//!
+//! With `--clip`, gaps at the ends of the new alignment are clipped instead,
+//! and `POS` is moved accordingly.
+use crate::clip::ClippedAlignment;
+use astarpa2::io::open;
+use clap::value_parser;
+use pa_heuristic::{prepare_input, NonAcgt};
+use pa_types::Aligner;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufWriter, Write},
+    path::PathBuf,
+};
+
+#[derive(clap::Args, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RealignArgs {
+    /// The SAM file to re-align, optionally bgzip compressed.
+    /// BAM files can be converted using `samtools view -h`.
+    #[clap(value_parser = value_parser!(PathBuf))]
+    pub sam: PathBuf,
+
+    /// Fasta file with the reference sequences the records are mapped to.
+    #[clap(short, long, value_parser = value_parser!(PathBuf))]
+    pub reference: PathBuf,
+
+    /// The SAM file to write. Defaults to stdout.
+    #[clap(short, long, value_parser = value_parser!(PathBuf))]
+    pub output: Option<PathBuf>,
+
+    /// Write `=` and `X` instead of `M` in cigar strings.
+    #[clap(long)]
+    pub eqx: bool,
+
+    /// Soft-clip insertions at the ends of the new alignments, and move `POS`
+    /// past deletions at the start.
+    #[clap(long)]
+    pub clip: bool,
+
+    /// Also write the realigned records to this PAF file.
+    #[clap(long, value_parser = value_parser!(PathBuf))]
+    pub paf: Option<PathBuf>,
+}
+
+/// The number of records in each category after `RealignArgs::run`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RealignCounts {
+    /// Records whose cigar was recomputed.
+    pub realigned: usize,
+    /// Realigned records whose edit distance decreased.
+    pub improved: usize,
+    /// Unmapped records, records without sequence, spliced records, and
+    /// records containing other characters than `ACGT`.
+    pub skipped: usize,
+}
+
+const FLAG_UNMAPPED: u16 = 0x4;
+const FLAG_REVERSE: u16 = 0x10;
+
+fn invalid(line: usize, msg: impl std::fmt::Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("line {line}: {msg}"))
+}
+
+impl RealignArgs {
+    /// Read all reference sequences by name.
+    pub fn read_reference(&self) -> io::Result<HashMap<String, Vec<u8>>> {
+        bio::io::fasta::Reader::new(open(&self.reference)?)
+            .records()
+            .map(|r| {
+                let r = r?;
+                Ok((r.id().to_string(), r.seq().to_vec()))
+            })
+            .collect()
+    }
+
+    /// Re-align all records using `aligner`, and write the new SAM file to `out`.
+    pub fn run(
+        &self,
+        aligner: &mut dyn Aligner,
+        out: &mut impl io::Write,
+    ) -> io::Result<RealignCounts> {
+        let reference = self.read_reference()?;
+        let mut paf = match &self.paf {
+            Some(path) => Some(BufWriter::new(File::create(path)?)),
+            None => None,
+        };
+        let mut counts = RealignCounts::default();
+        let mut in_header = true;
+        let pg = "@PG\tID:pa-bin-realign\tPN:pa-bin";
+        for (idx, line) in open(&self.sam)?.lines().enumerate() {
+            let line = line?;
+            if line.starts_with('@') {
+                writeln!(out, "{line}")?;
+                continue;
+            }
+            if in_header {
+                writeln!(out, "{pg}")?;
+                in_header = false;
+            }
+            match self
+                .realign_record(&line, &reference, aligner, &mut counts)
+                .map_err(|e| invalid(idx + 1, e))?
+            {
+                Some((record, paf_line)) => {
+                    writeln!(out, "{record}")?;
+                    if let Some(paf) = &mut paf {
+                        writeln!(paf, "{paf_line}")?;
+                    }
+                }
+                None => {
+                    counts.skipped += 1;
+                    writeln!(out, "{line}")?
+                }
+            }
+        }
+        if in_header {
+            // The file has no records.
+            writeln!(out, "{pg}")?;
+        }
+        Ok(counts)
+    }
+
+    /// The re-aligned record and its PAF line, or `None` when it is copied unchanged.
+    fn realign_record(
+        &self,
+        line: &str,
+        reference: &HashMap<String, Vec<u8>>,
+        aligner: &mut dyn Aligner,
+        counts: &mut RealignCounts,
+    ) -> Result<Option<(String, String)>, String> {
+        let mut fields = line.split('\t').collect::<Vec<_>>();
+        if fields.len() < 11 {
+            return Err(format!("expected 11 fields, found {}", fields.len()));
+        }
+        let flag = fields[1]
+            .parse::<u16>()
+            .map_err(|e| format!("invalid FLAG: {e}"))?;
+        let (seq, cigar) = (fields[9], fields[5]);
+        if flag & FLAG_UNMAPPED != 0 || seq == "*" || cigar == "*" {
+            return Ok(None);
+        }
+        let ops = parse_sam_cigar(cigar)?;
+        let (lead, ops, trail) = split_clips(&ops);
+        if ops.iter().any(|&(_, op)| !b"MIDX=".contains(&op)) {
+            // Spliced or padded alignments are not global alignments.
+            return Ok(None);
+        }
+        let read_len = |ops: &[(usize, u8)], soft: bool| {
+            ops.iter()
+                .filter(|&&(_, op)| b"MIX=".contains(&op) || (soft && op == b'S'))
+                .map(|&(cnt, _)| cnt)
+                .sum::<usize>()
+        };
+        let ref_len = ops
+            .iter()
+            .filter(|&&(_, op)| b"MDX=".contains(&op))
+            .map(|&(cnt, _)| cnt)
+            .sum::<usize>();
+        let start = read_len(lead, true);
+        let end = start + read_len(ops, false);
+        if end + read_len(trail, true) != seq.len() {
+            return Err(format!("CIGAR {cigar} does not match the length of SEQ"));
+        }
+
+        let rname = fields[2];
+        let ref_seq = reference
+            .get(rname)
+            .ok_or_else(|| format!("reference {rname} not found"))?;
+        let pos = fields[3]
+            .parse::<usize>()
+            .map_err(|e| format!("invalid POS: {e}"))?;
+        let ref_range = pos.saturating_sub(1)..pos.saturating_sub(1) + ref_len;
+        if pos == 0 || ref_range.end > ref_seq.len() {
+            return Err(format!("{rname}:{pos} is outside the reference"));
+        }
+
+        let Ok((a, b)) = prepare_input(
+            &ref_seq[ref_range.clone()],
+            &seq.as_bytes()[start..end],
+            NonAcgt::Error,
+        ) else {
+            return Ok(None);
+        };
+        let (_cost, new_cigar) = aligner.align(&a, &b);
+        let mut aln = ClippedAlignment::new(
+            ref_range.start,
+            new_cigar.expect("The aligner did not return a cigar."),
+        );
+        let clip_len = |ops: &[(usize, u8)], clip: u8| {
+            ops.iter()
+                .filter(|&&(_, op)| op == clip)
+                .map(|&(cnt, _)| cnt)
+                .sum::<usize>()
+        };
+        aln.hard_clip = (clip_len(lead, b'H'), clip_len(trail, b'H'));
+        aln.soft_clip = (clip_len(lead, b'S'), clip_len(trail, b'S'));
+
+        counts.realigned += 1;
+        let old_cost = ops
+            .iter()
+            .filter(|&&(_, op)| op != b'M' && op != b'=')
+            .map(|&(cnt, _)| cnt)
+            .sum::<usize>();
+        let nm = fields[11..]
+            .iter()
+            .find_map(|t| t.strip_prefix("NM:i:")?.parse::<usize>().ok());
+        // Without `NM`, mismatches inside `M` are unknown, and the old distance
+        // is only a lower bound.
+        // Compare before clipping, since clipping removes gaps instead of
+        // aligning them.
+        if aln.edit_distance() < nm.unwrap_or(old_cost) {
+            counts.improved += 1;
+        }
+        if self.clip {
+            aln.clip_end_gaps();
+        }
+
+        let mapq = fields[4].parse().unwrap_or(255);
+        let paf_line = aln.paf_line(
+            fields[0],
+            rname,
+            ref_seq.len(),
+            flag & FLAG_REVERSE != 0,
+            mapq,
+        );
+        let pos = aln.sam_pos().to_string();
+        let new_cigar = aln.sam_cigar(self.eqx);
+        let nm = format!("NM:i:{}", aln.edit_distance());
+        fields[3] = &pos;
+        fields[5] = &new_cigar;
+        fields.retain(|t| !t.starts_with("NM:i:") && !t.starts_with("MD:Z:"));
+        fields.push(&nm);
+        Ok(Some((fields.join("\t"), paf_line)))
+    }
+}
+
+/// Parse a SAM cigar string into `(count, op)` pairs.
+fn parse_sam_cigar(cigar: &str) -> Result<Vec<(usize, u8)>, String> {
+    let mut ops = vec![];
+    let mut cnt = None;
+    for c in cigar.bytes() {
+        if c.is_ascii_digit() {
+            cnt = Some(cnt.unwrap_or(0) * 10 + (c - b'0') as usize);
+        } else if b"MIDNSHP=X".contains(&c) {
+            ops.push((cnt.take().ok_or("missing CIGAR count")?, c));
+        } else {
+            return Err(format!("invalid CIGAR operation {:?}", c as char));
+        }
+    }
+    if cnt.is_some() {
+        return Err("trailing CIGAR count".into());
+    }
+    Ok(ops)
+}
+
+/// Split the leading and trailing hard and soft clips from the aligned part.
+fn split_clips(ops: &[(usize, u8)]) -> (&[(usize, u8)], &[(usize, u8)], &[(usize, u8)]) {
+    let is_clip = |&(_, op): &(usize, u8)| op == b'H' || op == b'S';
+    let lead = ops.iter().take_while(|e| is_clip(e)).count();
+    let trail = ops[lead..].iter().rev().take_while(|e| is_clip(e)).count();
+    let (lead, rest) = ops.split_at(lead);
+    let (mid, trail) = rest.split_at(rest.len() - trail);
+    (lead, mid, trail)
+}
@@ -0,0 +1,30 @@
+use bio::alignment::distance::simd::levenshtein;
+use criterion::{criterion_group, criterion_main, Criterion};
+use itertools::Itertools;
+use pa_bitpacking::batch::edit_distances;
+use pa_generate::uniform_seeded;
+use std::time::Duration;
+
+fn bench(c: &mut Criterion) {
+    for n in [100, 300, 1000] {
+        let c = &mut c.benchmark_group(&format!("{n}"));
+        let pairs = (0..1000)
+            .map(|seed| uniform_seeded(n, 0.1, seed))
+            .collect_vec();
+        let seqs = pairs
+            .iter()
+            .map(|(a, b)| (a.as_slice(), b.as_slice()))
+            .collect_vec();
+        c.bench_function("batch", |bb| bb.iter(|| edit_distances(&seqs)));
+        c.bench_function("levenshtein", |bb| {
+            bb.iter(|| seqs.iter().map(|&(a, b)| levenshtein(a, b)).collect_vec())
+        });
+    }
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_millis(2000)).warm_up_time(Duration::from_millis(500));
+    targets = bench
+);
+criterion_main!(benches);
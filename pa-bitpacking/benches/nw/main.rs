@@ -137,7 +137,8 @@ fn bench_simd<H: HEncoding>(
 }
 
 fn bench(c: &mut Criterion) {
-    for height in (64..=512).step_by(64) {
+    // Heights are multiples of `W`, so that there are no padding rows.
+    for height in (W..=512).step_by(W) {
         let c = &mut c.benchmark_group(&format!("{}", height));
         let rng = &mut get_rng(Some(31415));
         let a = &random_sequence(256, rng);
@@ -148,20 +149,37 @@ fn bench(c: &mut Criterion) {
             0
         };
 
+        // `B` is `u128` with the `wide_blocks` feature.
+        let word = format!("u{W}");
+
         let (ref pa, ref pb) = ScatterProfile::build(a, b);
-        bench_scalar::<ScatterProfile, (u64, u64)>(c, pa, pb, d, "scat/u64");
+        bench_scalar::<ScatterProfile, (B, B)>(c, pa, pb, d, &format!("scat/{word}"));
 
         let (ref pa, ref pb) = BitProfile::build(a, b);
-        bench_scalar::<BitProfile, (u64, u64)>(c, pa, pb, d, "bit/u64");
+        bench_scalar::<BitProfile, (B, B)>(c, pa, pb, d, &format!("bit/{word}"));
 
         // bench_simd::<(u8, u8)>(c, pa, pb, d, "bit/u8");
-        bench_simd::<(u64, u64)>(c, pa, pb, d, "bit/u64");
+        bench_simd::<(B, B)>(c, pa, pb, d, &format!("bit/{word}"));
+    }
+}
+
+/// The full edit distance, to compare the total per-word overhead with and
+/// without the `wide_blocks` feature.
+fn bench_distance(c: &mut Criterion) {
+    let c = &mut c.benchmark_group("distance");
+    for n in [1_000, 10_000] {
+        let rng = &mut get_rng(Some(31415));
+        let a = &random_sequence(n, rng);
+        let b = &random_sequence(n, rng);
+        c.bench_function(&format!("bit/u{W}/{n}"), |bb| {
+            bb.iter(|| wide::edit_distance::<BitProfile>(a, b))
+        });
     }
 }
 
 criterion_group!(
     name = benches;
     config = Criterion::default().measurement_time(Duration::from_millis(500)).warm_up_time(Duration::from_millis(100));
-    targets = bench
+    targets = bench, bench_distance
 );
 criterion_main!(benches);
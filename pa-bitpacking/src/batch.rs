@@ -0,0 +1,192 @@
+//! Inter-pair SIMD: the edit distances of many short pairs, one pair per lane.
+//!
+//! The kernels in `simd` parallelize a single alignment over rows, and need a
+//! few hundred columns to amortize the triangles at the edges of each block of
+//! rows. For thousands of pairs of a few hundred bp (barcodes, amplicons), this
+//! instead runs Myers' algorithm for `N * L` pairs in lockstep: lane `l` of
+//! vector `n` holds the vertical differences of one pair, and all pairs advance
+//! one column and word at a time. Pairs are sorted by length first, so that
+//! pairs sharing vectors need little padding.
+//!
+//! Only distances are computed. Characters other than `ACGT` never match.
+use crate::{myers, B, L, S, V, W};
+use itertools::Itertools;
+use pa_types::{Cost, Seq, I};
+use std::array::from_fn;
+
+/// Number of vectors of `L` pairs computed together, for instruction-level
+/// parallelism as in `simd::compute::<2, _, _>`.
+const N: usize = 2;
+/// Number of pairs computed together.
+const LANES: usize = N * L;
+
+/// The rank of `ACGT` (in any case), or `4` for other characters.
+fn rank(c: u8) -> usize {
+    match c {
+        b'A' | b'a' => 0,
+        b'C' | b'c' => 1,
+        b'G' | b'g' => 2,
+        b'T' | b't' => 3,
+        _ => 4,
+    }
+}
+
+fn to_simd(lanes: &[B; LANES]) -> [S<L>; N] {
+    from_fn(|n| S::from_slice(&lanes[n * L..(n + 1) * L]))
+}
+
+/// The edit distance of each pair.
+pub fn edit_distances(pairs: &[(Seq, Seq)]) -> Vec<Cost> {
+    let mut order = (0..pairs.len()).collect_vec();
+    order.sort_by_key(|&p| (pairs[p].1.len().div_ceil(W), pairs[p].0.len()));
+    let mut dists = vec![0; pairs.len()];
+    for chunk in order.chunks(LANES) {
+        // Unused lanes align two empty sequences.
+        let batch: [(Seq, Seq); LANES] =
+            from_fn(|l| chunk.get(l).map_or((&[][..], &[][..]), |&p| pairs[p]));
+        for (&p, d) in chunk.iter().zip(edit_distances_batch(&batch)) {
+            dists[p] = d;
+        }
+    }
+    dists
+}
+
+/// The edit distances of `LANES` pairs, computed in lockstep.
+fn edit_distances_batch(pairs: &[(Seq, Seq); LANES]) -> [Cost; LANES] {
+    let cols = pairs.iter().map(|(a, _)| a.len()).max().unwrap();
+    let words = pairs
+        .iter()
+        .map(|(_, b)| b.len().div_ceil(W))
+        .max()
+        .unwrap();
+
+    // `profile[k][c]`: the rows of word `k` where `b` has character `c`.
+    let mut profile = vec![[[0 as B; LANES]; 4]; words];
+    for (l, (_, b)) in pairs.iter().enumerate() {
+        for (j, &c) in b.iter().enumerate() {
+            if let Some(word) = profile[j / W].get_mut(rank(c)) {
+                word[l] |= 1 << (j % W);
+            }
+        }
+    }
+    let profile = profile.iter().map(|p| p.map(|c| to_simd(&c))).collect_vec();
+
+    // Vertical differences of the current column, initially `+1`.
+    let mut pv = vec![[S::splat(B::MAX); N]; words];
+    let mut mv = vec![[S::splat(0); N]; words];
+
+    // Pairs with empty `a` have distance `|b|`.
+    let mut dists = from_fn(|l| pairs[l].1.len() as Cost);
+    for i in 0..cols {
+        // All ones in the lanes whose `a` has character `c` in column `i`.
+        let mut masks = [[0 as B; LANES]; 4];
+        for (l, (a, _)) in pairs.iter().enumerate() {
+            if let Some(mask) = a.get(i).and_then(|&c| masks.get_mut(rank(c))) {
+                mask[l] = B::MAX;
+            }
+        }
+        let masks = masks.map(|m| to_simd(&m));
+
+        // Horizontal differences along the top row are `+1`.
+        let mut hp = [S::splat(1); N];
+        let mut hm = [S::splat(0); N];
+        for k in 0..words {
+            for n in 0..N {
+                let eq = (0..4).fold(S::splat(0), |eq, c| eq | (profile[k][c][n] & masks[c][n]));
+                myers::compute_block_simd(&mut hp[n], &mut hm[n], &mut pv[k][n], &mut mv[k][n], eq);
+            }
+        }
+
+        // Read off the distances of pairs whose `a` ends in this column.
+        for (l, (a, b)) in pairs.iter().enumerate() {
+            if a.len() == i + 1 {
+                let (n, lane) = (l / L, l % L);
+                let v = |k: usize| V::from(pv[k][n].as_array()[lane], mv[k][n].as_array()[lane]);
+                let full = b.len() / W;
+                let mut d = a.len() as Cost + (0..full).map(|k| v(k).value()).sum::<Cost>();
+                if b.len() % W > 0 {
+                    d += v(full).value_of_prefix((b.len() % W) as I);
+                }
+                dists[l] = d;
+            }
+        }
+    }
+    dists
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pa_generate::uniform_seeded;
+
+    /// Quadratic DP, where characters other than `ACGT` never match.
+    fn naive(a: Seq, b: Seq) -> Cost {
+        let mut row = (0..=b.len() as Cost).collect_vec();
+        for (i, &ca) in a.iter().enumerate() {
+            let mut next = vec![i as Cost + 1; b.len() + 1];
+            for (j, &cb) in b.iter().enumerate() {
+                let sub = (rank(ca) == 4 || rank(ca) != rank(cb)) as Cost;
+                next[j + 1] = (row[j + 1] + 1).min(next[j] + 1).min(row[j] + sub);
+            }
+            row = next;
+        }
+        row[b.len()]
+    }
+
+    fn check(pairs: &[(Vec<u8>, Vec<u8>)]) {
+        let seqs = pairs
+            .iter()
+            .map(|(a, b)| (a.as_slice(), b.as_slice()))
+            .collect_vec();
+        let expected = seqs.iter().map(|&(a, b)| naive(a, b)).collect_vec();
+        assert_eq!(edit_distances(&seqs), expected);
+    }
+
+    #[test]
+    fn empty() {
+        check(&[]);
+        let s = b"ACGTTGCA".to_vec();
+        check(&[
+            (vec![], vec![]),
+            (s.clone(), vec![]),
+            (vec![], s.clone()),
+            (s.clone(), s),
+        ]);
+    }
+
+    #[test]
+    fn word_boundaries() {
+        let mut pairs = vec![];
+        for m in [W - 1, W, W + 1, 2 * W, 3 * W] {
+            for e in [0.0, 0.1, 0.5] {
+                pairs.push(uniform_seeded(m, e, m as u64));
+            }
+            // `b` has exactly `m` characters.
+            let (a, _) = uniform_seeded(m + 3, 0., m as u64);
+            let (b, _) = uniform_seeded(m, 0., m as u64 + 1);
+            pairs.push((a, b));
+        }
+        check(&pairs);
+    }
+
+    #[test]
+    fn mixed_lengths() {
+        // More pairs than lanes, so that some batches mix lengths and have
+        // unused lanes.
+        let pairs = (0..3 * LANES as u64 + 1)
+            .map(|seed| uniform_seeded((seed * 37 % 300) as usize, 0.2, seed))
+            .collect_vec();
+        check(&pairs);
+    }
+
+    #[test]
+    fn non_acgt() {
+        check(&[
+            (b"NNNN".to_vec(), b"NNNN".to_vec()),
+            (b"ACNGT".to_vec(), b"ACNGT".to_vec()),
+            (b"acgt".to_vec(), b"ACGT".to_vec()),
+            (b"AC-GT".to_vec(), b"ACGT".to_vec()),
+            (b"ACGTNACGT".to_vec(), b"ACGTACGTN".to_vec()),
+        ]);
+    }
+}
@@ -1,8 +1,29 @@
 use crate::{B, W};
 use pa_types::{Cost, I};
 
+/// The vertical differences of `W` rows: a bitvector of positive (`P`) and
+/// negative (`M`) differences.
+///
+/// The two bitvectors are interleaved, since every access (`value`, `index`,
+/// `get_diff`, and the kernels) reads both. `V` is aligned to its size, so
+/// that `P` and `M` of a word always share a cache line.
 #[derive(Clone, Default, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "small_blocks", repr(C, align(2)))]
+#[cfg_attr(
+    not(any(feature = "small_blocks", feature = "wide_blocks")),
+    repr(C, align(16))
+)]
+#[cfg_attr(
+    all(feature = "wide_blocks", not(feature = "small_blocks")),
+    repr(C, align(32))
+)]
 pub struct V(B, B);
+
+const _: () = assert!(
+    std::mem::align_of::<V>() == std::mem::size_of::<V>() && 64 % std::mem::size_of::<V>() == 0,
+    "V must not straddle cache lines."
+);
+
 impl V {
     #[inline(always)]
     pub fn one() -> Self {
@@ -28,11 +28,21 @@
     test
 )]
 
+#[cfg(not(feature = "wide_blocks"))]
+pub mod batch;
 mod encoding;
 pub mod myers;
+mod prefetch;
 pub mod profile;
 pub mod scalar;
+pub mod search;
+#[cfg(not(feature = "wide_blocks"))]
 pub mod simd;
+#[cfg(feature = "wide_blocks")]
+#[path = "simd_fallback.rs"]
+pub mod simd;
+pub mod trace;
+pub mod wide;
 
 pub use encoding::*;
 pub use profile::*;
@@ -43,9 +53,19 @@ pub use profile::*;
 pub type B = u8;
 
 /// The type used for all bitvectors.
-#[cfg(not(feature = "small_blocks"))]
+#[cfg(not(any(feature = "small_blocks", feature = "wide_blocks")))]
 pub type B = u64;
 
+/// The type used for all bitvectors.
+/// Wide blocks halve the number of words per column, and thereby the
+/// per-word bookkeeping. Compare using
+/// `cargo bench -p pa-bitpacking --features wide_blocks`.
+#[cfg(all(feature = "wide_blocks", not(feature = "small_blocks")))]
+pub type B = u128;
+
+#[cfg(all(feature = "small_blocks", feature = "wide_blocks"))]
+compile_error!("The `small_blocks` and `wide_blocks` features are mutually exclusive.");
+
 /// The length of each bitvector.
 pub const W: usize = B::BITS as usize;
 
@@ -56,4 +76,5 @@ pub type H = (u8, u8);
 pub const L: usize = 4;
 
 /// The type for a Simd vector of `L` lanes of `B`.
+#[cfg(not(feature = "wide_blocks"))]
 pub type S<const L: usize> = std::simd::Simd<B, L>;
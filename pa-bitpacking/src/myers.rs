@@ -1,6 +1,10 @@
 //! The basic bitpacked algorithm from Myers'99.
-use crate::{HEncoding, Profile, B, S, V, W};
-use std::simd::{LaneCount, SupportedLaneCount};
+use crate::{HEncoding, Profile, B, V, W};
+#[cfg(not(feature = "wide_blocks"))]
+use {
+    crate::S,
+    std::simd::{LaneCount, SupportedLaneCount},
+};
 
 /// Implements Myers '99 bitpacking based algorithm. Terminology is as in the
 /// paper. The code is a translation from the implementation in Edlib.
@@ -25,6 +29,21 @@ use std::simd::{LaneCount, SupportedLaneCount};
 /// 20 operations, excluding `eq`.
 #[inline(always)]
 pub fn compute_block<P: Profile, H: HEncoding>(h0: &mut H, v: &mut V, ca: &P::A, cb: &P::B) {
+    compute_block_with_h::<P, H>(h0, v, ca, cb);
+}
+
+/// Same as `compute_block`, but also returns the horizontal differences of
+/// all rows of the word: bit `k` is `B(k+1) - A(k+1)` in the notation above.
+/// Together with `v`, these allow traceback without recomputation.
+///
+/// The unused return value is optimized away when inlined into `compute_block`.
+#[inline(always)]
+pub fn compute_block_with_h<P: Profile, H: HEncoding>(
+    h0: &mut H,
+    v: &mut V,
+    ca: &P::A,
+    cb: &P::B,
+) -> V {
     let eq = P::eq(ca, cb); // this one is not counted as an operation
     let (vp, vm) = v.pm();
     let vx = eq | vm;
@@ -45,18 +64,22 @@ pub fn compute_block<P: Profile, H: HEncoding>(h0: &mut H, v: &mut V, ca: &P::A,
     // NOTE: overflowing_add uses the carry bit, but is slow because reading the
     // carry bit right after this instruction interrupts pipelining.
     // NOTE: overflowing_shl returns whether the shift is too large, not the shifted out bit.
+    let (hp_row, hm_row) = (hp, hm);
     let hpw = hp >> (W - 1);
     let hmw = hm >> (W - 1);
     let hp = (hp << 1) | h0.p();
     let hm = (hm << 1) | h0.m();
 
+    let h = V::from(hp_row, hm_row);
     *h0 = H::from(hpw as B, hmw);
     *v = V::from(hm | !(vx | hp), hp & vx);
+    h
 }
 
 /// Simd version of `compute_block`.
 ///
 /// This assumes HEncoding of `(u64,u64)`.
+#[cfg(not(feature = "wide_blocks"))]
 #[inline(always)]
 pub fn compute_block_simd<const L: usize>(
     hp0: &mut S<L>,
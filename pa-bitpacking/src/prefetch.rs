@@ -0,0 +1,41 @@
+//! Software prefetching for the kernels.
+//!
+//! The kernels sweep over `a` and `h` once per row (or block of rows), so the
+//! next cache lines are always known. When the band is wide, `a` and `h` do not
+//! fit in L1 and the kernels are memory bound. The hardware prefetcher follows
+//! each sweep, but misses the jump back to the start of the next row.
+//!
+//! Prefetching is only enabled with the `prefetch` feature, since it has not
+//! yet been shown to help. Compare using
+//! `cargo bench -p pa-bitpacking --features prefetch`, or `just bench_prefetch`.
+//!
+//! The memory layout of the words is not changed here: `V` already stores `P`
+//! and `M` next to each other, and `h` and the profile are contiguous slices.
+
+/// How far ahead of the current element to prefetch.
+const PREFETCH_BYTES: usize = 256;
+
+/// Prefetch the cache line containing `p` into L1.
+/// Prefetches never fault, so `p` may point outside the allocation.
+#[inline(always)]
+pub fn prefetch<T>(p: *const T) {
+    #[cfg(all(target_arch = "x86_64", feature = "prefetch"))]
+    unsafe {
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+        _mm_prefetch::<_MM_HINT_T0>(p as *const i8);
+    }
+    #[cfg(not(all(target_arch = "x86_64", feature = "prefetch")))]
+    let _ = p;
+}
+
+/// Prefetch the element `PREFETCH_BYTES` after `slice[i]`, wrapping around to
+/// the start of `slice` for the next sweep.
+/// Takes a pointer so that `slice` can be prefetched while it is mutably borrowed.
+#[inline(always)]
+pub fn prefetch_ahead<T>(slice: *const [T], i: usize) {
+    let j = i + PREFETCH_BYTES / std::mem::size_of::<T>().max(1);
+    let j = if j < slice.len() { j } else { j - slice.len() };
+    if j < slice.len() {
+        prefetch((slice as *const T).wrapping_add(j));
+    }
+}
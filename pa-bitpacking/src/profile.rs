@@ -1,6 +1,7 @@
 use bio::alphabets::{Alphabet, RankTransform};
 use itertools::Itertools;
 use pa_types::Seq;
+use std::array::from_fn;
 
 use crate::{B, W};
 
@@ -41,14 +42,78 @@ impl Profile for ScatterProfile {
     }
 }
 
+/// Profile for arbitrary alphabets of up to `2^P` characters, generalizing
+/// `BitProfile` from 2 to `P` bit planes.
+///
+/// Characters are ranked in order of first occurrence in `a` and then `b`, so
+/// `P = 2` supports DNA, `P = 5` protein, and `P = 8` arbitrary bytes.
+/// Only the generic (scalar) kernels support this profile.
+#[derive(Clone, Copy, Debug)]
+pub struct AlphabetProfile<const P: usize>;
+
+impl<const P: usize> Profile for AlphabetProfile<P> {
+    /// Exploded bit planes of the rank of each character of `a`.
+    type A = [B; P];
+    /// 64-char packed *negated* bit planes of the ranks of `b`.
+    /// See `BitProfile` for details.
+    type B = [B; P];
+
+    fn build(a: Seq, b: Seq) -> (Vec<Self::A>, Vec<Self::B>) {
+        let ranks = Self::ranks(a, b);
+        let pa = a
+            .iter()
+            .map(|&ca| {
+                let r = ranks[ca as usize] as B;
+                from_fn(|k| (0 as B).wrapping_sub((r >> k) & 1))
+            })
+            .collect_vec();
+        let mut pb = vec![[0; P]; b.len().div_ceil(W)];
+        for (j, &cb) in b.iter().enumerate() {
+            let r = ranks[cb as usize] as B;
+            for (k, plane) in pb[j / W].iter_mut().enumerate() {
+                *plane |= (((r >> k) & 1) ^ 1) << (j % W);
+            }
+        }
+        (pa, pb)
+    }
+
+    /// Characters are equal when all their bit planes are equal.
+    #[inline(always)]
+    fn eq(ca: &Self::A, cb: &Self::B) -> B {
+        ca.iter().zip(cb).fold(B::MAX, |eq, (a, b)| eq & (a ^ b))
+    }
+}
+
+impl<const P: usize> AlphabetProfile<P> {
+    /// Rank the characters of `a` and `b` in order of first occurrence.
+    fn ranks(a: Seq, b: Seq) -> [u16; 256] {
+        let mut ranks = [u16::MAX; 256];
+        let mut n = 0;
+        for &c in a.iter().chain(b) {
+            if ranks[c as usize] == u16::MAX {
+                assert!(
+                    n < 1 << P,
+                    "More than {} distinct characters in the input.",
+                    1 << P
+                );
+                ranks[c as usize] = n;
+                n += 1;
+            }
+        }
+        ranks
+    }
+}
+
 pub use bit_profile::BitProfile;
 
 // Many public types with private members here, to keep things clean.
 pub mod bit_profile {
+    #[cfg(not(feature = "wide_blocks"))]
     use std::simd::{LaneCount, SupportedLaneCount};
 
     use pa_types::I;
 
+    #[cfg(not(feature = "wide_blocks"))]
     use crate::S;
 
     use super::*;
@@ -79,26 +144,7 @@ pub mod bit_profile {
         type B = Bits;
 
         fn build(a: Seq, b: Seq) -> (Vec<Self::A>, Vec<Self::B>) {
-            let r = RankTransform::new(&Alphabet::new(b"ACGT"));
-            let pa = a
-                .iter()
-                .map(|ca| {
-                    let a = CC(r.get(*ca));
-                    Bits(
-                        (0 as B).wrapping_sub(a.0 as B & 1),
-                        (0 as B).wrapping_sub((a.0 as B >> 1) & 1),
-                    )
-                })
-                .collect_vec();
-            let mut pb = vec![Bits(0, 0); b.len().div_ceil(W)];
-            for (j, &cb) in b.iter().enumerate() {
-                let cb = r.get(cb);
-                // !cb[0]
-                pb[j / W].0 |= ((cb as B & 1) ^ 1) << (j % W);
-                // !cb[1]
-                pb[j / W].1 |= (((cb as B >> 1) & 1) ^ 1) << (j % W);
-            }
-            (pa, pb)
+            (Self::build_a(a), Self::build_b(b))
         }
 
         /// `a` is equals to `b` if both bits are the same, so
@@ -113,6 +159,63 @@ pub mod bit_profile {
         }
     }
     impl BitProfile {
+        /// Build only the profile of `a`.
+        pub fn build_a(a: Seq) -> Vec<Bits> {
+            Self::build_a_codes(a.iter().map(Self::rank))
+        }
+
+        /// Build only the profile of `b`.
+        /// This can be reused when aligning many sequences `a` to the same `b`.
+        pub fn build_b(b: Seq) -> Vec<Bits> {
+            Self::build_b_codes(b.iter().map(Self::rank))
+        }
+
+        /// Build the profiles of the reverse of `a` and `b`, without
+        /// materializing reversed copies of the sequences.
+        pub fn build_rev(a: Seq, b: Seq) -> (Vec<Bits>, Vec<Bits>) {
+            (
+                Self::build_a_codes(a.iter().rev().map(Self::rank)),
+                Self::build_b_codes(b.iter().rev().map(Self::rank)),
+            )
+        }
+
+        /// The 2-bit code of an `ACGT` character: `A=0`, `C=1`, `G=2`, `T=3`.
+        fn rank(c: &u8) -> u8 {
+            match c {
+                b'A' => 0,
+                b'C' => 1,
+                b'G' => 2,
+                b'T' => 3,
+                _ => panic!("Unexpected character {:?}", *c as char),
+            }
+        }
+
+        /// Build the profile of `a` from 2-bit codes `A=0`, `C=1`, `G=2`, `T=3`,
+        /// as produced by `pa_heuristic::PackedSeq::codes`.
+        pub fn build_a_codes(a: impl Iterator<Item = u8>) -> Vec<Bits> {
+            a.map(|a| {
+                Bits(
+                    (0 as B).wrapping_sub(a as B & 1),
+                    (0 as B).wrapping_sub((a as B >> 1) & 1),
+                )
+            })
+            .collect_vec()
+        }
+
+        /// Build the profile of `b` from 2-bit codes `A=0`, `C=1`, `G=2`, `T=3`,
+        /// as produced by `pa_heuristic::PackedSeq::codes`.
+        pub fn build_b_codes(b: impl ExactSizeIterator<Item = u8>) -> Vec<Bits> {
+            let mut pb = vec![Bits(0, 0); b.len().div_ceil(W)];
+            for (j, cb) in b.enumerate() {
+                // !cb[0]
+                pb[j / W].0 |= ((cb as B & 1) ^ 1) << (j % W);
+                // !cb[1]
+                pb[j / W].1 |= (((cb as B >> 1) & 1) ^ 1) << (j % W);
+            }
+            pb
+        }
+
+        #[cfg(not(feature = "wide_blocks"))]
         #[inline(always)]
         pub fn eq_simd<const L: usize>(ca: (&S<L>, &S<L>), cb: (&S<L>, &S<L>)) -> S<L>
         where
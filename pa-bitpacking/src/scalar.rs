@@ -1,9 +1,9 @@
-use std::cmp::min;
+use std::{cmp::min, ops::Range};
 
-use itertools::izip;
+use itertools::{izip, Itertools};
 use pa_types::Cost;
 
-use crate::{myers, profile::Profile, HEncoding, V};
+use crate::{myers, prefetch::prefetch_ahead, profile::Profile, HEncoding, V};
 
 /// Compute a rectangle column by column.
 pub fn col<P: Profile, H: HEncoding>(a: &[P::A], b: &[P::B], h: &mut [H], v: &mut [V]) -> Cost {
@@ -37,14 +37,65 @@ pub fn col_local_h<P: Profile, H: HEncoding>(a: &[P::A], b: &[P::B], v: &mut [V]
 pub fn row<P: Profile, H: HEncoding>(a: &[P::A], b: &[P::B], h: &mut [H], v: &mut [V]) -> Cost {
     assert_eq!(a.len(), h.len());
     assert_eq!(b.len(), v.len());
+    let h_ptr: *const [H] = h;
     for (cb, v) in izip!(b, v.iter_mut()) {
-        for (ca, h) in izip!(a.iter(), h.iter_mut()) {
+        for (i, (ca, h)) in izip!(a.iter(), h.iter_mut()).enumerate() {
+            prefetch_ahead(a, i);
+            prefetch_ahead(h_ptr, i);
             myers::compute_block::<P, H>(h, v, ca, cb);
         }
     }
     h.iter().map(|h| h.value()).sum::<Cost>()
 }
 
+/// Same as `row`, but only computes the words `bands[i]` in column `i`.
+///
+/// The start and end of the bands must be non-decreasing, so that the band
+/// follows the diagonal. Cells outside the band are assumed to lie on a path
+/// along its boundary: the horizontal delta at the top of the band and the
+/// vertical delta at its left are `+1`. Values in the band are then upper
+/// bounds, that are exact when the optimal paths do not leave the band.
+///
+/// On return, `h[i]` is the horizontal delta below the band in column `i`,
+/// and `v[j]` is the vertical delta right of the last column containing word `j`.
+/// Words not in any band are not modified.
+pub fn row_banded<P: Profile, H: HEncoding>(
+    a: &[P::A],
+    b: &[P::B],
+    h: &mut [H],
+    v: &mut [V],
+    bands: &[Range<usize>],
+) -> Cost {
+    assert_eq!(a.len(), h.len());
+    assert_eq!(b.len(), v.len());
+    assert_eq!(a.len(), bands.len());
+    for (b1, b2) in bands.iter().tuple_windows() {
+        assert!(b1.start <= b2.start && b1.end <= b2.end);
+    }
+    assert!(bands.last().map_or(true, |band| band.end <= b.len()));
+
+    for (j, (cb, v)) in izip!(b, v.iter_mut()).enumerate() {
+        // The columns whose band contains word `j`.
+        let i_start = bands.partition_point(|band| band.end <= j);
+        let i_end = bands.partition_point(|band| band.start <= j);
+        if i_start >= i_end {
+            continue;
+        }
+        if i_start > 0 {
+            // The band enters this row from below.
+            *v = V::one();
+        }
+        for i in i_start..i_end {
+            if j == bands[i].start && j > 0 {
+                // The top of the band in this column.
+                h[i] = H::one();
+            }
+            myers::compute_block::<P, H>(&mut h[i], v, &a[i], cb);
+        }
+    }
+    h.iter().map(|h| h.value()).sum::<Cost>()
+}
+
 /// Diagonal by diagonal, where each diag goes right-up.
 // diagonal number d:
 // 1 2 3 .. n
@@ -425,3 +476,79 @@ pub fn fill<P: Profile, H: HEncoding>(
     }
     return h.iter().map(|h| h.value()).sum::<Cost>();
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{BitProfile, B, W};
+
+    type H = (B, B);
+
+    /// A pair of similar sequences, with `|b|` a multiple of `W`, so that the
+    /// horizontal deltas at the bottom are exact.
+    fn input(n: usize, e: f32) -> (Vec<u8>, Vec<u8>) {
+        let (a, mut b) = pa_generate::uniform_seeded(n, e, 31415);
+        b.truncate(b.len() / W * W);
+        (a, b)
+    }
+
+    /// For each column, the words within `d` rows of the main diagonal.
+    /// When `top` or `bottom` is false, the band extends to the top or bottom.
+    /// The last column always extends to the bottom.
+    fn diagonal_bands(
+        n: usize,
+        words: usize,
+        d: usize,
+        top: bool,
+        bottom: bool,
+    ) -> Vec<Range<usize>> {
+        (1..=n)
+            .map(|i| {
+                let start = if top { i.saturating_sub(d) / W } else { 0 };
+                let end = if bottom && i < n {
+                    (i + d).div_ceil(W).min(words)
+                } else {
+                    words
+                };
+                start..end
+            })
+            .collect()
+    }
+
+    #[test]
+    fn row_banded_full() {
+        let (a, b) = input(1000, 0.1);
+        let (pa, pb) = BitProfile::build(&a, &b);
+        let (mut h, mut v) = (vec![H::one(); pa.len()], vec![V::one(); pb.len()]);
+        let cost = row::<BitProfile, H>(&pa, &pb, &mut h, &mut v);
+        let (mut h2, mut v2) = (vec![H::one(); pa.len()], vec![V::one(); pb.len()]);
+        let bands = vec![0..pb.len(); pa.len()];
+        let cost2 = row_banded::<BitProfile, H>(&pa, &pb, &mut h2, &mut v2, &bands);
+        assert_eq!(cost, cost2);
+        assert_eq!(h, h2);
+        assert_eq!(v, v2);
+    }
+
+    #[test]
+    fn row_banded_diagonal() {
+        for e in [0.0, 0.05, 0.1] {
+            let (a, b) = input(2000, e);
+            let d = bio::alignment::distance::levenshtein(&a, &b) as Cost;
+            let (pa, pb) = BitProfile::build(&a, &b);
+            for (top, bottom) in [(true, false), (false, true)] {
+                let bands = diagonal_bands(pa.len(), pb.len(), 300, top, bottom);
+                let (mut h, mut v) = (vec![H::one(); pa.len()], vec![V::one(); pb.len()]);
+                let bottom_delta = row_banded::<BitProfile, H>(&pa, &pb, &mut h, &mut v, &bands);
+                if !bottom {
+                    // All bands end at the bottom, so `h` contains the bottom row.
+                    assert_eq!(b.len() as Cost + bottom_delta, d, "e={e} top={top}");
+                }
+                if !top {
+                    // All bands start at the top, so `v` contains the last column.
+                    let last = v.iter().map(|v| v.value()).sum::<Cost>();
+                    assert_eq!(a.len() as Cost + last, d, "e={e} bottom={bottom}");
+                }
+            }
+        }
+    }
+}
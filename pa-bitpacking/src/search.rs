@@ -0,0 +1,137 @@
+//! Approximate pattern search, as in Myers'99 and Edlib's `HW` mode.
+//!
+//! The pattern is semi-globally aligned to the text: it must be matched
+//! entirely, but the alignment may start and end anywhere in the text.
+//! The pattern is bitpacked vertically, and the text is processed one
+//! character (column) at a time.
+use itertools::izip;
+use pa_types::{Cost, Seq, I};
+
+use crate::{myers, HEncoding, Profile, H, V, W};
+
+/// An end position of an approximate occurrence of the pattern in the text.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SearchMatch {
+    /// The (exclusive) end position of the occurrence in the text.
+    pub end: usize,
+    /// The minimal edit distance between the pattern and a substring of the
+    /// text ending at `end`.
+    pub cost: Cost,
+}
+
+/// For each `i` in `0..=text.len()`, the minimal edit distance between the
+/// pattern and a substring of the text ending at position `i`.
+pub fn search_costs<P: Profile>(pattern: Seq, text: Seq) -> Vec<Cost> {
+    let (text_profile, pattern_profile) = P::build(text, pattern);
+    let m = pattern.len();
+    // Vertical deltas along the current column. In column 0, the cost
+    // increases by one in each row.
+    let mut v = vec![V::one(); pattern_profile.len()];
+    let mut costs = Vec::with_capacity(text.len() + 1);
+    costs.push(m as Cost);
+    for ca in &text_profile {
+        // The start in the text is free, so the top row is all zeros.
+        let mut h = H::from(0, 0);
+        for (cb, v) in izip!(&pattern_profile, &mut v) {
+            myers::compute_block::<P, H>(&mut h, v, ca, cb);
+        }
+        costs.push(column_cost(&v, m));
+    }
+    costs
+}
+
+/// Find all end positions in the text of occurrences of the pattern with at
+/// most `k` errors.
+pub fn search<P: Profile>(pattern: Seq, text: Seq, k: Cost) -> Vec<SearchMatch> {
+    search_costs::<P>(pattern, text)
+        .into_iter()
+        .enumerate()
+        .filter(|&(_, cost)| cost <= k)
+        .map(|(end, cost)| SearchMatch { end, cost })
+        .collect()
+}
+
+/// The cost in row `m` of a column with top value `0`.
+/// Rows beyond `m` in the last word are padding and are ignored.
+fn column_cost(v: &[V], m: usize) -> Cost {
+    let full = v[..m / W].iter().map(|v| v.value()).sum::<Cost>();
+    if m % W == 0 {
+        full
+    } else {
+        full + v[m / W].value_of_prefix((m % W) as I)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{AlphabetProfile, BitProfile};
+    use pa_generate::uniform_seeded;
+
+    /// Semi-global DP: the top row is zero, and the cost is read from the bottom row.
+    fn naive_costs(pattern: Seq, text: Seq) -> Vec<Cost> {
+        let mut col = (0..=pattern.len() as Cost).collect::<Vec<_>>();
+        let mut costs = vec![col[pattern.len()]];
+        for &c in text {
+            let mut next = vec![0; pattern.len() + 1];
+            for j in 1..=pattern.len() {
+                next[j] = (col[j] + 1)
+                    .min(next[j - 1] + 1)
+                    .min(col[j - 1] + (pattern[j - 1] != c) as Cost);
+            }
+            costs.push(next[pattern.len()]);
+            col = next;
+        }
+        costs
+    }
+
+    /// A pattern, and a text containing a mutated copy of it.
+    fn input(m: usize, n: usize, e: f32, seed: u64) -> (Vec<u8>, Vec<u8>) {
+        let (pattern, occurrence) = uniform_seeded(m, e, seed);
+        let (mut text, suffix) = uniform_seeded(n / 2, 0.5, seed + 1);
+        text.extend(occurrence);
+        text.extend(suffix);
+        (pattern, text)
+    }
+
+    #[test]
+    fn search_costs_matches_naive() {
+        for m in [0, 1, 10, 63, 64, 65, 150] {
+            for n in [0, 1, 100, 500] {
+                for e in [0.0, 0.1, 0.3] {
+                    let (pattern, text) = input(m, n, e, (m + n) as u64);
+                    let expected = naive_costs(&pattern, &text);
+                    assert_eq!(
+                        search_costs::<BitProfile>(&pattern, &text),
+                        expected,
+                        "m={m} n={n} e={e}"
+                    );
+                    assert_eq!(
+                        search_costs::<AlphabetProfile<2>>(&pattern, &text),
+                        expected,
+                        "m={m} n={n} e={e}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn search_matches_naive() {
+        let (pattern, text) = input(100, 1000, 0.1, 31415);
+        for k in [0, 5, 10, 20] {
+            let expected = naive_costs(&pattern, &text)
+                .into_iter()
+                .enumerate()
+                .filter(|&(_, cost)| cost <= k)
+                .map(|(end, cost)| SearchMatch { end, cost })
+                .collect::<Vec<_>>();
+            assert_eq!(search::<BitProfile>(&pattern, &text, k), expected, "k={k}");
+        }
+        // The exact occurrence ends right after the prefix of length 500.
+        let (pattern, text) = input(100, 1000, 0.0, 31415);
+        assert!(
+            search::<BitProfile>(&pattern, &text, 0).contains(&SearchMatch { end: 600, cost: 0 })
+        );
+    }
+}
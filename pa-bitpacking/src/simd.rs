@@ -28,11 +28,13 @@
 //!
 use super::*;
 use crate::bit_profile::Bits;
+use crate::prefetch::{prefetch, prefetch_ahead};
 use itertools::{izip, Itertools};
 use pa_types::Cost;
 use std::{
     array::from_fn,
     mem::transmute,
+    ops::Range,
     simd::{LaneCount, SupportedLaneCount},
 };
 
@@ -121,9 +123,12 @@ where
     let ap1 = a.iter().map(|ca| ca.1).collect_vec();
 
     // Iterate over blocks of L*N rows at a time.
+    let v_ptr = v.as_ptr();
     let b_chunks = b.array_chunks::<{ L * N }>();
     let v_chunks = v.array_chunks_mut::<{ L * N }>();
-    for (cbs, v) in izip!(b_chunks, v_chunks) {
+    for (idx, (cbs, v)) in izip!(b_chunks, v_chunks).enumerate() {
+        // The `v` words for the next block of rows.
+        prefetch(v_ptr.wrapping_add((idx + 1) * L * N));
         compute_block_of_rows(a, &ap0, &ap1, cbs, h, v);
     }
 
@@ -251,6 +256,11 @@ fn compute_block_of_rows<const N: usize, H: HEncoding, const L: usize>(
     )
     .enumerate()
     {
+        // Fetch the profile and `h` for the coming iterations and the next block of rows.
+        prefetch_ahead(ap0, i);
+        prefetch_ahead(ap1, i);
+        prefetch_ahead(h, i);
+
         // Read the unaligned lanes of a.
         let a0 = slice_to_simd(a0);
         let a1 = slice_to_simd(a1);
@@ -297,6 +307,54 @@ fn compute_block_of_rows<const N: usize, H: HEncoding, const L: usize>(
     }
 }
 
+/// Same as `scalar::row_banded`, but computes `L * N` words at a time using `compute`.
+///
+/// Bands are widened to whole chunks of `L * N` words: a chunk is computed in
+/// all columns whose band intersects it. The result equals `scalar::row_banded`
+/// with bands rounded outward to multiples of `L * N`.
+pub fn compute_banded<const N: usize, H: HEncoding, const L: usize>(
+    a: &[Bits],
+    b: &[Bits],
+    h: &mut [H],
+    v: &mut [V],
+    bands: &[Range<usize>],
+) -> Cost
+where
+    LaneCount<L>: SupportedLaneCount,
+    [(); L * N]: Sized,
+    [(); L * 1]: Sized,
+{
+    assert_eq!(a.len(), h.len());
+    assert_eq!(b.len(), v.len());
+    assert_eq!(a.len(), bands.len());
+    for (b1, b2) in bands.iter().tuple_windows() {
+        assert!(b1.start <= b2.start && b1.end <= b2.end);
+    }
+    assert!(bands.last().map_or(true, |band| band.end <= b.len()));
+
+    for (idx, (cbs, v)) in izip!(b.chunks(L * N), v.chunks_mut(L * N)).enumerate() {
+        let j = idx * L * N;
+        // The columns whose band intersects this chunk.
+        let i_start = bands.partition_point(|band| band.end <= j);
+        let i_end = bands.partition_point(|band| band.start < j + cbs.len());
+        if i_start >= i_end {
+            continue;
+        }
+        if i_start > 0 {
+            // The band enters these rows from below.
+            v.fill(V::one());
+        }
+        for i in i_start..i_end {
+            if bands[i].start >= j && j > 0 {
+                // The top of the band in this column.
+                h[i] = H::one();
+            }
+        }
+        compute::<N, H, L>(&a[i_start..i_end], cbs, &mut h[i_start..i_end], v, true);
+    }
+    h.iter().map(|h| h.value()).sum::<Cost>()
+}
+
 /// Same as `compute`, but returns all computed value.
 pub fn fill<const N: usize, H: HEncoding, const L: usize>(
     a: &[Bits],
@@ -357,10 +415,12 @@ where
     let ap1 = a.iter().map(|ca| ca.1).collect_vec();
 
     // Iterate over blocks of L*N rows at a time.
+    let v_ptr = v.as_ptr();
     let b_chunks = b.array_chunks::<{ L * N }>();
     let v_chunks = v.array_chunks_mut::<{ L * N }>();
     let mut offset = 0;
     for (cbs, v) in izip!(b_chunks, v_chunks) {
+        prefetch(v_ptr.wrapping_add(offset + L * N));
         fill_block_of_rows(a, &ap0, &ap1, cbs, h, v, values, offset);
         offset += L * N;
     }
@@ -457,6 +517,11 @@ fn fill_block_of_rows<const N: usize, H: HEncoding, const L: usize>(
     )
     .enumerate()
     {
+        // Fetch the profile and `h` for the coming iterations and the next block of rows.
+        prefetch_ahead(ap0, i);
+        prefetch_ahead(ap1, i);
+        prefetch_ahead(h, i);
+
         // Read the unaligned lanes of a.
         let a0 = slice_to_simd(a0);
         let a1 = slice_to_simd(a1);
@@ -550,3 +615,41 @@ pub fn vis_block_of_rows<const N: usize, const B: usize>(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{scalar, Profile};
+
+    type H = (B, B);
+
+    #[test]
+    fn compute_banded_matches_scalar() {
+        const CHUNK: usize = 2 * 4;
+        for n in [10, 100, 1000] {
+            let (a, b) = pa_generate::uniform_seeded(n, 0.1, 31415);
+            let (pa, pb) = BitProfile::build(&a, &b);
+            let words = pb.len();
+            for d in [0, 50, 300] {
+                let bands = (1..=pa.len())
+                    .map(|i| i.saturating_sub(d) / W..((i + d).div_ceil(W)).min(words))
+                    .collect_vec();
+                let (mut h, mut v) = (vec![H::one(); pa.len()], vec![V::one(); words]);
+                let cost = compute_banded::<2, H, 4>(&pa, &pb, &mut h, &mut v, &bands);
+
+                let widened = bands
+                    .iter()
+                    .map(|band| {
+                        band.start / CHUNK * CHUNK..band.end.next_multiple_of(CHUNK).min(words)
+                    })
+                    .collect_vec();
+                let (mut h2, mut v2) = (vec![H::one(); pa.len()], vec![V::one(); words]);
+                let cost2 =
+                    scalar::row_banded::<BitProfile, H>(&pa, &pb, &mut h2, &mut v2, &widened);
+                assert_eq!(cost, cost2, "n={n} d={d}");
+                assert_eq!(h, h2, "n={n} d={d}");
+                assert_eq!(v, v2, "n={n} d={d}");
+            }
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Replacement for the `simd` module when `B = u128`, which `std::simd` does not support.
+//!
+//! The functions have the same signatures as in `simd`, but compute one word
+//! at a time using `scalar`. All rows are computed exactly.
+use super::*;
+use crate::bit_profile::Bits;
+use pa_types::Cost;
+use std::ops::Range;
+
+/// Same as `simd::compute`, using `scalar::row`.
+pub fn compute<const N: usize, H: HEncoding, const L: usize>(
+    a: &[Bits],
+    b: &[Bits],
+    h: &mut [H],
+    v: &mut [V],
+    _exact_end: bool,
+) -> Cost {
+    scalar::row::<BitProfile, H>(a, b, h, v)
+}
+
+/// Same as `simd::compute_banded`, using `scalar::row_banded`.
+/// Bands are not widened.
+pub fn compute_banded<const N: usize, H: HEncoding, const L: usize>(
+    a: &[Bits],
+    b: &[Bits],
+    h: &mut [H],
+    v: &mut [V],
+    bands: &[Range<usize>],
+) -> Cost {
+    scalar::row_banded::<BitProfile, H>(a, b, h, v, bands)
+}
+
+/// Same as `simd::fill`, using `scalar::fill`.
+pub fn fill<const N: usize, H: HEncoding, const L: usize>(
+    a: &[Bits],
+    b: &[Bits],
+    h: &mut [H],
+    v: &mut [V],
+    _exact_end: bool,
+    values: &mut [Vec<V>],
+) -> Cost {
+    scalar::fill::<BitProfile, H>(a, b, h, v, values)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    type H = (B, B);
+
+    #[test]
+    fn compute_matches_col() {
+        assert_eq!(W, 128);
+        for n in [1, 127, 128, 129, 1000] {
+            let (a, b) = pa_generate::uniform_seeded(n, 0.1, 31415);
+            let (pa, pb) = BitProfile::build(&a, &b);
+            let (mut h, mut v) = (vec![H::one(); pa.len()], vec![V::one(); pb.len()]);
+            let cost = scalar::col::<BitProfile, H>(&pa, &pb, &mut h, &mut v);
+            let (mut h2, mut v2) = (vec![H::one(); pa.len()], vec![V::one(); pb.len()]);
+            let cost2 = compute::<2, H, 4>(&pa, &pb, &mut h2, &mut v2, true);
+            assert_eq!(cost, cost2);
+            assert_eq!(h, h2);
+            assert_eq!(v, v2);
+        }
+    }
+}
@@ -0,0 +1,161 @@
+//! Traceback through a block from recorded differences, without recomputation.
+//!
+//! `fill_deltas` stores both the horizontal and vertical differences of every
+//! word in every column, so that `trace_block` can determine the predecessor
+//! of each cell in constant time.
+use pa_types::{CigarOp, Cost};
+
+use crate::{myers, HEncoding, Profile, V, W};
+
+/// The differences of one word in one column.
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct Deltas {
+    /// Horizontal differences with the previous column.
+    pub h: V,
+    /// Vertical differences within the column.
+    pub v: V,
+}
+
+/// Same as `scalar::fill`, but records the horizontal and vertical
+/// differences of all words, for use by `trace_block`.
+///
+/// `deltas[i][w]` are the differences of word `w` in the column after `a[i]`.
+pub fn fill_deltas<P: Profile, H: HEncoding>(
+    a: &[P::A],
+    b: &[P::B],
+    h: &mut [H],
+    v: &mut [V],
+    deltas: &mut [Vec<Deltas>],
+) -> Cost {
+    assert_eq!(a.len(), h.len());
+    assert_eq!(deltas.len(), h.len());
+    assert_eq!(b.len(), v.len());
+    for (i, column) in deltas.iter_mut().enumerate() {
+        column.clear();
+        column.extend(b.iter().zip(v.iter_mut()).map(|(cb, v)| {
+            let dh = myers::compute_block_with_h::<P, H>(&mut h[i], v, &a[i], cb);
+            Deltas { h: dh, v: *v }
+        }));
+    }
+    h.iter().map(|h| h.value()).sum::<Cost>()
+}
+
+/// The difference at row `j > 0` of a column of words.
+fn bit(x: V, j: usize) -> Cost {
+    let k = (j - 1) % W;
+    ((x.p() >> k) & 1) as Cost - ((x.m() >> k) & 1) as Cost
+}
+
+/// Trace an optimal path back from cell `(i, j)` until the left column or top
+/// row of the block is reached.
+///
+/// Coordinates are relative to the block: column `0` and row `0` are its left
+/// and top boundary. `v0` are the vertical differences of the left column and
+/// `deltas` are as computed by `fill_deltas` with the same `a` and `b`.
+///
+/// Returns the operations of the path in reverse order, and the cell on the
+/// boundary where it ends.
+pub fn trace_block<P: Profile>(
+    a: &[P::A],
+    b: &[P::B],
+    v0: &[V],
+    deltas: &[Vec<Deltas>],
+    (mut i, mut j): (usize, usize),
+) -> (Vec<CigarOp>, (usize, usize)) {
+    assert_eq!(a.len(), deltas.len());
+    assert_eq!(b.len(), v0.len());
+    // `D(i, j) - D(i, j-1)`
+    let vertical = |i: usize, j: usize| {
+        let w = (j - 1) / W;
+        bit(if i == 0 { v0[w] } else { deltas[i - 1][w].v }, j)
+    };
+    // `D(i, j) - D(i-1, j)`
+    let horizontal = |i: usize, j: usize| bit(deltas[i - 1][(j - 1) / W].h, j);
+
+    let mut ops = vec![];
+    while i > 0 && j > 0 {
+        let is_match = (P::eq(&a[i - 1], &b[(j - 1) / W]) >> ((j - 1) % W)) & 1 == 1;
+        let diagonal = horizontal(i, j) + vertical(i - 1, j);
+        if diagonal == (!is_match) as Cost {
+            ops.push(if is_match {
+                CigarOp::Match
+            } else {
+                CigarOp::Sub
+            });
+            i -= 1;
+            j -= 1;
+        } else if vertical(i, j) == 1 {
+            ops.push(CigarOp::Ins);
+            j -= 1;
+        } else {
+            debug_assert_eq!(horizontal(i, j), 1);
+            ops.push(CigarOp::Del);
+            i -= 1;
+        }
+    }
+    (ops, (i, j))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{scalar, BitProfile, B};
+
+    type H = (B, B);
+
+    #[test]
+    fn fill_deltas_matches_fill() {
+        let (a, b) = pa_generate::uniform_seeded(500, 0.1, 31415);
+        let (pa, pb) = BitProfile::build(&a, &b);
+        let (mut h, mut v) = (vec![H::one(); pa.len()], vec![V::one(); pb.len()]);
+        let mut values = vec![vec![]; pa.len()];
+        let cost = scalar::fill::<BitProfile, H>(&pa, &pb, &mut h, &mut v, &mut values);
+        let (mut h2, mut v2) = (vec![H::one(); pa.len()], vec![V::one(); pb.len()]);
+        let mut deltas = vec![vec![]; pa.len()];
+        let cost2 = fill_deltas::<BitProfile, H>(&pa, &pb, &mut h2, &mut v2, &mut deltas);
+        assert_eq!(cost, cost2);
+        assert_eq!(h, h2);
+        assert_eq!(v, v2);
+        for (values, deltas) in values.iter().zip(&deltas) {
+            assert_eq!(values, &deltas.iter().map(|d| d.v).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn trace_block_full() {
+        for (n, e) in [(0, 0.0), (1, 1.0), (64, 0.1), (500, 0.1), (500, 0.5)] {
+            let (a, b) = pa_generate::uniform_seeded(n, e, 31415);
+            let (pa, pb) = BitProfile::build(&a, &b);
+            let v0 = vec![V::one(); pb.len()];
+            let (mut h, mut v) = (vec![H::one(); pa.len()], v0.clone());
+            let mut deltas = vec![vec![]; pa.len()];
+            fill_deltas::<BitProfile, H>(&pa, &pb, &mut h, &mut v, &mut deltas);
+            let (mut ops, (i, j)) =
+                trace_block::<BitProfile>(&pa, &pb, &v0, &deltas, (a.len(), b.len()));
+            // Complete the path along the boundary.
+            ops.extend(std::iter::repeat(CigarOp::Del).take(i));
+            ops.extend(std::iter::repeat(CigarOp::Ins).take(j));
+            ops.reverse();
+
+            // Replay the path and check that it is an optimal alignment.
+            let (mut i, mut j, mut cost) = (0, 0, 0);
+            for op in ops {
+                match op {
+                    CigarOp::Match => assert_eq!(a[i], b[j]),
+                    CigarOp::Sub => assert_ne!(a[i], b[j]),
+                    _ => {}
+                }
+                if op != CigarOp::Ins {
+                    i += 1;
+                }
+                if op != CigarOp::Del {
+                    j += 1;
+                }
+                cost += (op != CigarOp::Match) as Cost;
+            }
+            assert_eq!((i, j), (a.len(), b.len()));
+            let d = bio::alignment::distance::levenshtein(&a, &b) as Cost;
+            assert_eq!(cost, d, "n={n} e={e}");
+        }
+    }
+}
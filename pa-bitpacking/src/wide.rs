@@ -0,0 +1,122 @@
+//! Edit distance with 64-bit positions and costs.
+//!
+//! Positions and costs in `pa-types` are 32-bit (`I` and `Cost`), which limits
+//! the aligners to sequences of about 1 Gbp. This computes the global edit
+//! distance using `usize` positions and `WideCost` costs, so that costs above
+//! `i32::MAX` do not overflow. Within a word of `W` rows, values still fit in
+//! `Cost`; only the sums over words are wide.
+//!
+//! This is the plain bitpacked DP: it takes `O(|a| |b| / W)` time and stores
+//! only the current column, `O(|b| / W)` words. The profile of `a` is built in
+//! chunks, so that memory does not grow with `|a|`. Any `Profile` can be used,
+//! e.g. `AlphabetProfile` for sequences other than DNA.
+use pa_types::Seq;
+
+use crate::{myers, HEncoding, Profile, H, V, W};
+
+/// Cost type for sequences whose costs may exceed `Cost`.
+pub type WideCost = i64;
+
+/// Number of characters of `a` whose profile is built at once.
+const CHUNK: usize = 1 << 16;
+
+/// The unit cost edit distance between `a` and `b`.
+pub fn edit_distance<P: Profile>(a: Seq, b: Seq) -> WideCost {
+    let mut v = vec![V::one(); b.len().div_ceil(W)];
+    for a_chunk in a.chunks(CHUNK) {
+        // The profile of `b` is rebuilt for each chunk, since the encoding of
+        // e.g. `AlphabetProfile` depends on both sequences. This is cheap
+        // compared to the `CHUNK` columns computed with it.
+        let (pa, pb) = P::build(a_chunk, b);
+        for ca in &pa {
+            // The top row increases by one in each column.
+            let mut h = H::one();
+            for (cb, v) in pb.iter().zip(&mut v) {
+                myers::compute_block::<P, H>(&mut h, v, ca, cb);
+            }
+        }
+    }
+    // The value at the bottom of the last column: `|a|` at the top, plus the
+    // vertical differences of the first `|b|` rows.
+    let full = b.len() / W;
+    let mut cost = a.len() as WideCost
+        + v[..full]
+            .iter()
+            .map(|v| v.value() as WideCost)
+            .sum::<WideCost>();
+    if b.len() % W > 0 {
+        cost += v[full].value_of_prefix((b.len() % W) as _) as WideCost;
+    }
+    cost
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{AlphabetProfile, BitProfile};
+
+    #[test]
+    fn edit_distance_matches_naive() {
+        for n in [0, 1, 63, 64, 65, 200, 1000] {
+            for e in [0.0, 0.1, 0.5] {
+                let (a, b) = pa_generate::uniform_fixed(n, e);
+                let d = bio::alignment::distance::levenshtein(&a, &b) as WideCost;
+                assert_eq!(edit_distance::<BitProfile>(&a, &b), d, "n={n} e={e}");
+                assert_eq!(edit_distance::<BitProfile>(&b, &a), d, "n={n} e={e}");
+            }
+        }
+    }
+
+    #[test]
+    fn edit_distance_empty() {
+        assert_eq!(edit_distance::<BitProfile>(b"", b""), 0);
+        assert_eq!(edit_distance::<BitProfile>(b"ACGT", b""), 4);
+        assert_eq!(edit_distance::<BitProfile>(b"", &[b'A'; 130]), 130);
+    }
+
+    #[test]
+    fn edit_distance_across_chunks() {
+        let a = vec![b'A'; CHUNK + 10];
+        let b = vec![b'C'; 100];
+        assert_eq!(
+            edit_distance::<BitProfile>(&a, &b),
+            (CHUNK + 10) as WideCost
+        );
+    }
+
+    /// Map DNA to a 20 letter alphabet, using the position to pick the letter.
+    fn protein(seq: &[u8]) -> Vec<u8> {
+        const AMINO: &[u8] = b"ACDEFGHIKLMNPQRSTVWY";
+        seq.iter()
+            .enumerate()
+            .map(|(i, &c)| AMINO[(c as usize + i / 3) % AMINO.len()])
+            .collect()
+    }
+
+    #[test]
+    fn edit_distance_alphabet_profile() {
+        for n in [0, 1, 63, 64, 65, 200, 1000] {
+            for e in [0.0, 0.1, 0.5] {
+                let (a, b) = pa_generate::uniform_fixed(n, e);
+                let d = bio::alignment::distance::levenshtein(&a, &b) as WideCost;
+                assert_eq!(edit_distance::<AlphabetProfile<2>>(&a, &b), d);
+                let (a, b) = (protein(&a), protein(&b));
+                let d = bio::alignment::distance::levenshtein(&a, &b) as WideCost;
+                assert_eq!(edit_distance::<AlphabetProfile<5>>(&a, &b), d);
+                assert_eq!(edit_distance::<AlphabetProfile<8>>(&b, &a), d);
+            }
+        }
+    }
+
+    #[test]
+    fn edit_distance_alphabet_profile_across_chunks() {
+        // The ranks differ between chunks: `N` is only in the second one.
+        let mut a = vec![b'A'; CHUNK];
+        a.extend_from_slice(b"NNNN");
+        let b = b"NNNNB";
+        assert_eq!(
+            edit_distance::<AlphabetProfile<8>>(&a, b),
+            (CHUNK + 1) as WideCost
+        );
+    }
+}
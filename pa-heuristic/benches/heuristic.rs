@@ -0,0 +1,81 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use instant::Duration;
+use pa_generate::uniform_fixed;
+use pa_heuristic::{
+    contour::{rotate_to_front::RotateToFrontContour, Arrow, Contours, HintContours},
+    matches::find_matches,
+    *,
+};
+use pa_types::*;
+
+fn bench(c: &mut Criterion) {
+    for n in [10000, 100000, 1000000] {
+        let mut c = c.benchmark_group(format!("{n}"));
+        for e in [0.05, 0.20] {
+            let (a, b) = &uniform_fixed(n, e);
+            let config = MatchConfig::exact(15);
+            let matches = find_matches(a, b, config, false);
+
+            c.bench_function(&format!("{e}/find_matches"), |bb| {
+                bb.iter(|| find_matches(a, b, config, false))
+            });
+
+            // Matches are sorted by start, and contours need arrows in reverse order.
+            let arrows = matches
+                .matches
+                .iter()
+                .rev()
+                .map(|m| Arrow {
+                    start: m.start,
+                    end: m.end,
+                    score: m.score(),
+                })
+                .collect::<Vec<_>>();
+            c.bench_function(&format!("{e}/contours"), |bb| {
+                bb.iter(|| {
+                    HintContours::<RotateToFrontContour>::new(arrows.iter().cloned(), config.r as I)
+                })
+            });
+
+            let h = GCSH::new(config, Pruning::start());
+            c.bench_function(&format!("{e}/build"), |bb| bb.iter(|| h.build(a, b)));
+
+            // Queries along the main diagonal, reusing the hint as A* does.
+            let hi = h.build(a, b);
+            c.bench_function(&format!("{e}/h_with_hint"), |bb| {
+                bb.iter(|| {
+                    let mut hint = Default::default();
+                    let mut sum = 0;
+                    for i in 0..a.len().min(b.len()) as I {
+                        let (h, new_hint) = hi.h_with_hint(Pos(i, i), hint);
+                        hint = new_hint;
+                        sum += h;
+                    }
+                    sum
+                })
+            });
+
+            // Prune all matches by their start, from the end backwards.
+            c.bench_function(&format!("{e}/prune"), |bb| {
+                bb.iter_batched(
+                    || h.build(a, b),
+                    |mut hi| {
+                        for m in matches.matches.iter().rev() {
+                            let (_, hint) = hi.h_with_hint(m.start, Default::default());
+                            hi.prune(m.start, hint);
+                        }
+                        hi
+                    },
+                    BatchSize::LargeInput,
+                )
+            });
+        }
+    }
+}
+
+criterion_group!(
+    name = benches;
+    config = Criterion::default().measurement_time(Duration::from_millis(1000)).warm_up_time(Duration::from_millis(1000)).sample_size(10);
+    targets = bench
+);
+criterion_main!(benches);
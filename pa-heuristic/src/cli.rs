@@ -95,6 +95,13 @@ pub struct HeuristicParams {
     #[clap(long, hide_short_help = true)]
     #[serde(default)]
     pub skip_prune: Option<usize>,
+
+    /// Start a seed every this many positions, so that seeds overlap.
+    ///
+    /// Must be smaller than k. h is the max over the disjoint seedings.
+    #[clap(long, hide_short_help = true, value_parser = clap::value_parser!(I).range(1..))]
+    #[serde(default)]
+    pub seed_stride: Option<I>,
 }
 
 impl Default for HeuristicParams {
@@ -109,6 +116,7 @@ impl Default for HeuristicParams {
             kmax: None,
             max_matches: None,
             skip_prune: None,
+            seed_stride: None,
         }
     }
 }
@@ -163,9 +171,31 @@ pub trait HeuristicMapper {
 }
 
 impl HeuristicParams {
-    /// Apply a generic function F to the instantiated heuristic.
-    pub fn map<F: HeuristicMapper>(&self, f: F) -> F::R {
-        let match_config = MatchConfig {
+    /// Check the constraints between arguments that can not be checked while
+    /// parsing them, i.e. that `seed_stride` is smaller than `k`.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(stride) = self.seed_stride
+            && !(0 < stride && stride < self.k)
+        {
+            return Err(format!(
+                "--seed-stride must be in 1..k, but is {stride} for k = {}",
+                self.k
+            ));
+        }
+        Ok(())
+    }
+
+    /// Call `f` on the seed heuristic `h`, with overlapping seeds when `seed_stride` is set.
+    fn call_seeds<F: HeuristicMapper, H: Heuristic + 'static>(&self, f: F, h: H) -> F::R {
+        match self.seed_stride {
+            Some(stride) => f.call(OverlappingSeeds::new(h, stride)),
+            None => f.call(h),
+        }
+    }
+
+    /// The seed and match configuration described by these parameters.
+    pub fn match_config(&self) -> MatchConfig {
+        MatchConfig {
             length: if let Some(max) = self.max_matches {
                 LengthConfig::Max(crate::matches::MaxMatches {
                     max_matches: max,
@@ -177,19 +207,29 @@ impl HeuristicParams {
             },
             r: self.r,
             local_pruning: self.p,
-        };
-        let pruning = Pruning {
+        }
+    }
+
+    /// The pruning configuration described by these parameters.
+    pub fn pruning(&self) -> Pruning {
+        Pruning {
             enabled: self.prune,
             skip_prune: self.skip_prune,
-        };
+        }
+    }
+
+    /// Apply a generic function F to the instantiated heuristic.
+    pub fn map<F: HeuristicMapper>(&self, f: F) -> F::R {
+        let match_config = self.match_config();
+        let pruning = self.pruning();
         match self.heuristic {
             HeuristicType::None => f.call(NoCost),
             HeuristicType::Zero => f.call(ZeroCost),
             HeuristicType::Gap => f.call(GapCost),
             HeuristicType::Frequency => f.call(CountCost),
-            HeuristicType::SH => f.call(SH::new(match_config, pruning)),
-            HeuristicType::CSH => f.call(CSH::new(match_config, pruning)),
-            HeuristicType::GCSH => f.call(GCSH::new(match_config, pruning)),
+            HeuristicType::SH => self.call_seeds(f, SH::new(match_config, pruning)),
+            HeuristicType::CSH => self.call_seeds(f, CSH::new(match_config, pruning)),
+            HeuristicType::GCSH => self.call_seeds(f, GCSH::new(match_config, pruning)),
             // bruteforce variants
             HeuristicType::BruteForceGapCost => f.call(BruteForceGCSH {
                 match_config,
@@ -204,3 +244,24 @@ impl HeuristicParams {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seed_stride() {
+        let parse = |args: &[&str]| HeuristicParams::try_parse_from([&["test"][..], args].concat());
+        assert!(parse(&["--seed-stride", "0"]).is_err());
+        let params = parse(&["-k", "10", "--seed-stride", "5"]).unwrap();
+        assert_eq!(params.validate(), Ok(()));
+        let params = parse(&["-k", "10", "--seed-stride", "10"]).unwrap();
+        assert!(params.validate().is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn overlapping_seeds_stride_at_least_k() {
+        OverlappingSeeds::new(GCSH::new(MatchConfig::exact(10), Pruning::disabled()), 10);
+    }
+}
@@ -105,6 +105,11 @@ pub trait Contours: Default + Debug {
         unimplemented!();
     }
 
+    /// Replace all arrows starting in a column before `i` by `arrows`, and
+    /// compute their layers. Arrows starting at or after `i` keep their layers.
+    /// NOTE: Arrows must be reverse sorted by start, and start before `i`.
+    fn replace_before(&mut self, i: I, arrows: impl IntoIterator<Item = Arrow>);
+
     /// The value of the contour this point is on.
     /// Hint is guaranteed to be for the current position.
     fn score(&self, q: Pos) -> Cost;
@@ -135,6 +140,27 @@ pub trait Contours: Default + Debug {
         arrows: F,
     ) -> (bool, Cost);
 
+    /// Remove all arrows starting at the given positions, e.g. all matches
+    /// starting in a column of seed starts.
+    /// Positions are sorted left to right, and `hint` is for the first one.
+    /// Returns true when at least one point was removed, and the number of
+    /// layers removed when pruning the last position, as for `prune_with_hint`.
+    fn prune_block<R: Iterator<Item = Arrow>, F: Fn(&Pos) -> Option<R>>(
+        &mut self,
+        ps: &[Pos],
+        hint: Self::Hint,
+        arrows: F,
+    ) -> (bool, Cost) {
+        let mut pruned = false;
+        let mut shift = 0;
+        for &p in ps {
+            let (p_pruned, p_shift) = self.prune_with_hint(p, hint, &arrows);
+            pruned |= p_pruned;
+            shift = p_shift;
+        }
+        (pruned, shift)
+    }
+
     /// Update layers starting at layer `v`, continuing at least to layer `last_change`.
     /// Stop when contours are fully left of `right_of`.
     fn update_layers<R: Iterator<Item = Arrow>, F: Fn(&Pos) -> Option<R>>(
@@ -90,12 +90,20 @@ impl Contours for BruteForceContours {
             valued_arrows: Vec::default(),
         };
         for arrow in arrows {
-            let val = this.score(arrow.end) + arrow.score as Cost;
+            let val = crate::util::add_cost(this.score(arrow.end), arrow.score as Cost);
             this.valued_arrows.push((arrow, val));
         }
         this
     }
 
+    fn replace_before(&mut self, i: I, arrows: impl IntoIterator<Item = Arrow>) {
+        self.valued_arrows.retain(|(a, _)| a.start.0 >= i);
+        for arrow in arrows {
+            let val = crate::util::add_cost(self.score(arrow.end), arrow.score as Cost);
+            self.valued_arrows.push((arrow, val));
+        }
+    }
+
     fn score(&self, q: Pos) -> Cost {
         self.valued_arrows
             .iter()
@@ -4,6 +4,7 @@ use itertools::Itertools;
 
 use super::*;
 use crate::{prelude::*, split_vec::SplitVec, PRINT};
+use tracing::{debug, trace, Level};
 
 const D: bool = false;
 
@@ -11,6 +12,9 @@ const D: bool = false;
 #[derive(Default, Debug)]
 pub struct HintContours<C: Contour> {
     contours: SplitVec<C>,
+    /// The max score of an arrow, i.e. the number of layers a point can depend on.
+    /// Arrows may have different scores (e.g. for MEMs or chained matches),
+    /// so this is the max over all arrows, and at least the given `max_len`.
     // TODO: This should have units in the transformed domain instead.
     max_len: Layer,
     stats: RefCell<HintContourStats>,
@@ -89,28 +93,76 @@ impl Shift {
 }
 
 impl<C: Contour> HintContours<C> {
+    /// Push the arrows in order, computing the layer of each start from the
+    /// layers already present.
+    /// NOTE: Arrows must be reverse sorted by start, and start before all points
+    /// already present.
+    fn push_arrows(
+        &mut self,
+        arrows: impl IntoIterator<Item = Arrow>,
+        mut filter: impl FnMut(&Arrow, Cost) -> bool,
+    ) {
+        // Loop over all arrows from a given positions.
+        for (start, pos_arrows) in &arrows.into_iter().group_by(|a| a.start) {
+            let mut v = 0;
+            let mut l = 0;
+            // TODO: The self.score() could also be implemented using a fenwick tree, as done in LCSk++.
+            for a in pos_arrows {
+                let nv = crate::util::add_cost(self.score(a.end), a.score as Cost);
+                // Filter out arrows where filter returns false.
+                if !filter(&a, nv) {
+                    continue;
+                }
+                v = max(v, nv as Layer);
+                l = max(l, a.score);
+            }
+            if v == 0 {
+                // All arrows at pos filtered out.
+                continue;
+            }
+            self.max_len = max(self.max_len, l as Layer);
+            // Pushing also works after pruning removed layers.
+            while (self.contours.len() as Layer) <= v {
+                self.contours.push(C::with_max_len(self.max_len as I));
+            }
+            self.contours[v].push(start);
+        }
+    }
+
+    /// The layer containing point `p`, if it is still present, and the updated hint.
+    fn layer_of(&self, p: Pos, hint: Hint) -> Option<(Layer, Hint)> {
+        let (v, hint) = self.score_with_hint(p, hint);
+        let v = v as Layer;
+        // NOTE: The chain score of the point can actually be anywhere in v-max_len+1..=v.
+        // TODO: Figure out why not v - self.max_len + 1. The layer v - self.max_len is really needed sometimes.
+        (v.saturating_sub(self.max_len)..=v)
+            .rev()
+            .find(|&w| self.contours[w].contains_equal(p))
+            .map(|w| (w, hint))
+    }
+
     fn debug<R: Iterator<Item = Arrow>, F: Fn(&Pos) -> Option<R>>(
         &self,
         pos: Pos,
         v: Layer,
         arrows: &F,
     ) {
-        eprintln!("BEFORE PRUNE of {pos} layer {v}");
+        trace!("BEFORE PRUNE of {pos} layer {v}");
         let radius = 4;
         // For each layer around the current one, print:
         for layer in max(v.saturating_sub(radius), 1)..min(v + radius, self.contours.len() as Layer)
         {
             // - the positions in the layer
-            eprintln!("LAYER {layer}");
+            trace!("LAYER {layer}");
             self.contours[layer].iterate_points(|p: Pos| {
                 let max_len = arrows(&p).map_or(0, |arrows| {
                     arrows.map(|a| a.score).max().expect("Empty arrows")
                 });
-                eprintln!("Max len: {max_len}");
+                trace!("Max len: {max_len}");
                 // - the arrows starting at each position.
                 arrows(&p).map(|arrows| {
                     for a in arrows {
-                        eprintln!("{a}");
+                        trace!("{a}");
                     }
                 });
                 // assert!(
@@ -196,7 +248,7 @@ fn chain_score<C: Contour, R: Iterator<Item = Arrow>, F: Fn(&Pos) -> Option<R>>(
                 }
             });
             let witness = witness.unwrap();
-            println!("Arrow {arrow}: {end_layer}=>{start_layer} by {witness:?}");
+            trace!("Arrow {arrow}: {end_layer}=>{start_layer} by {witness:?}");
         }
         max_score = max(max_score, start_layer);
     }
@@ -213,7 +265,7 @@ impl<C: Contour> Contours for HintContours<C> {
     fn new_with_filter(
         arrows: impl IntoIterator<Item = Arrow>,
         max_len: Cost,
-        mut filter: impl FnMut(&Arrow, Cost) -> bool,
+        filter: impl FnMut(&Arrow, Cost) -> bool,
     ) -> Self {
         let mut this = HintContours {
             contours: {
@@ -226,34 +278,22 @@ impl<C: Contour> Contours for HintContours<C> {
             layers_removed: 0,
         };
         this.contours[0usize].push(Pos(I::MAX, I::MAX));
-        // Loop over all arrows from a given positions.
-        for (start, pos_arrows) in &arrows.into_iter().group_by(|a| a.start) {
-            let mut v = 0;
-            let mut l = 0;
-            // TODO: The this.score() could also be implemented using a fenwick tree, as done in LCSk++.
-            for a in pos_arrows {
-                let nv = this.score(a.end) + a.score as Cost;
-                // Filter out arrows where filter returns false.
-                if !filter(&a, nv) {
-                    continue;
-                }
-                v = max(v, nv as Layer);
-                l = max(l, a.score);
-            }
-            if v == 0 {
-                // All arrows at pos filtered out.
-                continue;
-            }
-            if (this.contours.len() as Layer) <= v {
-                this.contours
-                    .resize_with(v as usize + 1, || C::with_max_len(max_len));
-            }
-            this.contours[v].push(start);
-        }
-
+        this.push_arrows(arrows, filter);
         this
     }
 
+    fn replace_before(&mut self, i: I, arrows: impl IntoIterator<Item = Arrow>) {
+        for v in 1..self.contours.len() {
+            self.contours[v].prune_filter(&mut |p| p.0 < i);
+        }
+        // The remaining points are exactly those built from the arrows at or
+        // after `i`, so only the topmost layers can be empty.
+        while self.contours.len() > 1 && self.contours[self.contours.len() - 1].len() == 0 {
+            self.contours.remove(self.contours.len() - 1);
+        }
+        self.push_arrows(arrows, |_, _| true);
+    }
+
     /// The max sum of arrows starting at pos
     fn score(&self, q: Pos) -> Cost {
         // score >= low is known
@@ -352,22 +392,12 @@ impl<C: Contour> Contours for HintContours<C> {
         arrows: F,
     ) -> (bool, Cost) {
         // Work contour by contour.
-        let v = self.score_with_hint(p, hint).0 as Layer;
-        // NOTE: The chain score of the point can actually be anywhere in v-max_len+1..=v.
-        let v = 'v: {
-            // TODO: Figure out why not v - self.max_len + 1. The layer v - self.max_len is really needed sometimes.
-            for w in (v.saturating_sub(self.max_len)..=v).rev() {
-                if self.contours[w].contains_equal(p) {
-                    break 'v w;
-                }
-            }
+        let Some((v, _)) = self.layer_of(p, hint) else {
             // point is not present anymore anyway.
             return (false, 0);
-            // self.debug(p, v, &arrows);
-            // panic!("Did not find point {p} in contours around {v}!");
         };
         if D {
-            eprintln!("Pruning {p} in layer {v}");
+            trace!("Pruning {p} in layer {v}");
             self.debug(p, v, &arrows);
         }
 
@@ -445,7 +475,7 @@ impl<C: Contour> Contours for HintContours<C> {
         };
         if initial_shift > 0 {
             if D {
-                eprintln!("THIS WAS LAST ARROW IN LAYER {v}. SHIFT DOWN BY {initial_shift}");
+                trace!("THIS WAS LAST ARROW IN LAYER {v}. SHIFT DOWN BY {initial_shift}");
             }
             self.stats.borrow_mut().shifts += initial_shift;
         }
@@ -456,6 +486,62 @@ impl<C: Contour> Contours for HintContours<C> {
         (true, initial_shift as _)
     }
 
+    // NOTE: The set of arrows must already been pruned by the caller.
+    // All points but the last are moved down first, followed by a single pass
+    // over the layers, instead of one pass per point. The last point is pruned
+    // by `prune_with_hint`, which also finds the number of removed layers.
+    fn prune_block<R: Iterator<Item = Arrow>, F: Fn(&Pos) -> Option<R>>(
+        &mut self,
+        ps: &[Pos],
+        mut hint: Self::Hint,
+        arrows: F,
+    ) -> (bool, Cost) {
+        let Some((&last, ps)) = ps.split_last() else {
+            return (false, 0);
+        };
+
+        let mut first_to_check = Layer::MAX;
+        let mut last_change = 0;
+        for &p in ps {
+            let Some((v, new_hint)) = self.layer_of(p, hint) else {
+                continue;
+            };
+            hint = new_hint;
+            assert!(v > 0);
+            self.stats.borrow_mut().prunes += 1;
+
+            // The layers of other points in `ps` may not be updated yet, so
+            // this is an upper bound on the new score that is fixed by
+            // `update_layers` below.
+            let new_p_score = chain_score(&arrows, p, v, &self.contours);
+            if new_p_score == Some(v) {
+                continue;
+            }
+            if !self.contours[v].prune(p) {
+                self.debug(p, v, &arrows);
+                panic!("Pruning {p} from layer {v} failed!");
+            }
+            if let Some(new_p_score) = new_p_score {
+                self.contours[new_p_score].push(p)
+            }
+            first_to_check = min(first_to_check, new_p_score.unwrap_or(v) + 1);
+            last_change = max(last_change, v);
+        }
+        let pruned = first_to_check != Layer::MAX;
+        if pruned {
+            self.update_layers(
+                first_to_check,
+                last_change,
+                &arrows,
+                None::<(_, fn(_) -> _)>,
+            );
+            self.check_consistency(&arrows);
+        }
+
+        let (last_pruned, shift) = self.prune_with_hint(last, hint, &arrows);
+        (pruned || last_pruned, shift)
+    }
+
     /// Update layers starting at layer `v`, continuing at least to layer `last_change`.
     fn update_layers<R: Iterator<Item = Arrow>, F: Fn(&Pos) -> Option<R>>(
         &mut self,
@@ -472,7 +558,7 @@ impl<C: Contour> Contours for HintContours<C> {
         v = max(v, 1);
 
         if PRINT {
-            eprintln!("update_layers({v}, {last_change})");
+            trace!("update_layers({v}, {last_change})");
         }
         last_change = max(last_change, v);
         let chain_score = |contours: &SplitVec<C>, pos: Pos, v: Layer| -> Option<Layer> {
@@ -519,7 +605,7 @@ impl<C: Contour> Contours for HintContours<C> {
                 // Value v is still up to date. No need to loop over the remaining arrows starting here.
                 if new_layer == v {
                     if D {
-                        eprintln!("f: Push {pos} from {v} to at least {new_layer}");
+                        trace!("f: Push {pos} from {v} to at least {new_layer}");
                     }
                     self.stats.borrow_mut().checked_false += 1;
                     current_shift = Shift::Inconsistent;
@@ -530,7 +616,7 @@ impl<C: Contour> Contours for HintContours<C> {
 
                 // Either no arrows left (position already pruned), or none of its arrows yields value v.
                 if D {
-                    eprintln!("f: Push {pos} from {v} to {new_layer} shift {current_shift:?}");
+                    trace!("f: Push {pos} from {v} to {new_layer} shift {current_shift:?}");
                 }
                 self.contours[new_layer].push(pos);
 
@@ -565,7 +651,7 @@ impl<C: Contour> Contours for HintContours<C> {
                     || rolling_shift == current_shift
                 {
                     if D {
-                        eprintln!("EMPTIED LAYER {v}");
+                        trace!("EMPTIED LAYER {v}");
                     }
                     fully_shifted_layers += 1;
                     if rolling_shift == Shift::None {
@@ -594,14 +680,14 @@ impl<C: Contour> Contours for HintContours<C> {
                 // There are some tests that cover this.
                 if fully_shifted_layers >= self.max_len + shift - 1 {
                     if D {
-                        eprintln!("REMOVE {shift} CONTOURS, since {fully_shifted_layers} >= {}+{shift}-1 have shifted by {shift}", self.max_len);
+                        trace!("REMOVE {shift} CONTOURS, since {fully_shifted_layers} >= {}+{shift}-1 have shifted by {shift}", self.max_len);
                     }
                     // Shift all other contours one down.
                     self.stats.borrow_mut().shift_layers += 1;
 
                     for _ in 0..shift {
                         if D {
-                            eprintln!("REMOVE CONTOUR {v}");
+                            trace!("REMOVE CONTOUR {v}");
                         }
                         assert!(self.contours[v].len() == 0);
                         self.contours.remove(v as usize);
@@ -631,16 +717,11 @@ impl<C: Contour> Contours for HintContours<C> {
         self.print_stats();
     }
 
-    #[allow(unreachable_code, unused_variables)]
+    /// Emit the statistics of the contours as `debug` events.
     fn print_stats(&mut self) {
-        if !PRINT {
+        if !tracing::enabled!(Level::DEBUG) {
             return;
         }
-        // TODO: MAKE A FLAG FOR THIS.
-        // eprintln!("----------------------------");
-        // if self.stats.borrow().prunes > 0 {
-        //     return;
-        // }
         let mut num = 0;
         let mut total_len = 0;
         let mut total_dom = 0;
@@ -651,9 +732,12 @@ impl<C: Contour> Contours for HintContours<C> {
             total_len += c.len();
             total_dom += c.num_dominant();
         }
-        eprintln!("#contours             {}", num);
-        eprintln!("avg size              {}", total_len as f32 / num as f32);
-        eprintln!("avg domn              {}", total_dom as f32 / num as f32);
+        debug!(
+            contours = num,
+            avg_size = total_len as f32 / num as f32,
+            avg_dominant = total_dom as f32 / num as f32,
+            "Contours"
+        );
 
         let HintContourStats {
             prunes,
@@ -672,48 +756,132 @@ impl<C: Contour> Contours for HintContours<C> {
             score_with_hint_calls,
         }: HintContourStats = *self.stats.borrow();
 
-        return;
         if prunes == 0 {
             return;
         }
-
-        eprintln!("#prunes               {}", prunes);
-        eprintln!("contours per prune    {}", contours as f32 / prunes as f32);
-        eprintln!("#checks               {}", checked);
-        eprintln!("checked per prune     {}", checked as f32 / prunes as f32);
-        eprintln!(
-            "checked true per p    {}",
-            checked_true as f32 / prunes as f32
-        );
-        eprintln!(
-            "checked false per p   {}",
-            checked_false as f32 / prunes as f32
-        );
-        eprintln!(
-            "shift per check true  {}",
-            sum_prune_shifts as f32 / num_prune_shifts as f32
-        );
-        eprintln!("max shift             {}", max_prune_shift);
-        eprintln!("Stop count: no change    {}", no_change);
-        eprintln!("Stop count: shift layers {}", shift_layers);
-        eprintln!("layers removed total     {}", self.layers_removed);
-        eprintln!(
-            "layers removed change    {}",
-            self.layers_removed as usize - shift_layers
+        trace!(
+            prunes,
+            contours_per_prune = contours as f32 / prunes as f32,
+            checks = checked,
+            checked_per_prune = checked as f32 / prunes as f32,
+            checked_true_per_prune = checked_true as f32 / prunes as f32,
+            checked_false_per_prune = checked_false as f32 / prunes as f32,
+            shift_per_check_true = sum_prune_shifts as f32 / num_prune_shifts as f32,
+            max_prune_shift,
+            stop_no_change = no_change,
+            stop_shift_layers = shift_layers,
+            layers_removed = self.layers_removed,
+            layers_removed_change = self.layers_removed as usize - shift_layers,
+            shifts,
+            score_with_hint_calls,
+            binary_search_fallback_per_call =
+                binary_search_fallback as f32 / score_with_hint_calls as f32,
+            contains_calls_per_call = contains_calls as f32 / score_with_hint_calls as f32,
+            "Contour pruning"
         );
+    }
+}
 
-        eprintln!("#shifts                  {}", shifts);
-        eprintln!("");
-        eprintln!("score_hint calls         {}", score_with_hint_calls);
-        eprintln!(
-            "binary search flbck/call {}",
-            binary_search_fallback as f32 / score_with_hint_calls as f32
-        );
-        eprintln!(
-            "avg contains calls       {}",
-            contains_calls as f32 / score_with_hint_calls as f32
-        );
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::contour::rotate_to_front::RotateToFrontContour;
+
+    /// Arrows with scores up to 3, by start position.
+    fn arrows() -> HashMap<Pos, Vec<Arrow>> {
+        let mut arrows = HashMap::<Pos, Vec<Arrow>>::default();
+        for k in 0..20 {
+            for (d, score) in [(0, 1 + k % 3), (3, 1)] {
+                let start = Pos(10 * k + d, 10 * k);
+                let end = start + Pos(3 * score, 3 * score);
+                // For consistency, each arrow of score `s` comes with arrows
+                // of score `s-t` starting `t` indels later.
+                for t in 0..score {
+                    let start = start + Pos(t, 0);
+                    let score = (score - t) as MatchCost;
+                    arrows
+                        .entry(start)
+                        .or_default()
+                        .push(Arrow { start, end, score });
+                }
+            }
+        }
+        arrows
+    }
 
-        eprintln!("----------------------------");
+    /// HintContours and BruteForceContours built from the arrows, with `max_len` 1.
+    fn build(
+        arrows: &HashMap<Pos, Vec<Arrow>>,
+    ) -> (HintContours<RotateToFrontContour>, BruteForceContours) {
+        let sorted = || {
+            arrows
+                .values()
+                .flatten()
+                .cloned()
+                .sorted_by_key(|a| LexPos(a.start))
+                .rev()
+                .collect_vec()
+        };
+        (
+            HintContours::new(sorted(), 1),
+            BruteForceContours::new(sorted(), 1),
+        )
+    }
+
+    fn check(hint: &HintContours<RotateToFrontContour>, bruteforce: &BruteForceContours) {
+        for i in 0..220 {
+            for j in 0..220 {
+                let q = Pos(i, j);
+                assert_eq!(hint.score(q), bruteforce.score(q), "{q}");
+            }
+        }
+    }
+
+    #[test]
+    fn variable_length_arrows() {
+        let mut arrows = arrows();
+        let (mut hint, mut bruteforce) = build(&arrows);
+        check(&hint, &bruteforce);
+
+        for k in (0..20).rev().step_by(3) {
+            let p = Pos(10 * k, 10 * k);
+            arrows.remove(&p);
+            let arrows = |p: &Pos| arrows.get(p).map(|a| a.iter().cloned());
+            hint.prune_with_hint(p, Default::default(), arrows);
+            bruteforce.prune_with_hint(p, Default::default(), arrows);
+            check(&hint, &bruteforce);
+        }
+    }
+
+    #[test]
+    fn prune_block() {
+        let mut arrows = arrows();
+        let (mut hint, mut bruteforce) = build(&arrows);
+
+        // Pruned one position at a time.
+        let (mut sequential, _) = build(&arrows);
+
+        for k in [15, 8, 2] {
+            let ps = [
+                Pos(10 * k, 10 * k),
+                Pos(10 * k + 1, 10 * k),
+                Pos(10 * k + 3, 10 * k),
+            ];
+            for p in &ps {
+                arrows.remove(p);
+            }
+            let arrows = |p: &Pos| arrows.get(p).map(|a| a.iter().cloned());
+            let (pruned, shift) = hint.prune_block(&ps, Default::default(), arrows);
+            assert!(pruned);
+            bruteforce.prune_block(&ps, Default::default(), arrows);
+            check(&hint, &bruteforce);
+
+            let mut last_shift = 0;
+            for &p in &ps {
+                last_shift = sequential.prune_with_hint(p, Default::default(), arrows).1;
+            }
+            assert_eq!(shift, last_shift, "{k}");
+            check(&sequential, &bruteforce);
+        }
     }
 }
@@ -0,0 +1,170 @@
+//! Conversions between positions, diagonals, and antidiagonals, and intervals
+//! of positions.
+//!
+//! Conventions:
+//! - `i` indexes `a` and is horizontal, `j` indexes `b` and is vertical.
+//! - The diagonal of `Pos(i, j)` is `i - j`, so diagonals above the main
+//!   diagonal are positive.
+//! - The antidiagonal of `Pos(i, j)` is `i + j`.
+//!
+//! `pa_types` is an external crate, so the `Pos` helpers are an extension trait.
+use crate::prelude::*;
+use std::ops::Range;
+
+pub trait PosExt: Sized {
+    /// `i - j`.
+    fn diagonal(self) -> I;
+    /// `i + j`.
+    fn antidiagonal(self) -> I;
+    /// The position on the given diagonal and antidiagonal.
+    /// These should have the same parity; otherwise the result is rounded towards 0.
+    fn from_diagonals(diagonal: I, antidiagonal: I) -> Self;
+    /// Clamp the position into the DP matrix `0..=a.len()` by `0..=b.len()`.
+    fn clamp_to(self, a: Seq, b: Seq) -> Self;
+}
+
+impl PosExt for Pos {
+    #[inline]
+    fn diagonal(self) -> I {
+        self.0 - self.1
+    }
+    #[inline]
+    fn antidiagonal(self) -> I {
+        self.0 + self.1
+    }
+    #[inline]
+    fn from_diagonals(diagonal: I, antidiagonal: I) -> Self {
+        Pos((antidiagonal + diagonal) / 2, (antidiagonal - diagonal) / 2)
+    }
+    #[inline]
+    fn clamp_to(self, a: Seq, b: Seq) -> Self {
+        Pos(self.0.clamp(0, a.len() as I), self.1.clamp(0, b.len() as I))
+    }
+}
+
+/// A half-open interval `start..end` of positions in one sequence.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Interval {
+    pub start: I,
+    pub end: I,
+}
+
+impl Interval {
+    pub fn new(start: I, end: I) -> Self {
+        Self { start, end }
+    }
+    /// The full range `0..len` of a sequence.
+    pub fn of(s: Seq) -> Self {
+        Self::new(0, s.len() as I)
+    }
+    /// The number of positions, or 0 when empty.
+    pub fn len(&self) -> I {
+        max(self.end - self.start, 0)
+    }
+    pub fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+    pub fn contains(&self, x: I) -> bool {
+        self.start <= x && x < self.end
+    }
+    /// The positions in both intervals. May be empty.
+    pub fn intersect(self, other: Self) -> Self {
+        Self::new(max(self.start, other.start), min(self.end, other.end))
+    }
+    /// The smallest interval containing both intervals.
+    pub fn hull(self, other: Self) -> Self {
+        Self::new(min(self.start, other.start), max(self.end, other.end))
+    }
+}
+
+impl From<Range<I>> for Interval {
+    fn from(r: Range<I>) -> Self {
+        Self::new(r.start, r.end)
+    }
+}
+
+impl From<Interval> for Range<I> {
+    fn from(r: Interval) -> Self {
+        r.start..r.end
+    }
+}
+
+/// A rectangle of the DP matrix, aligning the query range `a[i]` to the target range `b[j]`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub i: Interval,
+    pub j: Interval,
+}
+
+impl Rect {
+    /// The rectangle from `start` up to `end`, exclusive.
+    pub fn new(start: Pos, end: Pos) -> Self {
+        Self {
+            i: Interval::new(start.0, end.0),
+            j: Interval::new(start.1, end.1),
+        }
+    }
+    /// The full alignment of `a` and `b`.
+    pub fn of(a: Seq, b: Seq) -> Self {
+        Self {
+            i: Interval::of(a),
+            j: Interval::of(b),
+        }
+    }
+    pub fn start(&self) -> Pos {
+        Pos(self.i.start, self.j.start)
+    }
+    pub fn end(&self) -> Pos {
+        Pos(self.i.end, self.j.end)
+    }
+    pub fn contains(&self, p: Pos) -> bool {
+        self.i.contains(p.0) && self.j.contains(p.1)
+    }
+    /// The range of diagonals that intersect the rectangle.
+    pub fn diagonals(&self) -> Range<I> {
+        self.i.start - (self.j.end - 1)..self.i.end - self.j.start
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn diagonals() {
+        for i in 0..10 {
+            for j in 0..10 {
+                let p = Pos(i, j);
+                assert_eq!(Pos::from_diagonals(p.diagonal(), p.antidiagonal()), p);
+            }
+        }
+        // Above the main diagonal is positive.
+        assert_eq!(Pos(3, 1).diagonal(), 2);
+        assert_eq!(Pos(1, 3).diagonal(), -2);
+        assert_eq!(Pos(-2, 12).clamp_to(b"ACG", b"ACGT"), Pos(0, 4));
+    }
+
+    #[test]
+    fn intervals() {
+        let x = Interval::from(2..5);
+        let y = Interval::new(4, 8);
+        assert_eq!(x.len(), 3);
+        assert!(x.contains(4) && !x.contains(5));
+        assert_eq!(x.intersect(y), Interval::new(4, 5));
+        assert_eq!(x.hull(y), Interval::new(2, 8));
+        assert!(Interval::new(1, 3)
+            .intersect(Interval::new(5, 6))
+            .is_empty());
+        assert_eq!(Interval::new(5, 3).len(), 0);
+
+        let r = Rect::new(Pos(2, 1), Pos(5, 3));
+        assert!(r.contains(Pos(4, 2)) && !r.contains(Pos(5, 2)));
+        let d = r.diagonals();
+        for i in r.i.start..r.i.end {
+            for j in r.j.start..r.j.end {
+                assert!(d.contains(&Pos(i, j).diagonal()));
+            }
+        }
+        assert_eq!(d, 0..4);
+    }
+}
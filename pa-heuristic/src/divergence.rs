@@ -0,0 +1,48 @@
+//! Fast estimation of the divergence between two sequences from shared k-mers.
+use crate::matches::qgrams::QGrams;
+use crate::prelude::*;
+use rustc_hash::FxHashSet as HashSet;
+
+/// The k-mer length used by `estimate_divergence`.
+const K: I = 12;
+
+/// Estimate the fraction of errors between `a` and `b`, using the fraction of
+/// disjoint k-mers of `a` that occur in `b`.
+///
+/// Each k-mer of `a` survives with probability `(1-e)^k`. Spurious matches,
+/// expected with probability `|b|/4^k`, are corrected for.
+/// The estimate is in `[0, 1]`, and `0` when either sequence is shorter than `k`.
+/// NOTE: This assumes an alphabet of 'ACGT'.
+pub fn estimate_divergence(a: Seq, b: Seq) -> f32 {
+    if a.len() < K as usize || b.len() < K as usize {
+        return 0.;
+    }
+    let qgrams = QGrams::new(a, b);
+    let b_kmers: HashSet<usize> = qgrams.b_qgrams(K).map(|(_, q)| q).collect();
+    let mut total = 0;
+    let mut found = 0;
+    for (_, q) in qgrams.a_qgrams(K) {
+        total += 1;
+        found += b_kmers.contains(&q) as usize;
+    }
+    let p_random = (b.len() as f32 / 4f32.powi(K)).min(0.99);
+    let p_survive = ((found as f32 / total as f32 - p_random) / (1. - p_random)).max(0.);
+    1. - p_survive.powf(1. / K as f32)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn estimate() {
+        for e in [0.0, 0.05, 0.1, 0.2] {
+            let (a, b) = pa_generate::uniform_seeded(100000, e, 31415);
+            let est = estimate_divergence(&a, &b);
+            assert!((est - e).abs() < 0.05, "e {e} estimated as {est}");
+        }
+        let (a, _) = pa_generate::uniform_seeded(10000, 0., 1);
+        let (b, _) = pa_generate::uniform_seeded(10000, 0., 2);
+        assert!(estimate_divergence(&a, &b) > 0.5);
+    }
+}
@@ -0,0 +1,207 @@
+//! Stable serde formats for `Pos`, `Cigar`, and `AlignResult`, so that results
+//! can be stored and compared across versions of these crates.
+//!
+//! Types from `pa_types` do not implement serde, and their internal layout may
+//! change. The modules below are meant to be used with `#[serde(with = ..)]`
+//! and fix the format instead.
+//!
+//! JSON schema, version 1:
+//! - `Pos`: `[i, j]`.
+//! - `Path`: a list of `Pos`.
+//! - `Cigar`: a string of `<cnt><op>` elements, where `op` is `=` for matches,
+//!   `X` for substitutions, `I` for insertions, and `D` for deletions,
+//!   e.g. `"3=1X2I"`.
+//! - `AlignResult`: `{"version": 1, "cost": .., "cigar": Cigar | null, "path":
+//!   Path | null, "stats": {..}}`, where `stats` contains all fields of
+//!   `AlignStats` by name, with the `HeuristicStats` fields nested under
+//!   `"h"`, and durations are `{"secs": .., "nanos": ..}`.
+//!
+//! New fields may be added to `stats` without changing the version. Any other
+//! change increases `VERSION`.
+use crate::prelude::*;
+use crate::AlignResult;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+/// The current version of the format.
+pub const VERSION: u32 = 1;
+
+/// An `AlignResult` tagged with the version of the format.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Versioned {
+    pub version: u32,
+    #[serde(flatten)]
+    pub result: AlignResult,
+}
+
+impl Versioned {
+    pub fn new(result: AlignResult) -> Self {
+        Self {
+            version: VERSION,
+            result,
+        }
+    }
+
+    /// The result, or an error when it was written in a different version of the format.
+    pub fn into_result(self) -> Result<AlignResult, String> {
+        if self.version != VERSION {
+            return Err(format!(
+                "Unsupported result format version {}; expected {VERSION}",
+                self.version
+            ));
+        }
+        Ok(self.result)
+    }
+}
+
+/// `Pos` as `[i, j]`.
+pub mod pos {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(&Pos(i, j): &Pos, s: S) -> Result<S::Ok, S::Error> {
+        [i, j].serialize(s)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Pos, D::Error> {
+        let [i, j] = <[I; 2]>::deserialize(d)?;
+        Ok(Pos(i, j))
+    }
+}
+
+/// `Option<Path>` as a list of `[i, j]`, or null.
+pub mod path {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(path: &Option<Path>, s: S) -> Result<S::Ok, S::Error> {
+        path.as_ref()
+            .map(|path| path.iter().map(|&Pos(i, j)| [i, j]).collect::<Vec<_>>())
+            .serialize(s)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Path>, D::Error> {
+        let path = Option::<Vec<[I; 2]>>::deserialize(d)?;
+        Ok(path.map(|path| path.into_iter().map(|[i, j]| Pos(i, j)).collect()))
+    }
+}
+
+/// `Option<Cigar>` as a cigar string, or null.
+pub mod cigar {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(cigar: &Option<Cigar>, s: S) -> Result<S::Ok, S::Error> {
+        cigar.as_ref().map(to_string).serialize(s)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Cigar>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|s| parse(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+
+    fn op_char(op: CigarOp) -> char {
+        match op {
+            CigarOp::Match => '=',
+            CigarOp::Sub => 'X',
+            CigarOp::Ins => 'I',
+            CigarOp::Del => 'D',
+        }
+    }
+
+    /// The cigar string in the stable format, independent of `Cigar::to_string`.
+    pub fn to_string(cigar: &Cigar) -> String {
+        cigar
+            .ops
+            .iter()
+            .map(|e| format!("{}{}", e.cnt, op_char(e.op)))
+            .collect()
+    }
+
+    pub fn parse(s: &str) -> Result<Cigar, String> {
+        let mut ops = vec![];
+        let mut cnt: Option<I> = None;
+        for c in s.chars() {
+            if let Some(d) = c.to_digit(10) {
+                cnt = Some(cnt.unwrap_or(0) * 10 + d as I);
+                continue;
+            }
+            let op = match c {
+                '=' => CigarOp::Match,
+                'X' => CigarOp::Sub,
+                'I' => CigarOp::Ins,
+                'D' => CigarOp::Del,
+                _ => return Err(format!("Invalid cigar operation {c:?} in {s:?}")),
+            };
+            let cnt = cnt
+                .take()
+                .ok_or_else(|| format!("Missing count before {c:?} in {s:?}"))?;
+            ops.push(CigarElem { op, cnt });
+        }
+        if cnt.is_some() {
+            return Err(format!("Trailing count in {s:?}"));
+        }
+        Ok(Cigar { ops })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{AlignStats, HeuristicStats};
+    use std::time::Duration;
+
+    #[test]
+    fn cigar_string() {
+        let cigar = cigar::parse("3=1X12I1D").unwrap();
+        assert_eq!(cigar.ops.len(), 4);
+        assert_eq!(cigar.ops[2].op, CigarOp::Ins);
+        assert_eq!(cigar.ops[2].cnt, 12);
+        assert_eq!(cigar::to_string(&cigar), "3=1X12I1D");
+        assert_eq!(cigar::parse("").unwrap().ops.len(), 0);
+        assert!(cigar::parse("3M").is_err());
+        assert!(cigar::parse("=").is_err());
+        assert!(cigar::parse("3=4").is_err());
+    }
+
+    #[test]
+    fn align_result() {
+        let cigar = cigar::parse("2=1X1I").unwrap();
+        let stats = AlignStats {
+            h: HeuristicStats {
+                num_seeds: 3,
+                ..Default::default()
+            },
+            expanded: 10,
+            t_total: Duration::from_millis(1500),
+            ..Default::default()
+        };
+        let result = AlignResult::new(2, Some(cigar), stats);
+        let json = serde_json::to_string(&Versioned::new(result.clone())).unwrap();
+        assert!(
+            json.starts_with(r#"{"version":1,"cost":2,"cigar":"2=1X1I","path":[[0,0],[1,1],"#),
+            "{json}"
+        );
+        assert!(json.contains(r#""t_total":{"secs":1,"nanos":500000000}"#));
+
+        let parsed = serde_json::from_str::<Versioned>(&json)
+            .unwrap()
+            .into_result()
+            .unwrap();
+        assert_eq!(parsed.cost, result.cost);
+        assert_eq!(parsed.cigar, result.cigar);
+        assert_eq!(parsed.path, result.path);
+        assert_eq!(parsed.stats.h.num_seeds, 3);
+        assert_eq!(parsed.stats.expanded, 10);
+        assert_eq!(parsed.stats.t_total, result.stats.t_total);
+
+        let json = json.replacen(r#""version":1"#, r#""version":2"#, 1);
+        assert!(serde_json::from_str::<Versioned>(&json)
+            .unwrap()
+            .into_result()
+            .is_err());
+    }
+
+    #[test]
+    fn failed_result() {
+        let result = AlignResult::new(5, None, AlignStats::default());
+        let json = serde_json::to_string(&Versioned::new(result)).unwrap();
+        assert!(json.contains(r#""cigar":null,"path":null"#), "{json}");
+        let parsed = serde_json::from_str::<Versioned>(&json).unwrap();
+        assert!(parsed.result.cigar.is_none());
+    }
+}
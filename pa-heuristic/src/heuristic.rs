@@ -1,6 +1,7 @@
 pub mod bruteforce_gcsh;
 pub mod csh;
 pub mod distances;
+pub mod landmark;
 pub mod sh;
 pub mod wrappers;
 
@@ -8,15 +9,18 @@ use std::ops::Range;
 
 use crate::matches::*;
 use crate::prelude::*;
-use crate::seeds::Seeds;
+use crate::seeds::{Seed, Seeds};
 use derive_more::AddAssign;
+use serde::{Deserialize, Serialize};
 
 pub use bruteforce_gcsh::*;
 pub use csh::*;
 pub use distances::*;
+pub use landmark::*;
 pub use sh::*;
 
-#[derive(Clone, AddAssign, Default, Copy, Debug)]
+#[derive(Clone, AddAssign, Default, Copy, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct HeuristicStats {
     pub num_seeds: I,
     pub num_matches: usize,
@@ -24,6 +28,8 @@ pub struct HeuristicStats {
     pub num_pruned: usize,
     pub h0: Cost,
     pub h0_end: Cost,
+    /// The number of contour layers at the start, before pruning.
+    pub contour_layers: Cost,
 
     // Timers
     pub prune_duration: f64,
@@ -56,6 +62,34 @@ pub trait Heuristic: std::fmt::Debug + Copy {
         unimplemented!();
     }
 
+    /// Build the heuristic using a precomputed k-mer index of `b`.
+    /// Heuristics that do not use matches simply ignore the index.
+    fn build_with_index<'a>(
+        &self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        _index: &ReferenceIndex,
+    ) -> Self::Instance<'a> {
+        self.build(a, b)
+    }
+
+    /// The seed and match configuration, for heuristics that use matches.
+    fn match_config(&self) -> Option<MatchConfig> {
+        None
+    }
+
+    /// Whether matches are pruned during the search, so that `h` increases
+    /// over time and is not consistent anymore.
+    fn is_pruning(&self) -> bool {
+        false
+    }
+
+    /// Whether `h(u) <= d(u, v) + h(v)` for all states `v` reachable from `u`,
+    /// ignoring pruning.
+    fn is_consistent(&self) -> bool {
+        true
+    }
+
     // Heuristic properties.
     fn name(&self) -> String;
 }
@@ -102,6 +136,21 @@ impl PosOrderT for I {
     }
 }
 
+/// The matches that determine `h(pos)`, as returned by `HeuristicInstance::explain`.
+#[derive(Clone, Debug, Default)]
+pub struct HExplanation {
+    /// The sum of seed potentials from `pos` to the end, i.e. `h` when there are no matches.
+    pub potential: Cost,
+    /// The contour layer of `pos`: the score of the best chain of matches.
+    pub layer: Cost,
+    /// A chain of active matches with total score `layer`, sorted by start.
+    pub chain: Vec<Match>,
+    /// Inactive (e.g. pruned) matches starting after `pos`, which no longer contribute.
+    pub inactive: Vec<Match>,
+    /// Seeds after `pos` without any match starting after `pos`.
+    pub unmatched_seeds: Vec<Seed>,
+}
+
 /// An instantiation of a heuristic for a specific pair of sequences.
 pub trait HeuristicInstance<'a> {
     fn h(&self, pos: Pos) -> Cost;
@@ -125,6 +174,14 @@ pub trait HeuristicInstance<'a> {
         (self.h(pos), Default::default())
     }
 
+    /// `h_with_hint`, using and updating the hint cached for the rows around `pos`.
+    fn h_with_cached_hint(&self, pos: Pos, hints: &mut HintCache<Self::Hint>) -> Cost {
+        let hint = hints.get_mut(pos);
+        let (h, new_hint) = self.h_with_hint(pos, *hint);
+        *hint = new_hint;
+        h
+    }
+
     fn h_with_hint_timed(&mut self, pos: Pos, hint: Self::Hint) -> ((Cost, Self::Hint), f64) {
         (self.h_with_hint(pos, hint), 0.)
     }
@@ -151,7 +208,7 @@ pub trait HeuristicInstance<'a> {
         //unimplemented!();
     }
 
-    /// Update contours from the current minimum changed layer up to the given `_pos`.
+    /// Update the contours for the matches removed by `prune_block`.
     fn update_contours(&mut self, _pos: Pos) {
         //unimplemented!();
     }
@@ -161,6 +218,14 @@ pub trait HeuristicInstance<'a> {
     /// priority queue.
     fn explore(&mut self, _pos: Pos) {}
 
+    /// Re-run match finding for the seeds in `rows` with more permissive
+    /// parameters, for when `h` has collapsed there.
+    /// This must never decrease `h`, so that states in the priority queue only
+    /// have to be retried. Returns true when the heuristic changed.
+    fn reseed(&mut self, _a: Seq, _b: Seq, _rows: Range<I>) -> bool {
+        false
+    }
+
     fn stats(&mut self) -> HeuristicStats {
         Default::default()
     }
@@ -173,12 +238,50 @@ pub trait HeuristicInstance<'a> {
         None
     }
 
+    /// The matches responsible for `h(pos)`, to find out why `h` is weak.
+    /// Only available for heuristics based on chains of matches.
+    fn explain(&self, _pos: Pos) -> Option<HExplanation> {
+        None
+    }
+
     /// A descriptive string of the heuristic settings, used for failing assertions.
     fn params_string(&self) -> String {
         "".into()
     }
 }
 
+/// Hints for `h_with_hint`, cached per band of rows so they can be reused
+/// across blocks of columns.
+///
+/// A hint only helps for nearby positions, while each block queries `h` both
+/// near the top and near the bottom of its range. Keeping one hint per band
+/// avoids jumping back and forth with a single hint.
+#[derive(Clone, Debug)]
+pub struct HintCache<Hint> {
+    band: I,
+    hints: Vec<Hint>,
+}
+
+impl<Hint: Copy + Default> HintCache<Hint> {
+    /// A cache with one hint per `band` rows.
+    pub fn new(band: I) -> Self {
+        assert!(band > 0);
+        Self {
+            band,
+            hints: vec![],
+        }
+    }
+
+    /// The hint for the band containing `pos`.
+    pub fn get_mut(&mut self, pos: Pos) -> &mut Hint {
+        let idx = (max(pos.1, 0) / self.band) as usize;
+        if idx >= self.hints.len() {
+            self.hints.resize(idx + 1, Hint::default());
+        }
+        &mut self.hints[idx]
+    }
+}
+
 impl<'a> HeuristicInstance<'a> for ! {
     type Hint = ();
     type Order = ();
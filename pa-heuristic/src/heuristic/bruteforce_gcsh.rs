@@ -22,6 +22,10 @@ where
         BruteForceGCSHI::new(a, b, *self)
     }
 
+    fn is_pruning(&self) -> bool {
+        self.pruning.is_enabled()
+    }
+
     fn name(&self) -> String {
         "Seed".into()
     }
@@ -86,6 +90,7 @@ where
         h.build();
 
         h.stats.h0 = h.h(Pos(0, 0));
+        h.stats.contour_layers = h.layer(Pos(0, 0)).unwrap();
         h.stats.num_seeds = h.seeds.seeds.len() as _;
         h.stats.num_matches = num_matches;
         h.stats.num_filtered_matches = num_filtered_matches;
@@ -8,6 +8,7 @@ use crate::util::Timer;
 use crate::*;
 use crate::{contour::*, wrappers::EqualHeuristic};
 use std::marker::PhantomData;
+use tracing::{debug, trace};
 
 pub struct CSH<C: Contours> {
     pub match_config: MatchConfig,
@@ -141,7 +142,24 @@ impl<C: Contours> Heuristic for CSH<C> {
         b: Seq<'a>,
         filter: Option<impl FnMut(&Match, Cost) -> bool>,
     ) -> Self::Instance<'a> {
-        CSHI::new(a, b, filter, *self)
+        CSHI::new(a, b, filter, None, *self)
+    }
+
+    fn build_with_index<'a>(
+        &self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        index: &ReferenceIndex,
+    ) -> Self::Instance<'a> {
+        CSHI::new(a, b, None::<fn(&Match, Cost) -> bool>, Some(index), *self)
+    }
+
+    fn match_config(&self) -> Option<MatchConfig> {
+        Some(self.match_config)
+    }
+
+    fn is_pruning(&self) -> bool {
+        self.pruning.is_enabled()
     }
 
     fn name(&self) -> String {
@@ -162,9 +180,9 @@ pub struct CSHI<C: Contours> {
     max_transformed_pos: Pos,
     contours: C,
 
-    /// For block-based pruning, the lowest and highest contour/layer from which matches were removed.
-    lowest_modified_contour: Layer,
-    highest_modified_contour: Layer,
+    /// For block-based pruning, the transformed start positions of the matches
+    /// removed since the last call to `update_contours`.
+    pruned_starts: Vec<Pos>,
 
     stats: HeuristicStats,
 }
@@ -196,14 +214,21 @@ impl<C: Contours> Drop for CSHI<C> {
 impl<C: Contours> CSHI<C> {
     /// `filter` is currently only used for pre-pruning when an optimal path is guessed and all matches on it are pruned directly.
     /// This is not in the paper yet.
+    /// `index` is an optional precomputed k-mer index of `b`.
     fn new(
         a: Seq,
         b: Seq,
         filter: Option<impl FnMut(&Match, Cost) -> bool>,
+        index: Option<&ReferenceIndex>,
         params: CSH<C>,
     ) -> Self {
-        let Matches { seeds, mut matches } =
-            find_matches(a, b, params.match_config, params.use_gap_cost);
+        let Matches { seeds, mut matches } = index
+            .and_then(|index| {
+                index
+                    .find_matches(a, b, params.match_config, params.use_gap_cost)
+                    .ok()
+            })
+            .unwrap_or_else(|| find_matches(a, b, params.match_config, params.use_gap_cost));
         let target = Pos::target(a, b);
         let t_target = if params.use_gap_cost {
             seeds.transform(target)
@@ -290,10 +315,10 @@ impl<C: Contours> CSHI<C> {
             max_transformed_pos: Pos(I::MIN, I::MIN),
 
             contours,
-            lowest_modified_contour: Layer::MAX,
-            highest_modified_contour: Layer::MIN,
+            pruned_starts: vec![],
         };
         h.stats.h0 = h.h(Pos(0, 0));
+        h.stats.contour_layers = h.layer(Pos(0, 0)).unwrap();
         h.stats.num_seeds = h.seeds.seeds.len() as _;
         h.stats.num_matches = num_matches;
         h.stats.num_filtered_matches = num_filtered_matches;
@@ -345,7 +370,7 @@ impl<'a, C: Contours> HeuristicInstance<'a> for CSHI<C> {
         if val == 0 {
             self.distance(pos, self.target)
         } else {
-            p - val
+            util::sub_cost(p, val)
         }
     }
 
@@ -370,7 +395,7 @@ impl<'a, C: Contours> HeuristicInstance<'a> for CSHI<C> {
         let ans = if val == 0 {
             (self.distance(pos, self.target), new_hint)
         } else {
-            (p - val, new_hint)
+            (util::sub_cost(p, val), new_hint)
         };
         ans
     }
@@ -437,29 +462,32 @@ impl<'a, C: Contours> HeuristicInstance<'a> for CSHI<C> {
         // ending in p), breaking the subsequent pruning step.
         pruned_start_positions.sort_by_key(|p| LexPos(*p));
 
-        // TODO: This should be optimized to a single `contours.prune` call.
+        let arrows = |pt: &Pos| {
+            let p = if self.params.use_gap_cost {
+                self.seeds.transform_back(*pt)
+            } else {
+                *pt
+            };
+            self.matches.matches_for_start(p).map(|ms| {
+                ms.iter()
+                    .filter(|m| m.is_active())
+                    .map(match_to_arrow)
+                    .filter(|a| a.end <= self.t_target)
+            })
+        };
+        // Prune all start positions at once, with a single pass over the layers.
+        let pts = pruned_start_positions
+            .iter()
+            .map(|&p| self.transform(p))
+            .collect_vec();
+        let (_, shift) = self.contours.prune_block(&pts, hint, arrows);
         let mut change = 0;
-        for p in pruned_start_positions {
-            let pt = self.transform(p);
-            let c = self.contours.prune_with_hint(pt, hint, |pt| {
-                let p = if self.params.use_gap_cost {
-                    self.seeds.transform_back(*pt)
-                } else {
-                    *pt
-                };
-                self.matches.matches_for_start(p).map(|ms| {
-                    ms.iter()
-                        .filter(|m| m.is_active())
-                        .map(match_to_arrow)
-                        .filter(|a| a.end <= self.t_target)
-                })
-            });
-            if p == pos {
-                // For CSH, propagating just works.
-                // For GCSH, we manually ensure here that all states in the queue are <= the pruned pos.
-                if !self.params.use_gap_cost || self.max_transformed_pos <= tpos {
-                    change = c.1;
-                }
+        // The shift is for the last position, which is `pos` itself when matches starting there were pruned.
+        if pruned_start_positions.last() == Some(&pos) {
+            // For CSH, propagating just works.
+            // For GCSH, we manually ensure here that all states in the queue are <= the pruned pos.
+            if !self.params.use_gap_cost || self.max_transformed_pos <= tpos {
+                change = shift;
             }
         }
         timer.end(&mut self.stats.contours_duration);
@@ -471,30 +499,21 @@ impl<'a, C: Contours> HeuristicInstance<'a> for CSHI<C> {
     /// NOTE that this does not update `h` or the contours yet; call `update_contours` for that.
     fn prune_block(&mut self, i_range: Range<I>, j_range: Range<I>) {
         let start = instant::Instant::now();
-        let mut hint = Self::Hint::default();
-        let mut lowest_modified_contour = self.lowest_modified_contour;
-        let mut highest_modified_contour = self.highest_modified_contour;
+        let mut pruned_starts = std::mem::take(&mut self.pruned_starts);
         self.matches.prune_block(i_range, j_range, |m| {
-            let (layer, new_hint) = self
-                .contours
-                .score_with_hint(Self::transform_2(&self.params, &self.seeds, m.start), hint);
             if PRINT {
-                eprintln!("Prune match {m:?} in layer {layer}");
+                trace!("Prune match {m:?}");
             }
-            // eprintln!("Prune match {m:?} in layer {layer}");
-            lowest_modified_contour = min(lowest_modified_contour, layer as Layer);
-            highest_modified_contour = max(highest_modified_contour, layer as Layer);
-            hint = new_hint;
+            pruned_starts.push(Self::transform_2(&self.params, &self.seeds, m.start));
         });
-        self.lowest_modified_contour = lowest_modified_contour;
-        self.highest_modified_contour = highest_modified_contour;
+        self.pruned_starts = pruned_starts;
 
         self.stats.prune_duration += start.elapsed().as_secs_f64();
     }
 
-    /// Update contours from `lowest_modified_contour` to `highest_modified_contour`.
-    /// Stop when the entire contour is *left of* `_pos.0`.
-    fn update_contours(&mut self, pos: Pos) {
+    /// Remove the matches pruned by `prune_block` since the last call from the
+    /// contours, using a single pass over the layers.
+    fn update_contours(&mut self, _pos: Pos) {
         let start = instant::Instant::now();
 
         let match_to_arrow = |m: &Match| Arrow {
@@ -511,19 +530,11 @@ impl<'a, C: Contours> HeuristicInstance<'a> for CSHI<C> {
             score: m.score(),
         };
 
-        // eprintln!(
-        //     "Prune contours from {} to {} right of {}",
-        //     self.lowest_modified_contour, self.highest_modified_contour, pos.0
-        // );
-        // FIXME Figure out why pruning up to Layer::MAX gives errors.
-        // Pruning up to highest_modified_contour also errors, which is
-        // explained by leaving the heuristic in an inconsistent state.
-        self.contours.update_layers(
-            self.lowest_modified_contour,
-            // continue to exactly the highest modified contour.
-            // self.highest_modified_contour,
-            Layer::MAX,
-            &|pt: &Pos| {
+        let mut pts = std::mem::take(&mut self.pruned_starts);
+        pts.sort_by_key(|p| LexPos(*p));
+        pts.dedup();
+        self.contours
+            .prune_block(&pts, Self::Hint::default(), |pt: &Pos| {
                 let p = if self.params.use_gap_cost {
                     self.seeds.transform_back(*pt)
                 } else {
@@ -535,20 +546,9 @@ impl<'a, C: Contours> HeuristicInstance<'a> for CSHI<C> {
                         .map(match_to_arrow)
                         .filter(|a| a.end <= self.t_target)
                 })
-            },
-            // None::<(_, fn(_) -> _)>,
-            Some((pos.0, |pt: Pos| {
-                if self.params.use_gap_cost {
-                    self.seeds.transform_back(pt)
-                } else {
-                    pt
-                }
-            })),
-        );
-        // self.lowest_modified_contour = Layer::MAX;
-        self.highest_modified_contour = Layer::MIN;
+            });
         if PRINT {
-            eprintln!("h0 after  update: {}", self.h(Pos(0, 0)));
+            debug!("h0 after  update: {}", self.h(Pos(0, 0)));
         }
         self.stats.contours_duration += start.elapsed().as_secs_f64();
     }
@@ -560,6 +560,114 @@ impl<'a, C: Contours> HeuristicInstance<'a> for CSHI<C> {
         self.max_transformed_pos.1 = max(self.max_transformed_pos.1, tpos.1);
     }
 
+    /// Turn the exact seeds overlapping `rows` into inexact seeds: their
+    /// potential goes from 1 to 2, and matches with one edit are added.
+    ///
+    /// For each chain using new matches, the chain without them has at most
+    /// the same cost, so `h` does not decrease. Pruned matches stay pruned.
+    /// The layers of all arrows before the re-seeded seeds change, so these
+    /// are rebuilt, while the contours after them are kept.
+    ///
+    /// Only CSH with exact matches of fixed length is supported. GCSH filters
+    /// matches using the original potentials, and some of the dropped matches
+    /// could be needed after increasing them.
+    fn reseed(&mut self, a: Seq, b: Seq, rows: Range<I>) -> bool {
+        let Some(k) = self.params.match_config.length.k() else {
+            return false;
+        };
+        if self.params.match_config.r != 1 || self.params.use_gap_cost {
+            return false;
+        }
+        let Some((first, last)) = self
+            .seeds
+            .seeds
+            .iter()
+            .positions(|s| s.start < rows.end && rows.start < s.end && s.seed_potential == 1)
+            .minmax()
+            .into_option()
+        else {
+            return false;
+        };
+        let start = instant::Instant::now();
+
+        // Seeds are consecutive, so the seeds found in the slice of `a` are
+        // exactly the seeds `first..=last`, shifted by `offset`.
+        let offset = self.seeds.seeds[first].start;
+        let end = self.seeds.seeds[last].end;
+        let Matches {
+            seeds: new_seeds,
+            matches: new_matches,
+        } = find_matches(
+            &a[offset as usize..end as usize],
+            b,
+            MatchConfig::inexact(k),
+            false,
+        );
+        let mut seeds = std::mem::take(&mut self.seeds.seeds);
+        let mut reseeded = vec![false; seeds.len()];
+        for (idx, s) in (first..=last).zip(new_seeds.seeds) {
+            // Seeds that were already re-seeded keep their current matches.
+            if seeds[idx].seed_potential == 1 {
+                seeds[idx] = Seed {
+                    start: s.start + offset,
+                    end: s.end + offset,
+                    ..s
+                };
+                reseeded[idx] = true;
+            }
+        }
+        let seed_idx = |i: I| self.seeds.seed_at[i as usize].unwrap() as usize;
+
+        // Exact matches are already present, possibly pruned.
+        let mut matches = self.matches.iter().cloned().collect_vec();
+        let num_old_matches = matches.len();
+        for m in &mut matches {
+            m.seed_potential = seeds[seed_idx(m.start.0)].seed_potential;
+        }
+        matches.extend(
+            new_matches
+                .into_iter()
+                .map(|m| Match {
+                    start: Pos(m.start.0 + offset, m.start.1),
+                    end: Pos(m.end.0 + offset, m.end.1),
+                    ..m
+                })
+                .filter(|m| m.match_cost > 0 && reseeded[seed_idx(m.start.0)]),
+        );
+        let num_new_matches = matches.len() - num_old_matches;
+
+        self.seeds = Seeds::new(a, seeds);
+        self.matches = MatchPruner::new(
+            self.params.pruning,
+            self.params.use_gap_cost,
+            matches,
+            &self.seeds,
+        );
+        // Only the layers of arrows starting before the last re-seeded seed
+        // change, so the arrows after it are kept.
+        let t_target = self.t_target;
+        self.contours.replace_before(
+            end,
+            self.matches
+                .iter()
+                .rev()
+                .filter(|m| m.start.0 < end && m.is_active())
+                .map(|m| Arrow {
+                    start: m.start,
+                    end: m.end,
+                    score: m.score(),
+                })
+                .filter(|a| a.end <= t_target),
+        );
+        // Prunes before `end` are included in the new arrows.
+        self.pruned_starts.retain(|p| p.0 >= end);
+
+        self.stats.num_matches += num_new_matches;
+        self.stats.num_filtered_matches += num_new_matches;
+        self.stats.contours_duration += start.elapsed().as_secs_f64();
+        true
+    }
+
     fn stats(&mut self) -> HeuristicStats {
         self.stats.h0_end = self.h(Pos(0, 0));
         self.stats
@@ -573,7 +681,113 @@ impl<'a, C: Contours> HeuristicInstance<'a> for CSHI<C> {
         Some(&self.seeds)
     }
 
+    /// Follows the contours from `pos`: each layer has a match starting at its
+    /// parent that ends in the layer the chain continues from.
+    fn explain(&self, pos: Pos) -> Option<HExplanation> {
+        let layer = self.contours.score(self.transform(pos));
+        let mut chain = vec![];
+        let mut v = layer;
+        let mut q = pos;
+        while v > 0 {
+            let parent = self.transform_back(self.contours.parent(self.transform(q)).1);
+            let Some(m) = self.matches.matches_for_start(parent).and_then(|ms| {
+                ms.iter().find(|m| {
+                    m.is_active()
+                        && m.score() as Cost + self.contours.score(self.transform(m.end)) == v
+                })
+            }) else {
+                break;
+            };
+            chain.push(m.clone());
+            v -= m.score() as Cost;
+            q = m.end;
+        }
+
+        let reachable = self.matches.iter().filter(|m| pos <= m.start);
+        let inactive = reachable
+            .clone()
+            .filter(|m| !m.is_active())
+            .cloned()
+            .collect();
+        let mut matched = vec![false; self.seeds.seeds.len()];
+        for m in reachable {
+            if let Some(idx) = self.seeds.seed_at[m.start.0 as usize] {
+                matched[idx as usize] = true;
+            }
+        }
+        let unmatched_seeds = (self.seeds.seeds.iter().zip(matched))
+            .filter(|(s, matched)| s.start >= pos.0 && !matched)
+            .map(|(s, _)| s.clone())
+            .collect();
+
+        Some(HExplanation {
+            potential: self.seeds.potential(pos),
+            layer,
+            chain,
+            inactive,
+            unmatched_seeds,
+        })
+    }
+
     fn params_string(&self) -> String {
         format!("{:?}", self.params)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn reseed() {
+        let (a, b) = pa_generate::uniform_seeded(2000, 0.10, 31415);
+        let dist = bio::alignment::distance::simd::levenshtein(&a, &b) as Cost;
+        let mut h = CSH::new(MatchConfig::exact(10), Pruning::disabled()).build(&a, &b);
+        let positions = (0..=a.len() as I)
+            .step_by(10)
+            .cartesian_product((0..=b.len() as I).step_by(50))
+            .map(|(i, j)| Pos(i, j))
+            .collect_vec();
+        let before = positions.iter().map(|&p| h.h(p)).collect_vec();
+        let num_matches = h.stats().num_matches;
+
+        assert!(h.reseed(&a, &b, 500..1000));
+        // Seeds that were already re-seeded are skipped.
+        assert!(!h.reseed(&a, &b, 500..1000));
+        assert!(h.stats().num_matches > num_matches);
+        for s in &h.seeds().unwrap().seeds {
+            let reseeded = s.start < 1000 && 500 < s.end;
+            assert_eq!(s.seed_potential, if reseeded { 2 } else { 1 });
+        }
+
+        // `h` only increases, and stays admissible.
+        for (&p, &h_before) in positions.iter().zip(&before) {
+            assert!(h.h(p) >= h_before, "h decreased at {p}");
+        }
+        assert!(h.h(Pos(0, 0)) <= dist);
+    }
+
+    /// Keeping the contours after the re-seeded rows gives the same `h` as the
+    /// bruteforce contours, also after pruning matches after them.
+    #[test]
+    fn reseed_incremental() {
+        let (a, b) = pa_generate::uniform_seeded(2000, 0.10, 31415);
+        let config = MatchConfig::exact(10);
+        let mut h = CSH::new(config, Pruning::start()).build(&a, &b);
+        let mut hb = CSH::new_bruteforce(config, Pruning::start()).build(&a, &b);
+        let matches = h.matches().unwrap();
+        for m in matches.iter().filter(|m| m.start.0 >= 1500).step_by(3) {
+            h.prune(m.start, Default::default());
+            hb.prune(m.start, Default::default());
+        }
+        for rows in [1200..1400, 500..1000] {
+            assert!(h.reseed(&a, &b, rows.clone()));
+            assert!(hb.reseed(&a, &b, rows));
+        }
+        for i in (0..=a.len() as I).step_by(10) {
+            for j in (0..=b.len() as I).step_by(50) {
+                assert_eq!(h.h(Pos(i, j)), hb.h(Pos(i, j)), "at {}", Pos(i, j));
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+//! A seed heuristic for aligning many queries `a` to one reference `b`.
+//!
+//! Instead of building contours of matches for each query, matches are only
+//! compared against evenly spaced *landmarks* in the reference, so that all
+//! work on `b` is in the shared `ReferenceIndex`.
+use super::*;
+use crate::util::Timer;
+use crate::*;
+
+#[derive(Debug, Copy, Clone)]
+pub struct LandmarkSH {
+    pub match_config: MatchConfig,
+    /// The distance between consecutive landmarks in `b`.
+    pub landmark_distance: I,
+}
+
+impl LandmarkSH {
+    pub fn new(match_config: MatchConfig, landmark_distance: I) -> Self {
+        assert!(landmark_distance > 0);
+        Self {
+            match_config,
+            landmark_distance,
+        }
+    }
+}
+
+impl Heuristic for LandmarkSH {
+    type Instance<'a> = LandmarkSHI;
+
+    fn build<'a>(&self, a: Seq<'a>, b: Seq<'a>) -> Self::Instance<'a> {
+        LandmarkSHI::new(a, b, None, *self)
+    }
+
+    fn build_with_index<'a>(
+        &self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        index: &ReferenceIndex,
+    ) -> Self::Instance<'a> {
+        LandmarkSHI::new(a, b, Some(index), *self)
+    }
+
+    fn match_config(&self) -> Option<MatchConfig> {
+        Some(self.match_config)
+    }
+
+    fn name(&self) -> String {
+        "LandmarkSH".into()
+    }
+}
+
+/// For landmark `l` at `l * landmark_distance`, each seed costs the minimal
+/// cost of its matches starting at or after `l`, or its potential when there
+/// is none. `h(i, j)` is the total cost of the seeds starting at or after `i`,
+/// for the last landmark `l <= j`.
+///
+/// This is a lower bound, since every path from `(i, j)` aligns these seeds to
+/// parts of `b[l..]`. Like SH, it can only decrease by more than the edge cost
+/// at seed starts.
+pub struct LandmarkSHI {
+    params: LandmarkSH,
+    seeds: Seeds,
+    /// `h[l][s]`: the total cost of seeds `s..` for landmark `l`.
+    h: Vec<Vec<Cost>>,
+    stats: HeuristicStats,
+}
+
+impl LandmarkSHI {
+    fn new(a: Seq, b: Seq, index: Option<&ReferenceIndex>, params: LandmarkSH) -> Self {
+        let Matches { seeds, matches } = index
+            .and_then(|index| index.find_matches(a, b, params.match_config, false).ok())
+            .unwrap_or_else(|| find_matches(a, b, params.match_config, false));
+
+        let num_landmarks = b.len() / params.landmark_distance as usize + 1;
+        let num_seeds = seeds.seeds.len();
+
+        // The cheapest match of each seed starting in each landmark interval.
+        let mut cost = vec![
+            seeds
+                .seeds
+                .iter()
+                .map(|s| s.seed_potential as Cost)
+                .collect::<Vec<_>>();
+            num_landmarks
+        ];
+        for m in &matches {
+            let s = seeds.seed_at[m.start.0 as usize].unwrap() as usize;
+            let l = (m.start.1 / params.landmark_distance) as usize;
+            cost[l][s] = min(cost[l][s], m.match_cost as Cost);
+        }
+        // A match starting after landmark `l` can also be used for all earlier landmarks.
+        for l in (0..num_landmarks - 1).rev() {
+            for s in 0..num_seeds {
+                cost[l][s] = min(cost[l][s], cost[l + 1][s]);
+            }
+        }
+        let h = cost
+            .into_iter()
+            .map(|cost| {
+                let mut h = vec![0; num_seeds + 1];
+                for s in (0..num_seeds).rev() {
+                    h[s] = h[s + 1] + cost[s];
+                }
+                h
+            })
+            .collect();
+
+        let mut stats = HeuristicStats::default();
+        stats.num_seeds = num_seeds as I;
+        stats.num_matches = matches.len();
+        stats.num_filtered_matches = matches.len();
+        let mut h = LandmarkSHI {
+            params,
+            seeds,
+            h,
+            stats,
+        };
+        h.stats.h0 = h.h(Pos(0, 0));
+        h
+    }
+}
+
+impl<'a> HeuristicInstance<'a> for LandmarkSHI {
+    fn h(&self, Pos(i, j): Pos) -> Cost {
+        let s = self.seeds.seeds.partition_point(|seed| seed.start < i);
+        self.h[(j / self.params.landmark_distance) as usize][s]
+    }
+
+    fn h_with_hint_timed(&mut self, pos: Pos, hint: Self::Hint) -> ((Cost, Self::Hint), f64) {
+        let timer = Timer::new(&mut self.stats.h_calls);
+        let ans = self.h_with_hint(pos, hint);
+        let t = timer.end(&mut self.stats.h_duration);
+        (ans, t)
+    }
+
+    fn root_potential(&self) -> Cost {
+        self.seeds.potential[0]
+    }
+
+    fn stats(&mut self) -> HeuristicStats {
+        self.stats.h0_end = self.h(Pos(0, 0));
+        self.stats
+    }
+
+    fn seeds(&self) -> Option<&Seeds> {
+        Some(&self.seeds)
+    }
+
+    fn params_string(&self) -> String {
+        format!("{:?}", self.params)
+    }
+}
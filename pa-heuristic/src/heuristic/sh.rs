@@ -23,7 +23,24 @@ impl Heuristic for SH {
     type Instance<'a> = SHI;
 
     fn build<'a>(&self, a: Seq<'a>, b: Seq<'a>) -> Self::Instance<'a> {
-        SHI::new(a, b, *self)
+        SHI::new(a, b, None, *self)
+    }
+
+    fn build_with_index<'a>(
+        &self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        index: &ReferenceIndex,
+    ) -> Self::Instance<'a> {
+        SHI::new(a, b, Some(index), *self)
+    }
+
+    fn match_config(&self) -> Option<MatchConfig> {
+        Some(self.match_config)
+    }
+
+    fn is_pruning(&self) -> bool {
+        self.pruning.is_enabled()
     }
 
     fn name(&self) -> String {
@@ -45,8 +62,10 @@ pub struct SHI {
 }
 
 impl SHI {
-    fn new(a: Seq, b: Seq, params: SH) -> Self {
-        let Matches { seeds, matches } = find_matches(a, b, params.match_config, false);
+    fn new(a: Seq, b: Seq, index: Option<&ReferenceIndex>, params: SH) -> Self {
+        let Matches { seeds, matches } = index
+            .and_then(|index| index.find_matches(a, b, params.match_config, false).ok())
+            .unwrap_or_else(|| find_matches(a, b, params.match_config, false));
 
         let contours = ShContours::new(
             &seeds,
@@ -67,6 +86,7 @@ impl SHI {
             contours,
         };
         h.stats.h0 = h.h(Pos(0, 0));
+        h.stats.contour_layers = h.layer(Pos(0, 0)).unwrap();
         h
     }
 }
@@ -88,7 +108,7 @@ impl<'a> HeuristicInstance<'a> for SHI {
     fn h(&self, pos: Pos) -> Cost {
         let p = self.seeds.potential(pos);
         let m = self.contours.score(pos.0);
-        p - m
+        util::sub_cost(p, m)
     }
 
     fn layer(&self, pos: Pos) -> Option<Cost> {
@@ -102,7 +122,7 @@ impl<'a> HeuristicInstance<'a> for SHI {
     fn h_with_hint(&self, pos: Pos, hint: Self::Hint) -> (Cost, Self::Hint) {
         let p = self.seeds.potential(pos);
         let (m, h) = self.contours.score_with_hint(pos.0, hint);
-        (p - m, h)
+        (util::sub_cost(p, m), h)
     }
 
     fn h_with_hint_timed(&mut self, pos: Pos, hint: Self::Hint) -> ((Cost, Self::Hint), f64) {
@@ -30,6 +30,14 @@ where
             h2: self.h2.build(a, b),
         }
     }
+
+    fn is_pruning(&self) -> bool {
+        self.h1.is_pruning() || self.h2.is_pruning()
+    }
+
+    fn is_consistent(&self) -> bool {
+        self.h1.is_consistent() && self.h2.is_consistent()
+    }
 }
 
 impl<'a, H1: Heuristic, H2: Heuristic> HeuristicInstance<'a> for EqualHeuristicI<'a, H1, H2>
@@ -121,12 +129,20 @@ where
     }
 }
 
+/// The pointwise maximum of two admissible heuristics, e.g. `GapCost` and `SH`.
+/// This is admissible as well, and consistent when both are.
 #[derive(Debug, Clone, Copy)]
 pub struct MaxHeuristic<H1: Heuristic, H2: Heuristic> {
     pub h1: H1,
     pub h2: H2,
 }
 
+impl<H1: Heuristic, H2: Heuristic> MaxHeuristic<H1, H2> {
+    pub fn new(h1: H1, h2: H2) -> Self {
+        Self { h1, h2 }
+    }
+}
+
 pub struct MaxHeuristicI<'a, H1: Heuristic, H2: Heuristic> {
     h1: H1::Instance<'a>,
     h2: H2::Instance<'a>,
@@ -150,6 +166,31 @@ where
             h2: self.h2.build(a, b),
         }
     }
+
+    /// NOTE: When both heuristics use matches, they must have the same match config as the index.
+    fn build_with_index<'a>(
+        &self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        index: &ReferenceIndex,
+    ) -> Self::Instance<'a> {
+        MaxHeuristicI {
+            h1: self.h1.build_with_index(a, b, index),
+            h2: self.h2.build_with_index(a, b, index),
+        }
+    }
+
+    fn match_config(&self) -> Option<MatchConfig> {
+        self.h2.match_config().or(self.h1.match_config())
+    }
+
+    fn is_pruning(&self) -> bool {
+        self.h1.is_pruning() || self.h2.is_pruning()
+    }
+
+    fn is_consistent(&self) -> bool {
+        self.h1.is_consistent() && self.h2.is_consistent()
+    }
 }
 
 impl<'a, H1: Heuristic, H2: Heuristic> HeuristicInstance<'a> for MaxHeuristicI<'a, H1, H2>
@@ -174,12 +215,23 @@ where
         s1 || s2
     }
 
+    /// The orders of the two heuristics may differ, so the queue is not shifted.
     fn prune(&mut self, pos: Pos, hint: Self::Hint) -> (Cost, ()) {
         let _c1 = self.h1.prune(pos, hint.0);
         let _c2 = self.h2.prune(pos, hint.1);
         (0, ())
     }
 
+    fn prune_block(&mut self, i_range: Range<I>, j_range: Range<I>) {
+        self.h1.prune_block(i_range.clone(), j_range.clone());
+        self.h2.prune_block(i_range, j_range);
+    }
+
+    fn update_contours(&mut self, pos: Pos) {
+        self.h1.update_contours(pos);
+        self.h2.update_contours(pos);
+    }
+
     fn h_with_hint(&self, pos: Pos, hint: Self::Hint) -> (Cost, Self::Hint) {
         let (c1, hint1) = self.h1.h_with_hint(pos, hint.0);
         let (c2, hint2) = self.h2.h_with_hint(pos, hint.1);
@@ -198,6 +250,18 @@ where
         self.h1.explore(pos);
         self.h2.explore(pos);
     }
+
+    fn seeds(&self) -> Option<&Seeds> {
+        self.h2.seeds().or(self.h1.seeds())
+    }
+
+    fn params_string(&self) -> String {
+        format!(
+            "max({}, {})",
+            self.h1.params_string(),
+            self.h2.params_string()
+        )
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -223,6 +287,14 @@ where
             h: self.0.build(b, a),
         }
     }
+
+    fn is_pruning(&self) -> bool {
+        self.0.is_pruning()
+    }
+
+    fn is_consistent(&self) -> bool {
+        self.0.is_consistent()
+    }
 }
 
 impl<'a, H: Heuristic> HeuristicInstance<'a> for MirrorHeuristicI<'a, H>
@@ -261,6 +333,150 @@ where
     }
 }
 
+/// Weighted A*: the heuristic is multiplied by a weight `w >= 1`.
+///
+/// The resulting heuristic is not admissible, but A* with it finds an alignment
+/// with cost at most `w` times the optimal cost, usually much faster.
+#[derive(Debug, Clone, Copy)]
+pub struct WeightedHeuristic<H: Heuristic> {
+    pub h: H,
+    pub w: f32,
+}
+
+impl<H: Heuristic> WeightedHeuristic<H> {
+    pub fn new(h: H, w: f32) -> Self {
+        assert!(w >= 1., "The weight must be at least 1, but is {w}.");
+        Self { h, w }
+    }
+
+    /// A lower bound on the optimal cost, given a cost found using this heuristic.
+    pub fn lower_bound(&self, cost: Cost) -> Cost {
+        // Subtract a small epsilon to guard against rounding errors.
+        max((cost as f64 / self.w as f64 - 1e-6).ceil() as Cost, 0)
+    }
+}
+
+pub struct WeightedHeuristicI<'a, H: Heuristic> {
+    h: H::Instance<'a>,
+    w: f32,
+}
+
+impl<H: Heuristic> Heuristic for WeightedHeuristic<H>
+where
+    for<'a> H::Instance<'a>: HeuristicInstance<'a>,
+{
+    type Instance<'a> = WeightedHeuristicI<'a, H>;
+
+    fn name(&self) -> String {
+        format!("weighted({},{})", self.h.name(), self.w)
+    }
+
+    fn build<'a>(&self, a: Seq<'a>, b: Seq<'a>) -> Self::Instance<'a> {
+        WeightedHeuristicI {
+            h: self.h.build(a, b),
+            w: self.w,
+        }
+    }
+
+    fn build_with_index<'a>(
+        &self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        index: &ReferenceIndex,
+    ) -> Self::Instance<'a> {
+        WeightedHeuristicI {
+            h: self.h.build_with_index(a, b, index),
+            w: self.w,
+        }
+    }
+
+    fn match_config(&self) -> Option<MatchConfig> {
+        self.h.match_config()
+    }
+
+    fn is_pruning(&self) -> bool {
+        self.h.is_pruning()
+    }
+
+    /// Scaling up `h` by `w > 1` breaks consistency.
+    fn is_consistent(&self) -> bool {
+        self.w <= 1.0 && self.h.is_consistent()
+    }
+}
+
+impl<'a, H: Heuristic> WeightedHeuristicI<'a, H> {
+    fn weigh(&self, h: Cost) -> Cost {
+        (self.w * h as f32) as Cost
+    }
+}
+
+impl<'a, H: Heuristic> HeuristicInstance<'a> for WeightedHeuristicI<'a, H>
+where
+    H::Instance<'a>: HeuristicInstance<'a>,
+{
+    fn h(&self, pos: Pos) -> Cost {
+        self.weigh(self.h.h(pos))
+    }
+
+    type Hint = <<H as Heuristic>::Instance<'a> as HeuristicInstance<'a>>::Hint;
+
+    fn h_with_hint(&self, pos: Pos, hint: Self::Hint) -> (Cost, Self::Hint) {
+        let (h, hint) = self.h.h_with_hint(pos, hint);
+        (self.weigh(h), hint)
+    }
+
+    fn h_with_hint_timed(&mut self, pos: Pos, hint: Self::Hint) -> ((Cost, Self::Hint), f64) {
+        let ((h, hint), t) = self.h.h_with_hint_timed(pos, hint);
+        ((self.weigh(h), hint), t)
+    }
+
+    fn root_potential(&self) -> Cost {
+        self.weigh(self.h.root_potential())
+    }
+
+    fn is_seed_start_or_end(&self, pos: Pos) -> bool {
+        self.h.is_seed_start_or_end(pos)
+    }
+
+    type Order = <<H as Heuristic>::Instance<'a> as HeuristicInstance<'a>>::Order;
+
+    /// The weighted value can drop by up to `ceil(w * shift)`. Rounding up ensures
+    /// that shifted priorities never exceed the new values.
+    fn prune(&mut self, pos: Pos, hint: Self::Hint) -> (Cost, Self::Order) {
+        let (shift, order) = self.h.prune(pos, hint);
+        ((self.w * shift as f32).ceil() as Cost, order)
+    }
+
+    fn prune_block(&mut self, i_range: Range<I>, j_range: Range<I>) {
+        self.h.prune_block(i_range, j_range)
+    }
+
+    fn update_contours(&mut self, pos: Pos) {
+        self.h.update_contours(pos)
+    }
+
+    fn explore(&mut self, pos: Pos) {
+        self.h.explore(pos)
+    }
+
+    /// NOTE: `h0` in the returned statistics is the unweighted value.
+    fn stats(&mut self) -> HeuristicStats {
+        self.h.stats()
+    }
+
+    fn matches(&self) -> Option<Vec<Match>> {
+        self.h.matches()
+    }
+
+    fn seeds(&self) -> Option<&Seeds> {
+        self.h.seeds()
+    }
+
+    fn params_string(&self) -> String {
+        format!("{} w={}", self.h.params_string(), self.w)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct PerfectHeuristic;
 
@@ -312,4 +528,197 @@ where
         };
         max_config.build(a, b)
     }
+
+    fn is_pruning(&self) -> bool {
+        self.0.is_pruning()
+    }
+
+    fn is_consistent(&self) -> bool {
+        self.0.is_consistent()
+    }
+}
+
+/// Overlapping seeds: seeds of length `k` start every `stride < k` positions.
+///
+/// Counting the cost of all overlapping seeds is not admissible, since a
+/// single edit can be in up to `k/stride` seeds. Instead, the overlapping
+/// seeds are split into the disjoint seedings starting at offsets
+/// `0, stride, 2*stride, ...`, and `h` is the maximum of the heuristics for
+/// each of them. Each is admissible, and so is the maximum.
+///
+/// States before the first seed of an offset get value 0 for that offset.
+#[derive(Debug, Clone, Copy)]
+pub struct OverlappingSeeds<H: Heuristic> {
+    pub h: H,
+    pub stride: I,
+}
+
+pub struct OverlappingSeedsI<'a, H: Heuristic> {
+    /// The offsets of the seedings, and their heuristics on `a[offset..]`.
+    hs: Vec<(I, H::Instance<'a>)>,
+}
+
+impl<H: Heuristic> OverlappingSeeds<H> {
+    /// Panics unless `h` uses a fixed seed length `k` and `0 < stride < k`.
+    pub fn new(h: H, stride: I) -> Self {
+        let k = Self::k(&h);
+        assert!(
+            0 < stride && stride < k,
+            "The seed stride must be in 1..k, but is {stride} for k = {k}."
+        );
+        Self { h, stride }
+    }
+
+    fn k(h: &H) -> I {
+        h.match_config()
+            .and_then(|mc| mc.length.k())
+            .expect("Overlapping seeds need a fixed seed length k.")
+    }
+
+    fn offsets(&self) -> impl Iterator<Item = I> {
+        (0..Self::k(&self.h)).step_by(self.stride as usize)
+    }
+}
+
+impl<H: Heuristic> Heuristic for OverlappingSeeds<H> {
+    type Instance<'a> = OverlappingSeedsI<'a, H>;
+
+    fn name(&self) -> String {
+        format!("overlapping({},{})", self.h.name(), self.stride)
+    }
+
+    fn build<'a>(&self, a: Seq<'a>, b: Seq<'a>) -> Self::Instance<'a> {
+        OverlappingSeedsI {
+            hs: self
+                .offsets()
+                .filter(|&o| o as usize <= a.len())
+                .map(|o| (o, self.h.build(&a[o as usize..], b)))
+                .collect(),
+        }
+    }
+
+    fn build_with_index<'a>(
+        &self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        index: &ReferenceIndex,
+    ) -> Self::Instance<'a> {
+        OverlappingSeedsI {
+            hs: self
+                .offsets()
+                .filter(|&o| o as usize <= a.len())
+                .map(|o| (o, self.h.build_with_index(&a[o as usize..], b, index)))
+                .collect(),
+        }
+    }
+
+    fn match_config(&self) -> Option<MatchConfig> {
+        self.h.match_config()
+    }
+
+    fn is_pruning(&self) -> bool {
+        self.h.is_pruning()
+    }
+
+    fn is_consistent(&self) -> bool {
+        self.h.is_consistent()
+    }
+}
+
+impl<'a, H: Heuristic> OverlappingSeedsI<'a, H> {
+    /// The heuristics with their position shifted by the offset, for positions after the offset.
+    fn shifted(&self, pos: Pos) -> impl Iterator<Item = (&H::Instance<'a>, Pos)> + '_ {
+        self.hs
+            .iter()
+            .filter(move |(o, _)| pos.0 >= *o)
+            .map(move |(o, h)| (h, Pos(pos.0 - o, pos.1)))
+    }
+}
+
+impl<'a, H: Heuristic> HeuristicInstance<'a> for OverlappingSeedsI<'a, H> {
+    fn h(&self, pos: Pos) -> Cost {
+        self.shifted(pos)
+            .map(|(h, pos)| h.h(pos))
+            .max()
+            .unwrap_or(0)
+    }
+
+    fn is_seed_start_or_end(&self, pos: Pos) -> bool {
+        self.shifted(pos)
+            .any(|(h, pos)| h.is_seed_start_or_end(pos))
+    }
+
+    /// The orders of the seedings differ, so the queue is not shifted.
+    fn prune(&mut self, pos: Pos, _hint: Self::Hint) -> (Cost, ()) {
+        for (o, h) in &mut self.hs {
+            if pos.0 >= *o {
+                h.prune(Pos(pos.0 - *o, pos.1), Default::default());
+            }
+        }
+        (0, ())
+    }
+
+    fn prune_block(&mut self, i_range: Range<I>, j_range: Range<I>) {
+        for (o, h) in &mut self.hs {
+            if i_range.end >= *o {
+                h.prune_block(
+                    max(i_range.start - *o, 0)..i_range.end - *o,
+                    j_range.clone(),
+                );
+            }
+        }
+    }
+
+    fn update_contours(&mut self, pos: Pos) {
+        for (o, h) in &mut self.hs {
+            h.update_contours(Pos(max(pos.0 - *o, 0), pos.1));
+        }
+    }
+
+    fn explore(&mut self, pos: Pos) {
+        for (o, h) in &mut self.hs {
+            if pos.0 >= *o {
+                h.explore(Pos(pos.0 - *o, pos.1));
+            }
+        }
+    }
+
+    fn root_potential(&self) -> Cost {
+        self.hs
+            .iter()
+            .map(|(_, h)| h.root_potential())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Counts and timings are summed over the seedings.
+    fn stats(&mut self) -> HeuristicStats {
+        let mut stats = HeuristicStats::default();
+        for (_, h) in &mut self.hs {
+            stats += h.stats();
+        }
+        stats.h0 = self
+            .hs
+            .iter()
+            .map(|(_, h)| h.h(Pos(0, 0)))
+            .max()
+            .unwrap_or(0);
+        stats.h0_end = self.h(Pos(0, 0));
+        stats
+    }
+
+    /// The seeds of the seeding at offset 0.
+    fn seeds(&self) -> Option<&Seeds> {
+        self.hs.first()?.1.seeds()
+    }
+
+    fn params_string(&self) -> String {
+        format!(
+            "overlapping {:?}: {}",
+            self.hs.iter().map(|(o, _)| o).collect::<Vec<_>>(),
+            self.hs
+                .first()
+                .map_or(String::new(), |(_, h)| h.params_string())
+        )
+    }
 }
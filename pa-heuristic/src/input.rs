@@ -0,0 +1,134 @@
+//! Validation of input sequences.
+//!
+//! Most aligners encode characters using 2 bits (bit profiles and q-grams), and
+//! hence only support the `ACGT` alphabet, while others compare bytes directly.
+//! `prepare_input` makes the handling of other bytes explicit, so that it does
+//! not depend on the code path taken.
+use std::borrow::Cow;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::prelude::*;
+
+/// How to handle bytes other than `ACGT` in the input. Lowercase `acgt` are
+/// always converted to uppercase.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+pub enum NonAcgt {
+    /// Return an error.
+    #[default]
+    Error,
+    /// Replace them by `N`, which matches only `N`.
+    ToN,
+    /// Replace them by a byte that never matches any character of the other sequence.
+    Mismatch,
+}
+
+impl NonAcgt {
+    /// Whether aligners that encode characters using 2 bits support this mode.
+    /// Those only support `Error`, since they can not represent other characters.
+    /// For other modes, pairs that are not `is_acgt` must be aligned by an
+    /// aligner that compares bytes directly.
+    pub fn supports_two_bit(&self) -> bool {
+        *self == NonAcgt::Error
+    }
+}
+
+/// Whether `seq` only contains (uppercase) `ACGT`, as required by aligners
+/// that encode characters using 2 bits.
+pub fn is_acgt(seq: Seq) -> bool {
+    seq.iter().all(|c| b"ACGT".contains(c))
+}
+
+/// The sequence containing an invalid byte.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SeqId {
+    A,
+    B,
+}
+
+/// An unexpected byte at position `pos` of sequence `seq`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct InvalidByte {
+    pub seq: SeqId,
+    pub pos: usize,
+    pub byte: u8,
+}
+
+impl std::fmt::Display for InvalidByte {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Unexpected byte {:?} at position {} of sequence {:?}.",
+            self.byte as char, self.pos, self.seq
+        )
+    }
+}
+
+/// Byte used by `NonAcgt::Mismatch` for sequence `a`.
+const MISMATCH_A: u8 = b'X';
+/// Byte used by `NonAcgt::Mismatch` for sequence `b`.
+const MISMATCH_B: u8 = b'Y';
+
+fn prepare_seq(seq: Seq, id: SeqId, mode: NonAcgt) -> Result<Cow<[u8]>, InvalidByte> {
+    if is_acgt(seq) {
+        return Ok(Cow::Borrowed(seq));
+    }
+    seq.iter()
+        .enumerate()
+        .map(|(pos, &byte)| match byte.to_ascii_uppercase() {
+            c @ (b'A' | b'C' | b'G' | b'T') => Ok(c),
+            _ => match mode {
+                NonAcgt::Error => Err(InvalidByte { seq: id, pos, byte }),
+                NonAcgt::ToN => Ok(b'N'),
+                NonAcgt::Mismatch => Ok(match id {
+                    SeqId::A => MISMATCH_A,
+                    SeqId::B => MISMATCH_B,
+                }),
+            },
+        })
+        .collect::<Result<Vec<u8>, _>>()
+        .map(Cow::Owned)
+}
+
+/// Validate `a` and `b` and handle bytes other than `ACGT` according to `mode`.
+///
+/// Sequences that only contain `ACGT` are returned without copying.
+pub fn prepare_input<'a>(
+    a: Seq<'a>,
+    b: Seq<'a>,
+    mode: NonAcgt,
+) -> Result<(Cow<'a, [u8]>, Cow<'a, [u8]>), InvalidByte> {
+    Ok((
+        prepare_seq(a, SeqId::A, mode)?,
+        prepare_seq(b, SeqId::B, mode)?,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn prepare() {
+        let (a, b) = (b"ACgtN".as_slice(), b"ACGTNR".as_slice());
+        assert_eq!(
+            prepare_input(a, b, NonAcgt::Error),
+            Err(InvalidByte {
+                seq: SeqId::A,
+                pos: 4,
+                byte: b'N'
+            })
+        );
+        let (a2, b2) = prepare_input(a, b, NonAcgt::ToN).unwrap();
+        assert_eq!((&*a2, &*b2), (b"ACGTN".as_slice(), b"ACGTNN".as_slice()));
+        // Invalid bytes never match anything in the other sequence.
+        let (a2, b2) = prepare_input(a, b, NonAcgt::Mismatch).unwrap();
+        assert!(a2[4] != b2[4] && !b2.contains(&a2[4]) && !a2.contains(&b2[5]));
+        // Valid input is not copied.
+        assert!(matches!(
+            prepare_input(b"ACGT", b"TT", NonAcgt::Error),
+            Ok((Cow::Borrowed(_), Cow::Borrowed(_)))
+        ));
+    }
+}
@@ -9,7 +9,17 @@
 
 mod cli;
 mod config;
+#[cfg(not(feature = "bench"))]
 mod contour;
+#[cfg(feature = "bench")]
+pub mod contour;
+pub mod coords;
+mod divergence;
+pub mod format;
+mod input;
+mod packed;
+mod query;
+mod result;
 // FIXME: MAKE MOST MODULES PRIVATE
 // SEEDS AND MATCHES DO NOT NEED TO BE EXPOSED.
 pub mod heuristic;
@@ -17,13 +27,20 @@ pub mod matches;
 pub mod prune;
 pub mod seeds;
 mod split_vec;
+mod trace_order;
 pub mod util;
 
 pub use cli::*;
+pub use divergence::estimate_divergence;
 pub use heuristic::*;
-pub use matches::{LengthConfig, MatchConfig};
+pub use input::{is_acgt, prepare_input, InvalidByte, NonAcgt, SeqId};
+pub use matches::{ConfigMismatch, LengthConfig, MatchConfig, ReferenceIndex, UnsupportedConfig};
+pub use packed::PackedSeq;
 pub use prune::{Prune, Pruning};
+pub use query::{write_seeds_and_matches, HeuristicQuery, SEEDS_AND_MATCHES_HEADER};
+pub use result::{AlignResult, AlignStats, ResultAligner};
 pub use seeds::MatchCost;
+pub use trace_order::{TraceEdge, TraceOrder};
 
 mod prelude {
     pub use crate::config::*;
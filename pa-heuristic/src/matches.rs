@@ -3,14 +3,17 @@ pub mod exact;
 pub mod inexact;
 pub mod prepruning;
 pub mod qgrams;
+pub mod reference;
 mod suffix_array;
 
-use crate::{prelude::*, seeds::*, PRINT};
+use crate::{coords::PosExt, prelude::*, seeds::*};
 use bio::{
     alphabets::{Alphabet, RankTransform},
     data_structures::qgram_index::QGramIndex,
 };
 use prepruning::preserve_for_local_pruning;
+pub use reference::{ConfigMismatch, ReferenceIndex, UnsupportedConfig};
+use tracing::{debug, trace, Level};
 
 /// Find all matches between `a` and `b` with the given match configuration.
 /// If `transform_filter` is true, then only matches with T(m.start) <= target are kept.
@@ -234,7 +237,7 @@ impl<'a> MatchBuilder<'a> {
         *sc = min(*sc, m.match_cost);
 
         if self.config.local_pruning != 0 {
-            let d = m.start.0 - m.start.1;
+            let d = m.start.diagonal();
             let old = self.next_match_per_diag.index_mut(d);
             assert!(
                 *old >= m.start.0,
@@ -290,9 +293,7 @@ impl<'a> MatchBuilder<'a> {
                 }
             }
         }
-        if PRINT {
-            eprintln!("Added {} matches for consistency", new_matches.len());
-        }
+        debug!("Added {} matches for consistency", new_matches.len());
         self.matches.extend(new_matches);
         self.sort();
     }
@@ -305,24 +306,21 @@ impl<'a> MatchBuilder<'a> {
 
         self.make_consistent();
 
-        if PRINT && self.config.local_pruning > 0 {
-            eprintln!(
-                "Matches after:
-        pushed        {:>8}
-        transform     {:>8}
-        local pruning {:>8}",
-                self.stats.pushed, self.stats.after_transform, self.stats.after_local_pruning
+        if self.config.local_pruning > 0 && tracing::enabled!(Level::DEBUG) {
+            debug!(
+                pushed = self.stats.pushed,
+                transform = self.stats.after_transform,
+                local_pruning = self.stats.after_local_pruning,
+                "Matches after local pruning"
             );
-
-            eprintln!("Local pruning up to");
-            for (g, cnt) in self.local_pruning_cache[2].iter().enumerate() {
-                eprint!("{g:>0$} ", format!("{cnt}").len());
-            }
-            eprintln!();
-            for cnt in &self.local_pruning_cache[2] {
-                eprint!("{cnt} ");
-            }
-            eprintln!();
+            let cache = &self.local_pruning_cache[2];
+            let g = cache
+                .iter()
+                .enumerate()
+                .map(|(g, cnt)| format!("{g:>0$}", format!("{cnt}").len()))
+                .join(" ");
+            let cnt = cache.iter().join(" ");
+            trace!("Local pruning up to\n{g}\n{cnt}");
         }
 
         Matches {
@@ -420,6 +418,34 @@ impl MatchConfig {
             local_pruning: 0,
         }
     }
+
+    /// Choose `k` and `r` for the given sequences and estimated error rate
+    /// `e_est`, e.g. from `estimate_divergence`.
+    ///
+    /// Following A*PA:
+    /// - `k` must be somewhat larger than `log_4(n)` to avoid spurious matches,
+    /// - the expected number of errors per seed `e*k` must be below `r`, with
+    ///   some margin, for the heuristic to be informative.
+    ///
+    /// Exact matches are used when `e*k <= 1/2`. Otherwise inexact matches
+    /// are used, and `k` is lowered when needed so that `e*k <= r/2 = 1`.
+    /// For very divergent inputs `k` becomes small and seeds have many
+    /// matches, so callers should not use seed heuristics there.
+    pub fn auto(a: Seq, b: Seq, e_est: f32) -> Self {
+        let n = max(max(a.len(), b.len()), 1) as f32;
+        let k = (n.log(4.).ceil() as I + 2).clamp(8, 31);
+        let e = e_est.clamp(0., 1.);
+        if e * (k as f32) <= 0.5 {
+            Self::exact(k)
+        } else {
+            // Since `e <= 1`, this holds at the latest for `k = 1`.
+            let mut k = k;
+            while e * (k as f32) > 1. {
+                k -= 1;
+            }
+            Self::inexact(k)
+        }
+    }
 }
 
 impl Default for MatchConfig {
@@ -431,3 +457,30 @@ impl Default for MatchConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn auto() {
+        for n in [100, 10_000, 1_000_000] {
+            let a = vec![b'A'; n];
+            for e in (0..=100).map(|e| e as f32 / 100.) {
+                let MatchConfig { length, r, .. } = MatchConfig::auto(&a, &a, e);
+                let Fixed(k) = length else {
+                    panic!("auto uses a fixed k");
+                };
+                assert!(k >= 1);
+                assert!(e * (k as f32) < r as f32, "n={n} e={e} k={k} r={r}");
+                assert!(2. * e * (k as f32) <= r as f32, "n={n} e={e} k={k} r={r}");
+            }
+        }
+        // Low divergence keeps `k` above `log_4(n)` and uses exact matches.
+        let a = vec![b'A'; 10_000];
+        for (e, k, r) in [(0.01, 9, 1), (0.1, 9, 2), (0.125, 8, 2), (0.25, 4, 2)] {
+            let config = MatchConfig::auto(&a, &a, e);
+            assert_eq!((config.length.k(), config.r), (Some(k), r), "e={e}");
+        }
+    }
+}
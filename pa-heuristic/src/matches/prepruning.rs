@@ -18,6 +18,7 @@ use std::{
 };
 
 use super::{CenteredVec, Match};
+use crate::coords::PosExt;
 use crate::seeds::Seeds;
 use pa_types::{Cost, Pos, Seq, I};
 
@@ -137,7 +138,7 @@ pub fn preserve_for_local_pruning(
         stats[0] += 1;
         return true;
     }
-    if next_match_per_diag.index(e.0 - e.1) <= fr[pd] {
+    if next_match_per_diag.index(e.diagonal()) <= fr[pd] {
         stats[0] += 1;
         return true;
     }
@@ -180,7 +181,7 @@ pub fn preserve_for_local_pruning(
         // extend
         for d in d_range.clone() {
             let i = &mut fr[d];
-            let dd = e.0 - e.1 + (d as I - pd as I);
+            let dd = e.diagonal() + (d as I - pd as I);
             let j = *i - dd;
             let old_i = *i;
 
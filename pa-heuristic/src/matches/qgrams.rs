@@ -1,6 +1,7 @@
 use itertools::izip;
 
 use super::*;
+use crate::packed::PackedSeq;
 use crate::prelude::*;
 
 // NOTE: This assumes an alphabet of 'ACGT'.
@@ -60,21 +61,39 @@ impl<'a> QGrams<'a> {
     }
 
     pub fn b_qgrams(&self, k: I) -> impl '_ + Iterator<Item = (I, usize)> + Clone {
+        Self::rolling_qgrams(k, self.b.iter().map(|&c| Self::char_to_bits(c)))
+    }
+
+    /// The qgram bits of a 2-bit `PackedSeq` code.
+    #[inline]
+    pub fn code_to_bits(code: u8) -> usize {
+        // A=0, C=1, G=2, T=3 => A=0, C=1, G=3, T=2.
+        (code ^ (code >> 1)) as usize
+    }
+
+    /// Like `b_qgrams`, but directly on a packed sequence, without unpacking it.
+    pub fn packed_qgrams(b: &PackedSeq, k: I) -> impl '_ + Iterator<Item = (I, usize)> + Clone {
+        Self::rolling_qgrams(k, b.codes().map(Self::code_to_bits))
+    }
+
+    /// All qgrams of length `k` of a sequence of character bits, with their start positions.
+    fn rolling_qgrams(
+        k: I,
+        bits: impl Iterator<Item = usize> + Clone,
+    ) -> impl Iterator<Item = (I, usize)> + Clone {
         let mut q = 0;
         let mask = 1usize
             .checked_shl(k as u32 * W)
             .unwrap_or(0)
             .wrapping_sub(1);
         (0..).zip(
-            self.b
-                .iter()
-                .map(move |&c| {
-                    q <<= W;
-                    q |= Self::char_to_bits(c);
-                    q &= mask;
-                    q
-                })
-                .skip(k as usize - 1),
+            bits.map(move |c| {
+                q <<= W;
+                q |= c;
+                q &= mask;
+                q
+            })
+            .skip(k as usize - 1),
         )
     }
 
@@ -122,6 +141,12 @@ mod test {
         assert_eq!(QGrams::char_to_bits(b'T'), 0b10);
         assert_eq!(QGrams::to_qgram(b"ACGT"), 0b00_01_11_10);
         assert_eq!(QGrams::to_qgram(b"TGCA"), 0b10_11_01_00);
+        for c in *b"ACGT" {
+            assert_eq!(
+                QGrams::code_to_bits(PackedSeq::encode(c)),
+                QGrams::char_to_bits(c)
+            );
+        }
     }
     #[test]
     fn iterators() {
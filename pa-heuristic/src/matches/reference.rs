@@ -0,0 +1,226 @@
+//! A k-mer index over a fixed reference sequence `b`, that can be reused when
+//! aligning many queries `a` against the same reference.
+use super::inexact::{key_for_sized_qgram, mutations};
+use super::*;
+use crate::packed::PackedSeq;
+use crate::prelude::*;
+use smallvec::SmallVec;
+
+/// Hashmap from (sized) k-mers of the reference to their start positions.
+///
+/// For `r=1` only k-mers of length `k` are stored.
+/// For `r=2` k-mers of length `k-1`, `k`, and `k+1` are stored, so that all
+/// inexact matches of a seed can be found.
+pub struct ReferenceIndex {
+    config: MatchConfig,
+    kmers: HashMap<usize, SmallVec<[I; 4]>>,
+}
+
+/// A `MatchConfig` that can not be used for a `ReferenceIndex`.
+#[derive(Clone, Copy, Debug)]
+pub struct UnsupportedConfig(pub MatchConfig);
+
+impl std::fmt::Display for UnsupportedConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "A ReferenceIndex only works for fixed k <= 31 and r = 1 or r = 2, not {:?}",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedConfig {}
+
+/// The `MatchConfig` passed to `ReferenceIndex::find_matches` has a different
+/// `k` or `r` than the index.
+#[derive(Clone, Copy, Debug)]
+pub struct ConfigMismatch {
+    pub index: MatchConfig,
+    pub query: MatchConfig,
+}
+
+impl std::fmt::Display for ConfigMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "MatchConfig {:?} does not match the ReferenceIndex config {:?}",
+            self.query, self.index
+        )
+    }
+}
+
+impl std::error::Error for ConfigMismatch {}
+
+impl ReferenceIndex {
+    /// Index all k-mers of `b`.
+    /// Only fixed-length seeds with `k <= 31` and `r=1` or `r=2` are
+    /// supported; other configs return an error.
+    pub fn new(b: Seq, config: MatchConfig) -> Result<Self, UnsupportedConfig> {
+        let qgrams = QGrams::new(&[], b);
+        Self::build(b.len(), config, |k| qgrams.b_qgrams(k))
+    }
+
+    /// Index all k-mers of a 2-bit packed reference, without unpacking it.
+    /// The resulting index is the same as for `new(&b.to_ascii(), config)`.
+    pub fn from_packed(b: &PackedSeq, config: MatchConfig) -> Result<Self, UnsupportedConfig> {
+        Self::build(b.len(), config, |k| QGrams::packed_qgrams(b, k))
+    }
+
+    fn build<It: Iterator<Item = (I, usize)>>(
+        n: usize,
+        config: MatchConfig,
+        b_qgrams: impl Fn(I) -> It,
+    ) -> Result<Self, UnsupportedConfig> {
+        let Fixed(k) = config.length else {
+            return Err(UnsupportedConfig(config));
+        };
+        if !(config.r == 1 || config.r == 2) || k > 31 {
+            return Err(UnsupportedConfig(config));
+        }
+        let ks = if config.r == 1 { k..=k } else { k - 1..=k + 1 };
+        let mut kmers = HashMap::<usize, SmallVec<[I; 4]>>::default();
+        kmers.reserve(ks.clone().count() * n);
+        for k in ks {
+            for (j, w) in b_qgrams(k) {
+                kmers.entry(key_for_sized_qgram(k, w)).or_default().push(j);
+            }
+        }
+        Ok(Self { config, kmers })
+    }
+
+    pub fn match_config(&self) -> MatchConfig {
+        self.config
+    }
+
+    fn get(&self, k: I, qgram: usize) -> &[I] {
+        self.kmers
+            .get(&key_for_sized_qgram(k, qgram))
+            .map_or(&[], |js| js.as_slice())
+    }
+
+    /// Find all matches between the seeds of `a` and the indexed reference `b`.
+    /// `b` must be the sequence this index was built on.
+    /// When `config` does not have the same `k` and `r` as the index, an
+    /// error is returned.
+    ///
+    /// Returns the same matches as `find_matches(a, b, config, transform_filter)`.
+    pub fn find_matches<'a>(
+        &self,
+        a: Seq<'a>,
+        b: Seq<'a>,
+        config: MatchConfig,
+        transform_filter: bool,
+    ) -> Result<Matches, ConfigMismatch> {
+        let k = self.config.length.k().unwrap();
+        let r = self.config.r;
+        if config.length.k() != Some(k) || config.r != r {
+            return Err(ConfigMismatch {
+                index: self.config,
+                query: config,
+            });
+        }
+
+        let qgrams = QGrams::new(a, b);
+        let mut matches = MatchBuilder::new(&qgrams, config, transform_filter);
+
+        for i in (0..matches.seeds.seeds.len()).rev() {
+            let Seed { start, end, .. } = matches.seeds.seeds[i];
+            let qgram = QGrams::to_qgram(&qgrams.a[start as usize..end as usize]);
+            let matches_before_seed = matches.matches.len();
+            for &j in self.get(k, qgram) {
+                matches.push(Match {
+                    start: Pos(start, j),
+                    end: Pos(end, j + k),
+                    match_cost: 0,
+                    seed_potential: r,
+                    pruned: MatchStatus::Active,
+                });
+            }
+            if r == 2 {
+                let ms = mutations(k, qgram, false);
+                for (ws, len) in [
+                    (ms.deletions, k - 1),
+                    (ms.substitutions, k),
+                    (ms.insertions, k + 1),
+                ] {
+                    for w in ws {
+                        for &j in self.get(len, w) {
+                            matches.push(Match {
+                                start: Pos(start, j),
+                                end: Pos(end, j + len),
+                                match_cost: 1,
+                                seed_potential: r,
+                                pruned: MatchStatus::Active,
+                            });
+                        }
+                    }
+                }
+            }
+            matches.matches[matches_before_seed..]
+                .sort_by_key(|m| (LexPos(m.start), LexPos(m.end), m.match_cost));
+        }
+
+        Ok(matches.finish())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use pa_generate::uniform_fixed;
+
+    use super::*;
+
+    #[test]
+    fn reference_index_equals_find_matches() {
+        for (k, r) in [(6, 1), (10, 1), (6, 2), (10, 2)] {
+            for n in [40, 200, 1000] {
+                for e in [0.01, 0.1, 0.3] {
+                    let (a, b) = uniform_fixed(n, e);
+                    let config = MatchConfig::new(k, r);
+                    let index = ReferenceIndex::new(&b, config).unwrap();
+                    let packed_index =
+                        ReferenceIndex::from_packed(&PackedSeq::from_ascii(&b), config).unwrap();
+                    let key = |m: &Match| (LexPos(m.start), LexPos(m.end), m.match_cost);
+                    let mut m1 = find_matches(&a, &b, config, false).matches;
+                    let mut m2 = index.find_matches(&a, &b, config, false).unwrap().matches;
+                    let mut m3 = packed_index
+                        .find_matches(&a, &b, config, false)
+                        .unwrap()
+                        .matches;
+                    m1.sort_by_key(key);
+                    m2.sort_by_key(key);
+                    m3.sort_by_key(key);
+                    assert_eq!(m1, m2);
+                    assert_eq!(m1, m3);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reference_index_rejects_unsupported_configs() {
+        let b = b"ACGTACGTACGT";
+        assert!(ReferenceIndex::new(b, MatchConfig::new(6, 3)).is_err());
+        assert!(ReferenceIndex::new(b, MatchConfig::new(32, 1)).is_err());
+        let variable = MatchConfig {
+            length: LengthConfig::Max(MaxMatches {
+                max_matches: 1,
+                k_min: 4,
+                k_max: 10,
+            }),
+            ..MatchConfig::new(6, 1)
+        };
+        assert!(ReferenceIndex::new(b, variable).is_err());
+    }
+
+    #[test]
+    fn reference_index_rejects_mismatched_config() {
+        let (a, b) = uniform_fixed(200, 0.1);
+        let index = ReferenceIndex::new(&b, MatchConfig::new(6, 1)).unwrap();
+        for ((k, r), ok) in [((7, 1), false), ((6, 2), false), ((6, 1), true)] {
+            let matches = index.find_matches(&a, &b, MatchConfig::new(k, r), false);
+            assert_eq!(matches.is_ok(), ok);
+        }
+    }
+}
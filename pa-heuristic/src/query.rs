@@ -0,0 +1,163 @@
+//! A type-erased interface to build a heuristic and query it from outside an
+//! aligner, e.g. to compare `h(0,0)` to the true distance.
+use crate::matches::Match;
+use crate::prelude::*;
+use crate::seeds::Seeds;
+use crate::{
+    HExplanation, Heuristic, HeuristicInstance, HeuristicMapper, HeuristicParams, HeuristicStats,
+};
+use std::io;
+
+/// A heuristic instantiated for a fixed pair of sequences.
+pub trait HeuristicQuery {
+    /// The value of the heuristic at `pos`.
+    fn h(&self, pos: Pos) -> Cost;
+
+    /// The contour layer at `pos`, for heuristics that use contours.
+    fn layer(&self, pos: Pos) -> Option<Cost>;
+
+    /// The seeds of `a`, for heuristics that use seeds.
+    fn seeds(&self) -> Option<&Seeds>;
+
+    /// All matches, including pruned ones, for heuristics that use matches.
+    fn matches(&self) -> Option<Vec<Match>>;
+
+    /// The matches responsible for `h(pos)`, for heuristics based on chains of matches.
+    fn explain(&self, pos: Pos) -> Option<HExplanation>;
+
+    /// Prune the matches starting or ending at `pos`, as A* does when `pos`
+    /// is expanded. Only does something when pruning is enabled.
+    fn prune(&mut self, pos: Pos);
+
+    fn stats(&mut self) -> HeuristicStats;
+}
+
+impl<'a, HI: HeuristicInstance<'a>> HeuristicQuery for HI {
+    fn h(&self, pos: Pos) -> Cost {
+        HeuristicInstance::h(self, pos)
+    }
+
+    fn layer(&self, pos: Pos) -> Option<Cost> {
+        HeuristicInstance::layer(self, pos)
+    }
+
+    fn seeds(&self) -> Option<&Seeds> {
+        HeuristicInstance::seeds(self)
+    }
+
+    fn matches(&self) -> Option<Vec<Match>> {
+        HeuristicInstance::matches(self)
+    }
+
+    fn explain(&self, pos: Pos) -> Option<HExplanation> {
+        HeuristicInstance::explain(self, pos)
+    }
+
+    fn prune(&mut self, pos: Pos) {
+        let (_, hint) = self.h_with_hint(pos, Default::default());
+        HeuristicInstance::prune(self, pos, hint);
+    }
+
+    fn stats(&mut self) -> HeuristicStats {
+        HeuristicInstance::stats(self)
+    }
+}
+
+impl HeuristicParams {
+    /// Build the heuristic for `a` and `b`, without running an aligner.
+    pub fn build_query<'a>(&self, a: Seq<'a>, b: Seq<'a>) -> Box<dyn HeuristicQuery + 'a> {
+        struct Builder<'a> {
+            a: Seq<'a>,
+            b: Seq<'a>,
+        }
+        impl<'a> HeuristicMapper for Builder<'a> {
+            type R = Box<dyn HeuristicQuery + 'a>;
+            fn call<H: Heuristic + 'static>(self, h: H) -> Self::R {
+                Box::new(h.build(self.a, self.b))
+            }
+        }
+        self.map(Builder { a, b })
+    }
+}
+
+/// The header line written by `write_seeds_and_matches`.
+pub const SEEDS_AND_MATCHES_HEADER: &str =
+    "#pair\tkind\tstart_i\tstart_j\tend_i\tend_j\tcost\tpotential\tstatus";
+
+/// Write one tab-separated line per seed and per match of `h`, starting with `pair`.
+/// Seeds only span `a`, so their `j` columns are `.`.
+/// For seeds, `cost` is a lower bound on the cost of crossing the seed.
+pub fn write_seeds_and_matches(
+    h: &dyn HeuristicQuery,
+    pair: usize,
+    out: &mut dyn io::Write,
+) -> io::Result<()> {
+    if let Some(seeds) = h.seeds() {
+        for s in &seeds.seeds {
+            writeln!(
+                out,
+                "{pair}\tseed\t{}\t.\t{}\t.\t{}\t{}\t.",
+                s.start, s.end, s.seed_cost, s.seed_potential
+            )?;
+        }
+    }
+    for m in h.matches().unwrap_or_default() {
+        writeln!(
+            out,
+            "{pair}\tmatch\t{}\t{}\t{}\t{}\t{}\t{}\t{:?}",
+            m.start.0, m.start.1, m.end.0, m.end.1, m.match_cost, m.seed_potential, m.pruned
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{HeuristicType, Prune};
+
+    #[test]
+    fn gcsh() {
+        let (a, b) = pa_generate::uniform_seeded(10000, 0.05, 31415);
+        let dist = bio::alignment::distance::simd::levenshtein(&a, &b) as Cost;
+        let params = HeuristicParams {
+            heuristic: HeuristicType::GCSH,
+            prune: Prune::Start,
+            k: 15,
+            r: 2,
+            ..Default::default()
+        };
+        let mut h = params.build_query(&a, &b);
+        let h0 = h.h(Pos(0, 0));
+        assert!(0 < h0 && h0 <= dist, "h0 {h0} dist {dist}");
+        assert_eq!(h.h(Pos::target(&a, &b)), 0);
+        assert_eq!(h.seeds().unwrap().seeds.len(), a.len() / 15);
+        let matches = h.matches().unwrap();
+        assert!(!matches.is_empty());
+
+        // The explanation of `h(0,0)` is a chain of matches over the full layer.
+        let e = h.explain(Pos(0, 0)).unwrap();
+        assert_eq!(e.layer, h.layer(Pos(0, 0)).unwrap());
+        assert_eq!(
+            e.chain.iter().map(|m| m.score() as Cost).sum::<Cost>(),
+            e.layer
+        );
+
+        // Pruning the first match does not increase `h` above the distance.
+        let first = matches.iter().map(|m| m.start).min().unwrap();
+        h.prune(first);
+        assert!(h.h(Pos(0, 0)) <= dist);
+        assert!(h.stats().num_pruned > 0);
+        let e = h.explain(Pos(0, 0)).unwrap();
+        assert!(e.inactive.iter().any(|m| m.start == first));
+
+        let mut out = vec![];
+        write_seeds_and_matches(h.as_ref(), 0, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out.lines().count(),
+            h.seeds().unwrap().seeds.len() + h.matches().unwrap().len()
+        );
+        assert!(out.lines().all(|l| l.split('\t').count() == 9));
+    }
+}
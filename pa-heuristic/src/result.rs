@@ -0,0 +1,53 @@
+//! A common return type for all aligners, so that configurations can be
+//! compared without knowing the aligner-specific statistics types.
+use crate::heuristic::HeuristicStats;
+use crate::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Statistics shared by all aligners. Fields that do not apply to an aligner are 0.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlignStats {
+    /// Seeds, matches, pruned matches, and contour layers of the heuristic.
+    pub h: HeuristicStats,
+    /// Number of states expanded by A*.
+    pub expanded: usize,
+    /// Number of computed rows, summed over all computed columns.
+    pub computed_rows: usize,
+    /// Total time spent aligning, including precomputation.
+    pub t_total: Duration,
+    /// Time spent building the heuristic.
+    pub t_precomp: Duration,
+}
+
+/// The result of aligning two sequences.
+///
+/// See `format` for the stable serialization format.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AlignResult {
+    pub cost: Cost,
+    #[serde(with = "crate::format::cigar")]
+    pub cigar: Option<Cigar>,
+    /// The path corresponding to `cigar`.
+    #[serde(with = "crate::format::path")]
+    pub path: Option<Path>,
+    pub stats: AlignStats,
+}
+
+impl AlignResult {
+    pub fn new(cost: Cost, cigar: Option<Cigar>, stats: AlignStats) -> Self {
+        let path = cigar.as_ref().map(|c| c.to_path());
+        AlignResult {
+            cost,
+            cigar,
+            path,
+            stats,
+        }
+    }
+}
+
+/// An aligner that additionally returns statistics in a common format.
+pub trait ResultAligner: Aligner {
+    fn align_result(&mut self, a: Seq, b: Seq) -> AlignResult;
+}
@@ -0,0 +1,45 @@
+//! The order in which parents of equal cost are preferred during traceback,
+//! shared by all aligners.
+use serde::{Deserialize, Serialize};
+
+/// A non-match edge considered when tracing back an alignment.
+/// Affine gap edges count as the linear edge in the same direction.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TraceEdge {
+    Sub,
+    Ins,
+    Del,
+}
+
+/// The order in which parents of equal cost are preferred during traceback.
+///
+/// Matches are always preferred over other edges. Using the same order for all
+/// aligners makes their cigars deterministic and comparable.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct TraceOrder(pub [TraceEdge; 3]);
+
+impl TraceOrder {
+    /// The rank of `edge` in this order, where lower is preferred.
+    /// Matches (`None`) have rank 0, and edges missing from the order come last.
+    pub fn rank(&self, edge: Option<TraceEdge>) -> usize {
+        match edge {
+            None => 0,
+            Some(edge) => 1 + self.0.iter().position(|&e| e == edge).unwrap_or(3),
+        }
+    }
+
+    /// All edges, from most to least preferred.
+    /// Edges missing from the order come last.
+    pub fn edges(self) -> impl DoubleEndedIterator<Item = TraceEdge> {
+        let missing = [TraceEdge::Sub, TraceEdge::Ins, TraceEdge::Del]
+            .into_iter()
+            .filter(move |e| !self.0.contains(e));
+        self.0.into_iter().chain(missing)
+    }
+}
+
+impl Default for TraceOrder {
+    fn default() -> Self {
+        TraceOrder([TraceEdge::Sub, TraceEdge::Del, TraceEdge::Ins])
+    }
+}
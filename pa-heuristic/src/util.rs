@@ -1,6 +1,8 @@
 use instant::Instant;
+use pa_types::{Cost, Seq, I};
 
 use crate::config::TIME;
+use tracing::error;
 
 // Time the duration once every n iterations.
 const TIME_EACH: usize = 64;
@@ -37,6 +39,149 @@ impl Timer {
     }
 }
 
+/// Whether `a` and `b` can be aligned with the 32-bit index type `I`.
+///
+/// Positions, diagonals and costs are all stored as `I`. The unit cost is at
+/// most `a.len() + b.len()`, and band doubling may try bounds up to twice
+/// that, so the total length must fit in half the range of `I`.
+/// Longer sequences need the 64-bit path in `pa_bitpacking::wide`.
+pub fn fits_index(a: Seq, b: Seq) -> bool {
+    2 * (a.len() + b.len()) <= I::MAX as usize
+}
+
+/// Panics when `a` and `b` do not `fits_index`.
+/// Call this before aligning, so that long inputs fail loudly instead of
+/// silently overflowing.
+pub fn assert_fits_index(a: Seq, b: Seq) {
+    assert!(
+        fits_index(a, b),
+        "Sequences of length {} and {} are too long: positions and costs must fit in {} bits. \
+         Use `pa_bitpacking::wide` for their distance.",
+        a.len(),
+        b.len(),
+        I::BITS
+    );
+}
+
+/// The number of bytes compared at once by `count_matches` and `count_matches_rev`.
+const W: usize = 8;
+
+/// The length of the longest common prefix of `a` and `b`.
+///
+/// Compares 8 characters at a time by XOR-ing them as `u64` words, and finds
+/// the first mismatch using `trailing_zeros`. The last few characters before
+/// the end of either sequence are compared one by one.
+#[inline]
+pub fn count_matches(a: &[u8], b: &[u8]) -> usize {
+    let max = std::cmp::min(a.len(), b.len());
+    let mut cnt = 0;
+    while cnt + W <= max {
+        let x = u64::from_le_bytes(a[cnt..cnt + W].try_into().unwrap());
+        let y = u64::from_le_bytes(b[cnt..cnt + W].try_into().unwrap());
+        if x != y {
+            return cnt + (x ^ y).trailing_zeros() as usize / 8;
+        }
+        cnt += W;
+    }
+    while cnt < max && a[cnt] == b[cnt] {
+        cnt += 1;
+    }
+    cnt
+}
+
+/// The length of the longest common suffix of `a` and `b`.
+///
+/// Same as `count_matches`, but going backwards, using `leading_zeros`.
+#[inline]
+pub fn count_matches_rev(a: &[u8], b: &[u8]) -> usize {
+    let max = std::cmp::min(a.len(), b.len());
+    let (a, b) = (&a[a.len() - max..], &b[b.len() - max..]);
+    let mut cnt = 0;
+    while cnt + W <= max {
+        let x = u64::from_le_bytes(a[max - cnt - W..max - cnt].try_into().unwrap());
+        let y = u64::from_le_bytes(b[max - cnt - W..max - cnt].try_into().unwrap());
+        if x != y {
+            return cnt + (x ^ y).leading_zeros() as usize / 8;
+        }
+        cnt += W;
+    }
+    while cnt < max && a[max - cnt - 1] == b[max - cnt - 1] {
+        cnt += 1;
+    }
+    cnt
+}
+
+/// A `Cost` addition that overflowed. See `add_cost`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CostOverflow {
+    pub lhs: Cost,
+    pub rhs: Cost,
+}
+
+impl std::fmt::Display for CostOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Cost overflow: {} + {}", self.lhs, self.rhs)
+    }
+}
+
+thread_local! {
+    /// The first overflow since the last `check_cost_overflow`.
+    static OVERFLOW: std::cell::Cell<Option<CostOverflow>> = const { std::cell::Cell::new(None) };
+}
+
+/// Add two costs.
+///
+/// With the `checked_cost` feature, also in release mode, an overflow is
+/// recorded for `check_cost_overflow` and the sum saturates. Otherwise, this is
+/// a plain addition.
+#[inline(always)]
+pub fn add_cost(lhs: Cost, rhs: Cost) -> Cost {
+    if cfg!(feature = "checked_cost") {
+        lhs.checked_add(rhs).unwrap_or_else(|| {
+            record_overflow(CostOverflow { lhs, rhs });
+            lhs.saturating_add(rhs)
+        })
+    } else {
+        lhs + rhs
+    }
+}
+
+/// Subtract two costs. See `add_cost`.
+#[inline(always)]
+pub fn sub_cost(lhs: Cost, rhs: Cost) -> Cost {
+    if cfg!(feature = "checked_cost") {
+        lhs.checked_sub(rhs).unwrap_or_else(|| {
+            record_overflow(CostOverflow {
+                lhs,
+                rhs: rhs.wrapping_neg(),
+            });
+            lhs.saturating_sub(rhs)
+        })
+    } else {
+        lhs - rhs
+    }
+}
+
+#[cold]
+fn record_overflow(overflow: CostOverflow) {
+    OVERFLOW.with(|o| {
+        if o.get().is_none() {
+            o.set(Some(overflow));
+        }
+    });
+}
+
+/// Run `f`, and return the first `CostOverflow` of an `add_cost` or
+/// `sub_cost` in it, if any. Only overflows on the current thread are seen.
+pub fn check_cost_overflow<T>(f: impl FnOnce() -> T) -> Result<T, CostOverflow> {
+    OVERFLOW.with(|o| o.set(None));
+    let t = f();
+    match OVERFLOW.with(|o| o.take()) {
+        None => Ok(t),
+        Some(overflow) => Err(overflow),
+    }
+}
+
 #[test]
 fn test_time_each() {
     use std::thread::sleep;
@@ -64,3 +209,52 @@ fn test_time_speed() {
     eprintln!("elapsed:    {t}");
     eprintln!("total time: {f}");
 }
+
+#[test]
+fn test_count_matches() {
+    let naive = |a: &[u8], b: &[u8]| a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let naive_rev = |a: &[u8], b: &[u8]| {
+        a.iter()
+            .rev()
+            .zip(b.iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count()
+    };
+    let a = b"ACGTACGTACGTACGTACGTAC";
+    for n in 0..=a.len() {
+        for m in 0..=a.len() {
+            let mut b = a.to_vec();
+            if m < a.len() {
+                b[m] = b'N';
+            }
+            assert_eq!(
+                count_matches(&a[..n], &b),
+                naive(&a[..n], &b),
+                "n={n} m={m}"
+            );
+            assert_eq!(
+                count_matches_rev(&a[a.len() - n..], &b),
+                naive_rev(&a[a.len() - n..], &b),
+                "n={n} m={m}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_check_cost_overflow() {
+    assert_eq!(check_cost_overflow(|| add_cost(1, 2)), Ok(3));
+    assert_eq!(check_cost_overflow(|| sub_cost(1, 2)), Ok(-1));
+    if cfg!(feature = "checked_cost") {
+        assert_eq!(
+            check_cost_overflow(|| add_cost(add_cost(Cost::MAX, 1), 2)),
+            Err(CostOverflow {
+                lhs: Cost::MAX,
+                rhs: 1
+            })
+        );
+        assert!(check_cost_overflow(|| sub_cost(Cost::MIN, 1)).is_err());
+        // The overflow is reset for the next check.
+        assert_eq!(check_cost_overflow(|| add_cost(1, 2)), Ok(3));
+    }
+}
@@ -4,6 +4,8 @@ use std::{
     time::Duration,
 };
 
+use crate::raster::Image;
+use itertools::Itertools;
 use pa_types::I;
 
 pub fn to_label(c: u8) -> String {
@@ -104,6 +106,19 @@ pub trait Canvas {
         self.draw_rect(p, 1, 1, color);
     }
     fn draw_line(&mut self, p: CPos, q: CPos, color: Color);
+    /// Draw all non-transparent pixels of `image` with its top-left corner at `p`.
+    /// By default, each horizontal run of equal pixels is drawn as a rectangle.
+    fn draw_image(&mut self, CPos(x, y): CPos, image: &Image) {
+        for (row, pixels) in image.rows().enumerate() {
+            let mut col = 0;
+            for (run, pixel) in pixels.iter().dedup_with_count() {
+                if let Some(color) = *pixel {
+                    self.fill_rect(CPos(x + col as i32, y + row as i32), run as I, 1, color);
+                }
+                col += run;
+            }
+        }
+    }
 
     fn write_text(&mut self, p: CPos, ha: HAlign, va: VAlign, text: &str, color: Color);
 
@@ -115,3 +130,54 @@ pub trait Canvas {
 }
 
 pub type CanvasBox = Box<dyn Canvas>;
+
+/// A canvas that shifts all drawing by `offset`, leaving a margin at the top
+/// and left of the wrapped canvas. Negative positions draw into the margin.
+pub struct MarginCanvas {
+    pub inner: CanvasBox,
+    pub offset: CPos,
+}
+
+impl Canvas for MarginCanvas {
+    fn fill_background(&mut self, color: Color) {
+        self.inner.fill_background(color)
+    }
+    fn fill_rect(&mut self, p: CPos, w: I, h: I, color: Color) {
+        self.inner.fill_rect(p + self.offset, w, h, color)
+    }
+    fn fill_rects(&mut self, rects: &[(CPos, I, I)], color: Color) {
+        let rects: Vec<_> = rects
+            .iter()
+            .map(|&(p, w, h)| (p + self.offset, w, h))
+            .collect();
+        self.inner.fill_rects(&rects, color)
+    }
+    fn draw_rect(&mut self, p: CPos, w: I, h: I, color: Color) {
+        self.inner.draw_rect(p + self.offset, w, h, color)
+    }
+    fn draw_point(&mut self, p: CPos, color: Color) {
+        self.inner.draw_point(p + self.offset, color)
+    }
+    fn draw_line(&mut self, p: CPos, q: CPos, color: Color) {
+        self.inner
+            .draw_line(p + self.offset, q + self.offset, color)
+    }
+    fn draw_image(&mut self, p: CPos, image: &Image) {
+        self.inner.draw_image(p + self.offset, image)
+    }
+    fn write_text(&mut self, p: CPos, ha: HAlign, va: VAlign, text: &str, color: Color) {
+        self.inner.write_text(p + self.offset, ha, va, text, color)
+    }
+    fn save(&mut self, path: &Path) {
+        self.inner.save(path)
+    }
+    fn save_transparent(&mut self, path: &Path, bg_color: Color) {
+        self.inner.save_transparent(path, bg_color)
+    }
+    fn present(&mut self) {
+        self.inner.present()
+    }
+    fn wait(&mut self, timeout: Duration) -> KeyboardAction {
+        self.inner.wait(timeout)
+    }
+}
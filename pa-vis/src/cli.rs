@@ -1,4 +1,4 @@
-use crate::visualizer::{Config, VisualizerStyle, When};
+use crate::visualizer::{Config, Palette, VisualizerStyle, When};
 use super::{canvas::*, VisualizerT};
 use clap::{value_parser, Parser};
 use pa_types::I;
@@ -31,6 +31,16 @@ pub struct VisualizerArgs {
     )]
     pub style: VisualizerStyle,
 
+    /// Color palette, overriding the colors of the style.
+    #[clap(
+        long,
+        default_value_t,
+        value_enum,
+        display_order = 2,
+        hide_short_help = true
+    )]
+    pub palette: Palette,
+
     /// Start paused.
     #[clap(short, long, display_order = 3, hide_short_help = true)]
     pub pause: bool,
@@ -72,6 +82,18 @@ pub struct VisualizerArgs {
     /// Draw parents for the chaining computation.
     #[clap(long, display_order = 10, hide_short_help = true)]
     pub draw_parents: bool,
+
+    /// Draw axes with sequence coordinates, a legend, and the title.
+    #[clap(long, display_order = 10, hide_short_help = true)]
+    pub annotate: bool,
+
+    /// Rasterize frames in memory before drawing them. Faster for large inputs.
+    #[clap(long, display_order = 10, hide_short_help = true)]
+    pub raster: bool,
+
+    /// The title drawn above the image with --annotate.
+    #[clap(long, display_order = 10, hide_short_help = true)]
+    pub title: Option<String>,
 }
 
 pub trait VisualizerRunner {
@@ -119,6 +141,7 @@ impl VisualizerArgs {
         update(&mut config.save);
 
         config.paused = self.pause;
+        config.style.palette = self.palette;
 
         // Apply CLI flag customizations to the style.
         config.cell_size = self.cell_size.unwrap_or(0);
@@ -132,6 +155,14 @@ impl VisualizerArgs {
             config.style.tree = None;
         }
 
+        if self.annotate {
+            config.style.draw_axes = true;
+            config.style.draw_legend = true;
+            config.style.draw_title = true;
+        }
+        config.title = self.title.clone();
+        config.raster |= self.raster;
+
         if self.draw_parents {
             config.style.draw_dt = false;
             config.style.draw_f = false;
@@ -0,0 +1,266 @@
+//! Dot-plots as SVG, a lightweight alternative to the visualizer for reports:
+//! - `dot_plot` shows the matches of a heuristic, with the alignment path on top.
+//! - `KmerDotPlot` shows all shared k-mers, possibly downsampled, which helps
+//!   to understand alignments of repetitive sequences.
+use crate::canvas::{Color, BLACK, BLUE, GRAY, RED};
+use pa_heuristic::{matches::qgrams::QGrams, matches::MatchStatus, HeuristicQuery};
+use pa_types::*;
+use std::{collections::HashMap, fmt::Write};
+
+/// The width in pixels of the longest side of the plot.
+const SIZE: usize = 1000;
+
+const EXACT: Color = BLACK;
+const INEXACT: Color = BLUE;
+const PRUNED: Color = RED;
+const PATH: Color = GRAY;
+
+/// Invalid arguments for a plot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlotError {
+    /// The maximum number of cells along an axis is 0.
+    ZeroSize,
+    /// The input matrix has no rows or no columns.
+    EmptyMatrix,
+}
+
+impl std::fmt::Display for PlotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlotError::ZeroSize => write!(f, "The plot must have at least one cell."),
+            PlotError::EmptyMatrix => write!(f, "The matrix must not be empty."),
+        }
+    }
+}
+
+impl std::error::Error for PlotError {}
+
+pub(crate) fn hex((r, g, b, _): Color) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// An SVG with `a` along the x-axis and `b` along the y-axis, showing the
+/// matches of `h` as black (exact), blue (inexact), or red (pruned) lines,
+/// and the path of `cigar` in gray.
+pub fn dot_plot(h: &dyn HeuristicQuery, a: Seq, b: Seq, cigar: Option<&Cigar>) -> String {
+    let Pos(n, m) = Pos::target(a, b);
+    let scale = SIZE as f32 / n.max(m).max(1) as f32;
+    let (w, h_px) = (n as f32 * scale, m as f32 * scale);
+
+    let mut s = String::new();
+    writeln!(
+        s,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.0}" height="{h_px:.0}" viewBox="0 0 {n} {m}">"#
+    )
+    .unwrap();
+    writeln!(s, r#"<rect width="{n}" height="{m}" fill="white"/>"#).unwrap();
+    writeln!(s, r#"<g stroke-width="2" stroke-linecap="round">"#).unwrap();
+    for mt in h.matches().unwrap_or_default() {
+        let color = if mt.pruned != MatchStatus::Active {
+            PRUNED
+        } else if mt.match_cost == 0 {
+            EXACT
+        } else {
+            INEXACT
+        };
+        writeln!(
+            s,
+            r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" vector-effect="non-scaling-stroke"/>"#,
+            mt.start.0,
+            mt.start.1,
+            mt.end.0,
+            mt.end.1,
+            hex(color)
+        )
+        .unwrap();
+    }
+    writeln!(s, "</g>").unwrap();
+
+    if let Some(cigar) = cigar {
+        let points = cigar
+            .to_path()
+            .iter()
+            .map(|Pos(i, j)| format!("{i},{j}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            s,
+            r#"<polyline points="{points}" fill="none" stroke="{}" stroke-width="3" vector-effect="non-scaling-stroke"/>"#,
+            hex(PATH)
+        )
+        .unwrap();
+    }
+    writeln!(s, "</svg>").unwrap();
+    s
+}
+
+/// A k-mer dot-plot of two sequences: the number of shared k-mers starting in
+/// each square of `scale x scale` positions.
+///
+/// NOTE: This takes time linear in the number of shared k-mers, which is
+/// quadratic for long repeats with small `k`.
+pub struct KmerDotPlot {
+    pub k: I,
+    /// The number of positions of `a` and `b` in each cell.
+    pub scale: I,
+    /// The number of cells along `a`.
+    pub width: usize,
+    /// The number of cells along `b`.
+    pub height: usize,
+    /// Row-major counts, indexed by `y * width + x`.
+    pub counts: Vec<u32>,
+}
+
+impl KmerDotPlot {
+    /// Count the shared k-mers of `a` and `b`, using at most `max_size` cells
+    /// along either axis. Returns an error when `max_size` is 0.
+    pub fn new(a: Seq, b: Seq, k: I, max_size: usize) -> Result<Self, PlotError> {
+        if max_size == 0 {
+            return Err(PlotError::ZeroSize);
+        }
+        let scale = a.len().max(b.len()).div_ceil(max_size).max(1) as I;
+        let width = a.len().div_ceil(scale as usize).max(1);
+        let height = b.len().div_ceil(scale as usize).max(1);
+        let mut counts = vec![0; width * height];
+
+        let mut b_kmers: HashMap<usize, Vec<I>> = HashMap::new();
+        for (j, q) in QGrams::new(a, b).b_qgrams(k) {
+            b_kmers.entry(q).or_default().push(j);
+        }
+        // `b_qgrams` iterates all k-mers, so swap the sequences for `a`.
+        for (i, q) in QGrams::new(b, a).b_qgrams(k) {
+            for &j in b_kmers.get(&q).into_iter().flatten() {
+                counts[(j / scale) as usize * width + (i / scale) as usize] += 1;
+            }
+        }
+        Ok(KmerDotPlot {
+            k,
+            scale,
+            width,
+            height,
+            counts,
+        })
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u32 {
+        self.counts[y * self.width + x]
+    }
+
+    /// An SVG with a black square for each non-empty cell, shaded by the
+    /// fraction of the maximum count.
+    pub fn to_svg(&self) -> String {
+        let max = self.counts.iter().copied().max().unwrap_or(0).max(1);
+        let scale = SIZE as f32 / self.width.max(self.height) as f32;
+        let mut s = String::new();
+        writeln!(
+            s,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+            self.width as f32 * scale,
+            self.height as f32 * scale,
+            self.width,
+            self.height
+        )
+        .unwrap();
+        writeln!(
+            s,
+            r#"<rect width="{}" height="{}" fill="white"/>"#,
+            self.width, self.height
+        )
+        .unwrap();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let c = self.get(x, y);
+                if c == 0 {
+                    continue;
+                }
+                // Keep single k-mers visible when the maximum is large.
+                let opacity = (c as f32 / max as f32).max(0.2);
+                writeln!(
+                    s,
+                    r#"<rect x="{x}" y="{y}" width="1" height="1" fill-opacity="{opacity:.2}"/>"#
+                )
+                .unwrap();
+            }
+        }
+        writeln!(s, "</svg>").unwrap();
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pa_heuristic::{Heuristic, MatchConfig, Pruning, GCSH};
+
+    #[test]
+    fn dot_plot_matches_and_path() {
+        let a = b"ACGTTGCAACGTTGCA";
+        let b = b"ACGTTGCAACGTAGCA";
+        let h = GCSH::new(MatchConfig::exact(4), Pruning::disabled()).build(a, b);
+        let num_matches = HeuristicQuery::matches(&h).unwrap().len();
+        assert!(num_matches > 0);
+
+        let svg = dot_plot(&h, a, b, None);
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert_eq!(svg.matches("<line").count(), num_matches);
+        assert!(!svg.contains("<polyline"));
+
+        let cigar = Cigar {
+            ops: vec![
+                CigarElem {
+                    op: CigarOp::Match,
+                    cnt: 12,
+                },
+                CigarElem {
+                    op: CigarOp::Sub,
+                    cnt: 1,
+                },
+                CigarElem {
+                    op: CigarOp::Match,
+                    cnt: 3,
+                },
+            ],
+        };
+        let svg = dot_plot(&h, a, b, Some(&cigar));
+        assert_eq!(svg.matches("<polyline").count(), 1);
+        assert!(svg.contains(r#"points="0,0 1,1"#));
+        assert!(svg.contains(r#" 16,16""#));
+    }
+
+    #[test]
+    fn kmer_dot_plot_counts() {
+        // `ACGT` occurs twice in `a` and three times in `b`.
+        let a = b"ACGTTTACGT";
+        let b = b"ACGTACGTACGT";
+        let plot = KmerDotPlot::new(a, b, 4, 100).unwrap();
+        assert_eq!(plot.scale, 1);
+        assert_eq!((plot.width, plot.height), (a.len(), b.len()));
+        for (i, j) in [(0, 0), (0, 4), (0, 8), (6, 0), (6, 4), (6, 8)] {
+            assert_eq!(plot.get(i, j), 1, "({i}, {j})");
+        }
+        // All shared k-mers are counted, also when downsampling.
+        let total: u32 = plot.counts.iter().sum();
+        let small = KmerDotPlot::new(a, b, 4, 3).unwrap();
+        assert!(small.width <= 3 && small.height <= 3);
+        assert_eq!(small.scale, 4);
+        assert_eq!(small.counts.iter().sum::<u32>(), total);
+
+        let svg = plot.to_svg();
+        let non_empty = plot.counts.iter().filter(|&&c| c > 0).count();
+        // One rect for the background, and one per non-empty cell.
+        assert_eq!(svg.matches("<rect").count(), 1 + non_empty);
+    }
+
+    #[test]
+    fn kmer_dot_plot_edge_cases() {
+        assert_eq!(
+            KmerDotPlot::new(b"ACGT", b"ACGT", 2, 0).err(),
+            Some(PlotError::ZeroSize)
+        );
+        let plot = KmerDotPlot::new(b"", b"", 4, 10).unwrap();
+        assert_eq!((plot.width, plot.height), (1, 1));
+        assert_eq!(plot.get(0, 0), 0);
+        plot.to_svg();
+    }
+}
@@ -0,0 +1,132 @@
+//! Heatmaps of `f(u) = g(u) + h(u)` as SVG, showing which states a bound on
+//! `f` excludes, as in the figures of the papers.
+use crate::canvas::{Color, GRAY};
+use crate::dot_plot::{hex, PlotError};
+use crate::visualizer::Gradient;
+use pa_heuristic::HeuristicQuery;
+use pa_types::*;
+use std::fmt::Write;
+
+/// The width in pixels of the longest side of the plot.
+const SIZE: usize = 1000;
+
+/// States with `f` larger than the distance are drawn in this color.
+const EXCLUDED: Color = GRAY;
+
+/// `f(u) = g(u) + h(u)`, sampled at the top-left state of each cell of a grid.
+pub struct FLandscape {
+    /// The number of positions of `a` and `b` in each cell.
+    pub scale: I,
+    /// The number of cells along `a`.
+    pub width: usize,
+    /// The number of cells along `b`.
+    pub height: usize,
+    /// The distance `g(t)`, which is the smallest `f` on the optimal path.
+    pub distance: Cost,
+    /// Row-major values of `f`, indexed by `y * width + x`.
+    pub f: Vec<Cost>,
+}
+
+impl FLandscape {
+    /// Sample `f` from the full matrix `g[i][j]` and `h`, using at most
+    /// `max_size` cells along either axis.
+    /// Returns an error when `max_size` is 0 or `g` is empty.
+    pub fn new(
+        g: &[Vec<Cost>],
+        h: &dyn HeuristicQuery,
+        max_size: usize,
+    ) -> Result<Self, PlotError> {
+        if max_size == 0 {
+            return Err(PlotError::ZeroSize);
+        }
+        if g.first().map_or(true, |column| column.is_empty()) {
+            return Err(PlotError::EmptyMatrix);
+        }
+        let (a_len, b_len) = (g.len() - 1, g[0].len() - 1);
+        let scale = a_len.max(b_len).div_ceil(max_size).max(1);
+        let width = a_len / scale + 1;
+        let height = b_len / scale + 1;
+        let mut f = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width {
+                let (i, j) = (x * scale, y * scale);
+                f.push(g[i][j] + h.h(Pos(i as I, j as I)));
+            }
+        }
+        Ok(FLandscape {
+            scale: scale as I,
+            width,
+            height,
+            distance: g[a_len][b_len],
+            f,
+        })
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Cost {
+        self.f[y * self.width + x]
+    }
+
+    /// An SVG with `a` along the x-axis and `b` along the y-axis, coloring
+    /// cells with `f <= distance` from blue (low) to red (high), and cells
+    /// excluded by the bound in gray.
+    pub fn to_svg(&self) -> String {
+        let gradient = Gradient::TurboGradient(0.1..0.9);
+        let min = self.f.iter().copied().min().unwrap_or(0);
+        let range = (self.distance - min).max(1) as f64;
+        let scale = SIZE as f32 / self.width.max(self.height) as f32;
+        let mut s = String::new();
+        writeln!(
+            s,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{:.0}" viewBox="0 0 {} {}" shape-rendering="crispEdges">"#,
+            self.width as f32 * scale,
+            self.height as f32 * scale,
+            self.width,
+            self.height
+        )
+        .unwrap();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let f = self.get(x, y);
+                let color = if f > self.distance {
+                    EXCLUDED
+                } else {
+                    gradient.color_f((f - min) as f64 / range)
+                };
+                writeln!(
+                    s,
+                    r#"<rect x="{x}" y="{y}" width="1" height="1" fill="{}"/>"#,
+                    hex(color)
+                )
+                .unwrap();
+            }
+        }
+        writeln!(s, "</svg>").unwrap();
+        s
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use pa_heuristic::{Heuristic, NoCost};
+
+    #[test]
+    fn f_landscape_invalid() {
+        let h = NoCost.build(b"", b"");
+        assert_eq!(
+            FLandscape::new(&[vec![0]], &h, 0).err(),
+            Some(PlotError::ZeroSize)
+        );
+        assert_eq!(
+            FLandscape::new(&[], &h, 10).err(),
+            Some(PlotError::EmptyMatrix)
+        );
+        assert_eq!(
+            FLandscape::new(&[vec![]], &h, 10).err(),
+            Some(PlotError::EmptyMatrix)
+        );
+        let landscape = FLandscape::new(&[vec![0]], &h, 10).unwrap();
+        assert_eq!((landscape.width, landscape.height), (1, 1));
+        assert_eq!(landscape.to_svg().matches("<rect").count(), 1);
+    }
+}
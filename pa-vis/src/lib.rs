@@ -1,6 +1,10 @@
 #![feature(let_chains, int_roundings, never_type)]
 
 pub mod cli;
+pub mod dot_plot;
+pub mod f_landscape;
+pub mod raster;
+pub mod recording;
 #[cfg(feature = "sdl")]
 mod sdl;
 pub mod visualizer;
@@ -31,6 +35,10 @@ pub trait VisualizerT: Clone + Default + Debug + PartialEq {
 }
 
 pub trait VisualizerInstance {
+    /// True for `NoVis`, so that aligners can skip work that is only needed
+    /// for drawing, such as keeping fronts for the tree in `last_frame`.
+    const IS_DEFAULT: bool = false;
+
     fn explore<'a, HI: HeuristicInstance<'a>>(
         &mut self,
         _pos: Pos,
@@ -126,4 +134,6 @@ impl VisualizerT for NoVis {
         Self
     }
 }
-impl VisualizerInstance for NoVis {}
+impl VisualizerInstance for NoVis {
+    const IS_DEFAULT: bool = true;
+}
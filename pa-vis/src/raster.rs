@@ -0,0 +1,223 @@
+//! Blit-based drawing for large inputs.
+//!
+//! Drawing each expanded state with a separate call to the canvas is the
+//! bottleneck for large inputs. `RasterCanvas` instead rasterizes all shapes
+//! into an in-memory `Image`, and copies it to the wrapped canvas in a single
+//! `draw_image` call, e.g. as one texture for SDL.
+//!
+//! Text is still drawn by the wrapped canvas. Pending pixels are flushed before
+//! each text, so that the order of drawing calls is preserved.
+use crate::canvas::*;
+use pa_types::I;
+use std::{path::Path, time::Duration};
+
+/// A rectangle of pixels. `None` pixels are transparent.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Image {
+    pub w: usize,
+    pub h: usize,
+    pub pixels: Vec<Option<Color>>,
+}
+
+impl Image {
+    pub fn new(w: usize, h: usize) -> Self {
+        Self {
+            w,
+            h,
+            pixels: vec![None; w * h],
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Option<Color>]> {
+        self.pixels.chunks(self.w)
+    }
+
+    /// Make all pixels transparent.
+    pub fn clear(&mut self) {
+        self.pixels.fill(None);
+    }
+
+    /// Fill the rectangle, clipped to the image.
+    pub fn fill_rect(&mut self, CPos(x, y): CPos, w: I, h: I, color: Color) {
+        let x0 = x.clamp(0, self.w as i32) as usize;
+        let y0 = y.clamp(0, self.h as i32) as usize;
+        let x1 = ((x + w).clamp(0, self.w as i32) as usize).max(x0);
+        let y1 = (y + h).clamp(0, self.h as i32) as usize;
+        for row in y0..y1 {
+            self.pixels[row * self.w + x0..row * self.w + x1].fill(Some(color));
+        }
+    }
+
+    pub fn draw_point(&mut self, CPos(x, y): CPos, color: Color) {
+        if (0..self.w as i32).contains(&x) && (0..self.h as i32).contains(&y) {
+            self.pixels[y as usize * self.w + x as usize] = Some(color);
+        }
+    }
+
+    /// Bresenham's line algorithm, including both endpoints.
+    pub fn draw_line(&mut self, CPos(x0, y0): CPos, CPos(x1, y1): CPos, color: Color) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y) = (x0, y0);
+        let mut err = dx + dy;
+        loop {
+            self.draw_point(CPos(x, y), color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+}
+
+/// A canvas that rasterizes into an `Image`, and draws it onto `inner` at once.
+pub struct RasterCanvas {
+    inner: CanvasBox,
+    image: Image,
+    /// Whether `image` contains pixels that were not yet drawn to `inner`.
+    dirty: bool,
+}
+
+impl RasterCanvas {
+    pub fn new(inner: CanvasBox, w: usize, h: usize) -> Self {
+        Self {
+            inner,
+            image: Image::new(w, h),
+            dirty: false,
+        }
+    }
+
+    /// Draw all pending pixels onto the inner canvas.
+    fn flush(&mut self) {
+        if self.dirty {
+            self.inner.draw_image(CPos(0, 0), &self.image);
+            self.image.clear();
+            self.dirty = false;
+        }
+    }
+}
+
+impl Canvas for RasterCanvas {
+    fn fill_background(&mut self, color: Color) {
+        self.image
+            .fill_rect(CPos(0, 0), self.image.w as I, self.image.h as I, color);
+        self.dirty = true;
+    }
+    fn fill_rect(&mut self, p: CPos, w: I, h: I, color: Color) {
+        self.image.fill_rect(p, w, h, color);
+        self.dirty = true;
+    }
+    fn draw_rect(&mut self, p: CPos, w: I, h: I, color: Color) {
+        self.image.fill_rect(p, w, 1, color);
+        self.image.fill_rect(p.down(h - 1), w, 1, color);
+        self.image.fill_rect(p, 1, h, color);
+        self.image.fill_rect(p.right(w - 1), 1, h, color);
+        self.dirty = true;
+    }
+    fn draw_point(&mut self, p: CPos, color: Color) {
+        self.image.draw_point(p, color);
+        self.dirty = true;
+    }
+    fn draw_line(&mut self, p: CPos, q: CPos, color: Color) {
+        self.image.draw_line(p, q, color);
+        self.dirty = true;
+    }
+    fn draw_image(&mut self, CPos(x, y): CPos, image: &Image) {
+        for (row, pixels) in image.rows().enumerate() {
+            for (col, &pixel) in pixels.iter().enumerate() {
+                if let Some(color) = pixel {
+                    self.image
+                        .draw_point(CPos(x + col as i32, y + row as i32), color);
+                }
+            }
+        }
+        self.dirty = true;
+    }
+    fn write_text(&mut self, p: CPos, ha: HAlign, va: VAlign, text: &str, color: Color) {
+        self.flush();
+        self.inner.write_text(p, ha, va, text, color)
+    }
+    fn save(&mut self, path: &Path) {
+        self.flush();
+        self.inner.save(path)
+    }
+    fn save_transparent(&mut self, path: &Path, bg_color: Color) {
+        self.flush();
+        self.inner.save_transparent(path, bg_color)
+    }
+    fn present(&mut self) {
+        self.flush();
+        self.inner.present()
+    }
+    fn wait(&mut self, timeout: Duration) -> KeyboardAction {
+        self.inner.wait(timeout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const C: Color = (1, 2, 3, 0);
+
+    /// The image as rows of `#` and `.`.
+    fn show(image: &Image) -> Vec<String> {
+        image
+            .rows()
+            .map(|row| {
+                row.iter()
+                    .map(|p| if p.is_some() { '#' } else { '.' })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn fill_rect_clips() {
+        let mut image = Image::new(4, 3);
+        image.fill_rect(CPos(1, 1), 2, 1, C);
+        assert_eq!(show(&image), ["....", ".##.", "...."]);
+
+        image.clear();
+        image.fill_rect(CPos(-2, -1), 4, 3, C);
+        image.fill_rect(CPos(3, 2), 10, 10, C);
+        assert_eq!(show(&image), ["##..", "##..", "...#"]);
+
+        // Rectangles outside the image, and with negative sizes, are empty.
+        image.clear();
+        image.fill_rect(CPos(-5, 0), 3, 3, C);
+        image.fill_rect(CPos(4, 0), 3, 3, C);
+        image.fill_rect(CPos(0, 3), 3, 3, C);
+        image.fill_rect(CPos(2, 2), -1, -1, C);
+        assert_eq!(image, Image::new(4, 3));
+    }
+
+    #[test]
+    fn draw_line() {
+        let mut image = Image::new(5, 3);
+        image.draw_line(CPos(0, 0), CPos(4, 2), C);
+        assert_eq!(show(&image), ["#....", ".##..", "...##"]);
+        image.clear();
+        image.draw_line(CPos(4, 2), CPos(0, 0), C);
+        assert_eq!(show(&image), ["##...", ".##..", "....#"]);
+
+        // Points outside the image are skipped.
+        image.clear();
+        image.draw_line(CPos(2, -3), CPos(2, 5), C);
+        assert_eq!(show(&image), ["..#..", "..#..", "..#.."]);
+
+        image.clear();
+        image.draw_line(CPos(1, 1), CPos(1, 1), C);
+        assert_eq!(show(&image), [".....", ".#...", "....."]);
+    }
+}
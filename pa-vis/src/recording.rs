@@ -0,0 +1,774 @@
+//! Record visualizer events to a file, and render them later.
+//!
+//! `Recorder` is a `VisualizerT` that writes all events that change the drawn
+//! image to a file as they happen, instead of drawing them.
+//! `Recording::replay` reads the events back one at a time and feeds them into
+//! any other visualizer, so that the style can be changed without re-running
+//! the alignment.
+//!
+//! The file starts with `MAGIC` and the two sequences, followed by the events.
+//! Each event is a one byte tag followed by its fields, with integers as 4
+//! byte little endian values.
+//!
+//! Events that need the heuristic (contours, matches) or the parent function
+//! (the tree) can not be recorded, and are not shown when rendering.
+use crate::{CanvasFactory, ParentFn, VisualizerInstance, VisualizerT};
+use itertools::Itertools;
+use pa_affine_types::AffineCigar;
+use pa_heuristic::HeuristicInstance;
+use pa_types::*;
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// The first bytes of a recording, including the version of the format.
+pub const MAGIC: &[u8; 8] = b"PAREC01\n";
+
+/// A position, stored as a plain tuple.
+type P = (I, I);
+
+fn p(Pos(i, j): Pos) -> P {
+    (i, j)
+}
+fn pos((i, j): P) -> Pos {
+    Pos(i, j)
+}
+
+/// A single call to a `VisualizerInstance`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Explore {
+        pos: P,
+        g: Cost,
+        f: Cost,
+    },
+    Expand {
+        pos: P,
+        g: Cost,
+        f: Cost,
+    },
+    Extend {
+        pos: P,
+        g: Cost,
+        f: Cost,
+    },
+    ExpandPreprune(P),
+    ExtendPreprune(P),
+    ExpandTrace(P),
+    ExtendTrace(P),
+    ExpandBlock {
+        pos: P,
+        size: P,
+        g: Cost,
+        f: Cost,
+    },
+    ExpandBlockTrace {
+        pos: P,
+        size: P,
+    },
+    ExpandBlocks {
+        poss: [P; 4],
+        sizes: [P; 4],
+        g: Cost,
+        f: Cost,
+    },
+    HCall(P),
+    FCall {
+        pos: P,
+        in_bounds: bool,
+        fixed: bool,
+    },
+    JRange(P, P),
+    FixedJRange(P, P),
+    FixedH(P, P),
+    NextFixedH(P, P),
+    NewLayer,
+    MeetingPoint(P),
+    /// The final frame, with the path of the alignment if there is one.
+    LastFrame {
+        path: Option<Vec<P>>,
+    },
+}
+
+fn write_i32(w: &mut impl Write, x: I) -> io::Result<()> {
+    w.write_all(&x.to_le_bytes())
+}
+fn write_p(w: &mut impl Write, (i, j): P) -> io::Result<()> {
+    write_i32(w, i)?;
+    write_i32(w, j)
+}
+fn write_bytes(w: &mut impl Write, bytes: &[u8]) -> io::Result<()> {
+    w.write_all(&(bytes.len() as u32).to_le_bytes())?;
+    w.write_all(bytes)
+}
+
+fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0; 1];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+fn read_u32(r: &mut impl Read) -> io::Result<u32> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+fn read_i32(r: &mut impl Read) -> io::Result<I> {
+    let mut buf = [0; 4];
+    r.read_exact(&mut buf)?;
+    Ok(I::from_le_bytes(buf))
+}
+fn read_p(r: &mut impl Read) -> io::Result<P> {
+    Ok((read_i32(r)?, read_i32(r)?))
+}
+fn read_bool(r: &mut impl Read) -> io::Result<bool> {
+    match read_u8(r)? {
+        0 => Ok(false),
+        1 => Ok(true),
+        x => Err(invalid(format!("invalid bool {x}"))),
+    }
+}
+fn read_bytes(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut bytes = vec![0; read_u32(r)? as usize];
+    r.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+impl Event {
+    /// Write the tag and fields of this event.
+    pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        match *self {
+            Event::Explore { pos, g, f } => {
+                w.write_all(&[0])?;
+                write_p(w, pos)?;
+                write_i32(w, g)?;
+                write_i32(w, f)
+            }
+            Event::Expand { pos, g, f } => {
+                w.write_all(&[1])?;
+                write_p(w, pos)?;
+                write_i32(w, g)?;
+                write_i32(w, f)
+            }
+            Event::Extend { pos, g, f } => {
+                w.write_all(&[2])?;
+                write_p(w, pos)?;
+                write_i32(w, g)?;
+                write_i32(w, f)
+            }
+            Event::ExpandPreprune(q) => {
+                w.write_all(&[3])?;
+                write_p(w, q)
+            }
+            Event::ExtendPreprune(q) => {
+                w.write_all(&[4])?;
+                write_p(w, q)
+            }
+            Event::ExpandTrace(q) => {
+                w.write_all(&[5])?;
+                write_p(w, q)
+            }
+            Event::ExtendTrace(q) => {
+                w.write_all(&[6])?;
+                write_p(w, q)
+            }
+            Event::ExpandBlock { pos, size, g, f } => {
+                w.write_all(&[7])?;
+                write_p(w, pos)?;
+                write_p(w, size)?;
+                write_i32(w, g)?;
+                write_i32(w, f)
+            }
+            Event::ExpandBlockTrace { pos, size } => {
+                w.write_all(&[8])?;
+                write_p(w, pos)?;
+                write_p(w, size)
+            }
+            Event::ExpandBlocks { poss, sizes, g, f } => {
+                w.write_all(&[9])?;
+                for q in poss.into_iter().chain(sizes) {
+                    write_p(w, q)?;
+                }
+                write_i32(w, g)?;
+                write_i32(w, f)
+            }
+            Event::HCall(q) => {
+                w.write_all(&[10])?;
+                write_p(w, q)
+            }
+            Event::FCall {
+                pos,
+                in_bounds,
+                fixed,
+            } => {
+                w.write_all(&[11])?;
+                write_p(w, pos)?;
+                w.write_all(&[in_bounds as u8, fixed as u8])
+            }
+            Event::JRange(s, e) => {
+                w.write_all(&[12])?;
+                write_p(w, s)?;
+                write_p(w, e)
+            }
+            Event::FixedJRange(s, e) => {
+                w.write_all(&[13])?;
+                write_p(w, s)?;
+                write_p(w, e)
+            }
+            Event::FixedH(s, e) => {
+                w.write_all(&[14])?;
+                write_p(w, s)?;
+                write_p(w, e)
+            }
+            Event::NextFixedH(s, e) => {
+                w.write_all(&[15])?;
+                write_p(w, s)?;
+                write_p(w, e)
+            }
+            Event::NewLayer => w.write_all(&[16]),
+            Event::MeetingPoint(q) => {
+                w.write_all(&[17])?;
+                write_p(w, q)
+            }
+            Event::LastFrame { ref path } => {
+                w.write_all(&[18])?;
+                match path {
+                    None => w.write_all(&[0]),
+                    Some(path) => {
+                        w.write_all(&[1])?;
+                        w.write_all(&(path.len() as u32).to_le_bytes())?;
+                        for &q in path {
+                            write_p(w, q)?;
+                        }
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+
+    /// Read the next event, or `None` at the end of the input.
+    pub fn read(r: &mut impl Read) -> io::Result<Option<Event>> {
+        let mut tag = [0; 1];
+        if r.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(match tag[0] {
+            0 => Event::Explore {
+                pos: read_p(r)?,
+                g: read_i32(r)?,
+                f: read_i32(r)?,
+            },
+            1 => Event::Expand {
+                pos: read_p(r)?,
+                g: read_i32(r)?,
+                f: read_i32(r)?,
+            },
+            2 => Event::Extend {
+                pos: read_p(r)?,
+                g: read_i32(r)?,
+                f: read_i32(r)?,
+            },
+            3 => Event::ExpandPreprune(read_p(r)?),
+            4 => Event::ExtendPreprune(read_p(r)?),
+            5 => Event::ExpandTrace(read_p(r)?),
+            6 => Event::ExtendTrace(read_p(r)?),
+            7 => Event::ExpandBlock {
+                pos: read_p(r)?,
+                size: read_p(r)?,
+                g: read_i32(r)?,
+                f: read_i32(r)?,
+            },
+            8 => Event::ExpandBlockTrace {
+                pos: read_p(r)?,
+                size: read_p(r)?,
+            },
+            9 => {
+                let mut ps = [(0, 0); 8];
+                for q in &mut ps {
+                    *q = read_p(r)?;
+                }
+                Event::ExpandBlocks {
+                    poss: ps[..4].try_into().unwrap(),
+                    sizes: ps[4..].try_into().unwrap(),
+                    g: read_i32(r)?,
+                    f: read_i32(r)?,
+                }
+            }
+            10 => Event::HCall(read_p(r)?),
+            11 => Event::FCall {
+                pos: read_p(r)?,
+                in_bounds: read_bool(r)?,
+                fixed: read_bool(r)?,
+            },
+            12 => Event::JRange(read_p(r)?, read_p(r)?),
+            13 => Event::FixedJRange(read_p(r)?, read_p(r)?),
+            14 => Event::FixedH(read_p(r)?, read_p(r)?),
+            15 => Event::NextFixedH(read_p(r)?, read_p(r)?),
+            16 => Event::NewLayer,
+            17 => Event::MeetingPoint(read_p(r)?),
+            18 => Event::LastFrame {
+                path: if read_bool(r)? {
+                    let len = read_u32(r)?;
+                    Some((0..len).map(|_| read_p(r)).collect::<io::Result<_>>()?)
+                } else {
+                    None
+                },
+            },
+            tag => return Err(invalid(format!("invalid event tag {tag}"))),
+        }))
+    }
+}
+
+/// The input of a single alignment, and a reader for its visualizer events.
+pub struct Recording<R = BufReader<File>> {
+    pub a: Vec<u8>,
+    pub b: Vec<u8>,
+    events: R,
+}
+
+impl Recording {
+    /// Open a recording and read its header.
+    /// The events are read while replaying.
+    pub fn read(path: &Path) -> io::Result<Self> {
+        Self::from_reader(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> Recording<R> {
+    /// Read the header of a recording from `reader`.
+    pub fn from_reader(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0; MAGIC.len()];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a visualizer recording".to_string()));
+        }
+        Ok(Recording {
+            a: read_bytes(&mut reader)?,
+            b: read_bytes(&mut reader)?,
+            events: reader,
+        })
+    }
+
+    /// Read the next event, or `None` after the last one.
+    pub fn next_event(&mut self) -> io::Result<Option<Event>> {
+        Event::read(&mut self.events)
+    }
+
+    /// Replay all events into a visualizer built from `v`, e.g. a `Config`
+    /// with a different style than the one used while aligning.
+    pub fn replay<V: VisualizerT>(self, v: &V) -> io::Result<()> {
+        let mut vi = v.build(&self.a, &self.b);
+        self.replay_into(&mut vi)
+    }
+
+    /// Same as `replay`, but builds the visualizer using the given canvas factory.
+    pub fn replay_with_factory<V: VisualizerT, CF: CanvasFactory>(self, v: &V) -> io::Result<()> {
+        let mut vi = v.build_from_factory::<CF>(&self.a, &self.b);
+        self.replay_into(&mut vi)
+    }
+
+    /// Replay all events into an existing visualizer instance.
+    pub fn replay_into(mut self, v: &mut impl VisualizerInstance) -> io::Result<()> {
+        while let Some(event) = self.next_event()? {
+            match event {
+                Event::Explore { pos: q, g, f } => v.explore::<!>(pos(q), g, f, None),
+                Event::Expand { pos: q, g, f } => v.expand::<!>(pos(q), g, f, None),
+                Event::Extend { pos: q, g, f } => v.extend::<!>(pos(q), g, f, None),
+                Event::ExpandPreprune(q) => v.expand_preprune(pos(q)),
+                Event::ExtendPreprune(q) => v.extend_preprune(pos(q)),
+                Event::ExpandTrace(q) => v.expand_trace(pos(q)),
+                Event::ExtendTrace(q) => v.extend_trace(pos(q)),
+                Event::ExpandBlock { pos: q, size, g, f } => {
+                    v.expand_block::<!>(pos(q), pos(size), g, f, None)
+                }
+                Event::ExpandBlockTrace { pos: q, size } => v.expand_block_trace(pos(q), pos(size)),
+                Event::ExpandBlocks { poss, sizes, g, f } => {
+                    v.expand_blocks::<!>(poss.map(pos), sizes.map(pos), g, f, None)
+                }
+                Event::HCall(q) => v.h_call(pos(q)),
+                Event::FCall {
+                    pos: q,
+                    in_bounds,
+                    fixed,
+                } => v.f_call(pos(q), in_bounds, fixed),
+                Event::JRange(s, e) => v.j_range(pos(s), pos(e)),
+                Event::FixedJRange(s, e) => v.fixed_j_range(pos(s), pos(e)),
+                Event::FixedH(s, e) => v.fixed_h(pos(s), pos(e)),
+                Event::NextFixedH(s, e) => v.next_fixed_h(pos(s), pos(e)),
+                Event::NewLayer => v.new_layer::<!>(None),
+                Event::MeetingPoint(q) => v.add_meeting_point::<!>(pos(q)),
+                Event::LastFrame { path } => {
+                    let cigar = path.map(|path| {
+                        let path = path.into_iter().map(pos).collect_vec();
+                        AffineCigar::from(&Cigar::from_path(&self.a, &self.b, &path))
+                    });
+                    v.last_frame::<!>(cigar.as_ref(), None, None)
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A visualizer that records all events to `path` instead of drawing them.
+///
+/// I/O errors can not be returned from the visualizer callbacks, so the
+/// first one stops the recording and is returned by `finish`.
+#[derive(Clone, Debug, Default)]
+pub struct Recorder {
+    pub path: PathBuf,
+    /// Shared with clones, so that the error of an instance built by an
+    /// aligner can be checked by the caller.
+    error: Arc<Mutex<Option<io::Error>>>,
+}
+
+impl PartialEq for Recorder {
+    fn eq(&self, other: &Self) -> bool {
+        self.path == other.path
+    }
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            error: Default::default(),
+        }
+    }
+
+    /// Returns the first I/O error of the instances built from this recorder
+    /// or its clones, if any.
+    pub fn finish(&self) -> io::Result<()> {
+        match self.error.lock().unwrap().take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl VisualizerT for Recorder {
+    type Instance = RecorderInstance;
+
+    fn build(&self, a: Seq, b: Seq) -> Self::Instance {
+        let mut this = RecorderInstance {
+            writer: None,
+            error: self.error.clone(),
+        };
+        let header = || -> io::Result<_> {
+            if let Some(parent) = self.path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut w = BufWriter::new(File::create(&self.path)?);
+            w.write_all(MAGIC)?;
+            write_bytes(&mut w, a)?;
+            write_bytes(&mut w, b)?;
+            Ok(w)
+        };
+        match header() {
+            Ok(w) => this.writer = Some(w),
+            Err(e) => this.fail(e),
+        }
+        this
+    }
+
+    fn build_from_factory<CF: CanvasFactory>(&self, a: Seq, b: Seq) -> Self::Instance {
+        self.build(a, b)
+    }
+}
+
+pub struct RecorderInstance {
+    /// `None` after an error.
+    writer: Option<BufWriter<File>>,
+    error: Arc<Mutex<Option<io::Error>>>,
+}
+
+impl RecorderInstance {
+    fn push(&mut self, event: Event) {
+        if let Some(w) = &mut self.writer
+            && let Err(e) = event.write(w)
+        {
+            self.fail(e);
+        }
+    }
+
+    /// Stop recording, and keep `e` unless an earlier error is kept.
+    fn fail(&mut self, e: io::Error) {
+        self.writer = None;
+        let mut error = self.error.lock().unwrap();
+        if error.is_none() {
+            *error = Some(e);
+        }
+    }
+}
+
+impl VisualizerInstance for RecorderInstance {
+    fn explore<'a, HI: HeuristicInstance<'a>>(
+        &mut self,
+        q: Pos,
+        g: Cost,
+        f: Cost,
+        _h: Option<&HI>,
+    ) {
+        self.push(Event::Explore { pos: p(q), g, f });
+    }
+    fn expand<'a, HI: HeuristicInstance<'a>>(&mut self, q: Pos, g: Cost, f: Cost, _h: Option<&HI>) {
+        self.push(Event::Expand { pos: p(q), g, f });
+    }
+    fn extend<'a, HI: HeuristicInstance<'a>>(&mut self, q: Pos, g: Cost, f: Cost, _h: Option<&HI>) {
+        self.push(Event::Extend { pos: p(q), g, f });
+    }
+    fn expand_preprune(&mut self, q: Pos) {
+        self.push(Event::ExpandPreprune(p(q)));
+    }
+    fn extend_preprune(&mut self, q: Pos) {
+        self.push(Event::ExtendPreprune(p(q)));
+    }
+    fn expand_trace(&mut self, q: Pos) {
+        self.push(Event::ExpandTrace(p(q)));
+    }
+    fn extend_trace(&mut self, q: Pos) {
+        self.push(Event::ExtendTrace(p(q)));
+    }
+    fn expand_block<'a, HI: HeuristicInstance<'a>>(
+        &mut self,
+        q: Pos,
+        size: Pos,
+        g: Cost,
+        f: Cost,
+        _h: Option<&HI>,
+    ) {
+        self.push(Event::ExpandBlock {
+            pos: p(q),
+            size: p(size),
+            g,
+            f,
+        });
+    }
+    fn expand_block_trace(&mut self, q: Pos, size: Pos) {
+        self.push(Event::ExpandBlockTrace {
+            pos: p(q),
+            size: p(size),
+        });
+    }
+    fn expand_blocks<'a, HI: HeuristicInstance<'a>>(
+        &mut self,
+        poss: [Pos; 4],
+        sizes: [Pos; 4],
+        g: Cost,
+        f: Cost,
+        _h: Option<&HI>,
+    ) {
+        self.push(Event::ExpandBlocks {
+            poss: poss.map(p),
+            sizes: sizes.map(p),
+            g,
+            f,
+        });
+    }
+
+    fn h_call(&mut self, q: Pos) {
+        self.push(Event::HCall(p(q)));
+    }
+    fn f_call(&mut self, q: Pos, in_bounds: bool, fixed: bool) {
+        self.push(Event::FCall {
+            pos: p(q),
+            in_bounds,
+            fixed,
+        });
+    }
+    fn j_range(&mut self, start: Pos, end: Pos) {
+        self.push(Event::JRange(p(start), p(end)));
+    }
+    fn fixed_j_range(&mut self, start: Pos, end: Pos) {
+        self.push(Event::FixedJRange(p(start), p(end)));
+    }
+    fn fixed_h(&mut self, start: Pos, end: Pos) {
+        self.push(Event::FixedH(p(start), p(end)));
+    }
+    fn next_fixed_h(&mut self, start: Pos, end: Pos) {
+        self.push(Event::NextFixedH(p(start), p(end)));
+    }
+
+    fn new_layer<'a, HI: HeuristicInstance<'a>>(&mut self, _h: Option<&HI>) {
+        self.push(Event::NewLayer);
+    }
+
+    fn add_meeting_point<'a, HI: HeuristicInstance<'a>>(&mut self, q: Pos) {
+        self.push(Event::MeetingPoint(p(q)));
+    }
+
+    fn last_frame<'a, HI: HeuristicInstance<'a>>(
+        &mut self,
+        cigar: Option<&AffineCigar>,
+        _parent: ParentFn<'_>,
+        _h: Option<&HI>,
+    ) {
+        let path = cigar.map(|c| c.to_path().into_iter().map(p).collect());
+        self.push(Event::LastFrame { path });
+        if let Some(mut w) = self.writer.take()
+            && let Err(e) = w.flush()
+        {
+            self.fail(e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pa-vis-{}-{name}.rec", std::process::id()))
+    }
+
+    fn all_events() -> Vec<Event> {
+        vec![
+            Event::Explore {
+                pos: (1, 2),
+                g: 3,
+                f: 4,
+            },
+            Event::Expand {
+                pos: (1, 2),
+                g: 3,
+                f: 4,
+            },
+            Event::Extend {
+                pos: (1, 2),
+                g: 3,
+                f: 4,
+            },
+            Event::ExpandPreprune((5, 6)),
+            Event::ExtendPreprune((5, 6)),
+            Event::ExpandTrace((5, 6)),
+            Event::ExtendTrace((5, 6)),
+            Event::ExpandBlock {
+                pos: (0, 0),
+                size: (64, 128),
+                g: 1,
+                f: -1,
+            },
+            Event::ExpandBlockTrace {
+                pos: (0, 0),
+                size: (64, 128),
+            },
+            Event::ExpandBlocks {
+                poss: [(0, 0), (1, 1), (2, 2), (3, 3)],
+                sizes: [(4, 4), (5, 5), (6, 6), (7, 7)],
+                g: 8,
+                f: 9,
+            },
+            Event::HCall((7, 8)),
+            Event::FCall {
+                pos: (7, 8),
+                in_bounds: true,
+                fixed: false,
+            },
+            Event::JRange((0, 1), (0, 9)),
+            Event::FixedJRange((0, 1), (0, 9)),
+            Event::FixedH((0, 1), (0, 9)),
+            Event::NextFixedH((0, 1), (0, 9)),
+            Event::NewLayer,
+            Event::MeetingPoint((I::MAX, I::MIN)),
+            Event::LastFrame { path: None },
+            Event::LastFrame {
+                path: Some(vec![(0, 0), (1, 1), (2, 1)]),
+            },
+        ]
+    }
+
+    #[test]
+    fn events() {
+        let events = all_events();
+        let mut bytes = vec![];
+        for e in &events {
+            e.write(&mut bytes).unwrap();
+        }
+        let mut r = &bytes[..];
+        let mut read = vec![];
+        while let Some(e) = Event::read(&mut r).unwrap() {
+            read.push(e);
+        }
+        assert_eq!(read, events);
+
+        // A truncated event is an error, not the end of the input.
+        let mut r = &bytes[..bytes.len() - 1];
+        let err = loop {
+            match Event::read(&mut r) {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("truncated input read completely"),
+                Err(e) => break e,
+            }
+        };
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        assert_eq!(
+            Event::read(&mut &[255u8][..]).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let (a, b) = (b"ACGT", b"AGT");
+        let path = temp_path("record");
+        let recorder = Recorder::new(path.clone());
+        let mut v = recorder.clone().build(a, b);
+        v.explore::<!>(Pos(1, 1), 0, 1, None);
+        v.expand::<!>(Pos(1, 1), 0, 1, None);
+        v.new_layer::<!>(None);
+        v.expand_block::<!>(Pos(0, 0), Pos(4, 3), 1, 1, None);
+        let trace = [Pos(0, 0), Pos(1, 1), Pos(2, 1), Pos(3, 2), Pos(4, 3)];
+        let cigar = Cigar::from_path(a, b, &trace);
+        v.last_frame::<!>(Some(&AffineCigar::from(&cigar)), None, None);
+        recorder.finish().unwrap();
+
+        let mut recording = Recording::read(&path).unwrap();
+        assert_eq!((&recording.a[..], &recording.b[..]), (&a[..], &b[..]));
+        assert_eq!(
+            recording.next_event().unwrap(),
+            Some(Event::Explore {
+                pos: (1, 1),
+                g: 0,
+                f: 1
+            })
+        );
+
+        // Replaying into a recorder writes the same recording.
+        let replayed = temp_path("replay");
+        let recorder = Recorder::new(replayed.clone());
+        Recording::read(&path).unwrap().replay(&recorder).unwrap();
+        recorder.finish().unwrap();
+        assert_eq!(
+            std::fs::read(&path).unwrap(),
+            std::fs::read(&replayed).unwrap()
+        );
+
+        std::fs::remove_file(path).unwrap();
+        std::fs::remove_file(replayed).unwrap();
+    }
+
+    #[test]
+    fn errors() {
+        // The parent of the output is a file, so it can not be created.
+        let recorder = Recorder::new(PathBuf::from("/dev/null/recording.rec"));
+        let mut v = recorder.build(b"A", b"A");
+        v.expand::<!>(Pos(0, 0), 0, 0, None);
+        v.last_frame::<!>(None, None, None);
+        assert!(recorder.finish().is_err());
+        // The error is only returned once.
+        assert!(recorder.finish().is_ok());
+
+        let not_a_recording = Recording::from_reader(&b"ACGTACGTACGT"[..]);
+        assert_eq!(
+            not_a_recording.err().unwrap().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
@@ -1,10 +1,13 @@
 use lazy_static::lazy_static;
-use super::{canvas::*, CanvasFactory};
+use super::{canvas::*, raster::Image, CanvasFactory};
+use itertools::Itertools;
 use pa_types::I;
 use sdl2::{
     event::Event,
     keyboard::Keycode,
+    pixels::PixelFormatEnum,
     rect::{Point, Rect},
+    render::BlendMode,
     ttf::{Font, Sdl2TtfContext},
     video::Window,
     Sdl,
@@ -130,6 +133,43 @@ impl Canvas for SdlCanvas {
         self.0.draw_line(to_point(p), to_point(q)).unwrap();
     }
 
+    fn draw_image(&mut self, CPos(x, y): CPos, image: &Image) {
+        let texture_creator = self.0.texture_creator();
+        let mut texture = texture_creator
+            .create_texture_streaming(PixelFormatEnum::RGBA32, image.w as u32, image.h as u32)
+            .unwrap();
+        // Copy the colors including their alpha, like the other drawing calls.
+        texture.set_blend_mode(BlendMode::None);
+        texture
+            .with_lock(None, |buf, pitch| {
+                for (row, pixels) in image.rows().enumerate() {
+                    for (col, pixel) in pixels.iter().enumerate() {
+                        if let Some((r, g, b, a)) = *pixel {
+                            buf[row * pitch + 4 * col..][..4].copy_from_slice(&[r, g, b, a]);
+                        }
+                    }
+                }
+            })
+            .unwrap();
+        // Only copy the runs of non-transparent pixels.
+        for (row, pixels) in image.rows().enumerate() {
+            let mut col = 0;
+            for (run, transparent) in pixels.iter().map(Option::is_none).dedup_with_count() {
+                if !transparent {
+                    let rect = |x, y| Rect::new(x, y, run as u32, 1);
+                    self.0
+                        .copy(
+                            &texture,
+                            Some(rect(col as i32, row as i32)),
+                            Some(rect(x + col as i32, y + row as i32)),
+                        )
+                        .unwrap();
+                }
+                col += run;
+            }
+        }
+    }
+
     fn write_text(&mut self, CPos(x, y): CPos, ha: HAlign, va: VAlign, text: &str, color: Color) {
         self.0.set_draw_color(color);
         let surface = FONT.with(|front| front.render(text).blended(self.0.draw_color()).unwrap());
@@ -8,7 +8,7 @@
 //! ffmpeg -framerate 20 -i %d.bmp -vf "pad=ceil(iw/2)*2:ceil(ih/2)*2" output.mp4
 //! ```
 
-use super::{canvas::*, *};
+use super::{canvas::*, raster::RasterCanvas, *};
 use clap::ValueEnum;
 use itertools::Itertools;
 use pa_affine_types::*;
@@ -31,10 +31,30 @@ use std::{
 pub enum VisualizerStyle {
     #[default]
     Default,
+    /// Downscaled images for large inputs. Combine with `--raster` for speed.
     Large,
     Detailed,
     Test,
     Debug,
+    /// Clean final images as used in the papers: no labels, a gradient of
+    /// expanded states, and the path on top.
+    Paper,
+}
+
+/// The colors used for all drawn elements. Applied on top of the `VisualizerStyle`.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, ValueEnum, Serialize, Deserialize)]
+pub enum Palette {
+    /// The colors set by the style.
+    #[default]
+    Default,
+    /// The color-blind-safe viridis color map for expanded states, and
+    /// Okabe-Ito colors for matches.
+    Viridis,
+    /// Same as `Viridis`, but using cividis, which is also readable for
+    /// people with blue-yellow color blindness.
+    Cividis,
+    /// Viridis on a dark background.
+    Dark,
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, ValueEnum, Serialize, Deserialize)]
@@ -96,6 +116,10 @@ pub struct Visualizer {
 
     // The size in pixels of the entire canvas.
     canvas_size: (i32, i32),
+    // The size of the margin at the top-left, used for annotations.
+    offset: CPos,
+    // The size in pixels of the canvas including margins.
+    full_size: (i32, i32),
     // The region of the NW states.
     nw: Region,
     // The region of the DT states.
@@ -313,6 +337,27 @@ impl VisualizerInstance for Visualizer {
     }
 }
 
+/// Predefined continuous color maps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorMap {
+    Turbo,
+    Viridis,
+    Cividis,
+}
+
+impl ColorMap {
+    fn at(&self, f: f64) -> Color {
+        let c = match self {
+            ColorMap::Turbo => colorgrad::turbo(),
+            ColorMap::Viridis => colorgrad::viridis(),
+            ColorMap::Cividis => colorgrad::cividis(),
+        }
+        .at(f)
+        .to_rgba8();
+        (c[0], c[1], c[2], c[3])
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Gradient {
     Fixed(Color),
@@ -322,21 +367,39 @@ pub enum Gradient {
     BoundedGradient(Range<Color>, usize),
     // 0 <= start < end <= 1
     BoundedTurboGradient(Range<f64>, usize),
+    // 0 <= start < end <= 1
+    Map(ColorMap, Range<f64>),
+    // 0 <= start < end <= 1
+    BoundedMap(ColorMap, Range<f64>, usize),
 }
 
 impl Gradient {
     fn color(&self, i: usize, cnt: usize) -> Color {
         match self {
             Gradient::Fixed(color) => *color,
-            Gradient::Gradient(_) | Gradient::TurboGradient(_) => {
+            Gradient::Gradient(_) | Gradient::TurboGradient(_) | Gradient::Map(..) => {
                 self.color_f(i as f64 / cnt as f64)
             }
-            Gradient::BoundedGradient(_, max) | Gradient::BoundedTurboGradient(_, max) => {
-                self.color_f(i as f64 / *max as f64)
+            Gradient::BoundedGradient(_, max)
+            | Gradient::BoundedTurboGradient(_, max)
+            | Gradient::BoundedMap(_, _, max) => self.color_f(i as f64 / *max as f64),
+        }
+    }
+
+    /// The same gradient using `map`, keeping the range and bound of color maps.
+    fn with_map(&self, map: ColorMap) -> Self {
+        match self {
+            Gradient::TurboGradient(range) | Gradient::Map(_, range) => {
+                Gradient::Map(map, range.clone())
             }
+            Gradient::BoundedTurboGradient(range, max) | Gradient::BoundedMap(_, range, max) => {
+                Gradient::BoundedMap(map, range.clone(), *max)
+            }
+            Gradient::Fixed(_) | Gradient::Gradient(_) => Gradient::Map(map, 0.2..0.95),
+            Gradient::BoundedGradient(_, max) => Gradient::BoundedMap(map, 0.2..0.95, *max),
         }
     }
-    fn color_f(&self, f: f64) -> Color {
+    pub(crate) fn color_f(&self, f: f64) -> Color {
         match self {
             Gradient::Fixed(color) => *color,
             Gradient::Gradient(range) | Gradient::BoundedGradient(range, _) => {
@@ -350,9 +413,10 @@ impl Gradient {
                 )
             }
             Gradient::TurboGradient(range) | Gradient::BoundedTurboGradient(range, _) => {
-                let f = range.start + f * (range.end - range.start);
-                let c = colorgrad::turbo().at(f).to_rgba8();
-                (c[0], c[1], c[2], c[3])
+                ColorMap::Turbo.at(range.start + f * (range.end - range.start))
+            }
+            Gradient::Map(map, range) | Gradient::BoundedMap(map, range, _) => {
+                map.at(range.start + f * (range.end - range.start))
             }
         }
     }
@@ -367,6 +431,10 @@ pub struct Style {
     pub fixed: Option<Color>,
     pub preprune: Option<Color>,
     pub bg_color: Color,
+    /// Text, outlines, and dividers.
+    pub fg_color: Color,
+    /// Overrides the colors of the style. See `Style::apply_palette`.
+    pub palette: Palette,
     /// None to disable
     pub path: Option<Color>,
     /// None to draw cells.
@@ -395,6 +463,12 @@ pub struct Style {
     pub draw_fixed_h: bool,
     pub h_call: Color,
     pub draw_labels: bool,
+    /// Draw ticks with sequence coordinates in a margin around the NW states.
+    pub draw_axes: bool,
+    /// Draw a legend with the f values of the `expanded` gradient below the image.
+    pub draw_legend: bool,
+    /// Draw `Config::title` and `Config::params` in a line above the image.
+    pub draw_title: bool,
     pub heuristic: Gradient,
     pub layer: Gradient,
     pub max_heuristic: Option<I>,
@@ -408,6 +482,59 @@ pub struct Style {
     pub contour: Color,
 }
 
+impl Style {
+    /// Replace the colors of the style by those of `self.palette`.
+    ///
+    /// Colors that are disabled (`None`) in the style stay disabled.
+    pub fn apply_palette(&mut self) {
+        // Okabe-Ito colors, which are distinguishable with all common kinds of color blindness.
+        const ORANGE: Color = (230, 159, 0, 0);
+        const SKY_BLUE: Color = (86, 180, 233, 0);
+        const VERMILLION: Color = (213, 94, 0, 0);
+        const REDDISH_PURPLE: Color = (204, 121, 167, 0);
+        const DARK_BG: Color = (24, 24, 24, 0);
+        const LIGHT_FG: Color = (230, 230, 230, 0);
+
+        let (map, bg, fg) = match self.palette {
+            Palette::Default => return,
+            Palette::Viridis => (ColorMap::Viridis, WHITE, BLACK),
+            Palette::Cividis => (ColorMap::Cividis, WHITE, BLACK),
+            Palette::Dark => (ColorMap::Viridis, DARK_BG, LIGHT_FG),
+        };
+        let set = |c: &mut Option<Color>, color: Color| {
+            if c.is_some() {
+                *c = Some(color);
+            }
+        };
+
+        self.bg_color = bg;
+        self.fg_color = fg;
+        self.expanded = self.expanded.with_map(map);
+        set(&mut self.explored, SKY_BLUE);
+        set(&mut self.extended, map.at(0.9));
+        set(&mut self.preprune, map.at(0.6));
+        set(&mut self.path, fg);
+        set(&mut self.tree, GRAY);
+        set(&mut self.tree_substitution, ORANGE);
+        set(&mut self.tree_match, SKY_BLUE);
+        set(&mut self.tree_direction_change, VERMILLION);
+        set(&mut self.tree_affine_open, REDDISH_PURPLE);
+        if let Some(trace) = &mut self.trace {
+            *trace = (ORANGE, SKY_BLUE);
+        }
+        self.h_call = VERMILLION;
+        self.active_match = fg;
+        self.pruned_match = VERMILLION;
+        self.pre_pruned_match = REDDISH_PURPLE;
+        self.filtered_match = ORANGE;
+        self.contour = fg;
+        if self.palette == Palette::Dark {
+            self.heuristic = Gradient::Gradient((40, 40, 40, 0)..(80, 80, 80, 0));
+            self.layer = Gradient::Gradient((40, 40, 40, 0)..(130, 130, 130, 0));
+        }
+    }
+}
+
 impl When {
     fn is_active(&self, frame: usize, layer: usize, is_last: bool, new_layer: bool) -> bool {
         match &self {
@@ -424,6 +551,11 @@ impl When {
 }
 
 const CANVAS_HEIGHT: I = 1000;
+// Sizes of the margins used for annotations.
+const TITLE_HEIGHT: i32 = 30;
+const AXIS_WIDTH: i32 = 50;
+const AXIS_HEIGHT: i32 = 25;
+const LEGEND_HEIGHT: i32 = 50;
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct Config {
@@ -447,6 +579,13 @@ pub struct Config {
     pub layer_drawing: bool,
     pub num_layers: Option<usize>,
     pub clear_after_meeting_point: bool,
+    /// Rasterize each frame in memory and copy it to the canvas at once,
+    /// instead of drawing each state separately. Much faster for large inputs.
+    pub raster: bool,
+    /// Name of the algorithm.
+    pub title: Option<String>,
+    /// Heuristic / algorithm parameters.
+    pub params: Option<String>,
 }
 
 impl Config {
@@ -469,6 +608,8 @@ impl Config {
                 fixed: None,
                 preprune: None,
                 bg_color: WHITE,
+                fg_color: BLACK,
+                palette: Palette::Default,
                 path: Some(BLACK),
                 path_width: Some(2),
                 tree: None,
@@ -491,6 +632,9 @@ impl Config {
                 draw_fixed_h: false,
                 h_call: RED,
                 draw_labels: true,
+                draw_axes: false,
+                draw_legend: false,
+                draw_title: false,
                 heuristic: Gradient::Gradient((250, 250, 250, 0)..(180, 180, 180, 0)),
                 layer: Gradient::Gradient((250, 250, 250, 0)..(100, 100, 100, 0)),
                 max_heuristic: None,
@@ -508,6 +652,9 @@ impl Config {
             num_layers: None,
             transparent_bmp: true,
             clear_after_meeting_point: true,
+            raster: false,
+            title: None,
+            params: None,
         };
 
         match style {
@@ -570,6 +717,16 @@ impl Config {
                 config.style.draw_f = false;
                 config.style.draw_dt = true;
             }
+            VisualizerStyle::Paper => {
+                config.cell_size = 0;
+                config.downscaler = 0;
+                config.style.expanded = Gradient::TurboGradient(0.25..0.90);
+                config.style.path_width = Some(4);
+                config.style.draw_dt = false;
+                config.style.draw_labels = false;
+                config.transparent_bmp = false;
+                config.clear_after_meeting_point = false;
+            }
         }
 
         config
@@ -619,9 +776,11 @@ struct Region {
 }
 
 impl Visualizer {
-    /// This sets the title and parameters based on the CLI arguments.
-    /// FIXME: Add algorithm and heuristic args or title/params/comment args.
+    /// The title and parameters are taken from the config.
+    /// FIXME: Add a comment arg.
     pub fn new<CF: CanvasFactory>(mut config: Config, a: Seq, b: Seq) -> Self {
+        config.style.apply_palette();
+
         // layout:
         //
         // ---------------
@@ -676,18 +835,45 @@ impl Visualizer {
             nw.size.1,
         );
 
+        // Annotations are drawn in margins around the canvas.
+        let style = &config.style;
+        let offset = CPos(
+            if style.draw_axes { AXIS_WIDTH } else { 0 },
+            if style.draw_title { TITLE_HEIGHT } else { 0 }
+                + if style.draw_axes { AXIS_HEIGHT } else { 0 },
+        );
+        let full_size = (
+            offset.0 + canvas_size.0 + if style.draw_axes { AXIS_HEIGHT } else { 0 },
+            offset.1 + canvas_size.1 + if style.draw_legend { LEGEND_HEIGHT } else { 0 },
+        );
+
         Visualizer {
-            title: None,
-            params: None,
+            title: config.title.clone(),
+            params: config.params.clone(),
             comment: None,
             canvas: {
                 (config.draw != When::None || config.save != When::None || config.save_last).then(
                     || {
-                        RefCell::new(CF::new(
-                            canvas_size.0 as usize,
-                            canvas_size.1 as usize,
+                        let mut canvas = CF::new(
+                            full_size.0 as usize,
+                            full_size.1 as usize,
                             &config.filepath.to_str().unwrap(),
-                        ))
+                        );
+                        if config.raster {
+                            canvas = Box::new(RasterCanvas::new(
+                                canvas,
+                                full_size.0 as usize,
+                                full_size.1 as usize,
+                            ));
+                        }
+                        RefCell::new(if offset == CPos(0, 0) {
+                            canvas
+                        } else {
+                            Box::new(MarginCanvas {
+                                inner: canvas,
+                                offset,
+                            })
+                        })
                     },
                 )
             },
@@ -711,6 +897,8 @@ impl Visualizer {
             meeting_points: vec![],
 
             canvas_size,
+            offset,
+            full_size,
             nw,
             dt,
             _tr: tr,
@@ -920,9 +1108,9 @@ impl Visualizer {
             };
             let mut canvas = canvas.borrow_mut();
             canvas.fill_rect(
-                CPos(0, 0),
-                self.canvas_size.0 as I,
-                self.canvas_size.1 as I,
+                CPos(0, 0) - self.offset,
+                self.full_size.0 as I,
+                self.full_size.1 as I,
                 self.config.style.bg_color,
             );
 
@@ -1215,7 +1403,7 @@ impl Visualizer {
                     if let Some(fixed) = self.config.style.fixed {
                         canvas.fill_rect(tl, wh.0, wh.1, fixed);
                     }
-                    canvas.draw_rect(tl, wh.0, wh.1, BLACK);
+                    canvas.draw_rect(tl, wh.0, wh.1, self.config.style.fg_color);
                     // canvas.draw_rect(tl - CPos(1, 1), wh.0 + 2, wh.1 + 2, BLACK);
                 }
             }
@@ -1228,7 +1416,7 @@ impl Visualizer {
                     if let Some(fixed) = self.config.style.fixed {
                         canvas.fill_rect(tl, wh.0, wh.1, fixed);
                     }
-                    canvas.draw_rect(tl, wh.0, wh.1, BLACK);
+                    canvas.draw_rect(tl, wh.0, wh.1, self.config.style.fg_color);
                     // canvas.draw_rect(tl - CPos(1, 1), wh.0 + 2, wh.1 + 2, BLACK);
                 }
 
@@ -1236,7 +1424,7 @@ impl Visualizer {
                 if let Some((start, end)) = self.next_fixed_h {
                     let tl = self.cell_begin(start);
                     let wh = self.cell_end(end) - tl;
-                    canvas.draw_rect(tl, wh.0, wh.1, BLACK);
+                    canvas.draw_rect(tl, wh.0, wh.1, self.config.style.fg_color);
                     // canvas.draw_rect(tl - CPos(1, 1), wh.0 + 2, wh.1 + 2, BLACK);
                 }
             }
@@ -1341,7 +1529,7 @@ impl Visualizer {
                         HAlign::Right,
                         VAlign::Top,
                         &layer.to_string(),
-                        BLACK,
+                        self.config.style.fg_color,
                     );
                 }
                 for (&(_top, layer), &(bottom, _)) in left_borders.iter().tuple_windows() {
@@ -1354,7 +1542,7 @@ impl Visualizer {
                         HAlign::Left,
                         VAlign::Bottom,
                         &layer.to_string(),
-                        BLACK,
+                        self.config.style.fg_color,
                     );
                 }
             }
@@ -1467,7 +1655,7 @@ impl Visualizer {
                         HAlign::Center,
                         VAlign::Top,
                         title,
-                        BLACK,
+                        self.config.style.fg_color,
                     );
                     row += 1;
                 }
@@ -1531,6 +1719,8 @@ impl Visualizer {
                     GRAY,
                 );
             }
+
+            self.draw_annotations(&mut canvas);
         }
 
         self.draw_dt(cigar);
@@ -1604,6 +1794,100 @@ impl Visualizer {
         }
     }
 
+    /// Draw the title, axes, and legend into the margins of the canvas.
+    fn draw_annotations(&self, canvas: &mut CanvasBox) {
+        let style = &self.config.style;
+        let fg = style.fg_color;
+
+        if style.draw_title {
+            let text = [&self.title, &self.params]
+                .into_iter()
+                .flatten()
+                .filter(|s| !s.is_empty())
+                .join("  |  ");
+            canvas.write_text(
+                CPos(self.canvas_size.0 / 2, -self.offset.1),
+                HAlign::Center,
+                VAlign::Top,
+                &text,
+                fg,
+            );
+        }
+
+        if style.draw_axes {
+            const TICK: i32 = 4;
+            // i runs along the top, j along the left.
+            let step = tick_step(max(self.target.0, self.target.1));
+            for i in (0..=self.target.0).step_by(step as usize) {
+                let x = self.cell_center(Pos(i, 0)).0;
+                canvas.draw_line(CPos(x, -TICK), CPos(x, -1), fg);
+                canvas.write_text(
+                    CPos(x, -TICK),
+                    HAlign::Center,
+                    VAlign::Bottom,
+                    &i.to_string(),
+                    fg,
+                );
+            }
+            for j in (0..=self.target.1).step_by(step as usize) {
+                let y = self.cell_center(Pos(0, j)).1;
+                canvas.draw_line(CPos(-TICK, y), CPos(-1, y), fg);
+                canvas.write_text(
+                    CPos(-TICK - 2, y),
+                    HAlign::Right,
+                    VAlign::Center,
+                    &j.to_string(),
+                    fg,
+                );
+            }
+            canvas.draw_rect(CPos(0, 0), self.nw.size.0 as I, self.nw.size.1 as I, fg);
+        }
+
+        // The expanded states are colored by the order of expansion. Label the
+        // legend with the f value of the state expanded at each point.
+        // With layer drawing, colors follow the layer instead, and no legend is drawn.
+        if style.draw_legend
+            && self.layer.is_none()
+            && !self.expanded.is_empty()
+            && !matches!(style.expanded, Gradient::Fixed(_))
+        {
+            const BAR_HEIGHT: i32 = 12;
+            const LABELS: usize = 4;
+            let cnt = self.expanded.len();
+            let width = min(self.nw.size.0, 400);
+            let left = (self.nw.size.0 - width) / 2;
+            let top = self.canvas_size.1 + 10;
+            for x in 0..width {
+                let i = x as usize * cnt / width as usize;
+                canvas.fill_rect(
+                    CPos(left + x, top),
+                    1,
+                    BAR_HEIGHT as I,
+                    style.expanded.color(i, cnt),
+                );
+            }
+            canvas.draw_rect(CPos(left, top), width as I, BAR_HEIGHT as I, fg);
+            for l in 0..=LABELS {
+                let x = left + (width - 1) * l as i32 / LABELS as i32;
+                let i = min(l * cnt / LABELS, cnt - 1);
+                canvas.write_text(
+                    CPos(x, top + BAR_HEIGHT + 2),
+                    HAlign::Center,
+                    VAlign::Top,
+                    &self.expanded[i].3.to_string(),
+                    fg,
+                );
+            }
+            canvas.write_text(
+                CPos(left - 6, top + BAR_HEIGHT / 2),
+                HAlign::Right,
+                VAlign::Center,
+                "f",
+                fg,
+            );
+        }
+    }
+
     // Draw DT states to the top-right 1/3rd of the canvas.
     fn draw_dt(&mut self, cigar: Option<&AffineCigar>) {
         if !self.config.style.draw_dt || self.expanded.is_empty() {
@@ -1643,7 +1927,7 @@ impl Visualizer {
         canvas.draw_line(
             self.nw.start.right(self.nw.size.0),
             self.nw.start + self.nw.size,
-            BLACK,
+            self.config.style.fg_color,
         );
 
         // Horizontal d lines
@@ -1957,3 +2241,119 @@ impl Visualizer {
         );
     }
 }
+
+/// A round distance between axis ticks, giving at most 10 ticks for `len`.
+fn tick_step(len: I) -> I {
+    let mut step = 1;
+    loop {
+        for m in [1, 2, 5] {
+            if len / (m * step) < 10 {
+                return m * step;
+            }
+        }
+        step *= 10;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn color_maps() {
+        for map in [ColorMap::Turbo, ColorMap::Viridis, ColorMap::Cividis] {
+            assert_ne!(map.at(0.0), map.at(1.0), "{map:?}");
+        }
+        assert_ne!(ColorMap::Viridis.at(0.5), ColorMap::Cividis.at(0.5));
+        // Map gradients only use the given part of the color map.
+        let gradient = Gradient::Map(ColorMap::Viridis, 0.25..0.75);
+        assert_eq!(gradient.color_f(0.0), ColorMap::Viridis.at(0.25));
+        assert_eq!(gradient.color_f(1.0), ColorMap::Viridis.at(0.75));
+        // Bounded gradients divide by the bound instead of the count.
+        let bounded = Gradient::BoundedMap(ColorMap::Viridis, 0.0..1.0, 10);
+        assert_eq!(bounded.color(5, 100), ColorMap::Viridis.at(0.5));
+    }
+
+    #[test]
+    fn default_palette() {
+        for vis in [
+            VisualizerStyle::Default,
+            VisualizerStyle::Detailed,
+            VisualizerStyle::Test,
+        ] {
+            let mut style = Config::new(vis).style;
+            let old = style.clone();
+            style.apply_palette();
+            assert_eq!(style, old);
+        }
+    }
+
+    #[test]
+    fn viridis_palette() {
+        let mut style = Config::new(VisualizerStyle::Detailed).style;
+        style.palette = Palette::Viridis;
+        style.tree = None;
+        style.apply_palette();
+        assert_eq!((style.bg_color, style.fg_color), (WHITE, BLACK));
+        // Fixed colors are replaced by the default part of the color map.
+        assert_eq!(style.expanded, Gradient::Map(ColorMap::Viridis, 0.2..0.95));
+        assert_eq!(style.preprune, Some(ColorMap::Viridis.at(0.6)));
+        // Disabled colors stay disabled.
+        assert_eq!(style.tree, None);
+        assert_eq!(style.path, None);
+        assert!(style.explored.is_some());
+    }
+
+    #[test]
+    fn cividis_and_dark_palettes() {
+        let mut style = Config::new(VisualizerStyle::Default).style;
+        style.expanded = Gradient::BoundedTurboGradient(0.25..0.9, 10);
+        style.palette = Palette::Cividis;
+        style.apply_palette();
+        // The range and bound of the gradient are kept.
+        assert_eq!(
+            style.expanded,
+            Gradient::BoundedMap(ColorMap::Cividis, 0.25..0.9, 10)
+        );
+        assert_eq!(style.path, Some(BLACK));
+
+        let mut style = Config::new(VisualizerStyle::Default).style;
+        style.palette = Palette::Dark;
+        style.apply_palette();
+        assert_ne!(style.bg_color, WHITE);
+        assert_eq!(style.path, Some(style.fg_color));
+        assert_eq!(style.contour, style.fg_color);
+        assert_eq!(style.expanded, Gradient::Map(ColorMap::Viridis, 0.2..0.95));
+        assert_ne!(
+            style.heuristic,
+            Config::new(VisualizerStyle::Default).style.heuristic
+        );
+    }
+
+    #[test]
+    fn tick_steps() {
+        for (len, step) in [
+            (0, 1),
+            (9, 1),
+            (10, 2),
+            (19, 2),
+            (20, 5),
+            (49, 5),
+            (50, 10),
+            (99, 10),
+            (100, 20),
+            (1000, 200),
+            (5000, 1000),
+            (123456, 20000),
+        ] {
+            assert_eq!(tick_step(len), step, "{len}");
+        }
+        // At most 10 ticks, at a round distance.
+        for len in 0..3000 {
+            let step = tick_step(len);
+            assert!(len / step < 10, "{len}");
+            let mantissa = step / (10 as I).pow(step.ilog10());
+            assert!([1, 2, 5].contains(&mantissa), "{len}");
+        }
+    }
+}
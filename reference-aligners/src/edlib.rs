@@ -0,0 +1,130 @@
+//! Global unit-cost alignment using edlib.
+use std::os::raw::{c_char, c_int, c_uchar};
+
+use pa_types::*;
+
+const EDLIB_MODE_NW: c_int = 0;
+const EDLIB_TASK_DISTANCE: c_int = 0;
+const EDLIB_TASK_PATH: c_int = 2;
+const EDLIB_STATUS_OK: c_int = 0;
+
+/// Consumes a character of both sequences.
+const EDLIB_EDOP_MATCH: c_uchar = 0;
+/// Insertion to the target, i.e. only consumes a character of the query.
+const EDLIB_EDOP_INSERT: c_uchar = 1;
+/// Deletion from the target, i.e. only consumes a character of the target.
+const EDLIB_EDOP_DELETE: c_uchar = 2;
+const EDLIB_EDOP_MISMATCH: c_uchar = 3;
+
+#[repr(C)]
+struct EdlibEqualityPair {
+    first: c_char,
+    second: c_char,
+}
+
+#[repr(C)]
+struct EdlibAlignConfig {
+    k: c_int,
+    mode: c_int,
+    task: c_int,
+    additional_equalities: *const EdlibEqualityPair,
+    additional_equalities_length: c_int,
+}
+
+#[repr(C)]
+struct EdlibAlignResult {
+    status: c_int,
+    edit_distance: c_int,
+    end_locations: *mut c_int,
+    start_locations: *mut c_int,
+    num_locations: c_int,
+    alignment: *mut c_uchar,
+    alignment_length: c_int,
+    alphabet_length: c_int,
+}
+
+#[link(name = "edlib")]
+extern "C" {
+    fn edlibAlign(
+        query: *const c_char,
+        query_length: c_int,
+        target: *const c_char,
+        target_length: c_int,
+        config: EdlibAlignConfig,
+    ) -> EdlibAlignResult;
+    fn edlibFreeAlignResult(result: EdlibAlignResult);
+}
+
+/// Edlib in global (NW) mode, with `a` as the query and `b` as the target.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Edlib {
+    /// When true, also return the alignment.
+    pub trace: bool,
+}
+
+impl Aligner for Edlib {
+    fn align(&mut self, a: Seq, b: Seq) -> (Cost, Option<Cigar>) {
+        let config = EdlibAlignConfig {
+            k: -1,
+            mode: EDLIB_MODE_NW,
+            task: if self.trace {
+                EDLIB_TASK_PATH
+            } else {
+                EDLIB_TASK_DISTANCE
+            },
+            additional_equalities: std::ptr::null(),
+            additional_equalities_length: 0,
+        };
+        let result = unsafe {
+            edlibAlign(
+                a.as_ptr() as *const c_char,
+                a.len() as c_int,
+                b.as_ptr() as *const c_char,
+                b.len() as c_int,
+                config,
+            )
+        };
+        assert_eq!(result.status, EDLIB_STATUS_OK, "edlib failed");
+        let cost = result.edit_distance as Cost;
+        let cigar = (self.trace && !result.alignment.is_null()).then(|| {
+            let ops = unsafe {
+                std::slice::from_raw_parts(result.alignment, result.alignment_length as usize)
+            };
+            to_cigar(ops)
+        });
+        unsafe { edlibFreeAlignResult(result) };
+        (cost, cigar)
+    }
+}
+
+/// Convert edlib's alignment ops, where `a` is the query, to a `Cigar`.
+fn to_cigar(ops: &[c_uchar]) -> Cigar {
+    let mut cigar = Cigar::default();
+    for &op in ops {
+        let op = match op {
+            EDLIB_EDOP_MATCH => CigarOp::Match,
+            EDLIB_EDOP_MISMATCH => CigarOp::Sub,
+            // Only consumes `a`.
+            EDLIB_EDOP_INSERT => CigarOp::Del,
+            // Only consumes `b`.
+            EDLIB_EDOP_DELETE => CigarOp::Ins,
+            _ => panic!("Unknown edlib operation {op}"),
+        };
+        match cigar.ops.last_mut() {
+            Some(el) if el.op == op => el.cnt += 1,
+            _ => cigar.ops.push(CigarElem { op, cnt: 1 }),
+        }
+    }
+    cigar
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn edlib() {
+        pa_test::test_aligner(Edlib { trace: true });
+        pa_test::test_aligner(Edlib { trace: false });
+    }
+}
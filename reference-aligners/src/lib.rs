@@ -0,0 +1,27 @@
+//! Bindings to established aligners, to cross-validate costs and cigars
+//! against ground truth implementations instead of only `triple_accel`.
+//!
+//! Each aligner is behind a feature of the same name, and none are enabled by
+//! default, since they link against C libraries that are not part of this
+//! workspace:
+//! - `edlib`: links against a system-installed `libedlib`, e.g. from a package
+//!   manager or `cmake --install` of https://github.com/Martinsos/edlib.
+//! - `wfa2` and `ksw2`: not available yet. Their C APIs take large,
+//!   version-dependent structs (`wavefront_aligner_attr_t`, `ksw_extz_t`) that
+//!   should be generated with `bindgen` from the installed headers rather than
+//!   written by hand, and neither library is available in the environment these
+//!   bindings were written in. Enabling these features is a compile error.
+//!
+//! Nothing here is built or tested without the features, so CI needs to install
+//! the libraries and run e.g. `cargo test -p reference-aligners --features edlib`.
+
+#[cfg(feature = "edlib")]
+mod edlib;
+#[cfg(feature = "edlib")]
+pub use edlib::Edlib;
+
+#[cfg(feature = "wfa2")]
+compile_error!("The `wfa2` bindings are not available yet; see the `reference-aligners` docs.");
+
+#[cfg(feature = "ksw2")]
+compile_error!("The `ksw2` bindings are not available yet; see the `reference-aligners` docs.");